@@ -62,6 +62,25 @@
 //! }
 //! ```
 //!
+//! Large columns can be marked `lazy`. They are left out of the model's default
+//! SELECTs and loaded on demand through a generated `load_<field>` method. Lazy
+//! fields must be `Option<T>`, which is `None` until loaded.
+//!
+//! ```rust
+//! use libsql_orm::Model;
+//! use serde::{Serialize, Deserialize};
+//!
+//! #[derive(Model, Clone, Serialize, Deserialize)]
+//! struct Post {
+//!     pub id: Option<i64>,
+//!     pub title: String,
+//!     #[orm_column(lazy)]
+//!     pub content: Option<String>,
+//! }
+//!
+//! // let body = post.load_content(&db).await?;
+//! ```
+//!
 //! # Function-like Macros
 //!
 //! ## `generate_migration!(Model)`
@@ -90,6 +109,18 @@ use syn::{parse_macro_input, Attribute, Data, DeriveInput, Field, Fields, Lit, T
 /// - `unique` - Add UNIQUE constraint  
 /// - `primary_key` - Mark as PRIMARY KEY
 /// - `auto_increment` - Add AUTOINCREMENT (for INTEGER PRIMARY KEY)
+/// - `lazy` - Exclude from default SELECTs and generate a `load_<field>` method
+/// - `lww` - Merge concurrent updates last-write-wins (see `libsql_orm::lww`)
+/// - `compressed = "zstd"` - Compress on write and decompress on read (see `libsql_orm::compression`)
+/// - `parent` - Self-referencing parent key used by `descendants` and `ancestors`
+/// - `references = "table"` - Foreign key to another model's table, re-pointed by `Model::merge`
+/// - `mirror_to = "column"` - Also write the value to another column during an expand/contract migration
+/// - `db_default` or `db_default = "SQL"` - Leave the column out of inserts so its SQL `DEFAULT` applies, reading the stored value back; the second form also declares `DEFAULT (SQL)`
+/// - `content_hash(of("col", ...))` - Hash of the listed columns, maintained on writes (see `libsql_orm::content_hash`)
+///
+/// Unique columns also get a generated `find_by_<field>` finder. Chrono
+/// fields named `created_at` and `updated_at` get `created_between` and
+/// `updated_since` finders.
 ///
 /// # Examples:
 ///
@@ -122,6 +153,15 @@ pub fn orm_column(_args: TokenStream, input: TokenStream) -> TokenStream {
 /// # Attributes:
 /// - `#[table_name("custom_name")]` - Specify custom table name
 /// - `#[orm_column(...)]` - Configure column properties
+/// - `#[orm(projection(name = "Summary", fields(...)))]` - Generate a projection
+///   struct named `<Model><name>` holding a subset of the fields
+/// - `#[orm(identity_eq)]` - Implement `PartialEq`, `Eq` and `Hash` by primary
+///   key, so records can be deduplicated in sets and maps. Records without a
+///   primary key are compared by all of their values instead
+/// - `#[orm(retention(days = 90, column = "created_at"))]` - Register a
+///   retention policy enforced by `retention::enforce_all`; `column` defaults
+///   to `created_at`, and `archive` moves expired rows to `<table>_archive`
+///   instead of deleting them
 ///
 /// # Examples:
 ///
@@ -137,7 +177,7 @@ pub fn orm_column(_args: TokenStream, input: TokenStream) -> TokenStream {
 ///     pub email: String,
 /// }
 /// ```
-#[proc_macro_derive(Model, attributes(table_name, orm_column))]
+#[proc_macro_derive(Model, attributes(table_name, orm_column, orm))]
 pub fn derive_model(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = input.ident;
@@ -149,37 +189,140 @@ pub fn derive_model(input: TokenStream) -> TokenStream {
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
     // Extract field names and column metadata for columns
-    let (field_names, column_definitions, boolean_field_names, boolean_flags) =
-        if let Data::Struct(data) = &input.data {
-            if let Fields::Named(fields) = &data.fields {
-                let mut field_names = Vec::new();
-                let mut column_defs = Vec::new();
-                let mut bool_field_names = Vec::new();
-                let mut bool_flags = Vec::new();
-
-                for field in &fields.named {
-                    let field_name = &field.ident;
-                    let field_name_str = quote! { stringify!(#field_name) };
-                    field_names.push(field_name_str);
-
-                    // Parse column attributes to get SQL definition
-                    let column_def = parse_column_definition(field);
-                    column_defs.push(column_def);
-
-                    // Extract field type information for conversion
-                    let field_type = &field.ty;
-                    let is_bool = is_boolean_type(field_type);
-                    bool_field_names.push(quote! { stringify!(#field_name) });
-                    bool_flags.push(is_bool);
+    let fields: Vec<FieldInfo> = if let Data::Struct(data) = &input.data {
+        if let Fields::Named(fields) = &data.fields {
+            fields.named.iter().map(parse_field).collect()
+        } else {
+            Vec::new()
+        }
+    } else {
+        Vec::new()
+    };
+
+    let field_names: Vec<_> = fields.iter().map(|f| f.name.as_str()).collect();
+    let column_definitions: Vec<_> = fields.iter().map(|f| f.definition.as_str()).collect();
+    let column_meta: Vec<_> = fields.iter().map(column_meta_tokens).collect();
+    let from_map_body = from_map_body(&fields.iter().collect::<Vec<_>>());
+    let write_hashes: Vec<_> = fields
+        .iter()
+        .filter_map(|f| write_content_hash(f, &fields))
+        .collect();
+    let write_blobs: Vec<_> = fields.iter().filter_map(write_blob).collect();
+    let write_compressions: Vec<_> = fields.iter().filter_map(write_compression).collect();
+    let write_mirrors: Vec<_> = fields
+        .iter()
+        .filter_map(|f| write_mirror(f, &fields))
+        .collect();
+    let inherent_methods: Vec<_> = fields
+        .iter()
+        .filter(|f| f.attrs.lazy)
+        .map(lazy_loader)
+        .chain(fields.iter().filter(|f| is_unique(f)).map(unique_finder))
+        .chain(fields.iter().filter_map(timestamp_finder))
+        .collect();
+    let inherent_impl = if inherent_methods.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                #(#inherent_methods)*
+            }
+        }
+    };
+
+    let (projections, identity) = match parse_orm_attrs(&input.attrs) {
+        Ok(attrs) if !attrs.projections.is_empty() && !input.generics.params.is_empty() => (
+            syn::Error::new_spanned(&name, "projections are not supported on generic models")
+                .to_compile_error(),
+            quote! {},
+        ),
+        Ok(attrs) => {
+            let generated = attrs
+                .projections
+                .iter()
+                .map(|spec| projection_tokens(&name, &table_name, &input.vis, &fields, spec));
+            let identity = if attrs.identity_eq {
+                quote! {
+                    impl #impl_generics PartialEq for #name #ty_generics #where_clause {
+                        fn eq(&self, other: &Self) -> bool {
+                            libsql_orm::model::identity_key(self)
+                                == libsql_orm::model::identity_key(other)
+                        }
+                    }
+
+                    impl #impl_generics Eq for #name #ty_generics #where_clause {}
+
+                    impl #impl_generics std::hash::Hash for #name #ty_generics #where_clause {
+                        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                            libsql_orm::model::identity_key(self).hash(state);
+                        }
+                    }
                 }
+            } else {
+                quote! {}
+            };
+            (quote! { #(#generated)* }, identity)
+        }
+        Err(err) => (err.to_compile_error(), quote! {}),
+    };
 
-                (field_names, column_defs, bool_field_names, bool_flags)
+    let (retention, retention_error) = match parse_orm_attrs(&input.attrs) {
+        Ok(OrmAttrs {
+            retention: Some(spec),
+            ..
+        }) => {
+            let column = spec.column.value();
+            if !input.generics.params.is_empty() {
+                (
+                    quote! { None },
+                    syn::Error::new_spanned(&name, "retention is not supported on generic models")
+                        .to_compile_error(),
+                )
+            } else if !field_names.contains(&column.as_str()) {
+                (
+                    quote! { None },
+                    syn::Error::new_spanned(
+                        &spec.column,
+                        format!("`{name}` has no field named `{column}`"),
+                    )
+                    .to_compile_error(),
+                )
             } else {
-                (vec![], vec![], vec![], vec![])
+                let (days, archive) = (&spec.days, spec.archive);
+                (
+                    quote! {
+                        Some(libsql_orm::RetentionPolicy {
+                            days: #days,
+                            column: #column,
+                            archive: #archive,
+                        })
+                    },
+                    quote! {},
+                )
             }
-        } else {
-            (vec![], vec![], vec![], vec![])
-        };
+        }
+        _ => (quote! { None }, quote! {}),
+    };
+
+    // Generic models cannot be placed in the global registry, since the
+    // registration has to be a `static` item
+    let registration = if input.generics.params.is_empty() {
+        quote! {
+            const _: () = {
+                const COLUMNS: &[libsql_orm::ColumnMeta] = &[#(#column_meta),*];
+                libsql_orm::inventory::submit! {
+                    libsql_orm::ModelMeta {
+                        table_name: #table_name,
+                        columns: COLUMNS,
+                        migration_sql: <#name as libsql_orm::Model>::migration_sql,
+                        retention: #retention,
+                    }
+                }
+            };
+        }
+    } else {
+        quote! {}
+    };
 
     let expanded = quote! {
         impl #impl_generics libsql_orm::Model for #name #ty_generics #where_clause {
@@ -199,6 +342,11 @@ pub fn derive_model(input: TokenStream) -> TokenStream {
                 vec![#(#field_names),*]
             }
 
+            fn column_meta() -> &'static [libsql_orm::ColumnMeta] {
+                const COLUMNS: &[libsql_orm::ColumnMeta] = &[#(#column_meta),*];
+                COLUMNS
+            }
+
             /// Generate SQL for creating the table
             fn migration_sql() -> String {
                 let columns = vec![#(#column_definitions),*];
@@ -234,130 +382,794 @@ pub fn derive_model(input: TokenStream) -> TokenStream {
                     };
                     result.insert(k, value);
                 }
+                #(#write_blobs)*
+                #(#write_hashes)*
+                #(#write_compressions)*
+                #(#write_mirrors)*
                 Ok(result)
             }
 
             fn from_map(map: std::collections::HashMap<String, libsql_orm::Value>) -> libsql_orm::Result<Self> {
-                use serde_json;
-                let mut json_map = serde_json::Map::new();
-
-                for (k, v) in map {
-                    let json_value = match v {
-                        libsql_orm::Value::Null => serde_json::Value::Null,
-                        libsql_orm::Value::Boolean(b) => serde_json::Value::Bool(b),
-                        libsql_orm::Value::Integer(i) => {
-                            // Convert integers to booleans for known boolean fields
-                            let field_name = k.as_str();
-                            let mut is_boolean_field = false;
-                            #(
-                                if field_name == #boolean_field_names {
-                                    is_boolean_field = #boolean_flags;
-                                }
-                            )*
-
-                            if is_boolean_field {
-                                serde_json::Value::Bool(i != 0)
-                            } else {
-                                serde_json::Value::Number(serde_json::Number::from(i))
-                            }
-                        }
-                        libsql_orm::Value::Real(f) => {
-                            if let Some(n) = serde_json::Number::from_f64(f) {
-                                serde_json::Value::Number(n)
-                            } else {
-                                serde_json::Value::String(f.to_string())
-                            }
-                        }
-                        libsql_orm::Value::Text(s) => serde_json::Value::String(s),
-                        libsql_orm::Value::Blob(b) => {
-                            serde_json::Value::Array(b.into_iter().map(|byte| serde_json::Value::Number(serde_json::Number::from(byte))).collect())
-                        }
-                    };
-                    json_map.insert(k, json_value);
-                }
-
-                let json_value = serde_json::Value::Object(json_map);
-                let result: Self = serde_json::from_value(json_value)?;
-                Ok(result)
+                #from_map_body
             }
         }
 
+        #registration
+
+        #inherent_impl
+
+        #projections
+        #retention_error
+
+        #identity
+
         // Note: Clone is already derived in the struct definition
     };
 
     TokenStream::from(expanded)
 }
 
-/// Parse column definition from field attributes
-fn parse_column_definition(field: &Field) -> proc_macro2::TokenStream {
-    let field_name = &field.ident;
-    let field_name_str = field_name.as_ref().unwrap().to_string();
+/// Column properties parsed from an `#[orm_column(...)]` attribute
+#[derive(Default)]
+struct ColumnAttrs {
+    column_type: Option<String>,
+    not_null: bool,
+    unique: bool,
+    primary_key: bool,
+    auto_increment: bool,
+    lazy: bool,
+    lww: bool,
+    parent: bool,
+    compressed: Option<syn::LitStr>,
+    references: Option<syn::LitStr>,
+    mirror_to: Option<syn::LitStr>,
+    content_hash: Option<Vec<syn::LitStr>>,
+    db_default: bool,
+    default_sql: Option<syn::LitStr>,
+}
 
-    // Default column definitions based on field type
-    let default_def = match &field.ty {
-        Type::Path(type_path) => {
-            let type_name = &type_path.path.segments.last().unwrap().ident;
-            match type_name.to_string().as_str() {
-                "i64" => format!("{field_name_str} INTEGER"),
-                "i32" => format!("{field_name_str} INTEGER"),
-                "f64" => format!("{field_name_str} REAL"),
-                "f32" => format!("{field_name_str} REAL"),
-                "bool" => format!("{field_name_str} BOOLEAN"),
-                "String" => format!("{field_name_str} TEXT"),
-                _ => format!("{field_name_str} TEXT"),
-            }
-        }
-        _ => format!("{field_name_str} TEXT"),
-    };
+/// Everything the derive needs to know about a single struct field
+struct FieldInfo {
+    name: String,
+    ty: Type,
+    vis: syn::Visibility,
+    serde_attrs: Vec<Attribute>,
+    attrs: ColumnAttrs,
+    definition: String,
+}
 
-    // Check for orm_column attributes
+/// Collect the column information for a named struct field
+fn parse_field(field: &Field) -> FieldInfo {
+    let name = field.ident.as_ref().unwrap().to_string();
+    let attrs = parse_column_attrs(field);
+    let definition = column_definition(&name, &field.ty, &attrs);
+    FieldInfo {
+        name,
+        ty: field.ty.clone(),
+        vis: field.vis.clone(),
+        serde_attrs: field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("serde"))
+            .cloned()
+            .collect(),
+        attrs,
+        definition,
+    }
+}
+
+/// Parse the `#[orm_column(...)]` attribute of a field, if present
+fn parse_column_attrs(field: &Field) -> ColumnAttrs {
+    let mut attrs = ColumnAttrs::default();
     for attr in &field.attrs {
         if attr.path().is_ident("orm_column") {
-            let mut column_type = None;
-            let mut not_null = false;
-            let mut unique = false;
-            let mut primary_key = false;
-            let mut auto_increment = false;
-
             // Parse the nested meta items
             let _ = attr.parse_nested_meta(|meta| {
                 if meta.path.is_ident("type") {
                     if let Ok(value) = meta.value() {
                         let lit: Lit = value.parse()?;
                         if let Lit::Str(lit_str) = lit {
-                            column_type = Some(lit_str.value());
+                            attrs.column_type = Some(lit_str.value());
                         }
                     }
                 } else if meta.path.is_ident("not_null") {
-                    not_null = true;
+                    attrs.not_null = true;
                 } else if meta.path.is_ident("unique") {
-                    unique = true;
+                    attrs.unique = true;
                 } else if meta.path.is_ident("primary_key") {
-                    primary_key = true;
+                    attrs.primary_key = true;
                 } else if meta.path.is_ident("auto_increment") {
-                    auto_increment = true;
+                    attrs.auto_increment = true;
+                } else if meta.path.is_ident("lazy") {
+                    attrs.lazy = true;
+                } else if meta.path.is_ident("lww") {
+                    attrs.lww = true;
+                } else if meta.path.is_ident("parent") {
+                    attrs.parent = true;
+                } else if meta.path.is_ident("db_default") {
+                    attrs.db_default = true;
+                    if meta.input.peek(syn::Token![=]) {
+                        attrs.default_sql = Some(meta.value()?.parse()?);
+                    }
+                } else if meta.path.is_ident("mirror_to") {
+                    attrs.mirror_to = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("references") {
+                    attrs.references = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("compressed") {
+                    attrs.compressed = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("content_hash") {
+                    meta.parse_nested_meta(|inner| {
+                        if inner.path.is_ident("of") {
+                            let content;
+                            syn::parenthesized!(content in inner.input);
+                            let columns = content.parse_terminated(
+                                |input| input.parse::<syn::LitStr>(),
+                                syn::Token![,],
+                            )?;
+                            attrs.content_hash = Some(columns.into_iter().collect());
+                        }
+                        Ok(())
+                    })?;
                 }
                 Ok(())
             });
+        }
+    }
+    attrs
+}
 
-            let mut column_def = column_type.unwrap_or_else(|| default_def.clone());
-            if primary_key {
-                column_def = format!("{column_def} PRIMARY KEY");
+/// Build the SQL column definition from the field type and attributes
+fn column_definition(name: &str, ty: &Type, attrs: &ColumnAttrs) -> String {
+    // Default column definitions based on field type
+    let default_type = match ty {
+        _ if column_kind(ty) == "Blob" => "BLOB",
+        Type::Path(type_path) => {
+            let type_name = &type_path.path.segments.last().unwrap().ident;
+            match type_name.to_string().as_str() {
+                "i64" | "i32" => "INTEGER",
+                "f64" | "f32" => "REAL",
+                "bool" => "BOOLEAN",
+                _ => "TEXT",
             }
-            if auto_increment {
-                column_def = format!("{column_def} AUTOINCREMENT");
+        }
+        _ => "TEXT",
+    };
+
+    let mut column_def = match &attrs.column_type {
+        Some(column_type) => format!("{name} {column_type}"),
+        None => format!("{name} {default_type}"),
+    };
+    if attrs.primary_key {
+        column_def = format!("{column_def} PRIMARY KEY");
+    }
+    if attrs.auto_increment {
+        column_def = format!("{column_def} AUTOINCREMENT");
+    }
+    if attrs.not_null {
+        column_def = format!("{column_def} NOT NULL");
+    }
+    if attrs.unique {
+        column_def = format!("{column_def} UNIQUE");
+    }
+    if let Some(default) = &attrs.default_sql {
+        column_def = format!("{column_def} DEFAULT ({})", default.value());
+    }
+    if let Some(table) = &attrs.references {
+        column_def = format!("{column_def} REFERENCES {}", table.value());
+    }
+    column_def
+}
+
+/// The declared type of a column definition, e.g. `DECIMAL(10, 2)` or
+/// `UNSIGNED BIG INT`
+///
+/// The type is every word after the column name up to the first constraint
+/// keyword outside parentheses; `TEXT` when there is none.
+fn declared_type(definition: &str) -> String {
+    const CONSTRAINTS: &[&str] = &[
+        "CONSTRAINT",
+        "PRIMARY",
+        "NOT",
+        "NULL",
+        "UNIQUE",
+        "CHECK",
+        "DEFAULT",
+        "COLLATE",
+        "REFERENCES",
+        "GENERATED",
+        "AS",
+        "AUTOINCREMENT",
+    ];
+    let rest = definition
+        .trim_start()
+        .split_once(char::is_whitespace)
+        .map_or("", |(_, rest)| rest);
+
+    let mut words: Vec<String> = Vec::new();
+    let mut depth = 0usize;
+    for word in rest.split_whitespace() {
+        if depth == 0 {
+            let keyword = word.split('(').next().unwrap_or_default();
+            if CONSTRAINTS.iter().any(|c| keyword.eq_ignore_ascii_case(c)) {
+                break;
             }
-            if not_null {
-                column_def = format!("{column_def} NOT NULL");
+        }
+        for c in word.chars() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                _ => {}
             }
-            if unique {
-                column_def = format!("{column_def} UNIQUE");
+        }
+        words.push(word.to_uppercase());
+    }
+    if words.is_empty() {
+        "TEXT".to_string()
+    } else {
+        words.join(" ")
+    }
+}
+
+/// Generate the `ColumnMeta` literal describing a field
+fn column_meta_tokens(field: &FieldInfo) -> proc_macro2::TokenStream {
+    let name = &field.name;
+    let definition = &field.definition;
+    let sql_type = declared_type(definition);
+    let upper = definition.to_uppercase();
+    let nullable = option_inner_type(&field.ty).is_some();
+    let primary_key = field.attrs.primary_key || upper.contains("PRIMARY KEY") || name == "id";
+    let unique = field.attrs.unique || upper.contains("UNIQUE");
+    let not_null = field.attrs.not_null || upper.contains("NOT NULL");
+    let lazy = field.attrs.lazy;
+    let lww = field.attrs.lww;
+    let parent = field.attrs.parent;
+    let compressed = field.attrs.compressed.is_some();
+    let db_default = field.attrs.db_default;
+    let mirror_to = match &field.attrs.mirror_to {
+        Some(column) => quote! { Some(#column) },
+        None => quote! { None },
+    };
+    let references = match &field.attrs.references {
+        Some(table) => quote! { Some(#table) },
+        None => quote! { None },
+    };
+    let hash_of: Vec<String> = field
+        .attrs
+        .content_hash
+        .iter()
+        .flatten()
+        .map(|column| column.value())
+        .collect();
+    let kind = column_kind(&field.ty);
+    let kind = syn::Ident::new(kind, proc_macro2::Span::call_site());
+
+    quote! {
+        libsql_orm::ColumnMeta {
+            name: #name,
+            sql_type: #sql_type,
+            definition: #definition,
+            kind: libsql_orm::ColumnKind::#kind,
+            nullable: #nullable,
+            primary_key: #primary_key,
+            unique: #unique,
+            not_null: #not_null,
+            lazy: #lazy,
+            lww: #lww,
+            parent: #parent,
+            compressed: #compressed,
+            references: #references,
+            mirror_to: #mirror_to,
+            hash_of: &[#(#hash_of),*],
+            db_default: #db_default,
+        }
+    }
+}
+
+/// Return the `T` of an `Option<T>` type
+fn option_inner_type(ty: &Type) -> Option<&Type> {
+    if let Type::Path(type_path) = ty {
+        if let Some(segment) = type_path.path.segments.last() {
+            if segment.ident == "Option" {
+                if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
+                    if let Some(syn::GenericArgument::Type(inner)) = args.args.first() {
+                        return Some(inner);
+                    }
+                }
             }
-            return quote! { #column_def };
         }
     }
-    // Return default definition
-    quote! { #default_def }
+    None
+}
+
+/// Classify a field type into a `ColumnKind` variant name
+fn column_kind(ty: &Type) -> &'static str {
+    let ty = option_inner_type(ty).unwrap_or(ty);
+    if let Type::Path(type_path) = ty {
+        if let Some(segment) = type_path.path.segments.last() {
+            return match segment.ident.to_string().as_str() {
+                "bool" => "Boolean",
+                "i8" | "i16" | "i32" | "i64" | "isize" | "u8" | "u16" | "u32" | "u64" | "usize" => {
+                    "Integer"
+                }
+                "f32" | "f64" => "Real",
+                "String" => "Text",
+                "DateTime" | "NaiveDateTime" | "NaiveDate" => "DateTime",
+                "Vec" => {
+                    let is_bytes = matches!(
+                        &segment.arguments,
+                        syn::PathArguments::AngleBracketed(args)
+                            if matches!(args.args.first(), Some(syn::GenericArgument::Type(Type::Path(p))) if p.path.is_ident("u8"))
+                    );
+                    if is_bytes {
+                        "Blob"
+                    } else {
+                        "Other"
+                    }
+                }
+                _ => "Other",
+            };
+        }
+    }
+    "Other"
+}
+
+/// A projection declared with `#[orm(projection(name = "...", fields(...)))]`
+struct ProjectionSpec {
+    name: syn::LitStr,
+    fields: Vec<syn::Ident>,
+}
+
+/// A `#[orm(retention(...))]` policy
+struct RetentionSpec {
+    days: syn::LitInt,
+    column: syn::LitStr,
+    archive: bool,
+}
+
+/// Options parsed from the `#[orm(...)]` attributes of a model
+#[derive(Default)]
+struct OrmAttrs {
+    projections: Vec<ProjectionSpec>,
+    identity_eq: bool,
+    retention: Option<RetentionSpec>,
+}
+
+/// Parse the `#[orm(...)]` attributes of a model
+fn parse_orm_attrs(attrs: &[Attribute]) -> syn::Result<OrmAttrs> {
+    let mut orm = OrmAttrs::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("orm")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("identity_eq") {
+                orm.identity_eq = true;
+                return Ok(());
+            }
+            if meta.path.is_ident("retention") {
+                let mut days = None;
+                let mut column = None;
+                let mut archive = false;
+                meta.parse_nested_meta(|option| {
+                    if option.path.is_ident("days") {
+                        days = Some(option.value()?.parse::<syn::LitInt>()?);
+                    } else if option.path.is_ident("column") {
+                        column = Some(option.value()?.parse::<syn::LitStr>()?);
+                    } else if option.path.is_ident("archive") {
+                        archive = true;
+                    } else {
+                        return Err(option.error(
+                            "unsupported retention option, expected `days`, `column` or `archive`",
+                        ));
+                    }
+                    Ok(())
+                })?;
+                let days = days.ok_or_else(|| meta.error("retention requires `days = N`"))?;
+                days.base10_parse::<u32>()?;
+                let column = column.unwrap_or_else(|| {
+                    syn::LitStr::new("created_at", proc_macro2::Span::call_site())
+                });
+                orm.retention = Some(RetentionSpec {
+                    days,
+                    column,
+                    archive,
+                });
+                return Ok(());
+            }
+            if !meta.path.is_ident("projection") {
+                return Err(meta.error(
+                    "unsupported orm attribute, expected `projection`, `identity_eq` or `retention`",
+                ));
+            }
+            let mut name = None;
+            let mut fields = Vec::new();
+            meta.parse_nested_meta(|option| {
+                if option.path.is_ident("name") {
+                    name = Some(option.value()?.parse::<syn::LitStr>()?);
+                    Ok(())
+                } else if option.path.is_ident("fields") {
+                    option.parse_nested_meta(|field| {
+                        let ident = field
+                            .path
+                            .get_ident()
+                            .cloned()
+                            .ok_or_else(|| field.error("expected a field name"))?;
+                        fields.push(ident);
+                        Ok(())
+                    })
+                } else {
+                    Err(option.error("unsupported projection option, expected `name` or `fields`"))
+                }
+            })?;
+            let name = name.ok_or_else(|| meta.error("projection requires `name = \"...\"`"))?;
+            if fields.is_empty() {
+                return Err(meta.error("projection requires at least one field"));
+            }
+            orm.projections.push(ProjectionSpec { name, fields });
+            Ok(())
+        })?;
+    }
+    Ok(orm)
+}
+
+/// Generate a projection struct and its `Projection` implementation
+fn projection_tokens(
+    model: &syn::Ident,
+    table_name: &str,
+    vis: &syn::Visibility,
+    fields: &[FieldInfo],
+    spec: &ProjectionSpec,
+) -> proc_macro2::TokenStream {
+    let mut selected = Vec::with_capacity(spec.fields.len());
+    for ident in &spec.fields {
+        match fields.iter().find(|f| *ident == f.name) {
+            Some(field) => selected.push(field),
+            None => {
+                return syn::Error::new_spanned(
+                    ident,
+                    format!("`{model}` has no field named `{ident}`"),
+                )
+                .to_compile_error();
+            }
+        }
+    }
+
+    let struct_name = quote::format_ident!("{}{}", model, spec.name.value());
+    let doc = format!("`{}` projection of [`{model}`]", spec.name.value());
+    let columns: Vec<_> = selected.iter().map(|f| f.name.as_str()).collect();
+    let definitions = selected.iter().map(|f| {
+        let ident = syn::Ident::new(&f.name, proc_macro2::Span::call_site());
+        let (field_vis, ty, serde_attrs) = (&f.vis, &f.ty, &f.serde_attrs);
+        quote! {
+            #(#serde_attrs)*
+            #field_vis #ident: #ty
+        }
+    });
+    let from_map_body = from_map_body(&selected);
+
+    quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+        #vis struct #struct_name {
+            #(#definitions),*
+        }
+
+        impl libsql_orm::Projection for #struct_name {
+            fn table_name() -> &'static str {
+                #table_name
+            }
+
+            fn columns() -> Vec<&'static str> {
+                vec![#(#columns),*]
+            }
+
+            fn from_map(map: std::collections::HashMap<String, libsql_orm::Value>) -> libsql_orm::Result<Self> {
+                #from_map_body
+            }
+        }
+    }
+}
+
+/// Generate the body of a `from_map` implementation for the given fields
+///
+/// Shared by models and projections, so both apply the same boolean, numeric
+/// and datetime conversions when reading rows.
+fn from_map_body(fields: &[&FieldInfo]) -> proc_macro2::TokenStream {
+    let boolean_field_names: Vec<_> = fields.iter().map(|f| f.name.as_str()).collect();
+    let boolean_flags: Vec<_> = fields.iter().map(|f| is_boolean_type(&f.ty)).collect();
+    let read_coercions: Vec<_> = fields.iter().filter_map(|f| read_coercion(f)).collect();
+
+    quote! {
+        use serde_json;
+        let mut json_map = serde_json::Map::new();
+
+        for (k, mut v) in map {
+            #(#read_coercions)*
+            let json_value = match v {
+                libsql_orm::Value::Null => serde_json::Value::Null,
+                libsql_orm::Value::Boolean(b) => serde_json::Value::Bool(b),
+                libsql_orm::Value::Integer(i) => {
+                    // Convert integers to booleans for known boolean fields
+                    let field_name = k.as_str();
+                    let mut is_boolean_field = false;
+                    #(
+                        if field_name == #boolean_field_names {
+                            is_boolean_field = #boolean_flags;
+                        }
+                    )*
+
+                    if is_boolean_field {
+                        serde_json::Value::Bool(i != 0)
+                    } else {
+                        serde_json::Value::Number(serde_json::Number::from(i))
+                    }
+                }
+                libsql_orm::Value::Real(f) => {
+                    if let Some(n) = serde_json::Number::from_f64(f) {
+                        serde_json::Value::Number(n)
+                    } else {
+                        serde_json::Value::String(f.to_string())
+                    }
+                }
+                libsql_orm::Value::Text(s) => serde_json::Value::String(s),
+                libsql_orm::Value::Blob(b) => {
+                    serde_json::Value::Array(b.into_iter().map(|byte| serde_json::Value::Number(serde_json::Number::from(byte))).collect())
+                }
+            };
+            json_map.insert(k, json_value);
+        }
+
+        let json_value = serde_json::Value::Object(json_map);
+        let result: Self = serde_json::from_value(json_value)?;
+        Ok(result)
+    }
+}
+
+/// Generate the value normalization applied to a field before deserialization
+fn read_coercion(field: &FieldInfo) -> Option<proc_macro2::TokenStream> {
+    let column = field.name.as_str();
+    let coercion = if field.attrs.compressed.is_some() {
+        quote! {
+            libsql_orm::compression::decompress_value(&k, v)?
+        }
+    } else if let Some(target) = datetime_target(&field.ty) {
+        let target = syn::Ident::new(target, proc_macro2::Span::call_site());
+        quote! {
+            libsql_orm::coercion::coerce_datetime(
+                &k,
+                v,
+                libsql_orm::coercion::DateTimeTarget::#target,
+            )?
+        }
+    } else {
+        match column_kind(&field.ty) {
+            "Blob" => quote! {
+                libsql_orm::coercion::coerce_blob(&k, v)?
+            },
+            kind @ ("Integer" | "Real") => {
+                let kind = syn::Ident::new(kind, proc_macro2::Span::call_site());
+                quote! {
+                    libsql_orm::coercion::coerce_number(&k, v, libsql_orm::ColumnKind::#kind)?
+                }
+            }
+            _ => return None,
+        }
+    };
+    Some(quote! {
+        if k == #column {
+            v = #coercion;
+        }
+    })
+}
+
+/// Generate the conversion of a `Vec<u8>` field to a BLOB in `to_map`
+fn write_blob(field: &FieldInfo) -> Option<proc_macro2::TokenStream> {
+    if column_kind(&field.ty) != "Blob" {
+        return None;
+    }
+    let column = field.name.as_str();
+    Some(quote! {
+        if let Some(v) = result.remove(#column) {
+            result.insert(
+                #column.to_string(),
+                libsql_orm::coercion::coerce_blob(#column, v)?,
+            );
+        }
+    })
+}
+
+/// Generate the computation of a content-hash column in `to_map`
+fn write_content_hash(field: &FieldInfo, fields: &[FieldInfo]) -> Option<proc_macro2::TokenStream> {
+    let sources = field.attrs.content_hash.as_ref()?;
+    if sources.is_empty() {
+        return Some(
+            syn::Error::new_spanned(
+                &field.ty,
+                "content_hash requires at least one column in of(...)",
+            )
+            .to_compile_error(),
+        );
+    }
+    for source in sources {
+        let name = source.value();
+        if name == field.name || !fields.iter().any(|f| f.name == name) {
+            return Some(
+                syn::Error::new_spanned(
+                    source,
+                    "content_hash must list other columns of the model",
+                )
+                .to_compile_error(),
+            );
+        }
+    }
+    let column = field.name.as_str();
+    Some(quote! {
+        let hash = libsql_orm::content_hash::hash_values(&[
+            #(result.get(#sources).unwrap_or(&libsql_orm::Value::Null)),*
+        ]);
+        result.insert(#column.to_string(), libsql_orm::Value::Text(hash));
+    })
+}
+
+/// Generate the copy of a column into its `mirror_to` column in `to_map`
+fn write_mirror(field: &FieldInfo, fields: &[FieldInfo]) -> Option<proc_macro2::TokenStream> {
+    let target = field.attrs.mirror_to.as_ref()?;
+    if fields.iter().any(|f| f.name == target.value()) {
+        return Some(
+            syn::Error::new_spanned(target, "mirror_to must name a column outside the model")
+                .to_compile_error(),
+        );
+    }
+    let column = field.name.as_str();
+    Some(quote! {
+        if let Some(value) = result.get(#column).cloned() {
+            result.insert(#target.to_string(), value);
+        }
+    })
+}
+
+/// Generate the compression of a compressed column in `to_map`
+fn write_compression(field: &FieldInfo) -> Option<proc_macro2::TokenStream> {
+    let algorithm = field.attrs.compressed.as_ref()?;
+    if algorithm.value() != "zstd" {
+        return Some(
+            syn::Error::new_spanned(algorithm, "unsupported compression, expected \"zstd\"")
+                .to_compile_error(),
+        );
+    }
+    let column = field.name.as_str();
+    Some(quote! {
+        if let Some(v) = result.remove(#column) {
+            result.insert(
+                #column.to_string(),
+                libsql_orm::compression::compress_value(#column, v)?,
+            );
+        }
+    })
+}
+
+/// Generate the `load_<field>` method for a lazy column
+fn lazy_loader(field: &FieldInfo) -> proc_macro2::TokenStream {
+    let Some(inner) = option_inner_type(&field.ty) else {
+        return syn::Error::new_spanned(&field.ty, "lazy columns must be of type Option<T>")
+            .to_compile_error();
+    };
+    let column = field.name.as_str();
+    let ident = syn::Ident::new(column, proc_macro2::Span::call_site());
+    let method = quote::format_ident!("load_{}", column);
+    let doc = format!("Load the lazy `{column}` column from the database");
+    quote! {
+        #[doc = #doc]
+        pub async fn #method(
+            &mut self,
+            db: &libsql_orm::Database,
+        ) -> libsql_orm::Result<Option<&#inner>> {
+            self.#ident = libsql_orm::Model::load_column(self, #column, db).await?.#ident;
+            Ok(self.#ident.as_ref())
+        }
+    }
+}
+
+/// Whether a field is a unique, non-primary-key column
+fn is_unique(field: &FieldInfo) -> bool {
+    let upper = field.definition.to_uppercase();
+    let primary_key =
+        field.attrs.primary_key || upper.contains("PRIMARY KEY") || field.name == "id";
+    (field.attrs.unique || upper.contains("UNIQUE")) && !primary_key
+}
+
+/// Generate the `find_by_<field>` finder for a unique column
+fn unique_finder(field: &FieldInfo) -> proc_macro2::TokenStream {
+    let column = field.name.as_str();
+    let ident = syn::Ident::new(column, proc_macro2::Span::call_site());
+    let method = quote::format_ident!("find_by_{}", column);
+    let doc = format!("Find the record with the given unique `{column}`");
+    let inner = option_inner_type(&field.ty).unwrap_or(&field.ty);
+    let param = match column_kind(inner) {
+        "Text" => quote! { &str },
+        "Blob" => quote! { &[u8] },
+        "Integer" | "Real" | "Boolean" => quote! { #inner },
+        _ => quote! { &#inner },
+    };
+    let to_value = if column_kind(inner) == "Blob" {
+        quote! { libsql_orm::Value::from(#ident) }
+    } else {
+        quote! { libsql_orm::Value::from(serde_json::to_value(#ident)?) }
+    };
+    quote! {
+        #[doc = #doc]
+        pub async fn #method(
+            #ident: #param,
+            db: &libsql_orm::Database,
+        ) -> libsql_orm::Result<Option<Self>> {
+            let value = #to_value;
+            <Self as libsql_orm::Model>::find_one(
+                libsql_orm::FilterOperator::Single(libsql_orm::Filter::eq(#column, value)),
+                db,
+            )
+            .await
+        }
+    }
+}
+
+/// Generate `created_between` / `updated_since` for conventional timestamp columns
+fn timestamp_finder(field: &FieldInfo) -> Option<proc_macro2::TokenStream> {
+    if column_kind(&field.ty) != "DateTime" {
+        return None;
+    }
+    let column = field.name.as_str();
+    let inner = option_inner_type(&field.ty).unwrap_or(&field.ty);
+    let ordered = quote! {
+        <Self as libsql_orm::Model>::query_builder()
+            .r#where(libsql_orm::FilterOperator::Single(filter))
+            .order_by(libsql_orm::Sort::new(#column, libsql_orm::SortOrder::Asc))
+            .execute_models::<Self>(db)
+            .await
+    };
+    match column {
+        "created_at" => Some(quote! {
+            /// Find records created between `start` and `end` (inclusive), oldest first
+            pub async fn created_between(
+                start: &#inner,
+                end: &#inner,
+                db: &libsql_orm::Database,
+            ) -> libsql_orm::Result<Vec<Self>> {
+                let filter = libsql_orm::Filter::between(
+                    #column,
+                    libsql_orm::Value::from(serde_json::to_value(start)?),
+                    libsql_orm::Value::from(serde_json::to_value(end)?),
+                );
+                #ordered
+            }
+        }),
+        "updated_at" => Some(quote! {
+            /// Find records updated at or after `since`, least recently updated first
+            pub async fn updated_since(
+                since: &#inner,
+                db: &libsql_orm::Database,
+            ) -> libsql_orm::Result<Vec<Self>> {
+                let filter = libsql_orm::Filter::ge(
+                    #column,
+                    libsql_orm::Value::from(serde_json::to_value(since)?),
+                );
+                #ordered
+            }
+        }),
+        _ => None,
+    }
+}
+
+/// Pick the `DateTimeTarget` variant for a chrono field type
+fn datetime_target(ty: &Type) -> Option<&'static str> {
+    let ty = option_inner_type(ty).unwrap_or(ty);
+    if let Type::Path(type_path) = ty {
+        if let Some(segment) = type_path.path.segments.last() {
+            return match segment.ident.to_string().as_str() {
+                "DateTime" => Some("Zoned"),
+                "NaiveDateTime" => Some("Naive"),
+                "NaiveDate" => Some("Date"),
+                _ => None,
+            };
+        }
+    }
+    None
 }
 
 /// Extract table name from struct attributes
@@ -383,6 +1195,152 @@ fn is_boolean_type(ty: &Type) -> bool {
     false
 }
 
+/// Derive macro for read-only projection structs
+///
+/// Implements `libsql_orm::Projection` for a struct holding a subset of a
+/// table's columns, or columns of a join. The struct needs `Deserialize`.
+///
+/// # Attributes:
+/// - `#[table_name("posts")]` - Table the projection reads from (required)
+/// - `#[projection(inner_join("users", "users.id = posts.author_id"))]` and
+///   `left_join(...)` - Join another table; unqualified fields are then read
+///   from the projection's table
+/// - `#[projection(column = "users.name")]` on a field - Read the field from
+///   another column or expression
+///
+/// # Examples:
+///
+/// ```rust
+/// use libsql_orm::Projection;
+/// use serde::Deserialize;
+///
+/// #[derive(Projection, Deserialize)]
+/// #[table_name("posts")]
+/// #[projection(left_join("users", "users.id = posts.author_id"))]
+/// struct PostRow {
+///     pub id: i64,
+///     pub title: String,
+///     #[projection(column = "users.name")]
+///     pub author: Option<String>,
+/// }
+///
+/// // let rows = PostRow::find_where(filter, &db).await?;
+/// ```
+#[proc_macro_derive(Projection, attributes(table_name, projection, orm_column))]
+pub fn derive_projection(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let Some(table_name) = extract_table_name(&input.attrs) else {
+        return syn::Error::new_spanned(name, "Projection requires #[table_name(\"...\")]")
+            .to_compile_error()
+            .into();
+    };
+    let named = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return syn::Error::new_spanned(name, "Projection requires named fields")
+                    .to_compile_error()
+                    .into()
+            }
+        },
+        _ => {
+            return syn::Error::new_spanned(name, "Projection can only be derived for structs")
+                .to_compile_error()
+                .into()
+        }
+    };
+
+    let mut joins = Vec::new();
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("projection"))
+    {
+        let parsed = attr.parse_nested_meta(|meta| {
+            let kind = if meta.path.is_ident("inner_join") {
+                quote! { libsql_orm::JoinType::Inner }
+            } else if meta.path.is_ident("left_join") {
+                quote! { libsql_orm::JoinType::Left }
+            } else {
+                return Err(meta.error("expected `inner_join` or `left_join`"));
+            };
+            let content;
+            syn::parenthesized!(content in meta.input);
+            let table: syn::LitStr = content.parse()?;
+            content.parse::<syn::Token![,]>()?;
+            let condition: syn::LitStr = content.parse()?;
+            joins.push(quote! { .join(#kind, #table, #condition) });
+            Ok(())
+        });
+        if let Err(err) = parsed {
+            return err.to_compile_error().into();
+        }
+    }
+
+    let mut columns = Vec::with_capacity(named.len());
+    for field in named {
+        let ident = field.ident.as_ref().unwrap().to_string();
+        let mut column = None;
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("projection"))
+        {
+            let parsed = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("column") {
+                    column = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `column = \"...\"`"))
+                }
+            });
+            if let Err(err) = parsed {
+                return err.to_compile_error().into();
+            }
+        }
+        columns.push(match column {
+            Some(column) => format!("{column} AS {ident}"),
+            None if !joins.is_empty() => format!("{table_name}.{ident} AS {ident}"),
+            None => ident,
+        });
+    }
+
+    let fields: Vec<FieldInfo> = named.iter().map(parse_field).collect();
+    let from_map_body = from_map_body(&fields.iter().collect::<Vec<_>>());
+    let query_builder = if joins.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            fn query_builder() -> libsql_orm::QueryBuilder {
+                libsql_orm::QueryBuilder::new(#table_name)
+                    .select(Self::columns())
+                    #(#joins)*
+            }
+        }
+    };
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics libsql_orm::Projection for #name #ty_generics #where_clause {
+            fn table_name() -> &'static str {
+                #table_name
+            }
+
+            fn columns() -> Vec<&'static str> {
+                vec![#(#columns),*]
+            }
+
+            fn from_map(map: std::collections::HashMap<String, libsql_orm::Value>) -> libsql_orm::Result<Self> {
+                #from_map_body
+            }
+
+            #query_builder
+        }
+    }
+    .into()
+}
+
 /// Macro to generate migration from a model
 ///
 /// Creates a migration instance from a model's schema definition. The migration
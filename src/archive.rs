@@ -0,0 +1,68 @@
+//! Moving rows to cold `<table>_archive` tables
+
+use crate::{Database, Result};
+
+/// Name of the archive table for `table`
+pub(crate) fn archive_table(table: &str) -> String {
+    format!("{table}_archive")
+}
+
+/// Column names and declared types of a table, in order
+async fn table_columns(db: &Database, table: &str) -> Result<Vec<(String, String)>> {
+    let mut rows = db
+        .query(
+            "SELECT name, type FROM pragma_table_info(?) ORDER BY cid",
+            vec![libsql::Value::Text(table.to_string())],
+        )
+        .await?;
+    let mut columns = Vec::new();
+    while let Some(row) = rows.next().await? {
+        columns.push((row.get::<String>(0)?, row.get::<String>(1)?));
+    }
+    Ok(columns)
+}
+
+/// Move the rows of `table` matching `where_sql` into its archive table
+///
+/// The archive table is created on first use with the same columns, without
+/// constraints, and gains any columns added to `table` since. The copy and
+/// the delete run in one transaction. Returns the number of rows moved.
+pub(crate) async fn archive_rows(
+    db: &Database,
+    table: &str,
+    where_sql: &str,
+    params: Vec<libsql::Value>,
+) -> Result<u64> {
+    let archive = archive_table(table);
+    db.transaction(|tx| async move {
+        tx.execute(
+            &format!("CREATE TABLE IF NOT EXISTS {archive} AS SELECT * FROM {table} WHERE 0"),
+            vec![],
+        )
+        .await?;
+        let columns = table_columns(tx, table).await?;
+        let archived = table_columns(tx, &archive).await?;
+        for (name, sql_type) in &columns {
+            if !archived.iter().any(|(archived, _)| archived == name) {
+                tx.execute(
+                    &format!("ALTER TABLE {archive} ADD COLUMN {name} {sql_type}"),
+                    vec![],
+                )
+                .await?;
+            }
+        }
+
+        let names: Vec<&str> = columns.iter().map(|(name, _)| name.as_str()).collect();
+        let names = names.join(", ");
+        tx.execute(
+            &format!("INSERT INTO {archive} ({names}) SELECT {names} FROM {table}{where_sql}"),
+            params.clone(),
+        )
+        .await?;
+        let moved = tx
+            .execute(&format!("DELETE FROM {table}{where_sql}"), params)
+            .await?;
+        Ok(moved)
+    })
+    .await
+}
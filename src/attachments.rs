@@ -0,0 +1,329 @@
+//! File attachments stored in object storage
+//!
+//! File bytes go to an object store, such as a Cloudflare R2 bucket binding,
+//! while their metadata is kept in the database as [`Attachment`] rows that
+//! point at the owning record:
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "cloudflare")]
+//! # async fn example(env: worker::Env, db: &libsql_orm::Database, post: &impl libsql_orm::Model) -> libsql_orm::Result<()> {
+//! use libsql_orm::attachments::Attachments;
+//!
+//! let bucket = env.bucket("UPLOADS").unwrap();
+//! let attachments = Attachments::new(bucket);
+//! attachments.setup(db).await?;
+//!
+//! let file = attachments
+//!     .attach(post, "cover.png", "image/png", vec![0x89, 0x50, 0x4e, 0x47], db)
+//!     .await?;
+//! let bytes = attachments.read(&file).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Deleting an owner with [`Attachments::delete_owner`] removes its files as
+//! well. Owners deleted any other way leave orphaned attachments behind, which
+//! [`Attachments::cleanup_orphans`] removes.
+//!
+//! R2 bindings write objects directly and cannot create presigned URLs. To let
+//! clients upload or download through presigned URLs, sign [`Attachment::key`]
+//! with R2's S3-compatible API.
+//!
+//! Requires the `attachments` feature; the R2 store also requires `cloudflare`.
+
+use crate::{Database, Error, Filter, FilterOperator, Model, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Metadata of a stored file
+#[derive(Model, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[table_name("attachments")]
+pub struct Attachment {
+    #[orm_column(type = "INTEGER PRIMARY KEY AUTOINCREMENT")]
+    pub id: Option<i64>,
+    /// Table of the owning record
+    pub owner_table: String,
+    /// Primary key of the owning record
+    pub owner_id: i64,
+    /// Object key in the store
+    #[orm_column(not_null, unique)]
+    pub key: String,
+    /// Original file name
+    pub filename: String,
+    /// MIME type of the file
+    pub content_type: String,
+    /// Size in bytes
+    pub size: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Object storage holding attachment bytes
+#[allow(async_fn_in_trait)]
+pub trait AttachmentStore {
+    /// Store `bytes` under `key`
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> Result<()>;
+
+    /// Read the bytes stored under `key`, if any
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Delete the objects stored under `keys`
+    async fn delete(&self, keys: &[String]) -> Result<()>;
+}
+
+#[cfg(feature = "cloudflare")]
+impl AttachmentStore for worker::Bucket {
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> Result<()> {
+        let metadata = worker::HttpMetadata {
+            content_type: Some(content_type.to_string()),
+            ..Default::default()
+        };
+        worker::Bucket::put(self, key, bytes)
+            .http_metadata(metadata)
+            .execute()
+            .await
+            .map_err(|e| Error::AnyhowError(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let object = worker::Bucket::get(self, key)
+            .execute()
+            .await
+            .map_err(|e| Error::AnyhowError(e.to_string()))?;
+        match object.as_ref().and_then(|object| object.body()) {
+            Some(body) => body
+                .bytes()
+                .await
+                .map(Some)
+                .map_err(|e| Error::AnyhowError(e.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    async fn delete(&self, keys: &[String]) -> Result<()> {
+        if keys.is_empty() {
+            return Ok(());
+        }
+        self.delete_multiple(keys.iter().map(String::as_str).collect())
+            .await
+            .map_err(|e| Error::AnyhowError(e.to_string()))
+    }
+}
+
+/// In-memory store, for tests and local development
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    objects: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl MemoryStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keys of the stored objects, sorted
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.objects.lock().unwrap().keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+}
+
+impl AttachmentStore for MemoryStore {
+    async fn put(&self, key: &str, bytes: Vec<u8>, _content_type: &str) -> Result<()> {
+        self.objects.lock().unwrap().insert(key.to_string(), bytes);
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.objects.lock().unwrap().get(key).cloned())
+    }
+
+    async fn delete(&self, keys: &[String]) -> Result<()> {
+        let mut objects = self.objects.lock().unwrap();
+        for key in keys {
+            objects.remove(key);
+        }
+        Ok(())
+    }
+}
+
+/// Attachment operations over an object store
+#[derive(Debug)]
+pub struct Attachments<S> {
+    store: S,
+    prefix: String,
+}
+
+impl<S: AttachmentStore> Attachments<S> {
+    /// Create attachment helpers storing objects in `store`
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            prefix: "attachments".to_string(),
+        }
+    }
+
+    /// Set the prefix of object keys, `attachments` by default
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Get the underlying store
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Create the `attachments` table if it does not exist
+    pub async fn setup(&self, db: &Database) -> Result<()> {
+        db.execute(&Attachment::migration_sql(), vec![]).await?;
+        db.execute(
+            "CREATE INDEX IF NOT EXISTS idx_attachments_owner ON attachments (owner_table, owner_id)",
+            vec![],
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Store a file and attach it to `owner`
+    ///
+    /// The bytes are written first; if recording the metadata fails, the
+    /// object is deleted again.
+    pub async fn attach<M: Model>(
+        &self,
+        owner: &M,
+        filename: &str,
+        content_type: &str,
+        bytes: Vec<u8>,
+        db: &Database,
+    ) -> Result<Attachment> {
+        let owner_id = owner_id(owner)?;
+        let key = format!(
+            "{}/{}/{owner_id}/{}-{filename}",
+            self.prefix,
+            M::table_name(),
+            uuid::Uuid::new_v4()
+        );
+        let attachment = Attachment {
+            id: None,
+            owner_table: M::table_name().to_string(),
+            owner_id,
+            key: key.clone(),
+            filename: filename.to_string(),
+            content_type: content_type.to_string(),
+            size: bytes.len() as i64,
+            created_at: Utc::now(),
+        };
+
+        self.store.put(&key, bytes, content_type).await?;
+        let stored = async {
+            let (sql, params) = attachment.insert_statement()?;
+            db.execute(&sql, params).await?;
+            Attachment::find_by_key(&key, db).await
+        }
+        .await;
+        match stored {
+            Ok(Some(attachment)) => Ok(attachment),
+            Ok(None) => Err(Error::NotFound(format!(
+                "Attachment {key} was not recorded"
+            ))),
+            Err(e) => {
+                let _ = self.store.delete(&[key]).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Get the attachments of `owner`, oldest first
+    pub async fn list<M: Model>(&self, owner: &M, db: &Database) -> Result<Vec<Attachment>> {
+        let filter = FilterOperator::And(vec![
+            FilterOperator::Single(Filter::eq("owner_table", M::table_name())),
+            FilterOperator::Single(Filter::eq("owner_id", owner_id(owner)?)),
+        ]);
+        Attachment::query_builder()
+            .r#where(filter)
+            .order_by(crate::Sort::asc("id"))
+            .execute_models::<Attachment>(db)
+            .await
+    }
+
+    /// Read the bytes of an attachment
+    pub async fn read(&self, attachment: &Attachment) -> Result<Vec<u8>> {
+        self.store
+            .get(&attachment.key)
+            .await?
+            .ok_or_else(|| Error::NotFound(format!("Object {} not found", attachment.key)))
+    }
+
+    /// Delete an attachment and its object
+    pub async fn remove(&self, attachment: &Attachment, db: &Database) -> Result<()> {
+        attachment.delete(db).await?;
+        self.store
+            .delete(std::slice::from_ref(&attachment.key))
+            .await
+    }
+
+    /// Delete `owner` together with its attachments
+    ///
+    /// The owner row is deleted first, so a failure while deleting objects
+    /// leaves orphans for [`cleanup_orphans`](Self::cleanup_orphans) rather
+    /// than attachments whose owner still exists without its files.
+    pub async fn delete_owner<M: Model>(&self, owner: &M, db: &Database) -> Result<bool> {
+        let deleted = owner.delete(db).await?;
+        let attachments = self.list(owner, db).await?;
+        self.delete_all(&attachments, db).await?;
+        Ok(deleted)
+    }
+
+    /// Delete attachments whose owner no longer exists
+    ///
+    /// Only owners of registered models are checked. Returns the number of
+    /// attachments removed.
+    pub async fn cleanup_orphans(&self, db: &Database) -> Result<usize> {
+        let tables: Vec<String> = Attachment::distinct("owner_table", db).await?;
+        let mut removed = 0;
+        for table in tables {
+            let Some(model) = crate::find_model(&table) else {
+                continue;
+            };
+            let pk = model
+                .columns
+                .iter()
+                .find(|column| column.primary_key)
+                .map_or("id", |column| column.name);
+            let owners = crate::QueryBuilder::new(model.table_name).select(vec![pk]);
+            let orphans = Attachment::query_builder()
+                .r#where(FilterOperator::Single(Filter::eq("owner_table", table)))
+                .r#where(FilterOperator::Single(Filter::not_in_subquery(
+                    "owner_id", owners,
+                )))
+                .execute_models::<Attachment>(db)
+                .await?;
+            self.delete_all(&orphans, db).await?;
+            removed += orphans.len();
+        }
+        Ok(removed)
+    }
+
+    /// Delete the objects and then the metadata of `attachments`
+    async fn delete_all(&self, attachments: &[Attachment], db: &Database) -> Result<()> {
+        if attachments.is_empty() {
+            return Ok(());
+        }
+        let keys: Vec<String> = attachments.iter().map(|a| a.key.clone()).collect();
+        self.store.delete(&keys).await?;
+        let ids: Vec<i64> = attachments.iter().filter_map(|a| a.id).collect();
+        Attachment::delete_where(FilterOperator::Single(Filter::in_values("id", ids)), db).await?;
+        Ok(())
+    }
+}
+
+fn owner_id<M: Model>(owner: &M) -> Result<i64> {
+    owner.get_primary_key().ok_or_else(|| {
+        Error::Validation("Cannot attach files to a record without primary key".to_string())
+    })
+}
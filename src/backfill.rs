@@ -0,0 +1,179 @@
+//! Resumable data backfills
+//!
+//! A [`Backfill`] walks a table in primary key order, one batch at a time,
+//! and hands each batch of models to a callback together with a transaction.
+//! The batch's checkpoint (the last primary key) commits in the same
+//! transaction, under the id `backfill_<name>` in the migrations table, so a
+//! run cut short by a crash or a Worker time limit resumes after the last
+//! committed batch the next time it is started. Once every row has been
+//! visited the backfill is marked executed and later runs return immediately.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use libsql_orm::{Backfill, Database, Model};
+//! use std::time::Duration;
+//!
+//! async fn normalize_emails<M: Model>(db: &Database) -> libsql_orm::Result<()> {
+//!     let report = Backfill::<M>::new("normalize_emails")
+//!         .batch(500)
+//!         // Leave headroom before the Worker's CPU limit; the next
+//!         // invocation picks up where this one stopped
+//!         .time_budget(Duration::from_secs(20))
+//!         .run(db, |rows, tx| async move {
+//!             for row in rows {
+//!                 row.update(tx).await?;
+//!             }
+//!             Ok(())
+//!         })
+//!         .await?;
+//!     if !report.finished {
+//!         println!("{} rows done, more to go", report.rows);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use crate::migrations::{backfill_checkpoint, finish_backfill, init_table, save_backfill_progress};
+use crate::runtime::Stopwatch;
+use crate::{Database, Error, Filter, FilterOperator, Model, Result, Sort};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+/// Outcome of one [`Backfill::run`] call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BackfillReport {
+    /// Rows passed to the callback during this call
+    pub rows: u64,
+    /// Batches committed during this call
+    pub batches: u32,
+    /// Whether every row has now been processed
+    pub finished: bool,
+}
+
+/// A named, resumable pass over every row of a model's table
+pub struct Backfill<M: Model> {
+    name: String,
+    filter: Option<FilterOperator>,
+    batch_size: u32,
+    time_budget: Option<Duration>,
+    _model: PhantomData<M>,
+}
+
+impl<M: Model> Backfill<M> {
+    /// Create a backfill; the name identifies its checkpoint
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            filter: None,
+            batch_size: 100,
+            time_budget: None,
+            _model: PhantomData,
+        }
+    }
+
+    /// Set the number of rows per batch
+    pub fn batch(mut self, batch_size: u32) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Only visit rows matching a filter
+    pub fn filter(mut self, filter: FilterOperator) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Stop starting new batches once this much time has passed
+    ///
+    /// The batch in progress always finishes. Has no effect on wasm targets
+    /// without a clock.
+    pub fn time_budget(mut self, budget: Duration) -> Self {
+        self.time_budget = Some(budget);
+        self
+    }
+
+    fn id(&self) -> String {
+        format!("backfill_{}", self.name)
+    }
+
+    /// Process the remaining batches, committing a checkpoint after each
+    ///
+    /// The callback runs inside the batch's transaction; returning an error
+    /// rolls back that batch and its checkpoint and stops the run.
+    pub async fn run<'a, F, Fut>(&self, db: &'a Database, mut callback: F) -> Result<BackfillReport>
+    where
+        F: FnMut(Vec<M>, &'a Database) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        if self.batch_size == 0 {
+            return Err(Error::Validation(
+                "Backfill batch size must be positive".to_string(),
+            ));
+        }
+        init_table(db).await?;
+
+        let id = self.id();
+        let description = format!(
+            "backfill of {} in batches of {}",
+            M::table_name(),
+            self.batch_size
+        );
+        let mut report = BackfillReport::default();
+        let Some(mut checkpoint) = backfill_checkpoint(db, &id, &self.name, &description).await?
+        else {
+            report.finished = true;
+            return Ok(report);
+        };
+
+        let stopwatch = Stopwatch::start();
+        loop {
+            if let Some(budget) = self.time_budget {
+                if report.batches > 0 && stopwatch.elapsed() >= budget {
+                    return Ok(report);
+                }
+            }
+
+            let mut builder = M::query_builder()
+                .order_by(Sort::asc(M::primary_key()))
+                .limit(self.batch_size);
+            if let Some(last) = checkpoint {
+                builder =
+                    builder.r#where(FilterOperator::Single(Filter::gt(M::primary_key(), last)));
+            }
+            if let Some(filter) = &self.filter {
+                builder = builder.r#where(filter.clone());
+            }
+            let rows: Vec<M> = builder.execute_models(db).await?;
+            let Some(last) = rows.iter().filter_map(|row| row.get_primary_key()).max() else {
+                finish_backfill(db, &id).await?;
+                report.finished = true;
+                return Ok(report);
+            };
+
+            let count = rows.len() as u64;
+            let callback = &mut callback;
+            let id = &id;
+            db.transaction(|tx| async move {
+                callback(rows, tx).await?;
+                save_backfill_progress(tx, id, last).await
+            })
+            .await?;
+            checkpoint = Some(last);
+            report.rows += count;
+            report.batches += 1;
+        }
+    }
+
+    /// Forget the checkpoint, so the next run starts from the first row
+    pub async fn reset(&self, db: &Database) -> Result<()> {
+        init_table(db).await?;
+        db.execute(
+            "DELETE FROM migrations WHERE id = ?",
+            vec![libsql::Value::Text(self.id())],
+        )
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,327 @@
+//! Value coercion applied when reading rows into models
+//!
+//! Data written by other tools does not always use the representation serde
+//! expects. This module normalizes column values before they are deserialized,
+//! so models can load legacy or mixed-format rows.
+//!
+//! # Datetime Parsing
+//!
+//! Datetime columns are parsed with a configurable chain of formats. Each
+//! format is tried in order and the first match wins. Values without an
+//! offset are interpreted in the configured default offset (UTC unless set).
+//!
+//! ```rust
+//! use libsql_orm::coercion::{self, DateTimeFormat, DateTimeParsing};
+//!
+//! coercion::set_datetime_parsing(
+//!     DateTimeParsing::new()
+//!         .formats(vec![
+//!             DateTimeFormat::Rfc3339,
+//!             DateTimeFormat::Custom("%d/%m/%Y %H:%M".to_string()),
+//!             DateTimeFormat::EpochMillis,
+//!         ])
+//!         .strict(true),
+//! );
+//! ```
+//!
+//! In strict mode a value that matches none of the formats is an error. In the
+//! default lenient mode it is passed to serde unchanged.
+//!
+//! # Numeric Coercion
+//!
+//! SQLite stores values by their own type rather than the column's, so `1.0`
+//! can come back from an INTEGER column and `1` from a REAL one. Under the
+//! default [`NumericCoercion::Lossless`] policy such values are converted when
+//! no information is lost: a REAL with no fractional part is read into integer
+//! fields and an INTEGER of at most 2^53 in magnitude into float fields. Any
+//! other mismatch is reported as an error naming the column.
+//!
+//! [`NumericCoercion::Strict`] disables the conversion, so every mismatch is
+//! an error:
+//!
+//! ```rust
+//! use libsql_orm::coercion::{self, NumericCoercion};
+//!
+//! coercion::set_numeric_coercion(NumericCoercion::Strict);
+//! ```
+//!
+//! # Binary Columns
+//!
+//! `Vec<u8>` fields are stored as BLOBs. Rows written as JSON arrays of bytes
+//! by earlier versions are still read, through [`coerce_blob`].
+
+use crate::{ColumnKind, Error, Result, Value};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeZone};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+/// A datetime representation accepted by the parse chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DateTimeFormat {
+    /// RFC 3339 / ISO 8601 with offset, e.g. `2024-01-31T12:00:00+02:00`
+    Rfc3339,
+    /// SQLite's `YYYY-MM-DD HH:MM:SS[.fff]`, with a space or `T` separator
+    SqlDateTime,
+    /// A bare `YYYY-MM-DD` date, read as midnight
+    Date,
+    /// Seconds since the Unix epoch, as an integer, real or numeric string
+    EpochSeconds,
+    /// Milliseconds since the Unix epoch, as an integer, real or numeric string
+    EpochMillis,
+    /// A chrono format string, with or without an offset specifier
+    Custom(String),
+}
+
+/// Where a parsed datetime is written to
+///
+/// Matches the chrono type of the model field, so the normalized value is in
+/// the representation that type deserializes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateTimeTarget {
+    /// `DateTime<Tz>` fields, written as RFC 3339
+    Zoned,
+    /// `NaiveDateTime` fields, written as UTC wall-clock time
+    Naive,
+    /// `NaiveDate` fields
+    Date,
+}
+
+/// Configuration of the datetime parse chain
+#[derive(Debug, Clone)]
+pub struct DateTimeParsing {
+    formats: Vec<DateTimeFormat>,
+    strict: bool,
+    default_offset: FixedOffset,
+}
+
+impl Default for DateTimeParsing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DateTimeParsing {
+    /// Create the default chain: RFC 3339, SQL datetime, date, epoch seconds
+    pub fn new() -> Self {
+        Self {
+            formats: vec![
+                DateTimeFormat::Rfc3339,
+                DateTimeFormat::SqlDateTime,
+                DateTimeFormat::Date,
+                DateTimeFormat::EpochSeconds,
+            ],
+            strict: false,
+            default_offset: FixedOffset::east_opt(0).unwrap(),
+        }
+    }
+
+    /// Replace the formats that are tried, in order
+    pub fn formats(mut self, formats: Vec<DateTimeFormat>) -> Self {
+        self.formats = formats;
+        self
+    }
+
+    /// Fail on values that match none of the formats
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Set the offset assumed for values that carry none
+    pub fn default_offset(mut self, offset: FixedOffset) -> Self {
+        self.default_offset = offset;
+        self
+    }
+
+    /// Whether strict mode is enabled
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Parse a value with the chain, returning `None` if no format matches
+    pub fn parse(&self, value: &Value) -> Option<DateTime<FixedOffset>> {
+        self.formats
+            .iter()
+            .find_map(|format| self.parse_with(format, value))
+    }
+
+    fn parse_with(&self, format: &DateTimeFormat, value: &Value) -> Option<DateTime<FixedOffset>> {
+        let offset = self.default_offset;
+        match format {
+            DateTimeFormat::EpochSeconds => epoch(value, 1.0).and_then(from_epoch),
+            DateTimeFormat::EpochMillis => epoch(value, 1000.0).and_then(from_epoch),
+            _ => {
+                let Value::Text(text) = value else {
+                    return None;
+                };
+                let text = text.trim();
+                match format {
+                    DateTimeFormat::Rfc3339 => DateTime::parse_from_rfc3339(text).ok(),
+                    DateTimeFormat::SqlDateTime => ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+                        .iter()
+                        .find_map(|f| NaiveDateTime::parse_from_str(text, f).ok())
+                        .and_then(|naive| offset.from_local_datetime(&naive).single()),
+                    DateTimeFormat::Date => NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                        .ok()
+                        .and_then(|date| date.and_hms_opt(0, 0, 0))
+                        .and_then(|naive| offset.from_local_datetime(&naive).single()),
+                    DateTimeFormat::Custom(f) => {
+                        DateTime::parse_from_str(text, f).ok().or_else(|| {
+                            NaiveDateTime::parse_from_str(text, f)
+                                .ok()
+                                .and_then(|naive| offset.from_local_datetime(&naive).single())
+                        })
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+/// Read an epoch value as fractional seconds, given the number of units per second
+fn epoch(value: &Value, per_second: f64) -> Option<f64> {
+    let raw = match value {
+        Value::Integer(i) => *i as f64,
+        Value::Real(f) => *f,
+        Value::Text(text) => text.trim().parse::<f64>().ok()?,
+        _ => return None,
+    };
+    Some(raw / per_second)
+}
+
+fn from_epoch(secs: f64) -> Option<DateTime<FixedOffset>> {
+    let whole = secs.floor();
+    let nanos = ((secs - whole) * 1e9).round() as u32;
+    DateTime::from_timestamp(whole as i64, nanos.min(999_999_999)).map(|dt| dt.fixed_offset())
+}
+
+static DATETIME_PARSING: RwLock<Option<DateTimeParsing>> = RwLock::new(None);
+
+/// Set the global datetime parse chain
+pub fn set_datetime_parsing(config: DateTimeParsing) {
+    *DATETIME_PARSING.write().unwrap() = Some(config);
+}
+
+/// Get the global datetime parse chain
+pub fn datetime_parsing() -> DateTimeParsing {
+    DATETIME_PARSING.read().unwrap().clone().unwrap_or_default()
+}
+
+/// Normalize a datetime column value for deserialization
+///
+/// Called by the `Model` derive for chrono fields. `NULL` is passed through.
+pub fn coerce_datetime(column: &str, value: Value, target: DateTimeTarget) -> Result<Value> {
+    if value == Value::Null {
+        return Ok(value);
+    }
+
+    let config = DATETIME_PARSING.read().unwrap();
+    let default_config;
+    let config = match config.as_ref() {
+        Some(config) => config,
+        None => {
+            default_config = DateTimeParsing::new();
+            &default_config
+        }
+    };
+
+    match config.parse(&value) {
+        Some(parsed) => Ok(Value::Text(match target {
+            DateTimeTarget::Zoned => parsed.to_rfc3339(),
+            DateTimeTarget::Naive => parsed
+                .naive_utc()
+                .format("%Y-%m-%dT%H:%M:%S%.f")
+                .to_string(),
+            DateTimeTarget::Date => parsed.date_naive().format("%Y-%m-%d").to_string(),
+        })),
+        None if config.strict => Err(Error::Serialization(format!(
+            "Column '{column}' has unrecognized datetime value: {value:?}"
+        ))),
+        None => Ok(value),
+    }
+}
+
+/// Policy for reading integers into float fields and floats into integer fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumericCoercion {
+    /// Convert between integer and float when the value is preserved exactly
+    #[default]
+    Lossless,
+    /// Never convert; a stored type that differs from the field is an error
+    Strict,
+}
+
+/// Largest integer magnitude an `f64` represents exactly
+const MAX_EXACT_FLOAT_INT: i64 = 1 << 53;
+
+static STRICT_NUMERIC: AtomicBool = AtomicBool::new(false);
+
+/// Set the global numeric coercion policy
+pub fn set_numeric_coercion(policy: NumericCoercion) {
+    STRICT_NUMERIC.store(policy == NumericCoercion::Strict, Ordering::Relaxed);
+}
+
+/// Get the global numeric coercion policy
+pub fn numeric_coercion() -> NumericCoercion {
+    if STRICT_NUMERIC.load(Ordering::Relaxed) {
+        NumericCoercion::Strict
+    } else {
+        NumericCoercion::Lossless
+    }
+}
+
+/// Normalize a numeric column value for deserialization
+///
+/// Called by the `Model` derive for integer and float fields, using the global
+/// policy.
+pub fn coerce_number(column: &str, value: Value, kind: ColumnKind) -> Result<Value> {
+    numeric_coercion().coerce(column, value, kind)
+}
+
+impl NumericCoercion {
+    /// Convert a value read from `column` for a field of the given kind
+    ///
+    /// Values that already match `kind`, and non-numeric values, are passed
+    /// through.
+    pub fn coerce(self, column: &str, value: Value, kind: ColumnKind) -> Result<Value> {
+        let lenient = self == NumericCoercion::Lossless;
+        match (kind, value) {
+            (ColumnKind::Integer, Value::Real(f)) => {
+                if lenient && f.fract() == 0.0 && f.abs() <= MAX_EXACT_FLOAT_INT as f64 {
+                    Ok(Value::Integer(f as i64))
+                } else {
+                    Err(Error::Serialization(format!(
+                        "Column '{column}' holds REAL value {f}, which cannot be read as an integer"
+                    )))
+                }
+            }
+            (ColumnKind::Real, Value::Integer(i)) => {
+                if lenient && i.abs() <= MAX_EXACT_FLOAT_INT {
+                    Ok(Value::Real(i as f64))
+                } else {
+                    Err(Error::Serialization(format!(
+                        "Column '{column}' holds INTEGER value {i}, which cannot be read as a float"
+                    )))
+                }
+            }
+            (_, value) => Ok(value),
+        }
+    }
+}
+
+/// Normalize a binary column value to a BLOB
+///
+/// Called by the `Model` derive for `Vec<u8>` fields, both when writing and
+/// when reading. Text holding a JSON array of bytes, the way serde renders a
+/// `Vec<u8>`, is converted to a BLOB; other values are passed through.
+pub fn coerce_blob(column: &str, value: Value) -> Result<Value> {
+    match value {
+        Value::Text(text) if text.starts_with('[') => serde_json::from_str::<Vec<u8>>(&text)
+            .map(Value::Blob)
+            .map_err(|e| {
+                Error::Serialization(format!("Column '{column}' does not hold bytes: {e}"))
+            }),
+        value => Ok(value),
+    }
+}
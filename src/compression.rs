@@ -0,0 +1,104 @@
+//! Transparent compression of large text columns
+//!
+//! Columns marked `#[orm_column(compressed = "zstd")]` are compressed when
+//! written and decompressed when read, which keeps large JSON or HTML payloads
+//! within row-size and storage limits:
+//!
+//! ```rust
+//! use libsql_orm::Model;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+//! struct Page {
+//!     pub id: Option<i64>,
+//!     #[orm_column(compressed = "zstd")]
+//!     pub html: String,
+//! }
+//! ```
+//!
+//! Compressed values are stored as BLOBs starting with [`MAGIC`], followed by
+//! a zstd frame. Values without the prefix are read as they are, so existing
+//! plain-text rows stay readable and a column can be switched to compression
+//! without migrating data. Values that do not shrink are stored uncompressed.
+//!
+//! Compression requires the `compression` feature. Compressed columns cannot
+//! be filtered or sorted on in SQL.
+
+use crate::{Error, Result, Value};
+
+/// Prefix marking a compressed value
+pub const MAGIC: &[u8] = b"\0zst";
+
+/// Compress a text value written to `column`
+///
+/// Called by the `Model` derive for compressed columns. Non-text values are
+/// passed through.
+pub fn compress_value(column: &str, value: Value) -> Result<Value> {
+    let Value::Text(text) = value else {
+        return Ok(value);
+    };
+    let compressed = compress(column, text.as_bytes())?;
+    if compressed.len() + MAGIC.len() >= text.len() {
+        return Ok(Value::Text(text));
+    }
+    let mut bytes = Vec::with_capacity(MAGIC.len() + compressed.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&compressed);
+    Ok(Value::Blob(bytes))
+}
+
+/// Decompress a value read from `column`
+///
+/// Called by the `Model` derive for compressed columns. BLOBs with the
+/// [`MAGIC`] prefix are decompressed, other BLOBs are read as UTF-8 text and
+/// all other values are passed through.
+pub fn decompress_value(column: &str, value: Value) -> Result<Value> {
+    let Value::Blob(bytes) = value else {
+        return Ok(value);
+    };
+    let bytes = match bytes.strip_prefix(MAGIC) {
+        Some(compressed) => decompress(column, compressed)?,
+        None => bytes,
+    };
+    String::from_utf8(bytes).map(Value::Text).map_err(|e| {
+        Error::Serialization(format!("Column '{column}' does not hold UTF-8 text: {e}"))
+    })
+}
+
+#[cfg(feature = "compression")]
+fn compress(_column: &str, bytes: &[u8]) -> Result<Vec<u8>> {
+    use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+    Ok(compress_to_vec(bytes, CompressionLevel::Fastest))
+}
+
+#[cfg(feature = "compression")]
+fn decompress(column: &str, bytes: &[u8]) -> Result<Vec<u8>> {
+    use std::io::Read;
+    let error = |e: &dyn std::fmt::Display| {
+        Error::Serialization(format!(
+            "Column '{column}' holds an invalid zstd frame: {e}"
+        ))
+    };
+    let mut input = bytes;
+    let mut decoder = ruzstd::decoding::StreamingDecoder::new(&mut input).map_err(|e| error(&e))?;
+    let mut output = Vec::new();
+    decoder.read_to_end(&mut output).map_err(|e| error(&e))?;
+    Ok(output)
+}
+
+#[cfg(not(feature = "compression"))]
+fn compress(column: &str, _bytes: &[u8]) -> Result<Vec<u8>> {
+    Err(missing_feature(column))
+}
+
+#[cfg(not(feature = "compression"))]
+fn decompress(column: &str, _bytes: &[u8]) -> Result<Vec<u8>> {
+    Err(missing_feature(column))
+}
+
+#[cfg(not(feature = "compression"))]
+fn missing_feature(column: &str) -> Error {
+    Error::Validation(format!(
+        "Column '{column}' is compressed, which requires the `compression` feature"
+    ))
+}
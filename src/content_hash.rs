@@ -0,0 +1,85 @@
+//! Content-hash columns for ETags and change detection
+//!
+//! A column marked `#[orm_column(content_hash(of("title", "content")))]` holds
+//! a hash of the listed columns. The hash is recomputed whenever the record is
+//! written, so it can serve as an HTTP ETag or let clients find out cheaply
+//! which records changed:
+//!
+//! ```rust
+//! use libsql_orm::Model;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+//! struct Article {
+//!     pub id: Option<i64>,
+//!     pub title: String,
+//!     pub content: String,
+//!     #[orm_column(content_hash(of("title", "content")))]
+//!     pub etag: Option<String>,
+//! }
+//! ```
+//!
+//! The hash is a 128-bit FNV-1a over the column values, rendered as 32
+//! lowercase hex digits. It is stable across platforms and releases, but it is
+//! not a cryptographic hash. The hash field of the struct itself is only
+//! refreshed when the record is read back; use
+//! [`Model::content_hash`](crate::Model::content_hash) to get the current hash
+//! of an in-memory record.
+//!
+//! [`Model::update_where`](crate::Model::update_where) and
+//! [`Model::increment`](crate::Model::increment) refuse to set the columns a
+//! hash is computed from, and LWW merges recompute it. Raw SQL does not
+//! maintain the hash.
+
+use crate::Value;
+
+const OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+/// Hash a list of column values
+///
+/// Called by the `Model` derive for content-hash columns. Booleans hash like
+/// the integers they are stored as, so a hash computed before writing matches
+/// one computed from the stored row.
+pub fn hash_values(values: &[&Value]) -> String {
+    let mut hasher = Fnv128(OFFSET_BASIS);
+    for value in values {
+        match value {
+            Value::Null => hasher.write(&[0]),
+            Value::Integer(i) => {
+                hasher.write(&[1]);
+                hasher.write(&i.to_le_bytes());
+            }
+            Value::Boolean(b) => {
+                hasher.write(&[1]);
+                hasher.write(&i64::from(*b).to_le_bytes());
+            }
+            Value::Real(f) => {
+                hasher.write(&[2]);
+                hasher.write(&f.to_bits().to_le_bytes());
+            }
+            Value::Text(s) => {
+                hasher.write(&[3]);
+                hasher.write(&(s.len() as u64).to_le_bytes());
+                hasher.write(s.as_bytes());
+            }
+            Value::Blob(b) => {
+                hasher.write(&[4]);
+                hasher.write(&(b.len() as u64).to_le_bytes());
+                hasher.write(b);
+            }
+        }
+    }
+    format!("{:032x}", hasher.0)
+}
+
+struct Fnv128(u128);
+
+impl Fnv128 {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u128::from(*byte);
+            self.0 = self.0.wrapping_mul(PRIME);
+        }
+    }
+}
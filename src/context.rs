@@ -0,0 +1,85 @@
+//! Request attribution
+//!
+//! A [`RequestContext`] names the tenant, user and request a database handle
+//! is working for. Attach it with [`Database::with_context`] and it is added to
+//! every statement as a trailing SQL comment and to the statement logs, so
+//! slow or failing queries can be traced back to who issued them.
+//!
+//! ```no_run
+//! use libsql_orm::{Database, Model, RequestContext};
+//!
+//! async fn handle<M: Model>(db: &Database, tenant: &str, request_id: &str) -> libsql_orm::Result<Vec<M>> {
+//!     let db = db.with_context(RequestContext::new().tenant(tenant).request(request_id));
+//!     // Runs `SELECT * FROM ... /* tenant=... request=... */`
+//!     M::find_all(&db).await
+//! }
+//! ```
+//!
+//! [`Database::with_context`]: crate::Database::with_context
+
+use std::fmt;
+
+/// Tenant, user and request a database handle is working for
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestContext {
+    /// Tenant the request belongs to
+    pub tenant_id: Option<String>,
+    /// User that made the request
+    pub user_id: Option<String>,
+    /// Identifier of the request, e.g. Cloudflare's `cf-ray`
+    pub request_id: Option<String>,
+}
+
+impl RequestContext {
+    /// Create an empty context
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the tenant id
+    pub fn tenant(mut self, tenant_id: impl Into<String>) -> Self {
+        self.tenant_id = Some(tenant_id.into());
+        self
+    }
+
+    /// Set the user id
+    pub fn user(mut self, user_id: impl Into<String>) -> Self {
+        self.user_id = Some(user_id.into());
+        self
+    }
+
+    /// Set the request id
+    pub fn request(mut self, request_id: impl Into<String>) -> Self {
+        self.request_id = Some(request_id.into());
+        self
+    }
+
+    /// The fields that are set, as `(name, value)` pairs
+    pub fn fields(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("tenant", &self.tenant_id),
+            ("user", &self.user_id),
+            ("request", &self.request_id),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.as_deref().map(|value| (name, value)))
+        .collect()
+    }
+
+    /// Whether no field is set
+    pub fn is_empty(&self) -> bool {
+        self.fields().is_empty()
+    }
+}
+
+/// Formats as `tenant=acme user=42 request=abc`, skipping unset fields
+impl fmt::Display for RequestContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields: Vec<String> = self
+            .fields()
+            .into_iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect();
+        f.write_str(&fields.join(" "))
+    }
+}
@@ -3,10 +3,24 @@
 //! This module handles the connection to libsql databases and provides
 //! query execution capabilities for Cloudflare Workers.
 
+use crate::runtime::Stopwatch;
+use crate::statement_cache::{StatementCache, StatementCacheStats, DEFAULT_CAPACITY};
+use crate::{Error, RequestContext, Result, RetryPolicy};
+use futures::{FutureExt, StreamExt, TryStreamExt};
 #[cfg(target_arch = "wasm32")]
 use libsql::wasm::{CloudflareSender, Connection, Rows};
 #[cfg(not(target_arch = "wasm32"))]
 use libsql::{Builder, Connection, Rows};
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(target_arch = "wasm32")]
+type Conn = Connection<CloudflareSender>;
+#[cfg(not(target_arch = "wasm32"))]
+type Conn = Connection;
 
 /// Database connection wrapper for libsql in Cloudflare Workers
 ///
@@ -32,19 +46,75 @@ pub struct Database {
     pub inner: Connection<CloudflareSender>,
     #[cfg(not(target_arch = "wasm32"))]
     pub inner: Connection,
+    /// Number of (possibly nested) transactions this handle has open, shared
+    /// with the handles derived inside them
+    tx_depth: Arc<AtomicUsize>,
+    /// Held by the transaction open on the connection, so that handles
+    /// outside it wait their turn
+    tx_lock: Arc<futures::lock::Mutex<()>>,
+    /// Whether any handle has a transaction open on the connection
+    tx_open: Arc<AtomicBool>,
+    /// Comment appended to every statement
+    tag: Option<String>,
+    /// Request the handle is working for
+    context: Option<Arc<RequestContext>>,
+    /// Prepared statements of the connection
+    statements: Arc<StatementCache>,
+    /// Priority of the handle's statements
+    hint: QueryHint,
+    /// The local database when this is an embedded replica
+    #[cfg(not(target_arch = "wasm32"))]
+    replica: Option<Arc<libsql::Database>>,
+    /// Read replica connections that reads are spread over
+    readers: Arc<Vec<Conn>>,
+    /// Round-robin position among the readers
+    next_reader: Arc<AtomicUsize>,
+    /// Whether reads stay on the primary
+    primary_only: bool,
+    /// When failed statements run again
+    retry: RetryPolicy,
+    /// Longest a statement may take
+    timeout: Option<Duration>,
+    /// Longest a statement of a background handle may take, if different
+    background_timeout: Option<Duration>,
 }
 
-#[cfg(target_arch = "wasm32")]
-impl From<Connection<CloudflareSender>> for Database {
-    fn from(inner: Connection<CloudflareSender>) -> Self {
-        Self { inner }
-    }
+/// How urgently a statement's result is needed
+///
+/// Request-path reads want a quick answer or a quick failure, while bulk
+/// background work can wait and retry. Handles carry a hint, set with
+/// [`Database::with_hint`] or per query with
+/// [`QueryBuilder::hint`](crate::QueryBuilder::hint), for the connection
+/// policies to tell the two apart.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum QueryHint {
+    /// Latency-critical work on a request path
+    #[default]
+    Interactive,
+    /// Bulk or deferred work that tolerates latency
+    Background,
 }
 
-#[cfg(not(target_arch = "wasm32"))]
-impl From<Connection> for Database {
-    fn from(inner: Connection) -> Self {
-        Self { inner }
+impl From<Conn> for Database {
+    fn from(inner: Conn) -> Self {
+        Self {
+            inner,
+            tx_depth: Arc::new(AtomicUsize::new(0)),
+            tx_lock: Arc::new(futures::lock::Mutex::new(())),
+            tx_open: Arc::new(AtomicBool::new(false)),
+            tag: None,
+            context: None,
+            statements: Arc::new(StatementCache::new(DEFAULT_CAPACITY)),
+            hint: QueryHint::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            replica: None,
+            readers: Arc::new(Vec::new()),
+            next_reader: Arc::new(AtomicUsize::new(0)),
+            primary_only: false,
+            retry: RetryPolicy::none(),
+            timeout: None,
+            background_timeout: None,
+        }
     }
 }
 
@@ -85,6 +155,197 @@ impl Database {
         conn.execute("SELECT 1", ()).await.map(|_| Self::from(conn))
     }
 
+    /// Connects to a primary database and its read replicas
+    ///
+    /// Reads (`SELECT` statements, such as those behind the `find_*` model
+    /// methods) are spread over the replicas in turn, while writes and
+    /// everything inside a transaction go to the primary at `url`. Replicas
+    /// can lag behind the primary; read through [`primary`](Self::primary)
+    /// when a read must see a preceding write. All URLs share the token.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::Database;
+    ///
+    /// async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let db = Database::new_connect_with_replicas(
+    ///         "libsql://your-db.turso.io",
+    ///         "your-auth-token",
+    ///         &["libsql://your-db-replica.turso.io"],
+    ///     )
+    ///     .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn new_connect_with_replicas(
+        url: &str,
+        token: &str,
+        replica_urls: &[&str],
+    ) -> std::result::Result<Self, libsql::Error> {
+        let mut db = Self::new_connect(url, token).await?;
+        for replica_url in replica_urls {
+            db = db.with_read_replica(Self::new_connect(replica_url, token).await?);
+        }
+        Ok(db)
+    }
+
+    /// Adds a read replica that reads are spread over
+    ///
+    /// See [`new_connect_with_replicas`](Self::new_connect_with_replicas).
+    /// Handles derived afterwards share the replicas.
+    pub fn with_read_replica(mut self, replica: Database) -> Self {
+        Arc::make_mut(&mut self.readers).push(replica.inner);
+        self
+    }
+
+    /// Creates a private in-memory database
+    ///
+    /// The database starts empty and disappears when the handle is dropped,
+    /// which makes it a fast stand-in for Turso in unit tests. Not available
+    /// on Cloudflare Workers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsql_orm::Database;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let db = Database::new_memory().await?;
+    /// db.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)", vec![])
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn new_memory() -> std::result::Result<Self, libsql::Error> {
+        let conn = Builder::new_local(":memory:").build().await?.connect()?;
+        Ok(Self::from(conn))
+    }
+
+    /// Opens a local SQLite file, created if missing, and sets `options` on
+    /// the connection
+    ///
+    /// Not available on Cloudflare Workers.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::{Database, DatabaseOptions, JournalMode};
+    ///
+    /// async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let options = DatabaseOptions::new().journal_mode(JournalMode::Wal);
+    ///     let db = Database::new_local("app.db", &options).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn new_local(
+        path: impl AsRef<std::path::Path>,
+        options: &crate::DatabaseOptions,
+    ) -> Result<Self> {
+        let conn = Builder::new_local(path.as_ref()).build().await?.connect()?;
+        let db = Self::from(conn);
+        db.apply_options(options).await?;
+        Ok(db)
+    }
+
+    /// Sets `options` on the connection with `PRAGMA` statements
+    ///
+    /// Use it right after opening an embedded replica. A journal mode the
+    /// database cannot switch to, such as WAL for an in-memory database, is
+    /// logged and left as it is. See [`options`](crate::options).
+    pub async fn apply_options(&self, options: &crate::DatabaseOptions) -> Result<()> {
+        for pragma in options.pragmas() {
+            let mut rows = self.inner.query(&pragma, ()).await?;
+            let Some(mode) = options
+                .journal_mode
+                .filter(|_| pragma.starts_with("PRAGMA journal_mode"))
+            else {
+                continue;
+            };
+            // The pragma answers with the journal mode in use
+            if let Some(row) = rows.next().await? {
+                let actual: String = row.get(0)?;
+                if !actual.eq_ignore_ascii_case(&mode.to_string()) {
+                    log::warn!("Journal mode {mode} was not applied; the database uses {actual}");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Opens an embedded replica of a remote database
+    ///
+    /// The replica lives in the SQLite file at `path`, created if missing.
+    /// Reads are served from it locally; writes go to the primary at `url`.
+    /// See [`replica`](crate::replica) and [`SyncConfig`](crate::SyncConfig) for when it syncs.
+    /// Not available on Cloudflare Workers.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::{Database, SyncConfig};
+    ///
+    /// async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let db = Database::new_replica(
+    ///         "local.db",
+    ///         "libsql://your-db.turso.io",
+    ///         "your-auth-token",
+    ///         SyncConfig::new(),
+    ///     )
+    ///     .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn new_replica(
+        path: impl AsRef<std::path::Path>,
+        url: &str,
+        token: &str,
+        config: crate::SyncConfig,
+    ) -> std::result::Result<Self, libsql::Error> {
+        let mut builder =
+            Builder::new_remote_replica(path.as_ref(), url.to_string(), token.to_string())
+                .read_your_writes(config.read_your_writes);
+        if let Some(interval) = config.interval {
+            builder = builder.sync_interval(interval);
+        }
+        let replica = builder.build().await?;
+        if config.sync_on_open {
+            replica.sync().await?;
+        }
+        let mut db = Self::from(replica.connect()?);
+        db.replica = Some(Arc::new(replica));
+        Ok(db)
+    }
+
+    /// Whether this is an embedded replica opened with
+    /// [`new_replica`](Self::new_replica)
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn is_replica(&self) -> bool {
+        self.replica.is_some()
+    }
+
+    /// Pulls the primary's latest changes into an embedded replica
+    ///
+    /// Returns the number of frames received. Fails on databases that are
+    /// not replicas.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn sync(&self) -> Result<usize> {
+        let replica = self
+            .replica
+            .as_ref()
+            .ok_or_else(|| Error::Connection("Database is not an embedded replica".to_string()))?;
+        let synced = replica.sync().await?;
+        log::debug!(
+            "Synced {} frames into embedded replica",
+            synced.frames_synced()
+        );
+        Ok(synced.frames_synced())
+    }
+
     /// Executes a SQL query with parameters
     ///
     /// # Arguments
@@ -113,7 +374,1014 @@ impl Database {
         &self,
         sql: &str,
         params: Vec<libsql::Value>,
-    ) -> Result<Rows, libsql::Error> {
-        self.inner.query(sql, params).await
+    ) -> std::result::Result<Rows, libsql::Error> {
+        let sql = self.tag_sql(sql);
+        let (sql, params) = (&*sql, &params);
+        self.run(sql, is_read(sql), || {
+            let conn = self.reader(sql).unwrap_or(&self.inner);
+            conn.query(sql, params.clone())
+        })
+        .await
+    }
+
+    /// The read replica to run `sql` on, if it is a read that may go to one
+    fn reader(&self, sql: &str) -> Option<&Conn> {
+        if self.readers.is_empty() || self.primary_only || self.in_transaction() || !is_read(sql) {
+            return None;
+        }
+        let next = self.next_reader.fetch_add(1, Ordering::Relaxed);
+        self.readers.get(next % self.readers.len())
+    }
+
+    /// Executes a SQL statement with parameters
+    ///
+    /// Returns the number of rows affected.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::Database;
+    ///
+    /// async fn execute_example(db: &Database) -> Result<(), Box<dyn std::error::Error>> {
+    ///     let affected = db.execute(
+    ///         "UPDATE users SET active = 0 WHERE age < ?",
+    ///         vec![libsql::Value::Integer(18)]
+    ///     ).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn execute(
+        &self,
+        sql: &str,
+        params: Vec<libsql::Value>,
+    ) -> std::result::Result<u64, libsql::Error> {
+        let sql = self.tag_sql(sql);
+        let (sql, params) = (&*sql, &params);
+        self.run(sql, is_read(sql), || {
+            self.execute_cached(sql, params.clone())
+        })
+        .await
+    }
+
+    /// Executes several statements given as one SQL text, like
+    /// [`execute`](Self::execute) does a single one
+    ///
+    /// Without a surrounding transaction, statements before a failing one
+    /// stay applied.
+    pub(crate) async fn execute_batch(&self, sql: &str) -> std::result::Result<(), libsql::Error> {
+        let sql = &*self.tag_sql(sql);
+        self.run(sql, false, || async move {
+            self.inner.execute_batch(sql).await.map(|_| ())
+        })
+        .await
+    }
+
+    /// Runs a statement with the handle's timeout, reconnects and retries,
+    /// recording metrics and logs for every attempt
+    ///
+    /// `attempt` sends the already tagged `sql` once; `read` says whether
+    /// the statement only reads.
+    async fn run<T, F>(
+        &self,
+        sql: &str,
+        read: bool,
+        attempt: impl Fn() -> F,
+    ) -> std::result::Result<T, libsql::Error>
+    where
+        F: Future<Output = std::result::Result<T, libsql::Error>>,
+    {
+        let (mut attempts, mut reconnected) = (1, false);
+        loop {
+            let stopwatch = Stopwatch::start();
+            let result = self.within_timeout(attempt()).await;
+            crate::metrics::record(sql, stopwatch.elapsed(), result.is_err());
+            self.log_statement(sql, result.as_ref().err());
+            match result {
+                Err(error) => match self
+                    .rerun_delay(&error, read, attempts, &mut reconnected)
+                    .await
+                {
+                    Some(delay) => crate::runtime::sleep(delay).await,
+                    None => return Err(error),
+                },
+                result => return result,
+            }
+            attempts += 1;
+        }
+    }
+
+    /// Runs a statement within the handle's [`timeout`](Self::timeout)
+    ///
+    /// A statement over time is dropped, which cancels its request, and
+    /// fails with `SQLITE_INTERRUPT`, which converts to [`Error::Timeout`].
+    pub(crate) async fn within_timeout<T>(
+        &self,
+        statement: impl Future<Output = std::result::Result<T, libsql::Error>>,
+    ) -> std::result::Result<T, libsql::Error> {
+        let Some(limit) = self.timeout() else {
+            return statement.await;
+        };
+        crate::runtime::timeout(limit, statement)
+            .await
+            .unwrap_or_else(|| {
+                Err(libsql::Error::SqliteFailure(
+                    crate::error::SQLITE_INTERRUPT,
+                    format!("statement timed out after {}ms", limit.as_millis()),
+                ))
+            })
+    }
+
+    /// Checks that the database answers, returning the round-trip time
+    ///
+    /// Runs `SELECT 1` on the primary connection, within the handle's
+    /// [timeout](Self::with_timeout) but without retries. Useful
+    /// for health endpoints and before work on a connection that has been
+    /// idle for a long time.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::Database;
+    ///
+    /// async fn health(db: &Database) -> bool {
+    ///     db.ping().await.is_ok()
+    /// }
+    /// ```
+    pub async fn ping(&self) -> Result<Duration> {
+        let stopwatch = Stopwatch::start();
+        self.within_timeout(self.inner.query("SELECT 1", ()))
+            .await?;
+        Ok(stopwatch.elapsed())
+    }
+
+    /// Drops the remote streams of the connection and its read replicas, so
+    /// the next statement opens fresh ones
+    ///
+    /// A remote stream the server has expired after a long idle time keeps
+    /// failing every statement sent on it. Statements outside transactions
+    /// that fail with a connection error reconnect once on their own. Reads,
+    /// and statements the server rejected for an expired stream, then run
+    /// again right away if the [retry policy](Self::with_retry_policy)
+    /// allows another attempt; other writes return the error, since the
+    /// server may have applied them before the response was lost. Local
+    /// databases have nothing to reconnect. Not available on Cloudflare
+    /// Workers, whose client cannot reset its stream; open a new `Database`
+    /// when [`ping`](Self::ping) fails there.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn reconnect(&self) {
+        self.inner.reset().await;
+        for reader in self.readers.iter() {
+            reader.reset().await;
+        }
+    }
+
+    /// How long to wait before running a statement that failed with `error`
+    /// on its `attempts`th run again, or `None` to give up
+    ///
+    /// The first connection error reconnects. The statement then runs again
+    /// right away if it only reads or never reached the server, and the
+    /// retry policy allows it; otherwise the policy decides as for any error.
+    pub(crate) async fn rerun_delay(
+        &self,
+        error: &libsql::Error,
+        read: bool,
+        attempts: u32,
+        reconnected: &mut bool,
+    ) -> Option<Duration> {
+        #[cfg(not(target_arch = "wasm32"))]
+        // A new connection would lose any handle's open transaction
+        if !*reconnected && !self.connection_in_transaction() && is_connection_error(error) {
+            *reconnected = true;
+            log::warn!("Reconnecting after connection error: {error}");
+            self.reconnect().await;
+            if (read || is_stale_stream(error)) && attempts < self.retry.attempts(self.hint) {
+                return Some(Duration::ZERO);
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = (read, reconnected);
+        self.retry_delay(error, attempts)
+    }
+
+    /// How long to wait before running a statement that failed with `error`
+    /// again, or `None` to give up
+    fn retry_delay(&self, error: &libsql::Error, attempts: u32) -> Option<Duration> {
+        if self.in_transaction() || !self.retry.retries(error, attempts, self.hint) {
+            return None;
+        }
+        let delay = self.retry.delay(attempts);
+        log::warn!("Retrying statement in {delay:?} after attempt {attempts} failed: {error}");
+        Some(delay)
+    }
+
+    async fn execute_cached(
+        &self,
+        sql: &str,
+        params: Vec<libsql::Value>,
+    ) -> std::result::Result<u64, libsql::Error> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(statement) = self.statements.checkout(&self.inner, sql).await? {
+            let result = statement
+                .execute(params)
+                .await
+                .map(|changes| changes as u64);
+            self.statements.checkin(sql.to_string(), statement);
+            return result;
+        }
+        self.inner.execute(sql, params).await
     }
+
+    /// Runs a query through the statement cache, converting every row with `f`
+    ///
+    /// Unlike [`query`](Self::query), the rows are read before returning, so
+    /// the prepared statement can be reused.
+    pub(crate) async fn query_map<T>(
+        &self,
+        sql: &str,
+        params: Vec<libsql::Value>,
+        mut f: impl FnMut(&libsql::Row) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        let sql = self.tag_sql(sql);
+        let (mut attempts, mut reconnected) = (1, false);
+        loop {
+            match self.query_map_once(&sql, params.clone(), &mut f).await {
+                Ok(mapped) => return mapped,
+                Err(error) => match self
+                    .rerun_delay(&error, is_read(&sql), attempts, &mut reconnected)
+                    .await
+                {
+                    Some(delay) => crate::runtime::sleep(delay).await,
+                    None => return Err(error.into()),
+                },
+            }
+            attempts += 1;
+        }
+    }
+
+    /// Runs a query once for [`query_map`](Self::query_map)
+    ///
+    /// Fails with the statement's error if it could not run; errors reading
+    /// or converting rows are in the inner result.
+    async fn query_map_once<T>(
+        &self,
+        sql: &str,
+        params: Vec<libsql::Value>,
+        f: &mut impl FnMut(&libsql::Row) -> Result<T>,
+    ) -> std::result::Result<Result<Vec<T>>, libsql::Error> {
+        let stopwatch = Stopwatch::start();
+        // Cached statements belong to the primary connection
+        let reader = self.reader(sql);
+        #[cfg(not(target_arch = "wasm32"))]
+        if reader.is_none() {
+            let statement = match self.statements.checkout(&self.inner, sql).await {
+                Ok(statement) => statement,
+                Err(error) => {
+                    crate::metrics::record(sql, stopwatch.elapsed(), true);
+                    self.log_statement(sql, Some(&error));
+                    return Err(error);
+                }
+            };
+            if let Some(statement) = statement {
+                let result = self.within_timeout(statement.query(params)).await;
+                crate::metrics::record(sql, stopwatch.elapsed(), result.is_err());
+                self.log_statement(sql, result.as_ref().err());
+                let mapped = match result {
+                    Ok(rows) => Ok(map_rows(rows, f).await),
+                    Err(error) => Err(error),
+                };
+                self.statements.checkin(sql.to_string(), statement);
+                return mapped;
+            }
+        }
+        let conn = reader.unwrap_or(&self.inner);
+        let result = self.within_timeout(conn.query(sql, params)).await;
+        crate::metrics::record(sql, stopwatch.elapsed(), result.is_err());
+        self.log_statement(sql, result.as_ref().err());
+        Ok(map_rows(result?, f).await)
+    }
+
+    /// Use a statement cache of the given size for this connection
+    ///
+    /// Replaces the current cache, so this is best called right after
+    /// connecting. Handles derived afterwards with [`tagged`](Self::tagged)
+    /// or [`with_context`](Self::with_context) share it. A capacity of 0
+    /// disables caching. On Cloudflare Workers statements are never cached
+    /// and this has no effect. See [`statement_cache`](crate::statement_cache).
+    pub fn with_statement_cache(mut self, capacity: usize) -> Self {
+        self.statements = Arc::new(StatementCache::new(capacity));
+        self
+    }
+
+    /// Retry statements that fail with transient errors
+    ///
+    /// Handles derived afterwards share the policy. See
+    /// [`retry`](crate::retry).
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// The policy for retrying failed statements
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    /// Hits, misses and size of the connection's statement cache
+    pub fn statement_cache_stats(&self) -> StatementCacheStats {
+        self.statements.stats()
+    }
+
+    /// Executes a script of several `;`-separated statements
+    ///
+    /// Statements run one at a time, in order, without an implicit
+    /// transaction; wrap the call in [`Database::transaction`] to make the
+    /// script all-or-nothing. Semicolons inside string literals, comments and
+    /// trigger bodies are handled. Rows returned by a statement are discarded.
+    ///
+    /// Returns the number of statements run. If one fails, the error is an
+    /// [`Error::Script`] naming the statement and where it starts, and the
+    /// statements after it are not run.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::Database;
+    ///
+    /// async fn load_schema(db: &Database) -> libsql_orm::Result<()> {
+    ///     let script = std::fs::read_to_string("vendor/schema.sql")?;
+    ///     db.transaction(|tx| async move {
+    ///         tx.execute_script(&script).await?;
+    ///         Ok(())
+    ///     })
+    ///     .await
+    /// }
+    /// ```
+    pub async fn execute_script(&self, script: &str) -> Result<usize> {
+        let statements = crate::script::split_statements(script);
+        for (index, (offset, sql)) in statements.iter().enumerate() {
+            match self.execute(sql, vec![]).await {
+                Ok(_) | Err(libsql::Error::ExecuteReturnedRows) => {}
+                Err(error) => {
+                    return Err(Error::Script {
+                        statement: index + 1,
+                        line: crate::script::line_at(script, *offset),
+                        offset: *offset,
+                        message: error.to_string(),
+                    })
+                }
+            }
+        }
+        Ok(statements.len())
+    }
+
+    /// Runs independent queries concurrently, at most `concurrency` at a time
+    ///
+    /// Each remote statement is an HTTP request, so a handler reading several
+    /// unrelated things can overlap them instead of waiting for each in turn;
+    /// the cap keeps a large fan-out from opening more requests at once than
+    /// the backend serves well. Results come back in the order of `queries`,
+    /// and the first error is returned once it occurs, dropping the queries
+    /// still pending. A `concurrency` of 0 is treated as 1.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::{Database, Model};
+    ///
+    /// async fn dashboard<M: Model>(db: &Database, ids: &[i64]) -> libsql_orm::Result<Vec<Option<M>>> {
+    ///     db.join_all(ids.iter().map(|id| M::find_by_id(*id, db)), 4).await
+    /// }
+    /// ```
+    pub async fn join_all<T, F>(
+        &self,
+        queries: impl IntoIterator<Item = F>,
+        concurrency: usize,
+    ) -> Result<Vec<T>>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        futures::stream::iter(queries)
+            .buffered(concurrency.max(1))
+            .try_collect()
+            .await
+    }
+
+    /// Executes several statements in one round trip
+    ///
+    /// The statements are sent to the database as a single transactional
+    /// batch, so either all of them apply or none do. Against Turso this
+    /// replaces one HTTP request per statement with one request. libsql's
+    /// batches take SQL text only, so the parameters are inlined as literals
+    /// (see [`Statement`](crate::Statement)). Inside a transaction the batch
+    /// joins it instead. The batch runs with the handle's timeout and retry
+    /// policy like a single statement.
+    ///
+    /// The Cloudflare Workers client cannot send transactional batches, so
+    /// there the statements are sent in one request within a transaction
+    /// that takes a few more.
+    ///
+    /// Returns the number of rows changed by the batch, including rows
+    /// changed by triggers.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::Database;
+    ///
+    /// async fn archive(db: &Database, id: i64) -> libsql_orm::Result<u64> {
+    ///     db.batch(vec![
+    ///         (
+    ///             "INSERT INTO orders_archive SELECT * FROM orders WHERE id = ?".to_string(),
+    ///             vec![libsql::Value::Integer(id)],
+    ///         ),
+    ///         (
+    ///             "DELETE FROM orders WHERE id = ?".to_string(),
+    ///             vec![libsql::Value::Integer(id)],
+    ///         ),
+    ///     ])
+    ///     .await
+    /// }
+    /// ```
+    pub async fn batch<S: Into<crate::Statement>>(&self, statements: Vec<S>) -> Result<u64> {
+        if statements.is_empty() {
+            return Ok(0);
+        }
+        let body = statements
+            .into_iter()
+            .map(|statement| format!("{};", statement.into()))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            // Counting the changes within the batch keeps it to one request
+            let script = format!("SELECT total_changes();\n{body}\nSELECT total_changes()");
+            let sql = &*self.tag_sql(&script);
+            let in_transaction = self.connection_in_transaction();
+            let before = self.inner.total_changes();
+            let mut batch = self
+                .run(sql, false, || async move {
+                    if in_transaction {
+                        self.inner.execute_batch(sql).await
+                    } else {
+                        self.inner.execute_transactional_batch(sql).await
+                    }
+                })
+                .await?;
+            let mut results = Vec::new();
+            while let Some(rows) = batch.next_stmt_row() {
+                results.push(rows);
+            }
+            // Local connections return no rows from transactional batches,
+            // but count their changes themselves
+            if results.len() < 3 {
+                return Ok(self.inner.total_changes() - before);
+            }
+            let mut counts = Vec::new();
+            for index in [0, results.len() - 1] {
+                if let Some(rows) = results[index].as_mut() {
+                    if let Some(row) = rows.next().await? {
+                        counts.push(row.get::<i64>(0)?);
+                    }
+                }
+            }
+            Ok(match counts[..] {
+                [first, last] => (last - first) as u64,
+                _ => self.inner.total_changes() - before,
+            })
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            let body = &body;
+            self.transaction(|tx| async move {
+                let before = tx.total_changes().await?;
+                let sql = &*tx.tag_sql(body);
+                tx.run(sql, false, || tx.inner.execute_batch(sql)).await?;
+                Ok(tx.total_changes().await? - before)
+            })
+            .await
+        }
+    }
+
+    /// Rows changed on the connection since it was opened
+    #[cfg(target_arch = "wasm32")]
+    async fn total_changes(&self) -> Result<u64> {
+        let changes = self
+            .query_map("SELECT total_changes()", vec![], |row| {
+                Ok(row.get::<i64>(0)?)
+            })
+            .await?;
+        Ok(changes.first().copied().unwrap_or(0) as u64)
+    }
+
+    /// Runs a SQL query and deserializes each row into `T`
+    ///
+    /// Rows become maps from column name to value, so `T` can be any
+    /// `Deserialize` struct whose fields match the selected columns or their
+    /// aliases. BLOBs deserialize into `Vec<u8>`. SQLite has no boolean type;
+    /// use [`deserialize_bool`](crate::deserialize_bool) for `bool` fields.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::Database;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct AuthorStats {
+    ///     name: String,
+    ///     posts: i64,
+    /// }
+    ///
+    /// async fn report(db: &Database) -> libsql_orm::Result<Vec<AuthorStats>> {
+    ///     db.query_as(
+    ///         "SELECT a.name, COUNT(p.id) AS posts FROM authors a
+    ///          LEFT JOIN posts p ON p.author_id = a.id
+    ///          WHERE a.active = ? GROUP BY a.id",
+    ///         vec![libsql::Value::Integer(1)],
+    ///     )
+    ///     .await
+    /// }
+    /// ```
+    pub async fn query_as<T: serde::de::DeserializeOwned>(
+        &self,
+        sql: &str,
+        params: Vec<libsql::Value>,
+    ) -> Result<Vec<T>> {
+        self.query_map(sql, params, |row| {
+            let mut map = serde_json::Map::new();
+            for i in 0..row.column_count() {
+                if let Some(column_name) = row.column_name(i) {
+                    let value = row.get_value(i).unwrap_or(libsql::Value::Null);
+                    map.insert(column_name.to_string(), crate::Value::from(value).into());
+                }
+            }
+            Ok(serde_json::from_value(serde_json::Value::Object(map))?)
+        })
+        .await
+    }
+
+    /// Counts rows in several tables in a single round trip
+    ///
+    /// Each entry names a table and an optional filter. The counts are
+    /// returned labeled by table, in the order requested, so the same table
+    /// can be counted with different filters:
+    ///
+    /// ```no_run
+    /// use libsql_orm::{Database, Filter, FilterOperator};
+    ///
+    /// async fn cards(db: &Database) -> libsql_orm::Result<()> {
+    ///     let pending = FilterOperator::Single(Filter::eq("status", "pending"));
+    ///     let counts = db
+    ///         .dashboard_counts(&[("users", None), ("orders", Some(pending))])
+    ///         .await?;
+    ///     for (table, count) in counts {
+    ///         println!("{table}: {count}");
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn dashboard_counts(
+        &self,
+        cards: &[(&str, Option<crate::FilterOperator>)],
+    ) -> Result<Vec<(String, u64)>> {
+        if cards.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut selects = Vec::with_capacity(cards.len());
+        let mut params = Vec::new();
+        for (i, (table, filter)) in cards.iter().enumerate() {
+            let mut builder = crate::QueryBuilder::new(*table);
+            if let Some(filter) = filter {
+                builder = builder.r#where(filter.clone());
+            }
+            let (count_sql, count_params) = builder.build_count()?;
+            selects.push(format!("SELECT {i}, ({count_sql})"));
+            params.extend(count_params);
+        }
+        let sql = selects.join(" UNION ALL ");
+
+        let mut counts = vec![0u64; cards.len()];
+        let mut rows = self.query(&sql, params).await?;
+        while let Some(row) = rows.next().await? {
+            let index: i64 = row.get(0)?;
+            let count: i64 = row.get(1)?;
+            if let Some(slot) = counts.get_mut(index as usize) {
+                *slot = count as u64;
+            }
+        }
+        Ok(cards
+            .iter()
+            .zip(counts)
+            .map(|((table, _), count)| (table.to_string(), count))
+            .collect())
+    }
+
+    /// Runs a closure inside a transaction
+    ///
+    /// The transaction is committed if the closure returns `Ok` and rolled back
+    /// if it returns `Err` or panics (the panic is resumed after the rollback).
+    /// Calls can be nested; inner transactions are implemented with savepoints.
+    ///
+    /// A connection holds one transaction at a time. A handle that is not in
+    /// the open transaction, such as another request's
+    /// [`with_context`](Self::with_context) handle, waits for it to finish
+    /// before starting its own. Handles derived inside the closure belong to
+    /// its transaction, while calls on a handle that is in it nest. So
+    /// concurrent requests sharing a `Database` must each use their own
+    /// derived handle, and a closure must not start a transaction on a handle
+    /// derived outside it, which would wait for itself.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::{Database, Model};
+    ///
+    /// async fn transfer(db: &Database) -> libsql_orm::Result<()> {
+    ///     db.transaction(|tx| async move {
+    ///         tx.query("UPDATE accounts SET balance = balance - 10 WHERE id = 1", vec![]).await?;
+    ///         tx.query("UPDATE accounts SET balance = balance + 10 WHERE id = 2", vec![]).await?;
+    ///         Ok(())
+    ///     })
+    ///     .await
+    /// }
+    /// ```
+    pub async fn transaction<'a, F, Fut, T>(&'a self, f: F) -> Result<T>
+    where
+        F: FnOnce(&'a Database) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        // Transactions of other handles on the connection go one at a time
+        let _turn = match self.in_transaction() {
+            true => None,
+            false => Some(self.tx_lock.lock().await),
+        };
+        let depth = self.tx_depth.fetch_add(1, Ordering::SeqCst);
+        if depth == 0 {
+            self.tx_open.store(true, Ordering::SeqCst);
+        }
+        let (begin, commit, rollback) = if depth == 0 {
+            (
+                "BEGIN".to_string(),
+                "COMMIT".to_string(),
+                "ROLLBACK".to_string(),
+            )
+        } else {
+            (
+                format!("SAVEPOINT orm_tx_{depth}"),
+                format!("RELEASE orm_tx_{depth}"),
+                format!("ROLLBACK TO orm_tx_{depth}; RELEASE orm_tx_{depth}"),
+            )
+        };
+
+        if let Err(e) = self.execute_batch(&begin).await {
+            self.end_transaction();
+            return Err(e.into());
+        }
+
+        let outcome = AssertUnwindSafe(f(self)).catch_unwind().await;
+        let result = match outcome {
+            Ok(Ok(value)) => match self.execute_batch(&commit).await {
+                Ok(()) => Ok(value),
+                Err(e) => {
+                    let _ = self.execute_batch(&rollback).await;
+                    Err(Error::from(e))
+                }
+            },
+            Ok(Err(e)) => {
+                let _ = self.execute_batch(&rollback).await;
+                Err(e)
+            }
+            Err(panic) => {
+                let _ = self.execute_batch(&rollback).await;
+                self.end_transaction();
+                std::panic::resume_unwind(panic);
+            }
+        };
+
+        self.end_transaction();
+        result
+    }
+
+    /// Leave a transaction level opened by [`transaction`](Self::transaction)
+    fn end_transaction(&self) {
+        if self.tx_depth.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.tx_open.store(false, Ordering::SeqCst);
+        }
+    }
+
+    /// Compare every registered model against the live database schema
+    ///
+    /// Returns the drift findings (missing table, missing column, type mismatch,
+    /// missing unique index) for each model that does not match. An empty result
+    /// means the database is consistent with the registry.
+    pub async fn check_consistency(&self) -> Result<Vec<crate::schema::ModelDrift>> {
+        crate::schema::check_models(self, &crate::registry()).await
+    }
+
+    /// Start collecting records to load in a single round trip
+    ///
+    /// See [`Prefetch`](crate::prefetch::Prefetch).
+    pub fn prefetch(&self) -> crate::prefetch::Prefetch<'_> {
+        crate::prefetch::Prefetch::new(self)
+    }
+
+    /// Open a request scope with an identity map
+    ///
+    /// See [`ScopedDatabase`](crate::scope::ScopedDatabase).
+    pub fn scoped(&self) -> crate::scope::ScopedDatabase<'_> {
+        crate::scope::ScopedDatabase::new(self)
+    }
+
+    /// Get a handle that tags every statement with a trailing SQL comment
+    ///
+    /// The handle shares this connection and its transactions. Tags show up in
+    /// server-side query analytics, attributing load to application call sites:
+    ///
+    /// ```no_run
+    /// use libsql_orm::{Database, Model};
+    ///
+    /// async fn list_posts<M: Model>(db: &Database) -> libsql_orm::Result<Vec<M>> {
+    ///     let db = db.tagged("endpoint:/posts GET");
+    ///     // Runs `SELECT * FROM posts /* endpoint:/posts GET */`
+    ///     M::find_all(&db).await
+    /// }
+    /// ```
+    pub fn tagged(&self, tag: impl Into<String>) -> Database {
+        Database {
+            tag: Some(tag.into()),
+            ..self.handle()
+        }
+    }
+
+    /// Get a handle that attributes its statements to a request
+    ///
+    /// The context is added to the SQL comment of every statement and to the
+    /// statement logs. The handle shares this connection, its transactions and
+    /// its tag. See [`RequestContext`].
+    pub fn with_context(&self, context: RequestContext) -> Database {
+        Database {
+            context: Some(Arc::new(context)),
+            ..self.handle()
+        }
+    }
+
+    /// Get a handle whose statements carry a priority hint
+    ///
+    /// The handle shares this connection, its transactions, tag and context.
+    ///
+    /// ```no_run
+    /// use libsql_orm::{Database, Model, QueryHint};
+    ///
+    /// async fn export<M: Model>(db: &Database) -> libsql_orm::Result<Vec<M>> {
+    ///     M::find_all(&db.with_hint(QueryHint::Background)).await
+    /// }
+    /// ```
+    pub fn with_hint(&self, hint: QueryHint) -> Database {
+        Database {
+            hint,
+            ..self.handle()
+        }
+    }
+
+    /// The priority hint of this handle's statements
+    pub fn hint(&self) -> QueryHint {
+        self.hint
+    }
+
+    /// Get a handle whose statements fail with [`Error::Timeout`] when they
+    /// take longer than `timeout`
+    ///
+    /// Set it once on the connection as a default, or on a handle for a
+    /// single call. It replaces the background timeout. Each attempt of a
+    /// [retried](crate::retry) statement gets the full time. The budget
+    /// bounds the wait for a remote database; a statement on a local file
+    /// runs to completion before its time can be checked.
+    ///
+    /// ```no_run
+    /// use libsql_orm::{Database, Model};
+    /// use std::time::Duration;
+    ///
+    /// async fn example<M: Model>(db: Database) -> libsql_orm::Result<Vec<M>> {
+    ///     let db = db
+    ///         .with_timeout(Duration::from_secs(2))
+    ///         .with_background_timeout(Duration::from_secs(30));
+    ///     // This report may take longer than the default
+    ///     M::find_all(&db.with_timeout(Duration::from_secs(10))).await
+    /// }
+    /// ```
+    pub fn with_timeout(&self, timeout: Duration) -> Database {
+        Database {
+            timeout: Some(timeout),
+            background_timeout: None,
+            ..self.handle()
+        }
+    }
+
+    /// Get a handle whose [`QueryHint::Background`] statements may take up
+    /// to `timeout` instead
+    pub fn with_background_timeout(&self, timeout: Duration) -> Database {
+        Database {
+            background_timeout: Some(timeout),
+            ..self.handle()
+        }
+    }
+
+    /// Get a handle whose statements may take as long as they need
+    pub fn without_timeout(&self) -> Database {
+        Database {
+            timeout: None,
+            background_timeout: None,
+            ..self.handle()
+        }
+    }
+
+    /// Longest this handle's statements may take, given its hint
+    pub fn timeout(&self) -> Option<Duration> {
+        match self.hint {
+            QueryHint::Background => self.background_timeout.or(self.timeout),
+            QueryHint::Interactive => self.timeout,
+        }
+    }
+
+    /// Get a handle that sends reads to the primary instead of the read
+    /// replicas
+    ///
+    /// Use it for reads that must see a write made just before, which may
+    /// not have reached the replicas yet:
+    ///
+    /// ```no_run
+    /// use libsql_orm::{Database, Model};
+    ///
+    /// async fn save<M: Model>(model: &M, db: &Database) -> libsql_orm::Result<Option<M>> {
+    ///     let saved = model.create(db).await?;
+    ///     M::find_by_id(saved.get_primary_key().unwrap_or_default(), &db.primary()).await
+    /// }
+    /// ```
+    pub fn primary(&self) -> Database {
+        Database {
+            primary_only: true,
+            ..self.handle()
+        }
+    }
+
+    /// A handle sharing everything with this one, including its transaction
+    /// if one is open
+    fn handle(&self) -> Database {
+        let tx_depth = match self.in_transaction() {
+            true => Arc::clone(&self.tx_depth),
+            false => Arc::new(AtomicUsize::new(0)),
+        };
+        Database {
+            inner: self.inner.clone(),
+            tx_depth,
+            tx_lock: Arc::clone(&self.tx_lock),
+            tx_open: Arc::clone(&self.tx_open),
+            tag: self.tag.clone(),
+            context: self.context.clone(),
+            statements: Arc::clone(&self.statements),
+            hint: self.hint,
+            #[cfg(not(target_arch = "wasm32"))]
+            replica: self.replica.clone(),
+            readers: Arc::clone(&self.readers),
+            next_reader: Arc::clone(&self.next_reader),
+            primary_only: self.primary_only,
+            retry: self.retry.clone(),
+            timeout: self.timeout,
+            background_timeout: self.background_timeout,
+        }
+    }
+
+    /// The request context attached to this handle
+    ///
+    /// Useful for stamping audit records with the tenant and user.
+    pub fn context(&self) -> Option<&RequestContext> {
+        self.context.as_deref()
+    }
+
+    /// The tag appended to this handle's statements
+    pub fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    fn tag_sql<'a>(&self, sql: &'a str) -> std::borrow::Cow<'a, str> {
+        let context = self
+            .context
+            .as_ref()
+            .map(|context| context.to_string())
+            .filter(|context| !context.is_empty());
+        let comment = match (&self.tag, context) {
+            (Some(tag), Some(context)) => format!("{tag} {context}"),
+            (Some(tag), None) => tag.clone(),
+            (None, Some(context)) => context,
+            (None, None) => return sql.into(),
+        };
+        let mut sql = sql.to_string();
+        append_comment(&mut sql, &comment);
+        sql.into()
+    }
+
+    /// Log a finished statement, with the request context if there is one
+    fn log_statement(&self, sql: &str, error: Option<&libsql::Error>) {
+        let prefix = match &self.context {
+            Some(context) if !context.is_empty() => format!("[{context}] "),
+            _ => String::new(),
+        };
+        match error {
+            Some(error) => {
+                let message = format!("{prefix}Statement failed: {error}: {sql}");
+                #[cfg(target_arch = "wasm32")]
+                web_sys::console::error_1(&format!("[ERROR] {message}").into());
+                #[cfg(not(target_arch = "wasm32"))]
+                log::error!("{message}");
+            }
+            None if !prefix.is_empty() => {
+                let message = format!("{prefix}SQL: {sql}");
+                #[cfg(target_arch = "wasm32")]
+                web_sys::console::log_1(&format!("[DEBUG] {message}").into());
+                #[cfg(not(target_arch = "wasm32"))]
+                log::debug!("{message}");
+            }
+            None => {}
+        }
+    }
+
+    /// Whether this handle is in a transaction opened through
+    /// [`Database::transaction`]
+    pub fn in_transaction(&self) -> bool {
+        self.tx_depth.load(Ordering::SeqCst) > 0
+    }
+
+    /// Whether any handle has a transaction open on the connection
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn connection_in_transaction(&self) -> bool {
+        self.tx_open.load(Ordering::SeqCst)
+    }
+}
+
+/// Whether an error comes from a broken or expired connection rather than
+/// from the statement
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn is_connection_error(error: &libsql::Error) -> bool {
+    match error {
+        libsql::Error::ConnectionFailed(_) => true,
+        libsql::Error::Hrana(error) => {
+            let message = error.to_string().to_ascii_lowercase();
+            ["stream", "baton", "http error"]
+                .iter()
+                .any(|pattern| message.contains(pattern))
+        }
+        _ => false,
+    }
+}
+
+/// Whether the server refused a request for its expired stream, so the
+/// statement in it never ran
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn is_stale_stream(error: &libsql::Error) -> bool {
+    match error {
+        libsql::Error::Hrana(error) => {
+            let message = error.to_string().to_ascii_lowercase();
+            ["stream expired", "stream not found", "baton"]
+                .iter()
+                .any(|pattern| message.contains(pattern))
+        }
+        _ => false,
+    }
+}
+
+/// Whether a statement only reads, so a read replica can run it
+///
+/// Errs towards the primary: a `WITH` statement mentioning a write keyword
+/// anywhere is treated as a write.
+fn is_read(sql: &str) -> bool {
+    let sql = sql.trim_start().to_ascii_uppercase();
+    let first = sql.split(|c: char| !c.is_ascii_alphabetic()).next();
+    match first {
+        Some("SELECT") => true,
+        Some("WITH") => !["INSERT", "UPDATE", "DELETE", "REPLACE"]
+            .iter()
+            .any(|keyword| sql.contains(keyword)),
+        _ => false,
+    }
+}
+
+/// Convert every row of a query result with `f`
+async fn map_rows<T>(
+    mut rows: Rows,
+    f: &mut impl FnMut(&libsql::Row) -> Result<T>,
+) -> Result<Vec<T>> {
+    let mut results = Vec::new();
+    while let Some(row) = rows.next().await? {
+        results.push(f(&row)?);
+    }
+    Ok(results)
+}
+
+/// Append text to a statement as a trailing `/* ... */` comment
+pub(crate) fn append_comment(sql: &mut String, text: &str) {
+    // A `*/` inside the text would end the comment early
+    let text = text.replace("*/", "* /");
+    sql.push_str(&format!(" /* {text} */"));
 }
@@ -0,0 +1,115 @@
+//! Column-level differences between two versions of a record
+//!
+//! [`ModelDiff::between`] compares two instances of a model column by column.
+//! The result drives partial updates, audit logs and webhook payloads:
+//!
+//! ```rust,ignore
+//! let before = User::find_by_id(1, &db).await?.unwrap();
+//! let mut after = before.clone();
+//! after.email = "new@example.com".to_string();
+//!
+//! let diff = ModelDiff::between(&before, &after)?;
+//! assert_eq!(diff.columns(), vec!["email"]);
+//! diff.apply(&after, &db).await?; // UPDATE users SET email = ? WHERE id = ?
+//! send_webhook(diff.to_json());
+//! ```
+
+use crate::{Database, Model, Result, Value};
+use serde::{Deserialize, Serialize};
+
+/// A column whose value differs between two records
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnChange {
+    /// Column name
+    pub column: String,
+    /// Value in the old record
+    pub before: Value,
+    /// Value in the new record
+    pub after: Value,
+}
+
+/// The columns that differ between two records of the same model
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelDiff {
+    /// Changed columns, in the model's column order
+    pub changes: Vec<ColumnChange>,
+}
+
+impl ModelDiff {
+    /// Compare two records column by column
+    ///
+    /// Values are compared as they would be written, so generated columns
+    /// such as content hashes are included. The primary key is never part of
+    /// the diff.
+    pub fn between<M: Model>(old: &M, new: &M) -> Result<Self> {
+        let mut before = old.to_map()?;
+        let mut after = new.to_map()?;
+
+        let mut columns: Vec<String> = M::column_meta()
+            .iter()
+            .map(|column| column.name.to_string())
+            .collect();
+        if columns.is_empty() {
+            columns = after.keys().chain(before.keys()).cloned().collect();
+            columns.sort();
+            columns.dedup();
+        }
+
+        let changes = columns
+            .into_iter()
+            .filter(|column| column != M::primary_key())
+            .filter_map(|column| {
+                let before = before.remove(&column).unwrap_or(Value::Null);
+                let after = after.remove(&column).unwrap_or(Value::Null);
+                (before != after).then_some(ColumnChange {
+                    column,
+                    before,
+                    after,
+                })
+            })
+            .collect();
+        Ok(Self { changes })
+    }
+
+    /// Whether the records are identical
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Names of the changed columns
+    pub fn columns(&self) -> Vec<&str> {
+        self.changes.iter().map(|c| c.column.as_str()).collect()
+    }
+
+    /// Get the change of a column, if it changed
+    pub fn get(&self, column: &str) -> Option<&ColumnChange> {
+        self.changes.iter().find(|c| c.column == column)
+    }
+
+    /// Render as `{"column": {"before": ..., "after": ...}}`
+    pub fn to_json(&self) -> serde_json::Value {
+        let changes = self
+            .changes
+            .iter()
+            .map(|change| {
+                let values = serde_json::json!({
+                    "before": serde_json::Value::from(change.before.clone()),
+                    "after": serde_json::Value::from(change.after.clone()),
+                });
+                (change.column.clone(), values)
+            })
+            .collect();
+        serde_json::Value::Object(changes)
+    }
+
+    /// Write only the changed columns of `record`
+    ///
+    /// `record` is normally the new record the diff was computed from. No
+    /// statement is run when nothing changed.
+    pub async fn apply<M: Model>(&self, record: &M, db: &Database) -> Result<M> {
+        if self.is_empty() {
+            return Ok(record.clone());
+        }
+        record.update_fields(&self.columns(), db).await
+    }
+}
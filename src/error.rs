@@ -13,6 +13,8 @@
 //! - **Not Found Errors**: Resource not found
 //! - **Pagination Errors**: Pagination parameter issues
 //! - **Query Errors**: Query building problems
+//! - **Timeout Errors**: Statements that ran past their time budget
+//! - **Script Errors**: A failing statement of a multi-statement script
 //!
 //! # Examples
 //!
@@ -55,6 +57,19 @@ pub enum Error {
     AnyhowError(String),
     /// Database error
     DatabaseError(String),
+    /// A statement took longer than its timeout, or was interrupted
+    Timeout(String),
+    /// A statement of a script failed
+    Script {
+        /// 1-based position of the statement in the script
+        statement: usize,
+        /// 1-based line the statement starts on
+        line: usize,
+        /// Byte offset of the statement in the script
+        offset: usize,
+        /// The underlying error message
+        message: String,
+    },
     /// Generic error
     Generic(String),
 }
@@ -73,14 +88,35 @@ impl fmt::Display for Error {
             Error::Query(msg) => write!(f, "Query error: {msg}"),
             Error::AnyhowError(msg) => write!(f, "Anyhow error: {msg}"),
             Error::DatabaseError(msg) => write!(f, "Database error: {msg}"),
+            Error::Timeout(msg) => write!(f, "Timeout: {msg}"),
+            Error::Script {
+                statement,
+                line,
+                offset,
+                message,
+            } => write!(
+                f,
+                "Script error in statement {statement} (line {line}, offset {offset}): {message}"
+            ),
             Error::Generic(msg) => write!(f, "Error: {msg}"),
         }
     }
 }
 
+/// Result code of an interrupted statement, also used for timed out ones
+pub(crate) const SQLITE_INTERRUPT: i32 = 9;
+
 impl From<libsql::Error> for Error {
     fn from(err: libsql::Error) -> Self {
-        Error::Sql(err.to_string())
+        match err {
+            libsql::Error::SqliteFailure(code, msg) if code & 0xff == SQLITE_INTERRUPT => {
+                Error::Timeout(msg)
+            }
+            libsql::Error::RemoteSqliteFailure(code, _, msg) if code & 0xff == SQLITE_INTERRUPT => {
+                Error::Timeout(msg)
+            }
+            err => Error::Sql(err.to_string()),
+        }
     }
 }
 
@@ -0,0 +1,105 @@
+//! Query plans
+//!
+//! [`QueryBuilder::explain`](crate::QueryBuilder::explain) and
+//! [`Statement::explain`](crate::Statement::explain) run `EXPLAIN QUERY PLAN`
+//! and parse the result into a [`QueryPlan`], so tests can assert that hot
+//! queries use the indexes defined for them:
+//!
+//! ```no_run
+//! use libsql_orm::{Database, Filter, FilterOperator, QueryBuilder};
+//!
+//! async fn check(db: &Database) -> libsql_orm::Result<()> {
+//!     let plan = QueryBuilder::new("users")
+//!         .r#where(FilterOperator::Single(Filter::eq("email", "a@example.com")))
+//!         .explain(db)
+//!         .await?;
+//!     assert!(plan.uses_index("idx_users_email"), "{plan}");
+//!     assert!(plan.full_scans().is_empty(), "{plan}");
+//!     Ok(())
+//! }
+//! ```
+
+use std::fmt;
+
+/// One node of a query plan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanStep {
+    pub id: i64,
+    pub parent: i64,
+    /// SQLite's description, e.g. `SEARCH users USING INDEX idx_email (email=?)`
+    pub detail: String,
+}
+
+impl PlanStep {
+    /// Whether this step reads every row of a table
+    ///
+    /// Scans through a covering index also read every row, but are not
+    /// reported, since they are usually intended.
+    pub fn is_full_scan(&self) -> bool {
+        self.detail.starts_with("SCAN ") && !self.detail.contains(" USING ")
+    }
+
+    /// The table or subquery the step reads, if it reads one
+    pub fn table(&self) -> Option<&str> {
+        let rest = self
+            .detail
+            .strip_prefix("SCAN ")
+            .or_else(|| self.detail.strip_prefix("SEARCH "))?;
+        rest.split_whitespace().next()
+    }
+}
+
+/// The parsed output of `EXPLAIN QUERY PLAN`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryPlan {
+    pub steps: Vec<PlanStep>,
+}
+
+impl QueryPlan {
+    /// Tables read by a full table scan
+    pub fn full_scans(&self) -> Vec<&str> {
+        self.steps
+            .iter()
+            .filter(|step| step.is_full_scan())
+            .filter_map(PlanStep::table)
+            .collect()
+    }
+
+    /// Whether any step uses the named index
+    pub fn uses_index(&self, index: &str) -> bool {
+        self.steps.iter().any(|step| {
+            step.detail
+                .split_whitespace()
+                .skip_while(|word| *word != "INDEX")
+                .nth(1)
+                == Some(index)
+        })
+    }
+
+    /// Whether sorting or grouping needs a temporary B-tree, i.e. no index
+    /// provides the order
+    pub fn uses_temp_btree(&self) -> bool {
+        self.steps
+            .iter()
+            .any(|step| step.detail.starts_with("USE TEMP B-TREE"))
+    }
+}
+
+impl fmt::Display for QueryPlan {
+    /// Indent steps under their parents, like the sqlite3 shell
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_children(
+            plan: &QueryPlan,
+            f: &mut fmt::Formatter<'_>,
+            parent: i64,
+            depth: usize,
+        ) -> fmt::Result {
+            for step in plan.steps.iter().filter(|step| step.parent == parent) {
+                writeln!(f, "{}{}", "  ".repeat(depth), step.detail)?;
+                write_children(plan, f, step.id, depth + 1)?;
+            }
+            Ok(())
+        }
+        write_children(self, f, 0, 0)
+    }
+}
@@ -69,8 +69,9 @@
 //! ];
 //! ```
 
-use crate::{Operator, Value};
+use crate::{Error, Operator, QueryBuilder, Result, Value};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// Filter operator for building complex queries
 ///
@@ -150,6 +151,9 @@ pub struct Filter {
 /// - Single values for basic comparisons (=, >, <, etc.)
 /// - Multiple values for IN/NOT IN operations  
 /// - Range values for BETWEEN/NOT BETWEEN operations
+/// - Subqueries for IN/NOT IN operations
+/// - Bound parameters of raw SQL conditions
+/// - Other columns, for comparisons between columns
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FilterValue {
     /// Single value
@@ -158,6 +162,15 @@ pub enum FilterValue {
     Multiple(Vec<Value>),
     /// Range values (for BETWEEN, NOT BETWEEN operators)
     Range(Value, Value),
+    /// A subquery selecting one column (for IN, NOT IN operators)
+    ///
+    /// Subquery filters cannot be serialized.
+    #[serde(skip)]
+    Subquery(Box<QueryBuilder>),
+    /// Parameters of a raw SQL condition held in the filter's `column`
+    Raw(Vec<Value>),
+    /// Another column, e.g. of an outer query in a correlated subquery
+    Column(String),
 }
 
 impl Filter {
@@ -184,11 +197,15 @@ impl Filter {
     }
 
     /// Create an equality filter
+    ///
+    /// Comparing with `Value::Null` builds `column IS NULL`.
     pub fn eq(column: impl Into<String>, value: impl Into<Value>) -> Self {
         Self::new(column, Operator::Eq, FilterValue::Single(value.into()))
     }
 
     /// Create a not-equal filter
+    ///
+    /// Comparing with `Value::Null` builds `column IS NOT NULL`.
     pub fn ne(column: impl Into<String>, value: impl Into<Value>) -> Self {
         Self::new(column, Operator::Ne, FilterValue::Single(value.into()))
     }
@@ -231,6 +248,85 @@ impl Filter {
         )
     }
 
+    /// Create a LIKE filter whose pattern escapes `%`, `_` and `\` with a
+    /// backslash (`LIKE ? ESCAPE '\'`)
+    ///
+    /// Build the literal parts of the pattern with
+    /// [`escape_like`](Self::escape_like). In [`like`](Self::like) patterns
+    /// a backslash is an ordinary character.
+    pub fn like_escaped(column: impl Into<String>, pattern: impl Into<String>) -> Self {
+        Self::new(
+            column,
+            Operator::LikeEscaped,
+            FilterValue::Single(Value::Text(pattern.into())),
+        )
+    }
+
+    /// Create a NOT LIKE filter whose pattern escapes with a backslash, like
+    /// [`like_escaped`](Self::like_escaped)
+    pub fn not_like_escaped(column: impl Into<String>, pattern: impl Into<String>) -> Self {
+        Self::new(
+            column,
+            Operator::NotLikeEscaped,
+            FilterValue::Single(Value::Text(pattern.into())),
+        )
+    }
+
+    /// Create a case-sensitive GLOB filter (`*`, `?` and `[...]` wildcards)
+    pub fn glob(column: impl Into<String>, pattern: impl Into<String>) -> Self {
+        Self::new(
+            column,
+            Operator::Glob,
+            FilterValue::Single(Value::Text(pattern.into())),
+        )
+    }
+
+    /// Create a NOT GLOB filter
+    pub fn not_glob(column: impl Into<String>, pattern: impl Into<String>) -> Self {
+        Self::new(
+            column,
+            Operator::NotGlob,
+            FilterValue::Single(Value::Text(pattern.into())),
+        )
+    }
+
+    /// Escape `%`, `_` and `\` so user input matches literally in a
+    /// [`like_escaped`](Self::like_escaped) pattern
+    ///
+    /// ```rust
+    /// use libsql_orm::Filter;
+    ///
+    /// let input = "100%_done";
+    /// let filter = Filter::like_escaped("title", format!("%{}%", Filter::escape_like(input)));
+    /// assert_eq!(Filter::escape_like(input), r"100\%\_done");
+    /// ```
+    pub fn escape_like(input: &str) -> String {
+        let mut escaped = String::with_capacity(input.len());
+        for c in input.chars() {
+            if matches!(c, '%' | '_' | '\\') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+
+    /// Escape `*`, `?` and `[` so user input matches literally in a GLOB pattern
+    pub fn escape_glob(input: &str) -> String {
+        let mut escaped = String::with_capacity(input.len());
+        for c in input.chars() {
+            match c {
+                '*' | '?' | '[' => {
+                    escaped.push('[');
+                    escaped.push(c);
+                    escaped.push(']');
+                }
+                _ => escaped.push(c),
+            }
+        }
+        escaped
+    }
+
     /// Create an IN filter
     pub fn in_values(column: impl Into<String>, values: Vec<impl Into<Value>>) -> Self {
         let values = values.into_iter().map(|v| v.into()).collect();
@@ -243,6 +339,111 @@ impl Filter {
         Self::new(column, Operator::NotIn, FilterValue::Multiple(values))
     }
 
+    /// Create an IN filter over a list of values
+    ///
+    /// Expands to one bound placeholder per value:
+    ///
+    /// ```rust
+    /// use libsql_orm::{Filter, FilterOperator, QueryBuilder, Value};
+    ///
+    /// let ids = vec![Value::Integer(3), Value::Integer(5)];
+    /// let (sql, params) = QueryBuilder::new("users")
+    ///     .r#where(FilterOperator::Single(Filter::in_list("id", ids)))
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(sql, "SELECT * FROM users WHERE id IN (?, ?)");
+    /// assert_eq!(params.len(), 2);
+    /// ```
+    ///
+    /// An empty list matches no rows. SQLite limits the number of parameters
+    /// per statement (32766 by default), so split very long lists.
+    pub fn in_list(column: impl Into<String>, values: Vec<Value>) -> Self {
+        Self::new(column, Operator::In, FilterValue::Multiple(values))
+    }
+
+    /// Create a NOT IN filter over a list of values
+    ///
+    /// An empty list matches every row.
+    pub fn not_in_list(column: impl Into<String>, values: Vec<Value>) -> Self {
+        Self::new(column, Operator::NotIn, FilterValue::Multiple(values))
+    }
+
+    /// Create a filter comparing two columns for equality
+    ///
+    /// Correlates a subquery with its outer query, e.g. the comments of each
+    /// post in [`QueryBuilder::select_subquery`]:
+    ///
+    /// ```rust
+    /// use libsql_orm::Filter;
+    ///
+    /// let filter = Filter::eq_column("comments.post_id", "posts.id");
+    /// ```
+    pub fn eq_column(column: impl Into<String>, other: impl Into<String>) -> Self {
+        Self::new(column, Operator::Eq, FilterValue::Column(other.into()))
+    }
+
+    /// Create a filter comparing two columns with any operator
+    pub fn compare_column(
+        column: impl Into<String>,
+        operator: Operator,
+        other: impl Into<String>,
+    ) -> Self {
+        Self::new(column, operator, FilterValue::Column(other.into()))
+    }
+
+    /// Create an IN filter matching the rows of a subquery
+    ///
+    /// The subquery should select a single column, e.g. posts whose author is
+    /// active:
+    ///
+    /// ```rust
+    /// use libsql_orm::{Filter, QueryBuilder};
+    ///
+    /// let active_authors = QueryBuilder::new("users")
+    ///     .select(vec!["id"])
+    ///     .r#where(libsql_orm::FilterOperator::Single(Filter::eq("active", true)));
+    /// let filter = Filter::in_subquery("user_id", active_authors);
+    /// ```
+    pub fn in_subquery(column: impl Into<String>, query: QueryBuilder) -> Self {
+        Self::new(column, Operator::In, FilterValue::Subquery(Box::new(query)))
+    }
+
+    /// Create a NOT IN filter excluding the rows of a subquery
+    pub fn not_in_subquery(column: impl Into<String>, query: QueryBuilder) -> Self {
+        Self::new(
+            column,
+            Operator::NotIn,
+            FilterValue::Subquery(Box::new(query)),
+        )
+    }
+
+    /// Create a filter from a raw SQL condition with bound parameters
+    ///
+    /// Each `?` in `sql` is bound to the next parameter, so values are never
+    /// interpolated into the SQL:
+    ///
+    /// ```rust
+    /// use libsql_orm::{Filter, FilterOperator, QueryBuilder};
+    ///
+    /// let (sql, params) = QueryBuilder::new("accounts")
+    ///     .r#where(FilterOperator::Single(Filter::raw(
+    ///         "json_extract(meta, '$.plan') = ?",
+    ///         vec!["pro"],
+    ///     )))
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(sql, "SELECT * FROM accounts WHERE (json_extract(meta, '$.plan') = ?)");
+    /// assert_eq!(params.len(), 1);
+    /// ```
+    ///
+    /// The condition is wrapped in parentheses. Building the query fails if
+    /// the number of placeholders outside string literals differs from the
+    /// number of parameters.
+    pub fn raw(sql: impl Into<String>, params: Vec<impl Into<Value>>) -> Self {
+        let params = params.into_iter().map(|v| v.into()).collect();
+        Self::new(sql, Operator::Eq, FilterValue::Raw(params))
+    }
+
     /// Create an IS NULL filter
     pub fn is_null(column: impl Into<String>) -> Self {
         Self::new(column, Operator::IsNull, FilterValue::Single(Value::Null))
@@ -321,6 +522,133 @@ impl FilterOperator {
             _ => FilterOperator::Or(vec![self, other]),
         }
     }
+
+    /// Build an AND filter from a JSON object of request parameters
+    ///
+    /// Each key is a column from `allowed`, optionally followed by an operator
+    /// suffix, so `?status=active&price_gte=10` collected into an object
+    /// becomes `status = 'active' AND price >= 10`:
+    ///
+    /// | Key | Condition |
+    /// |-----|-----------|
+    /// | `col` | `col = value` |
+    /// | `col_ne`, `col_gt`, `col_gte`, `col_lt`, `col_lte` | comparison |
+    /// | `col_contains` | substring match, wildcards in the value are literal |
+    /// | `col_in` | `col IN (...)` from an array or comma-separated string |
+    /// | `col_null` | `IS NULL` for `true`, `IS NOT NULL` for `false` |
+    ///
+    /// Keys naming any other column fail with [`Error::Validation`], so
+    /// remove unrelated parameters such as `page` before calling this.
+    ///
+    /// ```rust
+    /// use libsql_orm::FilterOperator;
+    ///
+    /// let params = serde_json::json!({"status": "active", "price_gte": "10"});
+    /// let filter = FilterOperator::from_json(&params, &["status", "price"]).unwrap();
+    /// assert!(FilterOperator::from_json(&params, &["status"]).is_err());
+    /// ```
+    pub fn from_json(params: &serde_json::Value, allowed: &[&str]) -> Result<Self> {
+        let serde_json::Value::Object(params) = params else {
+            return Err(Error::Validation(
+                "Filter parameters must be a JSON object".to_string(),
+            ));
+        };
+
+        let mut filters = Vec::with_capacity(params.len());
+        for (key, value) in params {
+            let (column, op) = if allowed.contains(&key.as_str()) {
+                (key.as_str(), "eq")
+            } else {
+                match key.rsplit_once('_') {
+                    Some((column, op)) if allowed.contains(&column) => (column, op),
+                    _ => {
+                        return Err(Error::Validation(format!(
+                            "Column '{key}' is not filterable"
+                        )))
+                    }
+                }
+            };
+
+            let scalar = || match value {
+                serde_json::Value::Array(_) | serde_json::Value::Object(_) => Err(
+                    Error::Validation(format!("Filter '{key}' expects a single value")),
+                ),
+                _ => Ok(Value::from(value.clone())),
+            };
+            let filter = match op {
+                "eq" => Filter::eq(column, scalar()?),
+                "ne" => Filter::ne(column, scalar()?),
+                "gt" => Filter::gt(column, scalar()?),
+                "gte" => Filter::ge(column, scalar()?),
+                "lt" => Filter::lt(column, scalar()?),
+                "lte" => Filter::le(column, scalar()?),
+                "contains" => {
+                    let text = match scalar()? {
+                        Value::Text(text) => text,
+                        other => serde_json::Value::from(other).to_string(),
+                    };
+                    Filter::like_escaped(column, format!("%{}%", Filter::escape_like(&text)))
+                }
+                "in" => {
+                    let values = match value {
+                        serde_json::Value::Array(items) => {
+                            items.iter().cloned().map(Value::from).collect()
+                        }
+                        serde_json::Value::String(list) => list
+                            .split(',')
+                            .map(|item| Value::Text(item.trim().to_string()))
+                            .collect(),
+                        _ => vec![scalar()?],
+                    };
+                    Filter::in_list(column, values)
+                }
+                "null" => match value {
+                    serde_json::Value::Bool(true) => Filter::is_null(column),
+                    serde_json::Value::Bool(false) => Filter::is_not_null(column),
+                    serde_json::Value::String(flag) if flag == "true" => Filter::is_null(column),
+                    serde_json::Value::String(flag) if flag == "false" => {
+                        Filter::is_not_null(column)
+                    }
+                    _ => {
+                        return Err(Error::Validation(format!(
+                            "Filter '{key}' expects true or false"
+                        )))
+                    }
+                },
+                _ => {
+                    return Err(Error::Validation(format!(
+                        "Unknown filter operator '{op}' in '{key}'"
+                    )))
+                }
+            };
+            filters.push(FilterOperator::Single(filter));
+        }
+        Ok(FilterOperator::And(filters))
+    }
+
+    /// Collect the `column = value` pairs this filter requires
+    ///
+    /// Only equality conditions that are combined with AND are returned, since
+    /// those are the values every matching row must have.
+    pub fn equality_values(&self) -> HashMap<String, Value> {
+        let mut values = HashMap::new();
+        match self {
+            FilterOperator::Single(Filter {
+                column,
+                operator: Operator::Eq,
+                value: FilterValue::Single(value),
+            }) => {
+                values.insert(column.clone(), value.clone());
+            }
+            FilterOperator::And(filters) => {
+                for filter in filters {
+                    values.extend(filter.equality_values());
+                }
+            }
+            _ => {}
+        }
+        values
+    }
 }
 
 impl std::ops::Not for FilterOperator {
@@ -395,7 +723,7 @@ impl SearchFilter {
             let filter = if self.exact_match {
                 Filter::eq(column, &*self.query)
             } else {
-                Filter::like(column, format!("%{}%", self.query))
+                Filter::like_escaped(column, format!("%{}%", Filter::escape_like(&self.query)))
             };
             filters.push(FilterOperator::Single(filter));
         }
@@ -433,17 +761,17 @@ impl SearchFilter {
 
         for column in &self.columns {
             let pattern = if self.exact_match {
-                self.query.clone()
+                Filter::escape_like(&self.query)
             } else {
-                format!("%{}%", self.query)
+                format!("%{}%", Filter::escape_like(&self.query))
             };
 
             let filter = if self.case_sensitive {
-                Filter::like(column.clone(), pattern)
+                Filter::like_escaped(column.clone(), pattern)
             } else {
                 // For case-insensitive search, we'll use LOWER() function
                 // This will be handled in the query builder
-                Filter::like(column.clone(), pattern)
+                Filter::like_escaped(column.clone(), pattern)
             };
 
             filters.push(FilterOperator::Single(filter));
@@ -476,6 +804,10 @@ impl SearchFilter {
 ///     Sort::new("priority", SortOrder::Desc),
 ///     Sort::new("name", SortOrder::Asc),
 /// ];
+///
+/// // Records without a due date go last
+/// let due = Sort::asc("due_at").nulls_last();
+/// assert_eq!(due.to_string(), "due_at ASC NULLS LAST");
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Sort {
@@ -483,6 +815,9 @@ pub struct Sort {
     pub column: String,
     /// Sort order
     pub order: crate::SortOrder,
+    /// Placement of NULLs, the database default when `None`
+    #[serde(default)]
+    pub nulls: Option<crate::NullsOrder>,
 }
 
 impl Sort {
@@ -491,19 +826,20 @@ impl Sort {
         Self {
             column: column.into(),
             order,
+            nulls: None,
         }
     }
 
     /// Create a new sort with boolean flag for ascending
     pub fn new_bool(column: impl Into<String>, ascending: bool) -> Self {
-        Self {
-            column: column.into(),
-            order: if ascending {
+        Self::new(
+            column,
+            if ascending {
                 crate::SortOrder::Asc
             } else {
                 crate::SortOrder::Desc
             },
-        }
+        )
     }
 
     /// Create an ascending sort
@@ -515,4 +851,26 @@ impl Sort {
     pub fn desc(column: impl Into<String>) -> Self {
         Self::new(column, crate::SortOrder::Desc)
     }
+
+    /// Sort NULLs before all other values
+    pub fn nulls_first(mut self) -> Self {
+        self.nulls = Some(crate::NullsOrder::First);
+        self
+    }
+
+    /// Sort NULLs after all other values
+    pub fn nulls_last(mut self) -> Self {
+        self.nulls = Some(crate::NullsOrder::Last);
+        self
+    }
+}
+
+impl std::fmt::Display for Sort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.column, self.order)?;
+        if let Some(nulls) = self.nulls {
+            write!(f, " {nulls}")?;
+        }
+        Ok(())
+    }
 }
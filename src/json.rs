@@ -0,0 +1,143 @@
+//! JSON aggregation of related rows
+//!
+//! A [`JsonColumn`] selects related rows as one JSON value next to each
+//! parent row, built with `json_group_array` and `json_object`, so a parent
+//! and its children are read in one query instead of two:
+//!
+//! ```rust
+//! use libsql_orm::{JsonColumn, QueryBuilder, Sort};
+//!
+//! let (sql, _) = QueryBuilder::new("users")
+//!     .select(vec!["users.id", "users.name"])
+//!     .json_column(
+//!         JsonColumn::array("posts", "posts.user_id = users.id")
+//!             .columns(vec!["id", "title"])
+//!             .order_by(Sort::asc("posts.id")),
+//!     )
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(
+//!     sql,
+//!     "SELECT users.id, users.name, (SELECT json_group_array(json(item)) FROM \
+//!      (SELECT json_object('id', posts.id, 'title', posts.title) AS item FROM posts \
+//!      WHERE posts.user_id = users.id ORDER BY posts.id ASC)) AS posts FROM users"
+//! );
+//! ```
+//!
+//! [`QueryBuilder::execute`](crate::QueryBuilder::execute) parses the column
+//! back into JSON, so it deserializes into a nested field such as
+//! `posts: Vec<Post>`, or `Option<Author>` for [`JsonColumn::object`].
+
+use crate::Sort;
+
+#[derive(Debug, Clone)]
+enum Shape {
+    Array,
+    Object,
+}
+
+/// Related rows selected as a JSON array or object column
+#[derive(Debug, Clone)]
+pub struct JsonColumn {
+    shape: Shape,
+    table: String,
+    condition: String,
+    fields: Vec<(String, String)>,
+    order_by: Vec<Sort>,
+    alias: String,
+}
+
+impl JsonColumn {
+    fn new(shape: Shape, table: &str, condition: &str) -> Self {
+        Self {
+            shape,
+            table: table.to_string(),
+            condition: condition.to_string(),
+            fields: Vec::new(),
+            order_by: Vec::new(),
+            alias: table.to_string(),
+        }
+    }
+
+    /// All rows of `table` matching `condition`, as a JSON array of objects
+    ///
+    /// `condition` usually correlates with the outer query, e.g.
+    /// `posts.user_id = users.id`. No matching rows give an empty array.
+    pub fn array(table: &str, condition: &str) -> Self {
+        Self::new(Shape::Array, table, condition)
+    }
+
+    /// The first row of `table` matching `condition`, as a JSON object
+    ///
+    /// No matching row gives NULL.
+    pub fn object(table: &str, condition: &str) -> Self {
+        Self::new(Shape::Object, table, condition)
+    }
+
+    /// Include columns of the related table under their own names
+    pub fn columns(mut self, columns: Vec<&str>) -> Self {
+        for column in columns {
+            let expr = format!("{}.{column}", self.table);
+            self.fields.push((column.to_string(), expr));
+        }
+        self
+    }
+
+    /// Include an SQL expression under the given key
+    pub fn field(mut self, key: &str, expr: &str) -> Self {
+        self.fields.push((key.to_string(), expr.to_string()));
+        self
+    }
+
+    /// Include another JSON column, nested under its alias
+    pub fn nest(mut self, column: JsonColumn) -> Self {
+        let expr = format!("json({})", column.subquery());
+        self.fields.push((column.alias.clone(), expr));
+        self
+    }
+
+    /// Order the related rows
+    pub fn order_by(mut self, sort: Sort) -> Self {
+        self.order_by.push(sort);
+        self
+    }
+
+    /// Name the resulting column, the table name by default
+    pub fn alias(mut self, alias: impl Into<String>) -> Self {
+        self.alias = alias.into();
+        self
+    }
+
+    /// Name of the resulting column
+    pub fn name(&self) -> &str {
+        &self.alias
+    }
+
+    fn subquery(&self) -> String {
+        let pairs: Vec<String> = self
+            .fields
+            .iter()
+            .map(|(key, expr)| format!("'{}', {expr}", key.replace('\'', "''")))
+            .collect();
+        let object = format!("json_object({})", pairs.join(", "));
+        let mut rows = format!("FROM {} WHERE {}", self.table, self.condition);
+        if !self.order_by.is_empty() {
+            let sorts: Vec<String> = self.order_by.iter().map(|sort| sort.to_string()).collect();
+            rows.push_str(&format!(" ORDER BY {}", sorts.join(", ")));
+        }
+        match self.shape {
+            // Aggregating an ordered subquery keeps the array in that order
+            Shape::Array => {
+                format!(
+                    "(SELECT json_group_array(json(item)) FROM (SELECT {object} AS item {rows}))"
+                )
+            }
+            Shape::Object => format!("(SELECT {object} {rows} LIMIT 1)"),
+        }
+    }
+
+    /// Build the SQL expression, including the alias
+    pub fn to_sql(&self) -> String {
+        format!("{} AS {}", self.subquery(), self.alias)
+    }
+}
@@ -194,27 +194,82 @@
 //!     Response::from_json(&users)
 //! }
 //! ```
+// Allows the derive macros to refer to `libsql_orm` from inside this crate
+extern crate self as libsql_orm;
+
+mod archive;
+#[cfg(feature = "attachments")]
+pub mod attachments;
+pub mod backfill;
+pub mod coercion;
+pub mod compression;
+pub mod content_hash;
+pub mod context;
 pub mod database;
+pub mod diff;
 pub mod error;
+pub mod explain;
 pub mod filters;
+pub mod json;
+pub mod loader;
+pub mod lww;
 pub mod macros;
+pub mod maintenance;
+pub mod metrics;
 pub mod migrations;
 pub mod model;
+pub mod options;
 pub mod pagination;
+pub mod prefetch;
+pub mod projection;
 pub mod query;
+pub mod registry;
+pub mod replica;
+pub mod retention;
+pub mod retry;
+mod runtime;
+pub mod schema;
+pub mod scope;
+mod script;
+mod similarity;
+pub mod statement_cache;
+pub mod sync;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod testing;
 pub mod types;
+pub mod unit_of_work;
+pub mod watcher;
+pub mod window;
 
 #[cfg(test)]
 mod tests;
 
-pub use database::Database;
+pub use backfill::{Backfill, BackfillReport};
+pub use context::RequestContext;
+pub use database::{Database, QueryHint};
+pub use diff::{ColumnChange, ModelDiff};
 pub use error::{Error, Result};
+pub use explain::{PlanStep, QueryPlan};
 pub use filters::{Filter, FilterOperator, SearchFilter, Sort};
-pub use migrations::{templates, Migration, MigrationBuilder, MigrationManager};
+pub use json::JsonColumn;
+pub use loader::BatchLoader;
+pub use migrations::{
+    templates, Migration, MigrationBuilder, MigrationCondition, MigrationManager, Verification,
+};
 pub use model::Model;
+pub use options::{DatabaseOptions, JournalMode, Synchronous};
 pub use pagination::{CursorPaginatedResult, CursorPagination, PaginatedResult, Pagination};
-pub use query::{QueryBuilder, QueryResult};
+pub use projection::Projection;
+pub use query::{QueryBuilder, QueryResult, Statement};
+pub use registry::{find_model, registry, ColumnKind, ColumnMeta, ModelMeta, RetentionPolicy};
+pub use replica::SyncConfig;
+pub use retry::RetryPolicy;
+pub use scope::ScopedDatabase;
+pub use statement_cache::StatementCacheStats;
 pub use types::*;
+pub use unit_of_work::UnitOfWork;
+pub use watcher::Watcher;
+pub use window::WindowFunction;
 
 // Export the boolean deserializer
 pub use types::deserialize_bool;
@@ -224,5 +279,9 @@ pub use chrono;
 pub use serde::{Deserialize, Serialize};
 pub use uuid::Uuid;
 
-/// Re-export the Model macro for convenience
-pub use libsql_orm_macros::{generate_migration, orm_column, Model};
+// Used by the `Model` derive to register models
+#[doc(hidden)]
+pub use inventory;
+
+/// Re-export the derive macros for convenience
+pub use libsql_orm_macros::{generate_migration, orm_column, Model, Projection};
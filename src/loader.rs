@@ -0,0 +1,106 @@
+//! Dataloader-style batching of primary key lookups
+//!
+//! A [`BatchLoader`] coalesces [`load`](BatchLoader::load) calls that are
+//! issued concurrently, for example from futures joined together, into one
+//! `WHERE id IN (...)` query. This removes the N+1 query pattern from nested
+//! resolvers without any GraphQL integration.
+//!
+//! Loaded records are memoized for the lifetime of the loader, so a loader is
+//! meant to live for a single request.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use libsql_orm::{BatchLoader, Database, Model};
+//!
+//! async fn authors<M: Model>(ids: &[i64], db: &Database) -> libsql_orm::Result<Vec<Option<M>>> {
+//!     let loader = BatchLoader::<M>::new(db);
+//!     // Issues a single query for all ids
+//!     futures::future::try_join_all(ids.iter().map(|id| loader.load(*id))).await
+//! }
+//! ```
+
+use crate::{Database, Filter, FilterOperator, Model, Result};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+
+struct LoaderState<M> {
+    pending: BTreeSet<i64>,
+    loaded: HashMap<i64, Option<M>>,
+}
+
+/// Batches concurrent `find_by_id` lookups into single queries
+pub struct BatchLoader<'a, M: Model> {
+    db: &'a Database,
+    state: Mutex<LoaderState<M>>,
+    dispatch: futures::lock::Mutex<()>,
+}
+
+impl<'a, M: Model> BatchLoader<'a, M> {
+    /// Create a loader for the given database
+    pub fn new(db: &'a Database) -> Self {
+        Self {
+            db,
+            state: Mutex::new(LoaderState {
+                pending: BTreeSet::new(),
+                loaded: HashMap::new(),
+            }),
+            dispatch: futures::lock::Mutex::new(()),
+        }
+    }
+
+    /// Load a record by primary key
+    ///
+    /// All ids requested before the loader's next dispatch are fetched
+    /// together.
+    pub async fn load(&self, id: i64) -> Result<Option<M>> {
+        {
+            let mut state = self.state.lock().unwrap();
+            if let Some(record) = state.loaded.get(&id) {
+                return Ok(record.clone());
+            }
+            state.pending.insert(id);
+        }
+
+        // Give sibling futures the chance to queue their ids
+        crate::runtime::yield_now().await;
+        let _dispatch = self.dispatch.lock().await;
+
+        let ids: Vec<i64> = {
+            let mut state = self.state.lock().unwrap();
+            if let Some(record) = state.loaded.get(&id) {
+                return Ok(record.clone());
+            }
+            // A failed dispatch may have taken this id without loading it
+            state.pending.insert(id);
+            std::mem::take(&mut state.pending).into_iter().collect()
+        };
+
+        let filter = FilterOperator::Single(Filter::in_values(M::primary_key(), ids.clone()));
+        let records = M::query_builder()
+            .r#where(filter)
+            .execute_models::<M>(self.db)
+            .await?;
+
+        let mut state = self.state.lock().unwrap();
+        for id in ids {
+            state.loaded.entry(id).or_insert(None);
+        }
+        for record in records {
+            if let Some(key) = record.get_primary_key() {
+                state.loaded.insert(key, Some(record));
+            }
+        }
+        Ok(state.loaded.get(&id).cloned().flatten())
+    }
+
+    /// Load several records, preserving the order of `ids`
+    pub async fn load_many(&self, ids: &[i64]) -> Result<Vec<Option<M>>> {
+        futures::future::try_join_all(ids.iter().map(|id| self.load(*id))).await
+    }
+
+    /// Forget all memoized records
+    pub fn clear(&self) {
+        self.state.lock().unwrap().loaded.clear();
+    }
+}
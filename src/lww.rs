@@ -0,0 +1,284 @@
+//! Last-write-wins merging of columns
+//!
+//! Columns marked `#[orm_column(lww)]` carry a clock per row, stored in a side
+//! table, recording when each was last written. Merging two versions of a row
+//! keeps, column by column, the value with the later clock, so concurrent edits
+//! to different columns both survive.
+//!
+//! Clocks are plain integers chosen by the application, such as epoch
+//! milliseconds or a hybrid logical clock. Equal clocks are broken by comparing
+//! the values, which makes [`merge`] deterministic and independent of the order
+//! in which replicas exchange changes.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use libsql_orm::lww::{self, LwwRow};
+//! use libsql_orm::{Database, Model};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+//! struct Profile {
+//!     pub id: Option<i64>,
+//!     #[orm_column(lww)]
+//!     pub name: String,
+//!     #[orm_column(lww)]
+//!     pub bio: String,
+//! }
+//!
+//! async fn receive(db: &Database, remote: LwwRow) -> libsql_orm::Result<Option<Profile>> {
+//!     lww::setup(db).await?;
+//!     lww::merge_into::<Profile>(1, &remote, db).await
+//! }
+//! ```
+
+use crate::model::mirror_column;
+use crate::sync::Row;
+use crate::{Database, Error, Model, Result, Value};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Clock of the last write to each column, keyed by column name
+pub type Clocks = HashMap<String, i64>;
+
+/// Table holding the column clocks of every row
+const CLOCKS_TABLE: &str = "_lww_clocks";
+
+/// A row's values along with the clocks of its columns
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LwwRow {
+    /// Column values
+    pub values: Row,
+    /// Clock of each column's last write
+    pub clocks: Clocks,
+}
+
+/// Merge two versions of a row
+///
+/// For every column with a clock on either side, the value with the later
+/// clock wins; on equal clocks the greater value wins. Columns without clocks
+/// keep the local value, falling back to the remote one.
+pub fn merge(local: &LwwRow, remote: &LwwRow) -> LwwRow {
+    let mut merged = local.clone();
+    for (column, value) in &remote.values {
+        merged
+            .values
+            .entry(column.clone())
+            .or_insert_with(|| value.clone());
+    }
+
+    for (column, &remote_clock) in &remote.clocks {
+        let remote_value = remote.values.get(column).unwrap_or(&Value::Null);
+        let remote_wins = match local.clocks.get(column) {
+            None => true,
+            Some(&local_clock) => {
+                let local_value = local.values.get(column).unwrap_or(&Value::Null);
+                remote_clock
+                    .cmp(&local_clock)
+                    .then_with(|| compare_values(remote_value, local_value))
+                    == Ordering::Greater
+            }
+        };
+        if remote_wins {
+            merged.values.insert(column.clone(), remote_value.clone());
+            merged.clocks.insert(column.clone(), remote_clock);
+        }
+    }
+    merged
+}
+
+/// A total order on values, following SQLite's ordering of storage classes
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+    fn rank(value: &Value) -> u8 {
+        match value {
+            Value::Null => 0,
+            Value::Integer(_) | Value::Real(_) | Value::Boolean(_) => 1,
+            Value::Text(_) => 2,
+            Value::Blob(_) => 3,
+        }
+    }
+    fn number(value: &Value) -> f64 {
+        match value {
+            Value::Integer(i) => *i as f64,
+            Value::Real(f) => *f,
+            Value::Boolean(b) => *b as i64 as f64,
+            _ => 0.0,
+        }
+    }
+
+    rank(a).cmp(&rank(b)).then_with(|| match (a, b) {
+        (Value::Text(a), Value::Text(b)) => a.cmp(b),
+        (Value::Blob(a), Value::Blob(b)) => a.cmp(b),
+        _ => number(a).total_cmp(&number(b)),
+    })
+}
+
+/// Create the table holding column clocks
+pub async fn setup(db: &Database) -> Result<()> {
+    db.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {CLOCKS_TABLE} (table_name TEXT NOT NULL, \
+             row_id INTEGER NOT NULL, column_name TEXT NOT NULL, clock INTEGER NOT NULL, \
+             PRIMARY KEY (table_name, row_id, column_name))"
+        ),
+        vec![],
+    )
+    .await?;
+    Ok(())
+}
+
+/// Names of a model's last-write-wins columns
+pub fn lww_columns<M: Model>() -> Vec<&'static str> {
+    M::column_meta()
+        .iter()
+        .filter(|column| column.lww)
+        .map(|column| column.name)
+        .collect()
+}
+
+/// Load the column clocks of a row
+pub async fn clocks<M: Model>(id: i64, db: &Database) -> Result<Clocks> {
+    let sql = format!(
+        "SELECT column_name, clock FROM {CLOCKS_TABLE} WHERE table_name = ? AND row_id = ?"
+    );
+    let mut rows = db
+        .query(&sql, vec![M::table_name().into(), id.into()])
+        .await?;
+    let mut clocks = Clocks::new();
+    while let Some(row) = rows.next().await? {
+        clocks.insert(row.get::<String>(0)?, row.get::<i64>(1)?);
+    }
+    Ok(clocks)
+}
+
+/// Record a local write to some of a model's last-write-wins columns
+///
+/// An empty `columns` list stamps all of them. Call this after saving the
+/// model, with the clock of the write.
+pub async fn stamp<M: Model>(model: &M, columns: &[&str], clock: i64, db: &Database) -> Result<()> {
+    let id = model
+        .get_primary_key()
+        .ok_or_else(|| Error::Validation("Cannot stamp record without primary key".to_string()))?;
+    let lww = lww_columns::<M>();
+    let columns: Vec<&str> = if columns.is_empty() {
+        lww.clone()
+    } else {
+        columns.to_vec()
+    };
+    if let Some(column) = columns.iter().find(|column| !lww.contains(column)) {
+        return Err(Error::Validation(format!(
+            "Column '{column}' of table {} is not last-write-wins",
+            M::table_name()
+        )));
+    }
+
+    let clocks = columns
+        .into_iter()
+        .map(|column| (column.to_string(), clock))
+        .collect();
+    write_clocks::<M>(id, &clocks, db).await
+}
+
+/// Load a row and its column clocks
+pub async fn load<M: Model>(id: i64, db: &Database) -> Result<Option<LwwRow>> {
+    let Some(model) = M::find_by_id(id, db).await? else {
+        return Ok(None);
+    };
+    Ok(Some(LwwRow {
+        values: model.to_map()?,
+        clocks: clocks::<M>(id, db).await?,
+    }))
+}
+
+/// Merge a remote version of a row into the database
+///
+/// Only the model's last-write-wins columns are taken from `remote`; other
+/// values are used just when the row does not exist yet. Runs in a transaction
+/// and returns the merged record.
+pub async fn merge_into<M: Model>(id: i64, remote: &LwwRow, db: &Database) -> Result<Option<M>> {
+    let lww = lww_columns::<M>();
+    if let Some(column) = remote.clocks.keys().find(|c| !lww.contains(&c.as_str())) {
+        return Err(Error::Validation(format!(
+            "Column '{column}' of table {} is not last-write-wins",
+            M::table_name()
+        )));
+    }
+
+    db.transaction(|tx| async move {
+        let local = match load::<M>(id, tx).await? {
+            Some(local) => local,
+            None => {
+                let mut values = remote.values.clone();
+                values.retain(|column, _| M::columns().contains(&column.as_str()));
+                values.insert(M::primary_key().to_string(), Value::Integer(id));
+                let mut model = M::from_map(values)?;
+                model.set_primary_key(id);
+                let (sql, params) = model.insert_statement()?;
+                tx.execute(&sql, params).await?;
+                LwwRow::default()
+            }
+        };
+
+        let merged = merge(&local, remote);
+        let changed: Vec<&String> = merged
+            .clocks
+            .iter()
+            .filter(|(column, clock)| local.clocks.get(*column) != Some(clock))
+            .map(|(column, _)| column)
+            .collect();
+        if !changed.is_empty() {
+            let mut sets = Vec::with_capacity(changed.len());
+            let mut params = Vec::with_capacity(changed.len() + 1);
+            for column in &changed {
+                let value = libsql::Value::from(merged.values[*column].clone());
+                sets.push(format!("{column} = ?"));
+                params.push(value.clone());
+                if let Some(mirror) = mirror_column::<M>(column) {
+                    sets.push(format!("{mirror} = ?"));
+                    params.push(value);
+                }
+            }
+            let sql = format!(
+                "UPDATE {} SET {} WHERE {} = ?",
+                M::table_name(),
+                sets.join(", "),
+                M::primary_key()
+            );
+            params.push(libsql::Value::Integer(id));
+            tx.execute(&sql, params).await?;
+        }
+        write_clocks::<M>(id, &merged.clocks, tx).await?;
+        let hash = M::column_meta()
+            .iter()
+            .find(|meta| changed.iter().any(|c| meta.hash_of.contains(&c.as_str())));
+        if let Some(hash) = hash {
+            // The hash is computed in Rust, so rewrite it from the merged row
+            if let Some(model) = M::find_by_id(id, tx).await? {
+                model.update_fields(&[hash.name], tx).await?;
+            }
+        }
+        M::find_by_id(id, tx).await
+    })
+    .await
+}
+
+async fn write_clocks<M: Model>(id: i64, clocks: &Clocks, db: &Database) -> Result<()> {
+    let sql = format!(
+        "INSERT INTO {CLOCKS_TABLE} (table_name, row_id, column_name, clock) VALUES (?, ?, ?, ?) \
+         ON CONFLICT(table_name, row_id, column_name) DO UPDATE SET clock = excluded.clock"
+    );
+    for (column, clock) in clocks {
+        db.execute(
+            &sql,
+            vec![
+                libsql::Value::Text(M::table_name().to_string()),
+                libsql::Value::Integer(id),
+                libsql::Value::Text(column.clone()),
+                libsql::Value::Integer(*clock),
+            ],
+        )
+        .await?;
+    }
+    Ok(())
+}
@@ -5,8 +5,76 @@
 pub use libsql_orm_macros::Model;
 
 /// Helper macro for creating filter conditions
+///
+/// Besides the comma-separated forms building a single [`Filter`](crate::Filter),
+/// it accepts a boolean expression over columns and builds the matching
+/// [`FilterOperator`](crate::FilterOperator) tree. Columns are plain
+/// identifiers compared with `==`, `!=`, `<`, `<=`, `>` or `>=`; conditions
+/// combine with `&&`, `||` (which binds looser), `!` and parentheses. The
+/// values are Rust expressions bound as parameters.
+///
+/// ```
+/// use libsql_orm::filter;
+///
+/// let min_price = 100.0;
+/// let condition = filter!(status == "active" && price > min_price || category == "sale");
+/// let excluded = filter!(!(role == "admin" || role == "owner") && age >= 18i64);
+/// ```
 #[macro_export]
 macro_rules! filter {
+    // `||` splits the expression into operands of an OR
+    (@or [$($done:expr,)*] [$($current:tt)*] || $($rest:tt)*) => {
+        $crate::filter!(@or [$($done,)* $crate::filter!(@and [] [] $($current)*),] [] $($rest)*)
+    };
+    (@or [$($done:expr,)*] [$($current:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::filter!(@or [$($done,)*] [$($current)* $next] $($rest)*)
+    };
+    (@or [] [$($current:tt)*]) => {
+        $crate::filter!(@and [] [] $($current)*)
+    };
+    (@or [$($done:expr,)+] [$($current:tt)*]) => {
+        $crate::FilterOperator::or(vec![$($done,)+ $crate::filter!(@and [] [] $($current)*)])
+    };
+
+    // `&&` splits each operand into conditions of an AND
+    (@and [$($done:expr,)*] [$($current:tt)*] && $($rest:tt)*) => {
+        $crate::filter!(@and [$($done,)* $crate::filter!(@atom $($current)*),] [] $($rest)*)
+    };
+    (@and [$($done:expr,)*] [$($current:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::filter!(@and [$($done,)*] [$($current)* $next] $($rest)*)
+    };
+    (@and [] [$($current:tt)*]) => {
+        $crate::filter!(@atom $($current)*)
+    };
+    (@and [$($done:expr,)+] [$($current:tt)*]) => {
+        $crate::FilterOperator::and(vec![$($done,)+ $crate::filter!(@atom $($current)*)])
+    };
+
+    (@atom ($($inner:tt)+)) => {
+        $crate::filter!(@or [] [] $($inner)+)
+    };
+    (@atom ! $($inner:tt)+) => {
+        $crate::FilterOperator::negate($crate::filter!(@atom $($inner)+))
+    };
+    (@atom $column:ident == $($value:tt)+) => {
+        $crate::FilterOperator::Single($crate::Filter::eq(stringify!($column), ($($value)+)))
+    };
+    (@atom $column:ident != $($value:tt)+) => {
+        $crate::FilterOperator::Single($crate::Filter::ne(stringify!($column), ($($value)+)))
+    };
+    (@atom $column:ident <= $($value:tt)+) => {
+        $crate::FilterOperator::Single($crate::Filter::le(stringify!($column), ($($value)+)))
+    };
+    (@atom $column:ident >= $($value:tt)+) => {
+        $crate::FilterOperator::Single($crate::Filter::ge(stringify!($column), ($($value)+)))
+    };
+    (@atom $column:ident < $($value:tt)+) => {
+        $crate::FilterOperator::Single($crate::Filter::lt(stringify!($column), ($($value)+)))
+    };
+    (@atom $column:ident > $($value:tt)+) => {
+        $crate::FilterOperator::Single($crate::Filter::gt(stringify!($column), ($($value)+)))
+    };
+
     ($column:expr, $op:expr, $value:expr) => {
         $crate::Filter::new($column, $op, $crate::FilterValue::Single($value.into()))
     };
@@ -34,6 +102,10 @@ macro_rules! filter {
     ($column:expr, is_not_null) => {
         $crate::Filter::is_not_null($column)
     };
+
+    ($($expression:tt)+) => {
+        $crate::filter!(@or [] [] $($expression)+)
+    };
 }
 
 /// Helper macro for creating sort specifications
@@ -92,10 +164,53 @@ macro_rules! filter_op {
     };
 
     (not, $filter:expr) => {
-        $crate::FilterOperator::not($filter)
+        $crate::FilterOperator::negate($filter)
     };
 
     ($filter:expr) => {
         $crate::FilterOperator::Single($filter)
     };
 }
+
+/// Generates unit tests checking that models match the schema they declare
+///
+/// For every listed model, a `model_schema_tests::<Model>` test module
+/// checks that its migration SQL runs on SQLite, that its column names are
+/// unique, and that its `Default` value survives conversion to a row and
+/// back. See [`testing`](crate::testing) for the checks. The models must
+/// implement `Default` and be in scope; invoke the macro once per module.
+/// Not available on Cloudflare Workers.
+///
+/// ```ignore
+/// libsql_orm::model_schema_tests!(User, Post);
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+#[macro_export]
+macro_rules! model_schema_tests {
+    ($($model:ident),+ $(,)?) => {
+        #[cfg(test)]
+        mod model_schema_tests {
+            $(
+                #[allow(non_snake_case)]
+                mod $model {
+                    #[test]
+                    fn migration_sql_runs() {
+                        $crate::testing::check_migration_sql::<super::super::$model>();
+                    }
+
+                    #[test]
+                    fn columns_are_unique() {
+                        $crate::testing::check_unique_columns::<super::super::$model>();
+                    }
+
+                    #[test]
+                    fn default_round_trips() {
+                        $crate::testing::check_round_trip(
+                            &<super::super::$model as ::core::default::Default>::default(),
+                        );
+                    }
+                }
+            )+
+        }
+    };
+}
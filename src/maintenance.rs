@@ -0,0 +1,180 @@
+//! Scheduled maintenance
+//!
+//! [`run_scheduled`] runs a list of housekeeping [`Task`]s one after another:
+//! purging expired rows, sweeping soft-deleted rows past their retention,
+//! refreshing planner statistics, and any custom job such as a
+//! [`Backfill`](crate::Backfill). It is meant to be called from a Cloudflare
+//! Workers `#[event(scheduled)]` handler, so a failing task is reported and
+//! the remaining tasks still run.
+//!
+//! Timestamps are compared in the format models store `DateTime<Utc>`
+//! fields in, so the columns should hold values written through a model.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use libsql_orm::maintenance::{run_scheduled, Task};
+//! use libsql_orm::{Backfill, Database, Model};
+//! use std::time::Duration;
+//!
+//! async fn on_cron<M: Model>(db: &Database) {
+//!     let tasks = vec![
+//!         Task::purge_expired("sessions", "expires_at"),
+//!         Task::sweep_soft_deleted("posts", "deleted_at", 30),
+//!         Task::custom("normalize_emails", |db| {
+//!             Box::pin(async move {
+//!                 let report = Backfill::<M>::new("normalize_emails")
+//!                     .time_budget(Duration::from_secs(10))
+//!                     .run(db, |rows, tx| async move {
+//!                         for row in rows {
+//!                             row.update(tx).await?;
+//!                         }
+//!                         Ok(())
+//!                     })
+//!                     .await?;
+//!                 Ok(report.rows)
+//!             })
+//!         }),
+//!         Task::analyze(),
+//!     ];
+//!     for report in run_scheduled(db, &tasks).await {
+//!         if let Err(error) = &report.outcome {
+//!             println!("{} failed: {error}", report.name);
+//!         }
+//!     }
+//! }
+//! ```
+
+use crate::{Database, Result, Value};
+use chrono::Utc;
+use futures::future::LocalBoxFuture;
+
+type CustomTask = Box<dyn for<'a> Fn(&'a Database) -> LocalBoxFuture<'a, Result<u64>>>;
+
+enum TaskKind {
+    PurgeExpired {
+        table: String,
+        column: String,
+    },
+    SweepSoftDeleted {
+        table: String,
+        column: String,
+        days: u32,
+    },
+    Analyze,
+    Custom(CustomTask),
+}
+
+/// One maintenance job for [`run_scheduled`]
+pub struct Task {
+    name: String,
+    kind: TaskKind,
+}
+
+impl Task {
+    /// Delete rows whose expiry timestamp has passed
+    pub fn purge_expired(table: &str, column: &str) -> Self {
+        Self {
+            name: format!("purge_expired:{table}"),
+            kind: TaskKind::PurgeExpired {
+                table: table.to_string(),
+                column: column.to_string(),
+            },
+        }
+    }
+
+    /// Delete rows soft-deleted more than `days` days ago
+    ///
+    /// `column` holds the deletion timestamp and is NULL for live rows.
+    pub fn sweep_soft_deleted(table: &str, column: &str, days: u32) -> Self {
+        Self {
+            name: format!("sweep_soft_deleted:{table}"),
+            kind: TaskKind::SweepSoftDeleted {
+                table: table.to_string(),
+                column: column.to_string(),
+                days,
+            },
+        }
+    }
+
+    /// Refresh the query planner's statistics with `ANALYZE`
+    pub fn analyze() -> Self {
+        Self {
+            name: "analyze".to_string(),
+            kind: TaskKind::Analyze,
+        }
+    }
+
+    /// Run arbitrary work, returning the number of rows it affected
+    pub fn custom<F>(name: impl Into<String>, task: F) -> Self
+    where
+        F: for<'a> Fn(&'a Database) -> LocalBoxFuture<'a, Result<u64>> + 'static,
+    {
+        Self {
+            name: name.into(),
+            kind: TaskKind::Custom(Box::new(task)),
+        }
+    }
+
+    /// The name the task is reported under
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn run(&self, db: &Database) -> Result<u64> {
+        let affected = match &self.kind {
+            TaskKind::PurgeExpired { table, column } => {
+                db.execute(
+                    &format!("DELETE FROM {table} WHERE {column} < ?"),
+                    vec![now_before(0)?],
+                )
+                .await?
+            }
+            TaskKind::SweepSoftDeleted {
+                table,
+                column,
+                days,
+            } => {
+                db.execute(
+                    &format!("DELETE FROM {table} WHERE {column} IS NOT NULL AND {column} < ?"),
+                    vec![now_before(*days)?],
+                )
+                .await?
+            }
+            TaskKind::Analyze => {
+                // The reported change count would be the previous statement's
+                db.execute("ANALYZE", vec![]).await?;
+                0
+            }
+            TaskKind::Custom(task) => task(db).await?,
+        };
+        Ok(affected)
+    }
+}
+
+/// The current time minus `days`, as a model would store it
+pub(crate) fn now_before(days: u32) -> Result<libsql::Value> {
+    let cutoff = Utc::now() - chrono::Duration::days(days.into());
+    Ok(Value::from(serde_json::to_value(cutoff)?).into())
+}
+
+/// Outcome of one task in [`run_scheduled`]
+#[derive(Debug)]
+pub struct TaskReport {
+    /// The task's name
+    pub name: String,
+    /// Rows affected, or the error the task failed with
+    pub outcome: Result<u64>,
+}
+
+/// Run maintenance tasks in order, continuing past failures
+pub async fn run_scheduled(db: &Database, tasks: &[Task]) -> Vec<TaskReport> {
+    let mut reports = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        reports.push(TaskReport {
+            name: task.name.clone(),
+            outcome: task.run(db).await,
+        });
+    }
+    reports
+}
@@ -0,0 +1,223 @@
+//! Query metrics
+//!
+//! Every statement run through [`Database::query`](crate::Database::query) or
+//! [`Database::execute`](crate::Database::execute) is counted by table and
+//! operation, along with its errors and latency. [`snapshot`] returns the
+//! current values, which render as the Prometheus text exposition format:
+//!
+//! ```no_run
+//! use libsql_orm::metrics;
+//!
+//! fn metrics_route() -> String {
+//!     metrics::snapshot().to_prometheus()
+//! }
+//! ```
+//!
+//! Metrics are process-wide. In Cloudflare Workers they cover the lifetime of
+//! the isolate, so scrape them often or aggregate across isolates.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bounds of the latency histogram buckets, in seconds
+pub const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Latency distribution of a set of queries
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Histogram {
+    /// Number of observations at or below each bound of [`LATENCY_BUCKETS`]
+    pub buckets: [u64; LATENCY_BUCKETS.len()],
+    /// Total of all observations, in seconds
+    pub sum: f64,
+    /// Number of observations
+    pub count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+/// Metrics of one operation on one table
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OperationMetrics {
+    /// Table the statement targets, empty if it could not be determined
+    pub table: String,
+    /// Lowercase statement keyword, such as `select` or `insert`
+    pub operation: String,
+    /// Number of statements run
+    pub queries: u64,
+    /// Number of statements that failed
+    pub errors: u64,
+    /// Statement latency
+    pub latency: Histogram,
+}
+
+/// Point-in-time copy of all query metrics
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Snapshot {
+    /// Metrics per table and operation, sorted by table then operation
+    pub operations: Vec<OperationMetrics>,
+}
+
+impl Snapshot {
+    /// Metrics of a table and operation, if any statement was recorded
+    pub fn get(&self, table: &str, operation: &str) -> Option<&OperationMetrics> {
+        self.operations
+            .iter()
+            .find(|m| m.table == table && m.operation == operation)
+    }
+
+    /// Render in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let labels = |m: &OperationMetrics| {
+            format!(
+                "table=\"{}\",operation=\"{}\"",
+                escape_label(&m.table),
+                escape_label(&m.operation)
+            )
+        };
+
+        writeln!(f, "# HELP libsql_orm_queries_total Statements executed.")?;
+        writeln!(f, "# TYPE libsql_orm_queries_total counter")?;
+        for m in &self.operations {
+            writeln!(f, "libsql_orm_queries_total{{{}}} {}", labels(m), m.queries)?;
+        }
+
+        writeln!(
+            f,
+            "# HELP libsql_orm_query_errors_total Statements that failed."
+        )?;
+        writeln!(f, "# TYPE libsql_orm_query_errors_total counter")?;
+        for m in &self.operations {
+            writeln!(
+                f,
+                "libsql_orm_query_errors_total{{{}}} {}",
+                labels(m),
+                m.errors
+            )?;
+        }
+
+        writeln!(
+            f,
+            "# HELP libsql_orm_query_duration_seconds Statement latency."
+        )?;
+        writeln!(f, "# TYPE libsql_orm_query_duration_seconds histogram")?;
+        for m in &self.operations {
+            let labels = labels(m);
+            for (count, bound) in m.latency.buckets.iter().zip(LATENCY_BUCKETS) {
+                writeln!(
+                    f,
+                    "libsql_orm_query_duration_seconds_bucket{{{labels},le=\"{bound}\"}} {count}"
+                )?;
+            }
+            writeln!(
+                f,
+                "libsql_orm_query_duration_seconds_bucket{{{labels},le=\"+Inf\"}} {}",
+                m.latency.count
+            )?;
+            writeln!(
+                f,
+                "libsql_orm_query_duration_seconds_sum{{{labels}}} {}",
+                m.latency.sum
+            )?;
+            writeln!(
+                f,
+                "libsql_orm_query_duration_seconds_count{{{labels}}} {}",
+                m.latency.count
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+static METRICS: Mutex<BTreeMap<(String, String), OperationMetrics>> = Mutex::new(BTreeMap::new());
+
+/// Get the current query metrics
+pub fn snapshot() -> Snapshot {
+    Snapshot {
+        operations: METRICS.lock().unwrap().values().cloned().collect(),
+    }
+}
+
+/// Clear all query metrics
+pub fn reset() {
+    METRICS.lock().unwrap().clear();
+}
+
+/// Record a finished statement
+pub(crate) fn record(sql: &str, elapsed: Duration, failed: bool) {
+    let (operation, table) = classify(sql);
+    let mut metrics = METRICS.lock().unwrap();
+    let entry = metrics
+        .entry((table.clone(), operation.clone()))
+        .or_insert_with(|| OperationMetrics {
+            table,
+            operation,
+            ..Default::default()
+        });
+    entry.queries += 1;
+    if failed {
+        entry.errors += 1;
+    }
+    entry.latency.observe(elapsed.as_secs_f64());
+}
+
+/// Determine the operation and target table of a statement
+fn classify(sql: &str) -> (String, String) {
+    let words: Vec<&str> = sql.split_whitespace().collect();
+    let operation = words
+        .first()
+        .map(|word| word.to_lowercase())
+        .unwrap_or_default();
+    let after = |keyword: &str| {
+        words
+            .iter()
+            .position(|word| word.eq_ignore_ascii_case(keyword))
+            .and_then(|i| words.get(i + 1))
+    };
+    let table = match operation.as_str() {
+        "select" | "delete" => after("FROM"),
+        "insert" | "replace" => after("INTO"),
+        "update" => words.iter().skip(1).find(|word| {
+            !["OR", "ROLLBACK", "ABORT", "REPLACE", "FAIL", "IGNORE"]
+                .iter()
+                .any(|k| word.eq_ignore_ascii_case(k))
+        }),
+        _ => None,
+    };
+    let table = table
+        .map(|table| {
+            table
+                .split(['(', ',', ';'])
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        })
+        .unwrap_or_default();
+    (operation, table)
+}
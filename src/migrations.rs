@@ -11,6 +11,7 @@
 //! - **Templates**: Pre-built migration templates for common operations
 //! - **History tracking**: Track which migrations have been executed
 //! - **Rollback support**: Reverse migrations with down scripts
+//! - **SQL files**: Load `.sql` files with `-- +migrate Up` / `-- +migrate Down` sections
 //! - **Batch execution**: Run multiple migrations in sequence
 //!
 //! # Basic Usage
@@ -58,7 +59,10 @@
 
 use crate::{database::Database, error::Error};
 use chrono::{DateTime, Utc};
+use futures::future::LocalBoxFuture;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
 
 /// Represents a database migration
 ///
@@ -82,6 +86,186 @@ pub struct Migration {
     pub sql: String,
     pub created_at: DateTime<Utc>,
     pub executed_at: Option<DateTime<Utc>>,
+    /// SQL that reverts the migration, run by
+    /// [`MigrationManager::rollback_migration`]
+    #[serde(default)]
+    pub down_sql: Option<String>,
+    /// Predicate deciding whether the migration's SQL runs, see
+    /// [`MigrationBuilder::run_if`]
+    #[serde(skip)]
+    pub condition: Option<MigrationCondition>,
+    /// Checks run after the migration's SQL, see [`MigrationBuilder::verify`]
+    #[serde(default)]
+    pub verifications: Vec<Verification>,
+    /// Commit the migration even when a verification fails
+    #[serde(default)]
+    pub keep_on_failed_verification: bool,
+}
+
+/// A query whose single value must equal `expect` after a migration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Verification {
+    pub sql: String,
+    pub expect: i64,
+}
+
+impl Verification {
+    /// Run the query, describing the mismatch if there is one
+    async fn failure(&self, db: &Database) -> Result<Option<String>, Error> {
+        let mut rows = db.query(&self.sql, vec![]).await?;
+        let actual = match rows.next().await? {
+            Some(row) => row.get_value(0)?,
+            None => libsql::Value::Null,
+        };
+        Ok(match actual {
+            libsql::Value::Integer(actual) if actual == self.expect => None,
+            libsql::Value::Integer(actual) => Some(format!(
+                "`{}` returned {actual}, expected {}",
+                self.sql, self.expect
+            )),
+            other => Some(format!(
+                "`{}` returned {other:?}, expected {}",
+                self.sql, self.expect
+            )),
+        })
+    }
+}
+
+type Predicate =
+    dyn for<'a> Fn(&'a Database) -> LocalBoxFuture<'a, Result<bool, Error>> + Send + Sync;
+
+/// A check run before a migration, see [`MigrationBuilder::run_if`]
+#[derive(Clone)]
+pub struct MigrationCondition(Arc<Predicate>);
+
+impl MigrationCondition {
+    /// Wrap a predicate
+    pub fn new<F>(predicate: F) -> Self
+    where
+        F: for<'a> Fn(&'a Database) -> LocalBoxFuture<'a, Result<bool, Error>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        Self(Arc::new(predicate))
+    }
+
+    /// Evaluate the predicate
+    pub async fn check(&self, db: &Database) -> Result<bool, Error> {
+        (self.0)(db).await
+    }
+}
+
+impl fmt::Debug for MigrationCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MigrationCondition(..)")
+    }
+}
+
+impl Migration {
+    /// Parse a migration from the contents of a `.sql` file
+    ///
+    /// The file is split into sections by `-- +migrate Up` and
+    /// `-- +migrate Down` marker lines; only comments may come before the
+    /// first marker. `-- +migrate StatementBegin` / `StatementEnd` markers
+    /// are accepted and ignored, since statements are split by SQLite itself.
+    /// A file without markers is used as the up SQL as a whole.
+    ///
+    /// The migration's id is its name, so the same file is recognised as
+    /// executed on later runs. Files can be embedded in the binary, which is
+    /// the only option on Workers:
+    ///
+    /// ```rust
+    /// use libsql_orm::Migration;
+    ///
+    /// let migration = Migration::from_sql(
+    ///     "0001_create_users",
+    ///     "-- +migrate Up
+    ///      CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT);
+    ///      -- +migrate Down
+    ///      DROP TABLE users;",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(migration.down_sql.as_deref(), Some("DROP TABLE users;"));
+    /// ```
+    ///
+    /// With a file system, [`MigrationManager::load_sql_dir`] loads a whole
+    /// directory.
+    pub fn from_sql(name: &str, contents: &str) -> Result<Migration, Error> {
+        #[derive(PartialEq)]
+        enum Section {
+            Preamble,
+            Up,
+            Down,
+        }
+
+        let has_markers = contents
+            .lines()
+            .any(|line| migrate_directive(line).is_some());
+        let mut section = if has_markers {
+            Section::Preamble
+        } else {
+            Section::Up
+        };
+        let mut up = Vec::new();
+        let mut down = Vec::new();
+        for (number, line) in contents.lines().enumerate() {
+            if let Some(directive) = migrate_directive(line) {
+                match directive.to_ascii_lowercase().as_str() {
+                    "up" => section = Section::Up,
+                    "down" => section = Section::Down,
+                    "statementbegin" | "statementend" => {}
+                    _ => {
+                        return Err(Error::Validation(format!(
+                            "Unknown migration marker '+migrate {directive}' on line {} of {name}",
+                            number + 1
+                        )))
+                    }
+                }
+                continue;
+            }
+            match section {
+                Section::Preamble => {
+                    let trimmed = line.trim();
+                    if !trimmed.is_empty() && !trimmed.starts_with("--") {
+                        return Err(Error::Validation(format!(
+                            "SQL before the first '-- +migrate' marker on line {} of {name}",
+                            number + 1
+                        )));
+                    }
+                }
+                Section::Up => up.push(line),
+                Section::Down => down.push(line),
+            }
+        }
+
+        let up = up.join("\n").trim().to_string();
+        if up.is_empty() {
+            return Err(Error::Validation(format!("Migration {name} has no up SQL")));
+        }
+        let down = down.join("\n").trim().to_string();
+        Ok(Migration {
+            id: name.to_string(),
+            name: name.to_string(),
+            sql: up,
+            created_at: Utc::now(),
+            executed_at: None,
+            down_sql: (!down.is_empty()).then_some(down),
+            condition: None,
+            verifications: Vec::new(),
+            keep_on_failed_verification: false,
+        })
+    }
+}
+
+/// The directive of a `-- +migrate <directive>` line
+fn migrate_directive(line: &str) -> Option<&str> {
+    let directive = line
+        .trim()
+        .strip_prefix("--")?
+        .trim_start()
+        .strip_prefix("+migrate")?;
+    directive.split_whitespace().next()
 }
 
 /// Migration manager for handling database schema changes
@@ -120,20 +304,7 @@ impl MigrationManager {
 
     /// Initialize the migration table
     pub async fn init(&self) -> Result<(), Error> {
-        let sql = r#"
-            CREATE TABLE IF NOT EXISTS migrations (
-                id TEXT PRIMARY KEY,
-                name TEXT NOT NULL,
-                sql TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                executed_at TEXT
-            )
-        "#;
-
-        let params = vec![libsql::Value::Null; 0];
-
-        self.db.inner.execute(sql, params).await?;
-        Ok(())
+        init_table(&self.db).await
     }
 
     /// Create a new migration
@@ -144,18 +315,18 @@ impl MigrationManager {
             sql: sql.to_string(),
             created_at: Utc::now(),
             executed_at: None,
+            down_sql: None,
+            condition: None,
+            verifications: Vec::new(),
+            keep_on_failed_verification: false,
         }
     }
 
     /// Get all migrations from the database
     pub async fn get_migrations(&self) -> Result<Vec<Migration>, Error> {
         let sql =
-            "SELECT id, name, sql, created_at, executed_at FROM migrations ORDER BY created_at";
-        let mut rows = self
-            .db
-            .inner
-            .query(sql, vec![libsql::Value::Null; 0])
-            .await?;
+            "SELECT id, name, sql, created_at, executed_at, down_sql FROM migrations ORDER BY created_at";
+        let mut rows = self.db.query(sql, vec![]).await?;
 
         let mut migrations = Vec::new();
         while let Some(row) = rows.next().await? {
@@ -177,6 +348,10 @@ impl MigrationManager {
                             .map(|dt| dt.with_timezone(&Utc))
                     })
                     .transpose()?,
+                down_sql: row.get::<Option<String>>(5)?,
+                condition: None,
+                verifications: Vec::new(),
+                keep_on_failed_verification: false,
             };
             migrations.push(migration);
         }
@@ -185,56 +360,95 @@ impl MigrationManager {
     }
 
     /// Execute a migration
+    ///
+    /// The migration SQL is run as a batch, so it may hold several statements
+    /// separated by semicolons; all of them run in the migration's
+    /// transaction.
+    ///
+    /// A migration with a [`run_if`](MigrationBuilder::run_if) condition that
+    /// does not hold is recorded as executed without running its SQL.
+    ///
+    /// If one of its [verifications](MigrationBuilder::verify) fails, the
+    /// migration is rolled back and an [`Error::Validation`] returned; with
+    /// [`keep_on_failed_verification`](MigrationBuilder::keep_on_failed_verification)
+    /// it is committed and the error still returned.
     pub async fn execute_migration(&self, migration: &Migration) -> Result<(), Error> {
-        // Begin transaction
-        self.db
-            .inner
-            .execute("BEGIN", vec![libsql::Value::Null; 0])
-            .await?;
-
-        // Execute the migration SQL
-        self.db
-            .inner
-            .execute(&migration.sql, vec![libsql::Value::Null; 0])
-            .await?;
-
-        // Record the migration
-        let sql = r#"
-            INSERT INTO migrations (id, name, sql, created_at, executed_at)
-            VALUES (?, ?, ?, ?, ?)
-        "#;
+        let failures = self
+            .db
+            .transaction(|db| async move {
+                let run = match &migration.condition {
+                    Some(condition) => condition.check(db).await?,
+                    None => true,
+                };
+                let mut failures = Vec::new();
+                if run {
+                    db.execute_batch(&migration.sql).await?;
+                    for verification in &migration.verifications {
+                        failures.extend(verification.failure(db).await?);
+                    }
+                }
+                if !failures.is_empty() && !migration.keep_on_failed_verification {
+                    return Err(verification_error(migration, &failures));
+                }
 
-        self.db
-            .inner
-            .execute(
-                sql,
-                vec![
-                    libsql::Value::Text(migration.id.clone()),
-                    libsql::Value::Text(migration.name.clone()),
-                    libsql::Value::Text(migration.sql.clone()),
-                    libsql::Value::Text(migration.created_at.to_rfc3339()),
-                    libsql::Value::Text(Utc::now().to_rfc3339()),
-                ],
-            )
-            .await?;
+                // Record the migration
+                let sql = r#"
+                    INSERT INTO migrations (id, name, sql, created_at, executed_at, down_sql)
+                    VALUES (?, ?, ?, ?, ?, ?)
+                "#;
 
-        // Commit transaction
-        self.db
-            .inner
-            .execute("COMMIT", vec![libsql::Value::Null; 0])
+                db.execute(
+                    sql,
+                    vec![
+                        libsql::Value::Text(migration.id.clone()),
+                        libsql::Value::Text(migration.name.clone()),
+                        libsql::Value::Text(migration.sql.clone()),
+                        libsql::Value::Text(migration.created_at.to_rfc3339()),
+                        libsql::Value::Text(Utc::now().to_rfc3339()),
+                        migration
+                            .down_sql
+                            .clone()
+                            .map_or(libsql::Value::Null, libsql::Value::Text),
+                    ],
+                )
+                .await?;
+                Ok(failures)
+            })
             .await?;
-
+        if !failures.is_empty() {
+            return Err(verification_error(migration, &failures));
+        }
         Ok(())
     }
 
     /// Rollback a migration
+    ///
+    /// Runs the migration's down SQL, if it was recorded with one, and
+    /// removes it from the history, in one transaction.
     pub async fn rollback_migration(&self, migration_id: &str) -> Result<(), Error> {
-        let sql = "DELETE FROM migrations WHERE id = ?";
         self.db
-            .inner
-            .execute(sql, vec![libsql::Value::Text(migration_id.to_string())])
-            .await?;
-        Ok(())
+            .transaction(|db| async move {
+                let id = libsql::Value::Text(migration_id.to_string());
+                let mut rows = db
+                    .query(
+                        "SELECT down_sql FROM migrations WHERE id = ?",
+                        vec![id.clone()],
+                    )
+                    .await?;
+                let down_sql = match rows.next().await? {
+                    Some(row) => row.get::<Option<String>>(0)?,
+                    None => None,
+                };
+                // The open statement would lock the tables the down SQL drops
+                drop(rows);
+                if let Some(down_sql) = down_sql {
+                    db.execute_batch(&down_sql).await?;
+                }
+                db.execute("DELETE FROM migrations WHERE id = ?", vec![id])
+                    .await?;
+                Ok(())
+            })
+            .await
     }
 
     /// Get pending migrations (not yet executed)
@@ -256,9 +470,18 @@ impl MigrationManager {
     }
 
     /// Run all pending migrations
+    ///
+    /// Migrations that are marked executed, or whose id is already in the
+    /// history, are skipped.
     pub async fn run_migrations(&self, migrations: Vec<Migration>) -> Result<(), Error> {
+        let executed: Vec<String> = self
+            .get_executed_migrations()
+            .await?
+            .into_iter()
+            .map(|migration| migration.id)
+            .collect();
         for migration in migrations {
-            if let Some(_executed_at) = migration.executed_at {
+            if migration.executed_at.is_some() || executed.contains(&migration.id) {
                 continue;
             }
 
@@ -268,7 +491,23 @@ impl MigrationManager {
         Ok(())
     }
 
+    /// Create the tables of all registered models
+    ///
+    /// Runs the `CREATE TABLE IF NOT EXISTS` statement of every model in the
+    /// global registry. Returns the number of models migrated.
+    pub async fn auto_migrate_all(&self) -> Result<usize, Error> {
+        let models = crate::registry();
+        for model in &models {
+            let sql = (model.migration_sql)();
+            self.db.execute(&sql, vec![]).await?;
+        }
+        Ok(models.len())
+    }
+
     /// Create a migration from a file
+    ///
+    /// The file may be split into up and down sections, see
+    /// [`Migration::from_sql`].
     pub async fn create_migration_from_file(
         name: &str,
         file_path: &str,
@@ -276,7 +515,39 @@ impl MigrationManager {
         let sql = std::fs::read_to_string(file_path)
             .map_err(|e| Error::DatabaseError(format!("Failed to read migration file: {e}")))?;
 
-        Ok(Self::create_migration(name, &sql))
+        Migration::from_sql(name, &sql)
+    }
+
+    /// Load every `.sql` file of a directory as a migration
+    ///
+    /// Files are parsed with [`Migration::from_sql`], named after the file
+    /// without its extension, and returned sorted by name, so prefixes such
+    /// as `0001_` or timestamps set the order. Pass the result to
+    /// [`run_migrations`](Self::run_migrations), which skips the ones already
+    /// executed.
+    pub fn load_sql_dir(dir: impl AsRef<std::path::Path>) -> Result<Vec<Migration>, Error> {
+        let read_error =
+            |e: std::io::Error| Error::DatabaseError(format!("Failed to read migration file: {e}"));
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(dir).map_err(read_error)? {
+            let path = entry.map_err(read_error)?.path();
+            if path.extension().is_some_and(|extension| extension == "sql") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        paths
+            .iter()
+            .map(|path| {
+                let name = path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let sql = std::fs::read_to_string(path).map_err(read_error)?;
+                Migration::from_sql(&name, &sql)
+            })
+            .collect()
     }
 
     /// Generate a migration name from a description
@@ -293,11 +564,202 @@ impl MigrationManager {
         format!("{timestamp}_{sanitized_description}")
     }
 
+    /// Run `UPDATE table SET assignments` over a table in rowid batches
+    ///
+    /// Each batch commits together with a checkpoint in the migrations table,
+    /// under the id `backfill_<name>`, so an interrupted backfill resumes
+    /// after the last finished batch when called again. Once every row is
+    /// done the backfill is marked executed and later calls return
+    /// immediately. Returns the number of rows updated by this call.
+    ///
+    /// ```rust,ignore
+    /// manager
+    ///     .backfill_in_batches("copy_email", "users", "email_normalized = lower(email)", 500)
+    ///     .await?;
+    /// ```
+    pub async fn backfill_in_batches(
+        &self,
+        name: &str,
+        table: &str,
+        assignments: &str,
+        batch_size: u32,
+    ) -> Result<u64, Error> {
+        if batch_size == 0 {
+            return Err(Error::Validation(
+                "Backfill batch size must be positive".to_string(),
+            ));
+        }
+        self.init().await?;
+
+        let id = format!("backfill_{name}");
+        let sql = format!("UPDATE {table} SET {assignments}");
+        let Some(checkpoint) = backfill_checkpoint(&self.db, &id, name, &sql).await? else {
+            return Ok(0);
+        };
+        let mut checkpoint = checkpoint.unwrap_or(i64::MIN);
+
+        let mut updated = 0;
+        loop {
+            let mut rows = self
+                .db
+                .query(
+                    &format!(
+                        "SELECT max(rowid) FROM \
+                         (SELECT rowid FROM {table} WHERE rowid > ? ORDER BY rowid LIMIT ?)"
+                    ),
+                    vec![
+                        libsql::Value::Integer(checkpoint),
+                        libsql::Value::Integer(batch_size.into()),
+                    ],
+                )
+                .await?;
+            let end = match rows.next().await? {
+                Some(row) => row.get::<Option<i64>>(0)?,
+                None => None,
+            };
+            let Some(end) = end else {
+                finish_backfill(&self.db, &id).await?;
+                return Ok(updated);
+            };
+
+            let id = &id;
+            updated += self
+                .db
+                .transaction(|db| async move {
+                    let affected = db
+                        .execute(
+                            &format!(
+                                "UPDATE {table} SET {assignments} WHERE rowid > ? AND rowid <= ?"
+                            ),
+                            vec![
+                                libsql::Value::Integer(checkpoint),
+                                libsql::Value::Integer(end),
+                            ],
+                        )
+                        .await?;
+                    save_backfill_progress(db, id, end).await?;
+                    Ok(affected)
+                })
+                .await?;
+            checkpoint = end;
+        }
+    }
+
     pub fn database(&self) -> &Database {
         &self.db
     }
 }
 
+/// Create the migrations table, adding columns missing from older versions
+pub(crate) async fn init_table(db: &Database) -> Result<(), Error> {
+    let sql = r#"
+        CREATE TABLE IF NOT EXISTS migrations (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            sql TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            executed_at TEXT,
+            progress INTEGER,
+            down_sql TEXT
+        )
+    "#;
+
+    let params = vec![libsql::Value::Null; 0];
+
+    db.execute(sql, params).await?;
+
+    // Tables created by earlier versions lack the newer columns
+    for (column, definition) in [("progress", "INTEGER"), ("down_sql", "TEXT")] {
+        let mut rows = db
+            .query(
+                "SELECT 1 FROM pragma_table_info('migrations') WHERE name = ?",
+                vec![libsql::Value::Text(column.to_string())],
+            )
+            .await?;
+        if rows.next().await?.is_none() {
+            db.execute(
+                &format!("ALTER TABLE migrations ADD COLUMN {column} {definition}"),
+                vec![],
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Load the checkpoint of a backfill, registering it on first use
+///
+/// Returns `None` once the backfill has finished, otherwise the last
+/// processed key, if any.
+pub(crate) async fn backfill_checkpoint(
+    db: &Database,
+    id: &str,
+    name: &str,
+    sql: &str,
+) -> Result<Option<Option<i64>>, Error> {
+    let mut rows = db
+        .query(
+            "SELECT executed_at, progress FROM migrations WHERE id = ?",
+            vec![libsql::Value::Text(id.to_string())],
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) if row.get::<Option<String>>(0)?.is_some() => Ok(None),
+        Some(row) => Ok(Some(row.get::<Option<i64>>(1)?)),
+        None => {
+            db.execute(
+                "INSERT INTO migrations (id, name, sql, created_at) VALUES (?, ?, ?, ?)",
+                vec![
+                    libsql::Value::Text(id.to_string()),
+                    libsql::Value::Text(name.to_string()),
+                    libsql::Value::Text(sql.to_string()),
+                    libsql::Value::Text(Utc::now().to_rfc3339()),
+                ],
+            )
+            .await?;
+            Ok(Some(None))
+        }
+    }
+}
+
+/// Record the last processed key of a backfill
+pub(crate) async fn save_backfill_progress(
+    db: &Database,
+    id: &str,
+    progress: i64,
+) -> Result<(), Error> {
+    db.execute(
+        "UPDATE migrations SET progress = ? WHERE id = ?",
+        vec![
+            libsql::Value::Integer(progress),
+            libsql::Value::Text(id.to_string()),
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+/// Mark a backfill as finished
+pub(crate) async fn finish_backfill(db: &Database, id: &str) -> Result<(), Error> {
+    db.execute(
+        "UPDATE migrations SET executed_at = ? WHERE id = ?",
+        vec![
+            libsql::Value::Text(Utc::now().to_rfc3339()),
+            libsql::Value::Text(id.to_string()),
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+fn verification_error(migration: &Migration, failures: &[String]) -> Error {
+    Error::Validation(format!(
+        "Verification of migration {} failed: {}",
+        migration.name,
+        failures.join("; ")
+    ))
+}
+
 /// Builder for creating migrations
 ///
 /// Provides a fluent interface for constructing migrations with up and down SQL.
@@ -316,6 +778,9 @@ pub struct MigrationBuilder {
     name: String,
     up_sql: String,
     down_sql: Option<String>,
+    condition: Option<MigrationCondition>,
+    verifications: Vec<Verification>,
+    keep_on_failed_verification: bool,
 }
 
 impl MigrationBuilder {
@@ -325,6 +790,9 @@ impl MigrationBuilder {
             name: name.to_string(),
             up_sql: String::new(),
             down_sql: None,
+            condition: None,
+            verifications: Vec::new(),
+            keep_on_failed_verification: false,
         }
     }
 
@@ -340,6 +808,101 @@ impl MigrationBuilder {
         self
     }
 
+    /// Only run the up SQL when a predicate holds
+    ///
+    /// The predicate runs inside the migration's transaction, just before the
+    /// SQL. When it returns `false` the migration is recorded as executed
+    /// without running, so idempotent or environment-specific steps need no
+    /// orchestration outside the migration list:
+    ///
+    /// ```rust
+    /// use libsql_orm::MigrationBuilder;
+    ///
+    /// let migration = MigrationBuilder::new("seed_plans")
+    ///     .up("INSERT INTO plans (name) VALUES ('free'), ('pro')")
+    ///     .run_if(|db| {
+    ///         Box::pin(async move {
+    ///             let rows: Vec<serde_json::Value> = db
+    ///                 .query_as("SELECT 1 AS seeded FROM plans LIMIT 1", vec![])
+    ///                 .await?;
+    ///             Ok(rows.is_empty())
+    ///         })
+    ///     })
+    ///     .build();
+    /// ```
+    pub fn run_if<F>(mut self, predicate: F) -> Self
+    where
+        F: for<'a> Fn(&'a Database) -> LocalBoxFuture<'a, Result<bool, Error>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.condition = Some(MigrationCondition::new(predicate));
+        self
+    }
+
+    /// Only run the up SQL when `table` has no column named `column`
+    pub fn run_if_column_missing(self, table: &str, column: &str) -> Self {
+        let table = table.to_string();
+        let column = column.to_string();
+        self.run_if(move |db| {
+            let table = table.clone();
+            let column = column.clone();
+            Box::pin(async move {
+                let mut rows = db
+                    .query(
+                        "SELECT 1 FROM pragma_table_info(?) WHERE name = ?",
+                        vec![libsql::Value::Text(table), libsql::Value::Text(column)],
+                    )
+                    .await?;
+                Ok(rows.next().await?.is_none())
+            })
+        })
+    }
+
+    /// Only run the up SQL when `table` has no rows
+    pub fn run_if_empty(self, table: &str) -> Self {
+        let sql = format!("SELECT 1 FROM {table} LIMIT 1");
+        self.run_if(move |db| {
+            let sql = sql.clone();
+            Box::pin(async move {
+                let mut rows = db.query(&sql, vec![]).await?;
+                Ok(rows.next().await?.is_none())
+            })
+        })
+    }
+
+    /// Check the data after the up SQL has run
+    ///
+    /// `sql` must return a single integer, which has to equal `expect`,
+    /// otherwise the migration fails and is rolled back. Catches data
+    /// transformations that ran without error but left bad rows:
+    ///
+    /// ```rust
+    /// use libsql_orm::MigrationBuilder;
+    ///
+    /// let migration = MigrationBuilder::new("backfill_emails")
+    ///     .up("UPDATE users SET email = lower(login) || '@example.com' WHERE email IS NULL")
+    ///     .verify("SELECT COUNT(*) FROM users WHERE email IS NULL", 0)
+    ///     .build();
+    /// ```
+    pub fn verify(mut self, sql: &str, expect: i64) -> Self {
+        self.verifications.push(Verification {
+            sql: sql.to_string(),
+            expect,
+        });
+        self
+    }
+
+    /// Commit the migration even if a verification fails
+    ///
+    /// The failure is still returned as an error, stopping
+    /// [`run_migrations`](MigrationManager::run_migrations).
+    pub fn keep_on_failed_verification(mut self) -> Self {
+        self.keep_on_failed_verification = true;
+        self
+    }
+
     /// Build the migration
     pub fn build(self) -> Migration {
         Migration {
@@ -348,6 +911,10 @@ impl MigrationBuilder {
             sql: self.up_sql,
             created_at: Utc::now(),
             executed_at: None,
+            down_sql: self.down_sql,
+            condition: self.condition,
+            verifications: self.verifications,
+            keep_on_failed_verification: self.keep_on_failed_verification,
         }
     }
 }
@@ -430,4 +997,239 @@ pub mod templates {
             .up(&sql)
             .build()
     }
+
+    /// Change the type of a column
+    ///
+    /// SQLite cannot alter a column's type in place, so this reads the live
+    /// schema of `table_name` and generates the rebuild SQLite documents:
+    /// create a new table with the changed column, copy the rows with
+    /// `cast_expr` computing the new value, drop the old table and rename the
+    /// new one. Constraints and foreign keys declared on the table are kept,
+    /// and its indexes, triggers and dependent views are recreated.
+    ///
+    /// Dropping a table that other tables reference violates their foreign
+    /// keys, so while `PRAGMA foreign_keys` is on such tables are rejected.
+    /// Turn enforcement off outside the migration, as SQLite's `ALTER TABLE`
+    /// documentation describes, to rebuild them.
+    ///
+    /// ```rust,ignore
+    /// let migration =
+    ///     templates::change_column_type("orders", "total", "INTEGER", "CAST(total * 100 AS INTEGER)", &db)
+    ///         .await?;
+    /// manager.execute_migration(&migration).await?;
+    /// ```
+    pub async fn change_column_type(
+        table_name: &str,
+        column_name: &str,
+        new_type: &str,
+        cast_expr: &str,
+        db: &Database,
+    ) -> Result<Migration, Error> {
+        let mut create_sql = None;
+        let mut tables = Vec::new();
+        let mut dependents = Vec::new();
+        let mut views = Vec::new();
+        let mut rows = db
+            .query(
+                "SELECT type, name, tbl_name, sql FROM sqlite_master WHERE sql IS NOT NULL",
+                vec![],
+            )
+            .await?;
+        while let Some(row) = rows.next().await? {
+            let (kind, name, owner, sql) = (
+                row.get::<String>(0)?,
+                row.get::<String>(1)?,
+                row.get::<String>(2)?,
+                row.get::<String>(3)?,
+            );
+            if kind == "table" {
+                tables.push(name.clone());
+            }
+            match kind.as_str() {
+                "table" if name.eq_ignore_ascii_case(table_name) => create_sql = Some(sql),
+                "index" | "trigger" if owner.eq_ignore_ascii_case(table_name) => {
+                    dependents.push(sql)
+                }
+                "view" if sql.to_lowercase().contains(&table_name.to_lowercase()) => {
+                    views.push((name, sql))
+                }
+                _ => {}
+            }
+        }
+        let create_sql =
+            create_sql.ok_or_else(|| Error::NotFound(format!("Table '{table_name}' not found")))?;
+
+        let mut rows = db.query("PRAGMA foreign_keys", vec![]).await?;
+        let enforced = match rows.next().await? {
+            Some(row) => row.get::<i64>(0)? != 0,
+            None => false,
+        };
+        if enforced {
+            for table in &tables {
+                let mut rows = db
+                    .query(&format!("PRAGMA foreign_key_list({table})"), vec![])
+                    .await?;
+                while let Some(row) = rows.next().await? {
+                    let parent = row.get::<String>(2)?;
+                    if parent.eq_ignore_ascii_case(table_name) {
+                        return Err(Error::Validation(format!(
+                            "Table '{table}' references '{table_name}'; \
+                             disable PRAGMA foreign_keys before rebuilding it"
+                        )));
+                    }
+                }
+            }
+        }
+
+        let open = create_sql.find('(').unwrap_or(create_sql.len());
+        let close = create_sql.rfind(')').unwrap_or(create_sql.len());
+        if open >= close {
+            return Err(Error::Query(format!(
+                "Cannot parse the definition of table '{table_name}'"
+            )));
+        }
+        let mut found = false;
+        let definitions: Vec<String> = split_top_level(&create_sql[open + 1..close])
+            .into_iter()
+            .map(|definition| {
+                let definition = definition.trim();
+                let (name, rest) = split_identifier(definition);
+                if unquote(name).eq_ignore_ascii_case(column_name) {
+                    found = true;
+                    let constraints = &rest[constraint_start(rest)..];
+                    format!("{name} {new_type} {constraints}")
+                        .trim_end()
+                        .to_string()
+                } else {
+                    definition.to_string()
+                }
+            })
+            .collect();
+        if !found {
+            return Err(Error::NotFound(format!(
+                "Column '{column_name}' not found in table '{table_name}'"
+            )));
+        }
+
+        let mut columns = Vec::new();
+        let mut rows = db
+            .query(&format!("PRAGMA table_info({table_name})"), vec![])
+            .await?;
+        while let Some(row) = rows.next().await? {
+            columns.push(row.get::<String>(1)?);
+        }
+        let selected: Vec<&str> = columns
+            .iter()
+            .map(|column| {
+                if column.eq_ignore_ascii_case(column_name) {
+                    cast_expr
+                } else {
+                    column.as_str()
+                }
+            })
+            .collect();
+
+        let new_table = format!("{table_name}__new");
+        let mut statements: Vec<String> = views
+            .iter()
+            .map(|(name, _)| format!("DROP VIEW {name}"))
+            .collect();
+        statements.push(format!(
+            "CREATE TABLE {new_table} ({}){}",
+            definitions.join(", "),
+            &create_sql[close + 1..]
+        ));
+        statements.push(format!(
+            "INSERT INTO {new_table} ({}) SELECT {} FROM {table_name}",
+            columns.join(", "),
+            selected.join(", ")
+        ));
+        statements.push(format!("DROP TABLE {table_name}"));
+        statements.push(format!("ALTER TABLE {new_table} RENAME TO {table_name}"));
+        statements.extend(dependents);
+        statements.extend(views.into_iter().map(|(_, sql)| sql));
+
+        let sql = statements.join(";\n") + ";";
+        Ok(
+            MigrationBuilder::new(&format!("change_column_type_{table_name}_{column_name}"))
+                .up(&sql)
+                .build(),
+        )
+    }
+
+    /// Split a definition list at commas outside parentheses and quotes
+    fn split_top_level(list: &str) -> Vec<&str> {
+        let mut parts = Vec::new();
+        let (mut depth, mut quote, mut start) = (0usize, None, 0);
+        for (i, c) in list.char_indices() {
+            match (quote, c) {
+                (Some(q), _) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '\'' | '"' | '`') => quote = Some(c),
+                (None, '[') => quote = Some(']'),
+                (None, '(') => depth += 1,
+                (None, ')') => depth = depth.saturating_sub(1),
+                (None, ',') if depth == 0 => {
+                    parts.push(&list[start..i]);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        parts.push(&list[start..]);
+        parts
+    }
+
+    /// Split a column definition into its (possibly quoted) name and the rest
+    fn split_identifier(definition: &str) -> (&str, &str) {
+        let end = match definition.chars().next() {
+            Some(q @ ('"' | '`' | '[')) => {
+                let close = if q == '[' { ']' } else { q };
+                definition[1..]
+                    .find(close)
+                    .map_or(definition.len(), |i| i + 2)
+            }
+            _ => definition
+                .find(char::is_whitespace)
+                .unwrap_or(definition.len()),
+        };
+        (&definition[..end], definition[end..].trim_start())
+    }
+
+    fn unquote(name: &str) -> &str {
+        name.trim_matches(|c| matches!(c, '"' | '`' | '[' | ']'))
+    }
+
+    /// Offset of the first column constraint after the type name
+    fn constraint_start(rest: &str) -> usize {
+        const KEYWORDS: &[&str] = &[
+            "CONSTRAINT",
+            "PRIMARY",
+            "NOT",
+            "NULL",
+            "UNIQUE",
+            "CHECK",
+            "DEFAULT",
+            "COLLATE",
+            "REFERENCES",
+            "GENERATED",
+            "AS",
+        ];
+        let mut depth = 0usize;
+        let mut offset = 0;
+        for word in rest.split_inclusive(char::is_whitespace) {
+            let token = word.trim();
+            let keyword = token
+                .split(|c: char| !c.is_ascii_alphabetic())
+                .next()
+                .unwrap_or_default();
+            if depth == 0 && KEYWORDS.iter().any(|k| keyword.eq_ignore_ascii_case(k)) {
+                return offset;
+            }
+            depth += token.matches('(').count();
+            depth = depth.saturating_sub(token.matches(')').count());
+            offset += word.len();
+        }
+        rest.len()
+    }
 }
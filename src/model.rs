@@ -25,13 +25,274 @@
 //! ```
 
 use crate::{
-    Aggregate, Database, Error, FilterOperator, PaginatedResult, Pagination, QueryBuilder, Result,
-    SearchFilter, Sort,
+    Aggregate, CursorPaginatedResult, CursorPagination, Database, Error, Filter, FilterOperator,
+    PaginatedResult, Pagination, Projection, QueryBuilder, Result, SearchFilter, Sort,
 };
-use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
 
 use serde::{de::DeserializeOwned, Serialize};
 
+/// Append the primary key to `sorts` so rows with equal keys keep a stable
+/// order across pages
+fn stable_sorts<M: Model>(mut sorts: Vec<Sort>) -> Vec<Sort> {
+    if !sorts.is_empty() && !sorts.iter().any(|sort| sort.column == M::primary_key()) {
+        sorts.push(Sort::asc(M::primary_key()));
+    }
+    sorts
+}
+
+/// Run a grouped aggregate query and read its `(group_key, value)` rows
+async fn query_groups<M: Model>(
+    builder: QueryBuilder,
+    db: &Database,
+) -> Result<Vec<(crate::Value, Option<f64>)>> {
+    let (sql, params) = builder.build()?;
+    M::log_debug(&format!("SQL: {sql}"));
+    let mut rows = db.query(&sql, params).await?;
+    let mut groups = Vec::new();
+    while let Some(row) = rows.next().await? {
+        let key = M::libsql_value_to_value(&row.get_value(0)?);
+        let value = match row.get_value(1)? {
+            libsql::Value::Integer(i) => Some(i as f64),
+            libsql::Value::Real(f) => Some(f),
+            libsql::Value::Null => None,
+            _ => return Err(Error::Query("Failed to get aggregate value".to_string())),
+        };
+        groups.push((key, value));
+    }
+    Ok(groups)
+}
+
+/// Run `MIN` or `MAX` over a timestamp column and parse the result
+async fn datetime_aggregate<M: Model>(
+    function: Aggregate,
+    column: &str,
+    filter: Option<FilterOperator>,
+    db: &Database,
+) -> Result<Option<DateTime<Utc>>> {
+    let mut builder =
+        QueryBuilder::new(M::table_name()).aggregate(function, column, None::<String>);
+    if let Some(filter) = filter {
+        builder = builder.r#where(filter);
+    }
+    let (sql, params) = builder.build()?;
+    M::log_debug(&format!("SQL: {sql}"));
+
+    let values = db
+        .query_map(&sql, params, |row| {
+            Ok(M::libsql_value_to_value(&row.get_value(0)?))
+        })
+        .await?;
+    match values.into_iter().next() {
+        None | Some(crate::Value::Null) => Ok(None),
+        Some(value) => match crate::coercion::datetime_parsing().parse(&value) {
+            Some(parsed) => Ok(Some(parsed.with_timezone(&Utc))),
+            None => Err(Error::Serialization(format!(
+                "Column '{column}' has unrecognized datetime value: {value:?}"
+            ))),
+        },
+    }
+}
+
+/// Name of the model's content-hash column, if it has one
+fn content_hash_column<M: Model>() -> Option<&'static str> {
+    M::column_meta()
+        .iter()
+        .find(|meta| !meta.hash_of.is_empty())
+        .map(|meta| meta.name)
+}
+
+/// Reject a raw write to a column that a content hash is computed from
+///
+/// The hash is computed in Rust, so statements that set the column in SQL
+/// alone would leave it stale.
+fn check_not_hashed<M: Model>(column: &str) -> Result<()> {
+    match M::column_meta()
+        .iter()
+        .find(|meta| meta.hash_of.contains(&column))
+    {
+        Some(hash) => Err(Error::Validation(format!(
+            "Column '{column}' of table {} feeds content hash '{}'; update it with update_fields",
+            M::table_name(),
+            hash.name
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Whether SQLite would pick random keys for records inserted without one
+///
+/// New keys go above the largest one in the table, unless that is already
+/// `i64::MAX`, or `records` bring it along.
+async fn assigns_random_keys<M: Model>(records: &[M], db: &Database) -> Result<bool> {
+    if records
+        .iter()
+        .any(|record| record.get_primary_key() == Some(i64::MAX))
+    {
+        return Ok(true);
+    }
+    let sql = format!("SELECT max({}) FROM {}", M::primary_key(), M::table_name());
+    let mut rows = db.query(&sql, vec![]).await?;
+    let largest = match rows.next().await? {
+        Some(row) => row.get::<Option<i64>>(0)?,
+        None => None,
+    };
+    Ok(largest == Some(i64::MAX))
+}
+
+/// What `#[orm(identity_eq)]` records compare and hash by
+///
+/// Called by the `Model` derive. A saved record is identified by its primary
+/// key alone. Unsaved records have no identity yet, so they are identified
+/// by all of their values and distinct new records stay apart in sets and
+/// maps.
+pub fn identity_key<M: Model>(record: &M) -> (Option<i64>, Option<String>) {
+    match record.get_primary_key() {
+        Some(id) => (Some(id), None),
+        None => (None, serde_json::to_string(record).ok()),
+    }
+}
+
+/// Column that receives a copy of every write to `column`, if any
+pub(crate) fn mirror_column<M: Model>(column: &str) -> Option<&'static str> {
+    M::column_meta()
+        .iter()
+        .find(|meta| meta.name == column)
+        .and_then(|meta| meta.mirror_to)
+}
+
+/// Name of the column linking a record to its parent in a tree
+fn tree_parent_column<M: Model>() -> Result<&'static str> {
+    let meta = M::column_meta();
+    meta.iter()
+        .find(|column| column.parent)
+        .or_else(|| meta.iter().find(|column| column.name == "parent_id"))
+        .map(|column| column.name)
+        .ok_or_else(|| {
+            Error::Validation(format!(
+                "Model for table {} has no parent column",
+                M::table_name()
+            ))
+        })
+}
+
+/// Select the keys of `M` records whose number of related `C` records
+/// compares with `count` using `op`
+fn related_counts<M: Model, C: Model>(op: &str, count: u64) -> Result<QueryBuilder> {
+    let mut columns = C::column_meta()
+        .iter()
+        .filter(|column| column.references == Some(M::table_name()));
+    let column = match (columns.next(), columns.next()) {
+        (Some(column), None) => column.name,
+        (None, _) => {
+            return Err(Error::Validation(format!(
+                "Table {} has no column referencing {}",
+                C::table_name(),
+                M::table_name()
+            )))
+        }
+        (Some(_), Some(_)) => {
+            return Err(Error::Validation(format!(
+                "Table {} has more than one column referencing {}",
+                C::table_name(),
+                M::table_name()
+            )))
+        }
+    };
+    let count = i64::try_from(count).unwrap_or(i64::MAX);
+    // NOT IN never matches once the subquery yields a NULL
+    Ok(QueryBuilder::new(C::table_name())
+        .select(vec![column])
+        .r#where(FilterOperator::Single(Filter::is_not_null(column)))
+        .group_by(vec![column])
+        .having(FilterOperator::Single(Filter::raw(
+            format!("COUNT(*) {op} ?"),
+            vec![count],
+        ))))
+}
+
+/// Build the ranked query of [`Model::search_columns`]
+fn search_columns_statement<M: Model>(
+    columns: &[&str],
+    term: &str,
+) -> Result<(String, Vec<libsql::Value>)> {
+    if columns.is_empty() {
+        return Err(Error::Validation("No columns to search".to_string()));
+    }
+    let known = M::columns();
+    if let Some(unknown) = columns.iter().find(|column| !known.contains(column)) {
+        return Err(Error::Validation(format!(
+            "Table {} has no column {unknown}",
+            M::table_name()
+        )));
+    }
+    // Every condition shares the one pattern parameter
+    let matches: Vec<String> = columns
+        .iter()
+        .map(|column| format!("{column} LIKE ?1 ESCAPE '\\'"))
+        .collect();
+    let score: Vec<String> = matches
+        .iter()
+        .enumerate()
+        .map(|(index, condition)| {
+            // A NULL column would make the whole score NULL
+            format!("COALESCE({condition}, 0) * {}", columns.len() - index)
+        })
+        .collect();
+    let sql = format!(
+        "SELECT * FROM {} WHERE {} ORDER BY {} DESC, {}",
+        M::table_name(),
+        matches.join(" OR "),
+        score.join(" + "),
+        M::primary_key()
+    );
+    let pattern = format!("%{}%", Filter::escape_like(term));
+    Ok((sql, vec![libsql::Value::Text(pattern)]))
+}
+
+/// Order the ancestors of a record from its parent up to the root
+///
+/// The root is the record whose parent is not in the set; each record
+/// before it is the one whose parent comes next. If the parent links form
+/// a cycle, the records are returned unchanged.
+fn order_ancestors<M: Model>(records: Vec<M>, parent: &str) -> Result<Vec<M>> {
+    let mut parents = HashMap::with_capacity(records.len());
+    for record in &records {
+        let parent_id = match record.to_map()?.remove(parent) {
+            Some(crate::Value::Integer(id)) => Some(id),
+            _ => None,
+        };
+        parents.insert(record.get_primary_key(), parent_id);
+    }
+    let ids: HashSet<Option<i64>> = parents.keys().copied().collect();
+    let Some(root) = records
+        .iter()
+        .position(|r| !ids.contains(&parents[&r.get_primary_key()]))
+    else {
+        return Ok(records);
+    };
+
+    let mut children: HashMap<Option<i64>, M> = HashMap::new();
+    let mut ordered = Vec::with_capacity(records.len());
+    for (i, record) in records.into_iter().enumerate() {
+        if i == root {
+            ordered.push(record);
+        } else {
+            children.insert(parents[&record.get_primary_key()], record);
+        }
+    }
+    while let Some(child) = ordered
+        .last()
+        .and_then(|last| children.remove(&last.get_primary_key()))
+    {
+        ordered.push(child);
+    }
+    ordered.extend(children.into_values());
+    ordered.reverse();
+    Ok(ordered)
+}
+
 /// Mask numeric IDs for logging
 fn mask_id(id: i64) -> String {
     if id < 100 {
@@ -43,6 +304,201 @@ fn mask_id(id: i64) -> String {
     format!("{}{}", &id_str[..visible_digits], "*".repeat(masked_digits))
 }
 
+/// Most parameters SQLite binds in one statement
+const MAX_PARAMS: usize = 32766;
+
+/// Build the `ON CONFLICT(...) DO UPDATE SET ...` clause of an upsert
+///
+/// Every inserted column except the conflict columns and the primary key is
+/// overwritten with the incoming (`excluded`) value.
+fn conflict_clause(conflict_columns: &[&str], columns: &[String], primary_key: &str) -> String {
+    let mut updates: Vec<String> = columns
+        .iter()
+        .filter(|c| c.as_str() != primary_key && !conflict_columns.contains(&c.as_str()))
+        .map(|c| format!("{c} = excluded.{c}"))
+        .collect();
+    if updates.is_empty() {
+        // Still touch the row so that RETURNING yields it
+        let column = conflict_columns[0];
+        updates.push(format!("{column} = excluded.{column}"));
+    }
+    format!(
+        "ON CONFLICT({}) DO UPDATE SET {}",
+        conflict_columns.join(", "),
+        updates.join(", ")
+    )
+}
+
+/// Run an upsert of `model`, returning the stored row unless the condition
+/// of the `DO UPDATE` branch skipped it
+async fn upsert_returning<M: Model>(
+    model: &M,
+    conflict_columns: &[&str],
+    condition: Option<FilterOperator>,
+    db: &Database,
+) -> Result<Option<M>> {
+    let mut map = model.to_map()?;
+    if model.get_primary_key().is_none() {
+        map.remove(M::primary_key());
+    }
+    remove_unloaded_lazy(&mut map, M::column_meta());
+    remove_db_defaults(&mut map, M::column_meta());
+
+    if conflict_columns.is_empty() {
+        return Err(Error::Validation(
+            "No conflict columns provided for upsert".to_string(),
+        ));
+    }
+    if let Some(missing) = conflict_columns.iter().find(|c| !map.contains_key(**c)) {
+        return Err(Error::Validation(format!(
+            "Conflict column '{missing}' is not a column of {}",
+            M::table_name()
+        )));
+    }
+
+    let columns: Vec<String> = map.keys().cloned().collect();
+    let placeholders: Vec<&str> = columns.iter().map(|_| "?").collect();
+    let mut params: Vec<libsql::Value> = columns
+        .iter()
+        .map(|c| M::value_to_libsql_value(&map[c]))
+        .collect();
+    let (where_sql, where_params) = match condition {
+        Some(condition) => QueryBuilder::new(M::table_name())
+            .r#where(condition)
+            .build_where()?,
+        None => (String::new(), Vec::new()),
+    };
+    params.extend(where_params);
+    let sql = format!(
+        "INSERT INTO {} ({}) VALUES ({}) {}{} RETURNING *",
+        M::table_name(),
+        columns.join(", "),
+        placeholders.join(", "),
+        conflict_clause(conflict_columns, &columns, M::primary_key()),
+        where_sql
+    );
+
+    M::log_info(&format!("Upserting record in table: {}", M::table_name()));
+    M::log_debug(&format!("SQL: {sql}"));
+
+    let mut rows = db.query(&sql, params).await?;
+    match rows.next().await? {
+        Some(row) => Ok(Some(M::from_map(M::row_to_map(&row)?)?)),
+        None => Ok(None),
+    }
+}
+
+/// Order `builder` by primary key and limit it to one batch
+fn batch_builder<M: Model>(builder: QueryBuilder, batch_size: u32) -> Result<QueryBuilder> {
+    if batch_size == 0 {
+        return Err(Error::Validation("Batch size must be positive".to_string()));
+    }
+    Ok(builder
+        .order_by(Sort::asc(M::primary_key()))
+        .limit(batch_size))
+}
+
+/// Read the batch after primary key `last`, advancing `last` past it
+async fn next_batch<M: Model>(
+    builder: &QueryBuilder,
+    last: &mut Option<i64>,
+    db: &Database,
+) -> Result<Vec<M>> {
+    let mut page = builder.clone();
+    if let Some(last) = *last {
+        page = page.r#where(FilterOperator::Single(Filter::gt(M::primary_key(), last)));
+    }
+    let batch: Vec<M> = page.execute_models(db).await?;
+    *last = batch
+        .iter()
+        .filter_map(|record| record.get_primary_key())
+        .max();
+    Ok(batch)
+}
+
+/// Run `builder` in primary key batches, passing each batch to `callback`
+async fn in_batches<M: Model, F, Fut>(
+    builder: QueryBuilder,
+    batch_size: u32,
+    db: &Database,
+    mut callback: F,
+) -> Result<u64>
+where
+    F: FnMut(Vec<M>) -> Fut,
+    Fut: std::future::Future<Output = Result<()>>,
+{
+    let builder = batch_builder::<M>(builder, batch_size)?;
+    let mut last = None;
+    let mut visited = 0;
+    loop {
+        let batch = next_batch::<M>(&builder, &mut last, db).await?;
+        let full = batch.len() == batch_size as usize;
+        visited += batch.len() as u64;
+        if !batch.is_empty() {
+            callback(batch).await?;
+        }
+        if !full || last.is_none() {
+            return Ok(visited);
+        }
+    }
+}
+
+/// Run a single-column query built for `M`, converting each value into `T`
+async fn select_column<M: Model, T: DeserializeOwned>(
+    builder: QueryBuilder,
+    column: &str,
+    db: &Database,
+) -> Result<Vec<T>> {
+    if !M::columns().contains(&column) {
+        return Err(Error::Validation(format!(
+            "Unknown column '{column}' for table {}",
+            M::table_name()
+        )));
+    }
+    let meta = M::column_meta().iter().find(|meta| meta.name == column);
+    let kind = meta.map(|meta| meta.kind);
+    let compressed = meta.is_some_and(|meta| meta.compressed);
+
+    let (sql, params) = builder.build()?;
+    M::log_debug(&format!("SQL: {sql}"));
+
+    let mut rows = db.query(&sql, params).await?;
+    let mut values = Vec::new();
+    while let Some(row) = rows.next().await? {
+        let value = match (kind, M::libsql_value_to_value(&row.get_value(0)?)) {
+            (Some(crate::ColumnKind::Boolean), crate::Value::Integer(i)) => {
+                crate::Value::Boolean(i != 0)
+            }
+            (Some(kind @ (crate::ColumnKind::Integer | crate::ColumnKind::Real)), value) => {
+                crate::coercion::coerce_number(column, value, kind)?
+            }
+            (_, value) if compressed => crate::compression::decompress_value(column, value)?,
+            (_, value) => value,
+        };
+        values.push(serde_json::from_value(serde_json::Value::from(value))?);
+    }
+    Ok(values)
+}
+
+/// Drop lazy columns that were never loaded from a column map
+///
+/// An unloaded lazy column is `None`, which must not be written back as NULL.
+fn remove_unloaded_lazy(map: &mut HashMap<String, crate::Value>, meta: &[crate::ColumnMeta]) {
+    for column in meta.iter().filter(|column| column.lazy) {
+        if map.get(column.name) == Some(&crate::Value::Null) {
+            map.remove(column.name);
+        }
+    }
+}
+
+/// Drop the columns declared `db_default` from a column map about to be
+/// inserted, so the database fills them in
+fn remove_db_defaults(map: &mut HashMap<String, crate::Value>, meta: &[crate::ColumnMeta]) {
+    for column in meta.iter().filter(|column| column.db_default) {
+        map.remove(column.name);
+    }
+}
+
 /// Core trait for all database models
 #[allow(async_fn_in_trait)]
 pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
@@ -63,49 +519,173 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
     /// Get all column names for this model
     fn columns() -> Vec<&'static str>;
 
+    /// Get the column metadata for this model
+    fn column_meta() -> &'static [crate::ColumnMeta] {
+        &[]
+    }
+
     /// Generate SQL for creating the table
     fn migration_sql() -> String;
 
+    /// Create a query builder selecting the model's default columns
+    ///
+    /// Lazy columns are left out of the selection; load them on demand with
+    /// the generated `load_<field>` methods.
+    fn query_builder() -> QueryBuilder {
+        let builder = QueryBuilder::new(Self::table_name());
+        let meta = Self::column_meta();
+        if meta.iter().any(|column| column.lazy) {
+            builder.select(
+                meta.iter()
+                    .filter(|column| !column.lazy)
+                    .map(|column| column.name)
+                    .collect(),
+            )
+        } else {
+            builder
+        }
+    }
+
+    /// Create a query builder selecting only the given columns
+    ///
+    /// Read the rows with [`QueryBuilder::execute_models`] to get models
+    /// whose other fields hold their defaults, or with
+    /// [`QueryBuilder::execute_projections`] for a projection type.
+    ///
+    /// Unlike unloaded lazy columns, the defaulted fields cannot be told
+    /// apart from stored values, so [`update`](Self::update) and a
+    /// [`UnitOfWork`](crate::UnitOfWork) would write them back over the real
+    /// data. Save such models with
+    /// [`update_fields`](Self::update_fields) and the selected columns.
+    fn select(columns: &[&str]) -> QueryBuilder {
+        QueryBuilder::new(Self::table_name()).select_columns(columns)
+    }
+
     /// Convert the model to a HashMap for database operations
     fn to_map(&self) -> Result<HashMap<String, crate::Value>>;
 
     /// Create a model from a HashMap
     fn from_map(map: HashMap<String, crate::Value>) -> Result<Self>;
 
-    /// Create a new record in the database
-    async fn create(&self, db: &Database) -> Result<Self> {
-        let map = self.to_map()?;
-        let columns: Vec<String> = map.keys().cloned().collect();
-        let values: Vec<String> = map.keys().map(|_| "?".to_string()).collect();
+    /// Build the `INSERT` statement that [`create`](Self::create) executes,
+    /// without its `RETURNING` clause
+    ///
+    /// Columns declared with `#[orm_column(db_default)]` are left out.
+    fn insert_statement(&self) -> Result<(String, Vec<libsql::Value>)> {
+        let mut map = self.to_map()?;
+        remove_db_defaults(&mut map, Self::column_meta());
+        // Sorted, so the SQL text is the same for every record and its
+        // prepared statement can be cached
+        let mut entries: Vec<_> = map.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        let columns: Vec<&str> = entries.iter().map(|(column, _)| column.as_str()).collect();
+        let placeholders: Vec<&str> = columns.iter().map(|_| "?").collect();
 
         let sql = format!(
             "INSERT INTO {} ({}) VALUES ({})",
             Self::table_name(),
             columns.join(", "),
-            values.join(", ")
+            placeholders.join(", ")
         );
+        let params = entries
+            .iter()
+            .map(|(_, value)| Self::value_to_libsql_value(value))
+            .collect();
+        Ok((sql, params))
+    }
 
-        Self::log_info(&format!("Creating record in table: {}", Self::table_name()));
-        Self::log_debug(&format!("SQL: {sql}"));
+    /// Build the `UPDATE` statement that [`update`](Self::update) executes
+    fn update_statement(&self) -> Result<(String, Vec<libsql::Value>)> {
+        let id = self.get_primary_key().ok_or_else(|| {
+            Error::Validation("Cannot update record without primary key".to_string())
+        })?;
 
-        let params: Vec<libsql::Value> = map
-            .values()
-            .map(|v| Self::value_to_libsql_value(v))
+        let mut map = self.to_map()?;
+        remove_unloaded_lazy(&mut map, Self::column_meta());
+        map.remove(Self::primary_key());
+
+        let mut entries: Vec<_> = map.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        let set_clauses: Vec<String> = entries.iter().map(|(k, _)| format!("{k} = ?")).collect();
+        let sql = format!(
+            "UPDATE {} SET {} WHERE {} = ?",
+            Self::table_name(),
+            set_clauses.join(", "),
+            Self::primary_key()
+        );
+        let mut params: Vec<libsql::Value> = entries
+            .iter()
+            .map(|(_, value)| Self::value_to_libsql_value(value))
             .collect();
+        params.push(libsql::Value::Integer(id));
+        Ok((sql, params))
+    }
 
-        db.inner.execute(&sql, params).await?;
-        let id = 1i64; // Placeholder - libsql WASM doesn't support last_insert_rowid
+    /// Build the `DELETE` statement that [`delete`](Self::delete) executes
+    fn delete_statement(&self) -> Result<(String, Vec<libsql::Value>)> {
+        let id = self.get_primary_key().ok_or_else(|| {
+            Error::Validation("Cannot delete record without primary key".to_string())
+        })?;
 
-        let mut result = self.clone();
-        result.set_primary_key(id);
+        let sql = format!(
+            "DELETE FROM {} WHERE {} = ?",
+            Self::table_name(),
+            Self::primary_key()
+        );
+        Ok((sql, vec![libsql::Value::Integer(id)]))
+    }
 
-        Self::log_info(&format!(
-            "Successfully created record with ID: {}",
-            mask_id(id)
-        ));
+    /// Build the `SELECT` statement that [`find_where`](Self::find_where) executes
+    fn find_where_statement(filter: FilterOperator) -> Result<(String, Vec<libsql::Value>)> {
+        Self::query_builder().r#where(filter).build()
+    }
+
+    /// Build the `DELETE` statement that [`delete_where`](Self::delete_where) executes
+    fn delete_where_statement(filter: FilterOperator) -> Result<(String, Vec<libsql::Value>)> {
+        let (where_sql, params) = QueryBuilder::new(Self::table_name())
+            .r#where(filter)
+            .build_where()?;
+        let sql = format!("DELETE FROM {}{}", Self::table_name(), where_sql);
+        Ok((sql, params))
+    }
+
+    /// Create a new record in the database
+    ///
+    /// The row is read back with `RETURNING *` in the same statement, so the
+    /// result carries the assigned primary key and any column defaults.
+    async fn create(&self, db: &Database) -> Result<Self> {
+        let (sql, params) = self.insert_statement()?;
+        let sql = format!("{sql} RETURNING *");
+
+        Self::log_info(&format!("Creating record in table: {}", Self::table_name()));
+        Self::log_debug(&format!("SQL: {sql}"));
+
+        let result = db
+            .query_map(&sql, params, |row| Self::from_map(Self::row_to_map(row)?))
+            .await?
+            .pop()
+            .ok_or_else(|| Error::Query("Insert returned no row".to_string()))?;
+
+        if let Some(id) = result.get_primary_key() {
+            Self::log_info(&format!(
+                "Successfully created record with ID: {}",
+                mask_id(id)
+            ));
+        }
         Ok(result)
     }
 
+    /// Save a record, inserting it when it has no primary key and updating it otherwise
+    ///
+    /// Unlike [`create_or_update`](Self::create_or_update), no lookup is made
+    /// first, so updating a record whose row was deleted affects nothing.
+    async fn save(&self, db: &Database) -> Result<Self> {
+        match self.get_primary_key() {
+            Some(_) => self.update(db).await,
+            None => self.create(db).await,
+        }
+    }
+
     /// Create or update a record based on whether it has a primary key
     async fn create_or_update(&self, db: &Database) -> Result<Self> {
         if let Some(id) = self.get_primary_key() {
@@ -135,157 +715,712 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         }
     }
 
-    /// Create or update a record based on unique constraints
-    async fn upsert(&self, unique_columns: &[&str], db: &Database) -> Result<Self> {
-        let map = self.to_map()?;
+    /// Insert a record, or update the existing row conflicting on the given columns
+    ///
+    /// Compiles to a single `INSERT ... ON CONFLICT(...) DO UPDATE SET ...`
+    /// statement, so concurrent callers cannot race between the existence check
+    /// and the write. The conflict columns must be covered by a UNIQUE index or
+    /// constraint. Returns the stored row.
+    async fn upsert(&self, conflict_columns: &[&str], db: &Database) -> Result<Self> {
+        match upsert_returning(self, conflict_columns, None, db).await? {
+            Some(model) => Ok(model),
+            None => Err(Error::Query("Upsert returned no row".to_string())),
+        }
+    }
 
-        // Build WHERE clause for unique columns
-        let mut where_conditions = Vec::new();
-        let mut where_params = Vec::new();
+    /// Upsert, but only overwrite the existing row when `condition` holds
+    ///
+    /// The condition becomes the `WHERE` of the `DO UPDATE` branch: plain
+    /// columns refer to the stored row and `excluded.<column>` to the
+    /// incoming values. Returns `None` when a conflicting row exists and the
+    /// condition kept it unchanged, e.g. for idempotent webhook ingestion:
+    ///
+    /// ```rust,ignore
+    /// use libsql_orm::{Filter, FilterOperator, Operator};
+    ///
+    /// // Only overwrite with newer data
+    /// let newer = FilterOperator::Single(Filter::compare_column(
+    ///     "excluded.updated_at",
+    ///     Operator::Gt,
+    ///     "updated_at",
+    /// ));
+    /// let stored = order.upsert_where(&["external_id"], newer, &db).await?;
+    /// ```
+    async fn upsert_where(
+        &self,
+        conflict_columns: &[&str],
+        condition: FilterOperator,
+        db: &Database,
+    ) -> Result<Option<Self>> {
+        upsert_returning(self, conflict_columns, Some(condition), db).await
+    }
 
-        for &column in unique_columns {
-            if let Some(value) = map.get(column) {
-                where_conditions.push(format!("{column} = ?"));
-                where_params.push(Self::value_to_libsql_value(value));
-            }
+    /// Insert or update many records, conflicting on the given columns
+    ///
+    /// Sends multi-row `INSERT ... VALUES (...), (...) ON CONFLICT(...) DO
+    /// UPDATE SET ...` statements, as few as SQLite's parameter limit allows,
+    /// in one transaction. Records without a primary key get one assigned when
+    /// inserted. Returns the stored rows in input order, matched to the
+    /// records by their conflict columns.
+    async fn bulk_upsert(
+        models: &[Self],
+        conflict_columns: &[&str],
+        db: &Database,
+    ) -> Result<Vec<Self>> {
+        if models.is_empty() {
+            return Ok(Vec::new());
         }
-
-        if where_conditions.is_empty() {
+        if conflict_columns.is_empty() {
             return Err(Error::Validation(
-                "No unique columns provided for upsert".to_string(),
+                "No conflict columns provided for upsert".to_string(),
             ));
         }
 
-        let where_clause = where_conditions.join(" AND ");
-        let sql = format!(
-            "SELECT {} FROM {} WHERE {}",
-            Self::primary_key(),
-            Self::table_name(),
-            where_clause
-        );
+        let maps = models
+            .iter()
+            .map(|model| {
+                let mut map = model.to_map()?;
+                remove_db_defaults(&mut map, Self::column_meta());
+                Ok(map)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let columns: Vec<String> = maps[0].keys().cloned().collect();
+        if let Some(missing) = conflict_columns
+            .iter()
+            .find(|c| !columns.iter().any(|column| column == *c))
+        {
+            return Err(Error::Validation(format!(
+                "Conflict column '{missing}' is not a column of {}",
+                Self::table_name()
+            )));
+        }
+        let row_placeholders = format!("({})", vec!["?"; columns.len()].join(", "));
+        let rows_per_statement = (MAX_PARAMS / columns.len().max(1)).max(1);
+        let conflict = conflict_clause(conflict_columns, &columns, Self::primary_key());
+        // NULLs never conflict, so rows holding one have no key
+        let conflict_key = |map: &HashMap<String, crate::Value>| {
+            conflict_columns
+                .iter()
+                .map(|column| match map.get(*column) {
+                    None | Some(crate::Value::Null) => None,
+                    Some(value) => Some(format!("{:?}", Self::value_to_libsql_value(value))),
+                })
+                .collect::<Option<Vec<_>>>()
+        };
+        let conflict_key = &conflict_key;
+
+        db.transaction(|tx| async move {
+            let mut results = Vec::with_capacity(models.len());
+            for chunk in maps.chunks(rows_per_statement) {
+                let sql = format!(
+                    "INSERT INTO {} ({}) VALUES {} {conflict} RETURNING *",
+                    Self::table_name(),
+                    columns.join(", "),
+                    vec![row_placeholders.as_str(); chunk.len()].join(", ")
+                );
+                let mut params = Vec::with_capacity(chunk.len() * columns.len());
+                for map in chunk {
+                    for column in &columns {
+                        // A NULL primary key lets SQLite assign one
+                        let value = map.get(column).unwrap_or(&crate::Value::Null);
+                        params.push(Self::value_to_libsql_value(value));
+                    }
+                }
 
-        Self::log_info(&format!(
-            "Checking for existing record in table: {}",
-            Self::table_name()
-        ));
-        Self::log_debug(&format!("SQL: {sql}"));
+                Self::log_debug(&format!("SQL: {sql}"));
+                // SQLite does not promise an order for RETURNING rows, so put
+                // them back in input order by their conflict columns
+                let positions: HashMap<Vec<String>, usize> = chunk
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, map)| Some((conflict_key(map)?, index)))
+                    .collect();
+                let mut slots: Vec<Option<Self>> = vec![None; chunk.len()];
+                let mut unmatched = Vec::new();
+                let mut rows = tx.query(&sql, params).await?;
+                while let Some(row) = rows.next().await? {
+                    let map = Self::row_to_map(&row)?;
+                    let position = conflict_key(&map)
+                        .and_then(|key| positions.get(&key).copied())
+                        .filter(|&index| slots[index].is_none());
+                    let model = Self::from_map(map)?;
+                    match position {
+                        Some(index) => slots[index] = Some(model),
+                        None => unmatched.push(model),
+                    }
+                }
+                // Rows inserted with a NULL conflict column were assigned
+                // ascending keys
+                unmatched.sort_by_key(|model| model.get_primary_key());
+                let mut unmatched = unmatched.into_iter();
+                for slot in slots {
+                    results.push(slot.or_else(|| unmatched.next()).ok_or_else(|| {
+                        Error::Query("Bulk upsert returned fewer rows than sent".to_string())
+                    })?);
+                }
+            }
+            Ok(results)
+        })
+        .await
+    }
 
-        let mut rows = db.inner.query(&sql, where_params).await?;
+    /// Update the records matching a filter, or create one if none match
+    ///
+    /// Every matching record has `changes` applied and is saved. When nothing
+    /// matches, a new record is built from the filter's equality conditions plus
+    /// `changes` and inserted. Returns the first updated record or the created
+    /// one. Runs inside a transaction.
+    async fn update_or_create(
+        filter: FilterOperator,
+        changes: HashMap<String, crate::Value>,
+        db: &Database,
+    ) -> Result<Self> {
+        db.transaction(|tx| async move {
+            let existing = Self::find_where(filter.clone(), tx).await?;
+            if existing.is_empty() {
+                Self::log_info("No matching record found, creating new one");
+                let mut map = filter.equality_values();
+                map.extend(changes);
+                return Self::from_map(map)?.create(tx).await;
+            }
 
-        if let Some(row) = rows.next().await? {
-            // Record exists, update it
-            if let Some(existing_id) = row.get_value(0).ok().and_then(|v| match v {
-                libsql::Value::Integer(i) => Some(i),
-                _ => None,
-            }) {
-                Self::log_info(&format!(
-                    "Found existing record with ID: {}, updating",
-                    mask_id(existing_id)
-                ));
-                let mut updated_self = self.clone();
-                updated_self.set_primary_key(existing_id);
-                updated_self.update(db).await
-            } else {
-                Err(Error::Query(
-                    "Failed to get primary key from existing record".to_string(),
-                ))
+            Self::log_info(&format!(
+                "Updating {} matching record(s) in table: {}",
+                existing.len(),
+                Self::table_name()
+            ));
+            let mut updated = Vec::with_capacity(existing.len());
+            for record in existing {
+                let mut map = record.to_map()?;
+                map.extend(changes.clone());
+                updated.push(Self::from_map(map)?.update(tx).await?);
             }
-        } else {
-            // Record doesn't exist, create it
-            Self::log_info("No existing record found, creating new one");
-            self.create(db).await
-        }
+            Ok(updated.swap_remove(0))
+        })
+        .await
     }
 
     /// Create multiple records in the database
+    ///
+    /// Inserts with multi-row `INSERT ... VALUES (...), (...)` statements,
+    /// as few as SQLite's parameter limit allows, in one transaction. The
+    /// returned records, in input order, carry their assigned primary keys and
+    /// the values the database chose for `db_default` columns.
+    ///
+    /// Returned rows are matched to the input by primary key, relying on
+    /// SQLite assigning each new key above the largest one in the table. Once
+    /// a table has used the largest possible key SQLite picks keys at random
+    /// instead, so then the records are inserted one at a time.
     async fn bulk_create(models: &[Self], db: &Database) -> Result<Vec<Self>> {
         if models.is_empty() {
             return Ok(Vec::new());
         }
 
-        let mut results = Vec::new();
-        // Note: Manual transaction handling for WASM
-        db.inner
-            .execute("BEGIN", vec![libsql::Value::Null; 0])
-            .await?;
+        let maps = models
+            .iter()
+            .map(|model| {
+                let mut map = model.to_map()?;
+                remove_db_defaults(&mut map, Self::column_meta());
+                Ok(map)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let columns: Vec<String> = maps[0].keys().cloned().collect();
+        let row_placeholders = format!("({})", vec!["?"; columns.len()].join(", "));
+        let rows_per_statement = (MAX_PARAMS / columns.len().max(1)).max(1);
+        let defaults: Vec<&str> = Self::column_meta()
+            .iter()
+            .filter(|column| column.db_default)
+            .map(|column| column.name)
+            .collect();
+        let returning = std::iter::once(Self::primary_key())
+            .chain(defaults.iter().copied())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let returning = &returning;
+        let has_defaults = !defaults.is_empty();
+
+        db.transaction(|tx| async move {
+            let mut results = Vec::with_capacity(models.len());
+            if assigns_random_keys::<Self>(models, tx).await? {
+                Self::log_debug("Keys are assigned at random, inserting one record at a time");
+                for model in models {
+                    results.push(model.create(tx).await?);
+                }
+                return Ok(results);
+            }
+            for (chunk, chunk_maps) in models
+                .chunks(rows_per_statement)
+                .zip(maps.chunks(rows_per_statement))
+            {
+                let sql = format!(
+                    "INSERT INTO {} ({}) VALUES {} RETURNING {returning}",
+                    Self::table_name(),
+                    columns.join(", "),
+                    vec![row_placeholders.as_str(); chunk.len()].join(", "),
+                );
+                let mut params = Vec::with_capacity(chunk.len() * columns.len());
+                for map in chunk_maps {
+                    for column in &columns {
+                        let value = map.get(column).unwrap_or(&crate::Value::Null);
+                        params.push(Self::value_to_libsql_value(value));
+                    }
+                }
 
-        for model in models {
-            let map = model.to_map()?;
-            let columns: Vec<String> = map.keys().cloned().collect();
-            let values: Vec<String> = map.keys().map(|_| "?".to_string()).collect();
+                Self::log_debug(&format!("SQL: {sql}"));
+                let mut rows = tx.query(&sql, params).await?;
+                let mut returned = Vec::with_capacity(chunk.len());
+                while let Some(row) = rows.next().await? {
+                    // Local rows read the statement's current step, so copy them out
+                    let values = if has_defaults {
+                        Some(Self::row_to_map(&row)?)
+                    } else {
+                        None
+                    };
+                    returned.push((row.get::<i64>(0)?, values));
+                }
+                if returned.len() != chunk.len() {
+                    return Err(Error::Query(format!(
+                        "Bulk insert returned {} rows for {} inserted",
+                        returned.len(),
+                        chunk.len()
+                    )));
+                }
 
-            let sql = format!(
-                "INSERT INTO {} ({}) VALUES ({})",
-                Self::table_name(),
-                columns.join(", "),
-                values.join(", ")
-            );
+                // SQLite does not promise an order for RETURNING rows, so
+                // match them by primary key. Records that brought one find
+                // their row directly; the others were assigned keys above the
+                // largest key at the time, which ascend in insertion order.
+                returned.sort_by_key(|(id, _)| *id);
+                let given: HashSet<i64> = chunk
+                    .iter()
+                    .filter_map(|model| model.get_primary_key())
+                    .collect();
+                let mut assigned = returned.iter().filter(|(id, _)| !given.contains(id));
+                for model in chunk {
+                    let row = match model.get_primary_key() {
+                        Some(id) => returned
+                            .binary_search_by_key(&id, |(id, _)| *id)
+                            .ok()
+                            .map(|index| &returned[index]),
+                        None => assigned.next(),
+                    };
+                    let (id, values) = row.ok_or_else(|| {
+                        Error::Query("Bulk insert returned an unexpected row".to_string())
+                    })?;
+                    let mut result = model.clone();
+                    result.set_primary_key(*id);
+                    if let Some(values) = values {
+                        // Take the values the database filled in
+                        let mut map = result.to_map()?;
+                        map.extend(values.clone());
+                        result = Self::from_map(map)?;
+                    }
+                    results.push(result);
+                }
+            }
+            Ok(results)
+        })
+        .await
+    }
 
-            let params: Vec<libsql::Value> = map
-                .values()
-                .map(|v| Self::value_to_libsql_value(v))
-                .collect();
+    /// Find a record by its primary key
+    async fn find_by_id(id: i64, db: &Database) -> Result<Option<Self>> {
+        let builder = Self::query_builder()
+            .r#where(FilterOperator::Single(Filter::eq(Self::primary_key(), id)))
+            .limit(1);
 
-            db.inner.execute(&sql, params).await?;
-            let id = 1i64; // Placeholder - libsql WASM doesn't support last_insert_rowid
+        Self::log_debug(&format!("Finding record by ID: {}", mask_id(id)));
 
-            let mut result = model.clone();
-            result.set_primary_key(id);
-            results.push(result);
+        match builder.execute_models::<Self>(db).await?.into_iter().next() {
+            Some(record) => {
+                Self::log_debug(&format!("Found record with ID: {}", mask_id(id)));
+                Ok(Some(record))
+            }
+            None => {
+                Self::log_debug(&format!("No record found with ID: {}", mask_id(id)));
+                Ok(None)
+            }
         }
-
-        db.inner
-            .execute("COMMIT", vec![libsql::Value::Null; 0])
-            .await?;
-        Ok(results)
     }
 
-    /// Find a record by its primary key
-    async fn find_by_id(id: i64, db: &Database) -> Result<Option<Self>> {
+    /// Load a single column of this record from the database
+    ///
+    /// Returns a copy of the record with `column` set to its stored value.
+    /// Used by the generated `load_<field>` methods of lazy columns.
+    async fn load_column(&self, column: &str, db: &Database) -> Result<Self> {
+        let id = self.get_primary_key().ok_or_else(|| {
+            Error::Validation("Cannot load column of record without primary key".to_string())
+        })?;
+        if !Self::columns().contains(&column) {
+            return Err(Error::Validation(format!(
+                "Unknown column '{column}' for table {}",
+                Self::table_name()
+            )));
+        }
+
         let sql = format!(
-            "SELECT * FROM {} WHERE {} = ?",
+            "SELECT {column} FROM {} WHERE {} = ?",
             Self::table_name(),
             Self::primary_key()
         );
-
-        Self::log_debug(&format!("Finding record by ID: {}", mask_id(id)));
         Self::log_debug(&format!("SQL: {sql}"));
 
-        let mut rows = db
-            .inner
-            .query(&sql, vec![libsql::Value::Integer(id)])
-            .await?;
+        let mut rows = db.query(&sql, vec![libsql::Value::Integer(id)]).await?;
+        let row = rows
+            .next()
+            .await?
+            .ok_or_else(|| Error::NotFound(format!("Record with ID {} not found", mask_id(id))))?;
 
-        if let Some(row) = rows.next().await? {
-            let map = Self::row_to_map(&row)?;
-            Self::log_debug(&format!("Found record with ID: {}", mask_id(id)));
-            Ok(Some(Self::from_map(map)?))
-        } else {
-            Self::log_debug(&format!("No record found with ID: {}", mask_id(id)));
-            Ok(None)
-        }
+        let mut map = self.to_map()?;
+        map.insert(
+            column.to_string(),
+            Self::libsql_value_to_value(&row.get_value(0)?),
+        );
+        Self::from_map(map)
     }
 
     /// Find a single record by a specific condition
     async fn find_one(filter: FilterOperator, db: &Database) -> Result<Option<Self>> {
-        let builder = QueryBuilder::new(Self::table_name())
+        let builder = Self::query_builder().r#where(filter).limit(1);
+
+        let results = builder.execute_models::<Self>(db).await?;
+        Ok(results.into_iter().next())
+    }
+
+    /// Find the first record matching a filter in the given order
+    ///
+    /// Applies `ORDER BY ... LIMIT 1`, so only one row is fetched.
+    async fn find_first(
+        filter: FilterOperator,
+        sort: Vec<Sort>,
+        db: &Database,
+    ) -> Result<Option<Self>> {
+        let builder = Self::query_builder()
             .r#where(filter)
+            .order_by_multiple(sort)
             .limit(1);
 
-        let results = builder.execute::<Self>(db).await?;
+        let results = builder.execute_models::<Self>(db).await?;
         Ok(results.into_iter().next())
     }
 
     /// Find all records
     async fn find_all(db: &Database) -> Result<Vec<Self>> {
-        let builder = QueryBuilder::new(Self::table_name());
-        builder.execute::<Self>(db).await
+        let builder = Self::query_builder();
+        builder.execute_models::<Self>(db).await
+    }
+
+    /// Walk all records in primary key order, passing batches to `callback`
+    ///
+    /// Each batch is read with `WHERE pk > last ORDER BY pk LIMIT n`, so
+    /// records inserted or deleted during the walk never shift the batches.
+    /// Stops at the first error. Returns the number of records visited. For
+    /// work that must resume after an interruption, see
+    /// [`Backfill`](crate::Backfill).
+    async fn find_in_batches<F, Fut>(batch_size: u32, db: &Database, callback: F) -> Result<u64>
+    where
+        F: FnMut(Vec<Self>) -> Fut,
+        Fut: std::future::Future<Output = Result<()>>,
+    {
+        in_batches(Self::query_builder(), batch_size, db, callback).await
+    }
+
+    /// Walk the records matching a filter in batches, see
+    /// [`find_in_batches`](Self::find_in_batches)
+    async fn find_where_in_batches<F, Fut>(
+        filter: FilterOperator,
+        batch_size: u32,
+        db: &Database,
+        callback: F,
+    ) -> Result<u64>
+    where
+        F: FnMut(Vec<Self>) -> Fut,
+        Fut: std::future::Future<Output = Result<()>>,
+    {
+        in_batches(
+            Self::query_builder().r#where(filter),
+            batch_size,
+            db,
+            callback,
+        )
+        .await
+    }
+
+    /// Walk all records one at a time, reading them in batches
+    async fn find_each<F, Fut>(batch_size: u32, db: &Database, mut callback: F) -> Result<u64>
+    where
+        F: FnMut(Self) -> Fut,
+        Fut: std::future::Future<Output = Result<()>>,
+    {
+        let builder = batch_builder::<Self>(Self::query_builder(), batch_size)?;
+        let mut last = None;
+        let mut visited = 0;
+        loop {
+            let batch = next_batch::<Self>(&builder, &mut last, db).await?;
+            let full = batch.len() == batch_size as usize;
+            visited += batch.len() as u64;
+            for record in batch {
+                callback(record).await?;
+            }
+            if !full || last.is_none() {
+                return Ok(visited);
+            }
+        }
+    }
+
+    /// Pick up to `n` random records
+    ///
+    /// Uses `ORDER BY RANDOM()`, which reads the whole table; see
+    /// [`sample_by_rowid`](Self::sample_by_rowid) for large tables.
+    async fn sample(n: u32, db: &Database) -> Result<Vec<Self>> {
+        Self::query_builder()
+            .order_by(Sort::asc("RANDOM()"))
+            .limit(n)
+            .execute_models::<Self>(db)
+            .await
+    }
+
+    /// Pick up to `n` random records by probing random rowids
+    ///
+    /// Only touches the probed rows, so it stays fast on large tables. Rows
+    /// are returned in rowid order, and fewer than `n` come back when the
+    /// rowid range has many gaps. Requires a rowid table.
+    async fn sample_by_rowid(n: u32, db: &Database) -> Result<Vec<Self>> {
+        let table = Self::table_name();
+        let probes = Filter::raw(
+            format!(
+                "rowid IN (WITH RECURSIVE _probe(i) AS \
+                 (SELECT 1 UNION ALL SELECT i + 1 FROM _probe WHERE i < ?) \
+                 SELECT lo + abs(random() % (hi - lo + 1)) FROM _probe, \
+                 (SELECT min(rowid) AS lo, max(rowid) AS hi FROM {table}))"
+            ),
+            // Oversample to make up for gaps and repeated probes
+            vec![i64::from(n) * 2],
+        );
+        Self::query_builder()
+            .r#where(FilterOperator::Single(probes))
+            .limit(n)
+            .execute_models::<Self>(db)
+            .await
     }
 
     /// Find records with a filter
     async fn find_where(filter: FilterOperator, db: &Database) -> Result<Vec<Self>> {
-        let builder = QueryBuilder::new(Self::table_name()).r#where(filter);
-        builder.execute::<Self>(db).await
+        let builder = Self::query_builder().r#where(filter);
+        builder.execute_models::<Self>(db).await
+    }
+
+    /// Stream records matching a filter, converting rows as they are read
+    ///
+    /// Suited to exports of large tables; see
+    /// [`QueryBuilder::stream_models`].
+    fn stream_where(
+        filter: FilterOperator,
+        db: &Database,
+    ) -> impl futures::Stream<Item = Result<Self>> + '_ {
+        Self::query_builder()
+            .r#where(filter)
+            .stream_models::<Self>(db)
+    }
+
+    /// Find records with a filter, ordered by one or more sorts
+    async fn find_where_sorted(
+        filter: FilterOperator,
+        sorts: Vec<Sort>,
+        db: &Database,
+    ) -> Result<Vec<Self>> {
+        let builder = Self::query_builder()
+            .r#where(filter)
+            .order_by_multiple(sorts);
+        builder.execute_models::<Self>(db).await
+    }
+
+    /// Find all records as a projection
+    ///
+    /// Selects only the projection's columns from this model's table.
+    async fn find_all_as<P: Projection>(db: &Database) -> Result<Vec<P>> {
+        QueryBuilder::new(Self::table_name())
+            .select(P::columns())
+            .execute_projections::<P>(db)
+            .await
+    }
+
+    /// Find records matching a filter as a projection
+    async fn find_where_as<P: Projection>(filter: FilterOperator, db: &Database) -> Result<Vec<P>> {
+        QueryBuilder::new(Self::table_name())
+            .select(P::columns())
+            .r#where(filter)
+            .execute_projections::<P>(db)
+            .await
+    }
+
+    /// Fetch a single column of the matching records
+    ///
+    /// Only `column` is selected and each value is deserialized straight into
+    /// `T`, without building full models. Integer columns holding 0/1 are read
+    /// as booleans when the model declares the column as `bool`.
+    async fn pluck<T: DeserializeOwned>(
+        column: &str,
+        filter: Option<FilterOperator>,
+        db: &Database,
+    ) -> Result<Vec<T>> {
+        let mut builder = QueryBuilder::new(Self::table_name()).select(vec![column]);
+        if let Some(filter) = filter {
+            builder = builder.r#where(filter);
+        }
+        select_column::<Self, T>(builder, column, db).await
+    }
+
+    /// Fetch the distinct values of a column, in ascending order
+    ///
+    /// Generates `SELECT DISTINCT column`, which is handy for building filter
+    /// options without loading every record. Values are converted like
+    /// [`pluck`](Self::pluck).
+    async fn distinct<T: DeserializeOwned>(column: &str, db: &Database) -> Result<Vec<T>> {
+        let builder = QueryBuilder::new(Self::table_name())
+            .select_distinct(column)
+            .order_by(Sort::asc(column));
+        select_column::<Self, T>(builder, column, db).await
+    }
+
+    /// Compute the content hash of this record
+    ///
+    /// Returns the value the model's `content_hash` column would be written
+    /// with, e.g. to answer an `If-None-Match` request without a query, or
+    /// `None` if the model has no such column.
+    fn content_hash(&self) -> Result<Option<String>> {
+        let Some(column) = content_hash_column::<Self>() else {
+            return Ok(None);
+        };
+        match self.to_map()?.remove(column) {
+            Some(crate::Value::Text(hash)) => Ok(Some(hash)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Find the records whose content hash is not in `hashes`
+    ///
+    /// Pass the hashes a client already holds to get back only the records
+    /// that were added or changed since. Records written before the hash
+    /// column existed are always returned.
+    async fn find_changed_since(hashes: &HashSet<String>, db: &Database) -> Result<Vec<Self>> {
+        let column = content_hash_column::<Self>().ok_or_else(|| {
+            Error::Validation(format!(
+                "Model for table {} has no content_hash column",
+                Self::table_name()
+            ))
+        })?;
+        let mut builder = Self::query_builder();
+        if !hashes.is_empty() {
+            builder = builder.r#where(FilterOperator::Or(vec![
+                FilterOperator::Single(Filter::is_null(column)),
+                FilterOperator::Single(Filter::not_in_values(
+                    column,
+                    hashes.iter().cloned().collect(),
+                )),
+            ]));
+        }
+        builder.execute_models::<Self>(db).await
+    }
+
+    /// Find all records below a record in its tree
+    ///
+    /// Follows the model's `parent` column (or `parent_id`) downwards with a
+    /// recursive query and returns children, grandchildren and so on, ordered
+    /// by primary key. The record itself is not included.
+    async fn descendants(id: i64, db: &Database) -> Result<Vec<Self>> {
+        let (table, pk) = (Self::table_name(), Self::primary_key());
+        let parent = tree_parent_column::<Self>()?;
+        let base = QueryBuilder::new(table)
+            .select(vec![pk])
+            .r#where(FilterOperator::Single(Filter::eq(parent, id)));
+        let step = QueryBuilder::new(table)
+            .select(vec![format!("{table}.{pk}")])
+            .join(
+                crate::JoinType::Inner,
+                "_tree",
+                format!("{table}.{parent} = _tree.node"),
+            );
+        Self::query_builder()
+            .with_recursive_cte("_tree(node)", base, step)
+            .where_in(pk, QueryBuilder::new("_tree").select(vec!["node"]))
+            .order_by(Sort::asc(pk))
+            .execute_models::<Self>(db)
+            .await
+    }
+
+    /// Find all records above a record in its tree
+    ///
+    /// Follows the model's `parent` column (or `parent_id`) upwards and
+    /// returns the parent, grandparent and so on up to the root, nearest
+    /// first. The record itself is not included.
+    async fn ancestors(id: i64, db: &Database) -> Result<Vec<Self>> {
+        let (table, pk) = (Self::table_name(), Self::primary_key());
+        let parent = tree_parent_column::<Self>()?;
+        let base = QueryBuilder::new(table)
+            .select(vec![parent])
+            .r#where(FilterOperator::Single(Filter::eq(pk, id)));
+        let step = QueryBuilder::new(table)
+            .select(vec![format!("{table}.{parent}")])
+            .join(
+                crate::JoinType::Inner,
+                "_tree",
+                format!("{table}.{pk} = _tree.node"),
+            );
+        let records = Self::query_builder()
+            .with_recursive_cte("_tree(node)", base, step)
+            .where_in(pk, QueryBuilder::new("_tree").select(vec!["node"]))
+            .order_by(Sort::asc(pk))
+            .execute_models::<Self>(db)
+            .await?;
+        order_ancestors(records, parent)
+    }
+
+    /// Find the records with at least `count` related `C` records
+    ///
+    /// The relation is the column of `C` declared with
+    /// `#[orm_column(references = "<this table>")]`, e.g. posts with at least
+    /// five comments:
+    ///
+    /// ```no_run
+    /// # use libsql_orm::{Database, Model, Result};
+    /// # #[derive(Model, Clone, serde::Serialize, serde::Deserialize)]
+    /// # #[table_name("posts")]
+    /// # struct Post { id: Option<i64>, title: String }
+    /// # #[derive(Model, Clone, serde::Serialize, serde::Deserialize)]
+    /// # #[table_name("comments")]
+    /// # struct Comment {
+    /// #     id: Option<i64>,
+    /// #     #[orm_column(references = "posts")]
+    /// #     post_id: i64,
+    /// # }
+    /// # async fn example(db: &Database) -> Result<()> {
+    /// let popular = Post::having_at_least::<Comment>(5, db).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    async fn having_at_least<C: Model>(count: u64, db: &Database) -> Result<Vec<Self>> {
+        let related = related_counts::<Self, C>(">=", count)?;
+        Self::query_builder()
+            .where_in(Self::primary_key(), related)
+            .execute_models::<Self>(db)
+            .await
+    }
+
+    /// Find the records with at most `count` related `C` records
+    ///
+    /// Records without any related record are included. See
+    /// [`having_at_least`](Model::having_at_least) for how the relation is
+    /// found.
+    async fn having_at_most<C: Model>(count: u64, db: &Database) -> Result<Vec<Self>> {
+        let related = related_counts::<Self, C>(">", count)?;
+        Self::query_builder()
+            .r#where(FilterOperator::Single(Filter::not_in_subquery(
+                Self::primary_key(),
+                related,
+            )))
+            .execute_models::<Self>(db)
+            .await
     }
 
     /// Find records with pagination
@@ -293,8 +1428,10 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         pagination: &Pagination,
         db: &Database,
     ) -> Result<PaginatedResult<Self>> {
-        let builder = QueryBuilder::new(Self::table_name());
-        builder.execute_paginated::<Self>(db, pagination).await
+        let builder = Self::query_builder();
+        builder
+            .execute_models_paginated::<Self>(db, pagination)
+            .await
     }
 
     /// Find records with filter and pagination
@@ -303,8 +1440,29 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         pagination: &Pagination,
         db: &Database,
     ) -> Result<PaginatedResult<Self>> {
-        let builder = QueryBuilder::new(Self::table_name()).r#where(filter);
-        builder.execute_paginated::<Self>(db, pagination).await
+        let builder = Self::query_builder().r#where(filter);
+        builder
+            .execute_models_paginated::<Self>(db, pagination)
+            .await
+    }
+
+    /// Find one page of records with keyset pagination
+    async fn find_cursor(
+        pagination: &CursorPagination,
+        db: &Database,
+    ) -> Result<CursorPaginatedResult<Self>> {
+        let builder = Self::query_builder();
+        builder.execute_models_cursor::<Self>(db, pagination).await
+    }
+
+    /// Find one page of records with a filter and keyset pagination
+    async fn find_where_cursor(
+        filter: FilterOperator,
+        pagination: &CursorPagination,
+        db: &Database,
+    ) -> Result<CursorPaginatedResult<Self>> {
+        let builder = Self::query_builder().r#where(filter);
+        builder.execute_models_cursor::<Self>(db, pagination).await
     }
 
     /// Search records with text search
@@ -319,10 +1477,101 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         Self::find_where_paginated(filter, &pagination, db).await
     }
 
+    /// Search several columns for a term, best matches first
+    ///
+    /// Finds the records where any of the columns contains `term`
+    /// (case-insensitive for ASCII, like `LIKE`). Each matching column adds
+    /// to a record's score, and earlier columns weigh more: with three
+    /// columns, a match in the first counts 3, the second 2 and the last 1.
+    /// Records are ordered by score, then primary key.
+    ///
+    /// Every row is scanned, so this suits small tables; larger ones are
+    /// better served by an FTS5 index.
+    ///
+    /// ```no_run
+    /// # use libsql_orm::{Database, Model, Result};
+    /// # #[derive(Model, Clone, serde::Serialize, serde::Deserialize)]
+    /// # #[table_name("posts")]
+    /// # struct Post { id: Option<i64>, title: String, content: String, author: String }
+    /// # async fn example(db: &Database) -> Result<()> {
+    /// let posts = Post::search_columns(&["title", "content", "author"], "rust", db).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    async fn search_columns(columns: &[&str], term: &str, db: &Database) -> Result<Vec<Self>> {
+        let (sql, params) = search_columns_statement::<Self>(columns, term)?;
+
+        Self::log_debug(&format!("SQL: {sql}"));
+
+        db.query_map(&sql, params, |row| Self::from_map(Self::row_to_map(row)?))
+            .await
+    }
+
+    /// Find records whose column is spelled like `term`, best matches first
+    ///
+    /// Tolerates typos: `"rsut"` finds `"Rust tips"`. A record matches when
+    /// the column, or a run of its words as long as the term, is at least
+    /// `threshold` similar to the term, where similarity runs from 0 to 1 and
+    /// is one minus the case-insensitive edit distance divided by the longer
+    /// length. Around 0.7 tolerates one typo in a short word.
+    ///
+    /// Candidates are narrowed with `LIKE` where the threshold allows it and
+    /// ranked in the client, so this suits small and medium tables.
+    async fn fuzzy_search(
+        column: &str,
+        term: &str,
+        threshold: f64,
+        db: &Database,
+    ) -> Result<Vec<Self>> {
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(Error::Validation(format!(
+                "Similarity threshold {threshold} is not between 0 and 1"
+            )));
+        }
+        if !Self::columns().contains(&column) {
+            return Err(Error::Validation(format!(
+                "Table {} has no column {column}",
+                Self::table_name()
+            )));
+        }
+
+        let mut filters = vec![FilterOperator::Single(Filter::is_not_null(column))];
+        if let Some(pairs) = crate::similarity::required_bigrams(term, threshold) {
+            filters.push(FilterOperator::Or(
+                pairs
+                    .iter()
+                    .map(|pair| {
+                        FilterOperator::Single(Filter::like_escaped(
+                            column,
+                            format!("%{}%", Filter::escape_like(pair)),
+                        ))
+                    })
+                    .collect(),
+            ));
+        }
+        let candidates = Self::query_builder()
+            .r#where(FilterOperator::And(filters))
+            .order_by(Sort::asc(Self::primary_key()))
+            .execute_models::<Self>(db)
+            .await?;
+
+        let mut scored = Vec::new();
+        for model in candidates {
+            if let Some(crate::Value::Text(text)) = model.to_map()?.get(column) {
+                let score = crate::similarity::similarity(term, text);
+                if score >= threshold {
+                    scored.push((score, model));
+                }
+            }
+        }
+        scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        Ok(scored.into_iter().map(|(_, model)| model).collect())
+    }
+
     /// Count all records
     async fn count(db: &Database) -> Result<u64> {
         let sql = format!("SELECT COUNT(*) FROM {}", Self::table_name());
-        let mut rows = db.inner.query(&sql, vec![libsql::Value::Null; 0]).await?;
+        let mut rows = db.query(&sql, vec![libsql::Value::Null; 0]).await?;
 
         if let Some(row) = rows.next().await? {
             row.get_value(0)
@@ -342,7 +1591,7 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         let builder = QueryBuilder::new(Self::table_name()).r#where(filter);
 
         let (sql, params) = builder.build_count()?;
-        let mut rows = db.inner.query(&sql, params).await?;
+        let mut rows = db.query(&sql, params).await?;
 
         if let Some(row) = rows.next().await? {
             row.get_value(0)
@@ -357,18 +1606,102 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         }
     }
 
+    /// Check whether any record matches a filter
+    ///
+    /// Compiles to `SELECT EXISTS(SELECT 1 FROM table WHERE ...)`, which stops
+    /// at the first matching row.
+    async fn exists(filter: FilterOperator, db: &Database) -> Result<bool> {
+        let (where_sql, params) = QueryBuilder::new(Self::table_name())
+            .r#where(filter)
+            .build_where()?;
+        let sql = format!(
+            "SELECT EXISTS(SELECT 1 FROM {}{})",
+            Self::table_name(),
+            where_sql
+        );
+        Self::log_debug(&format!("SQL: {sql}"));
+
+        let mut rows = db.query(&sql, params).await?;
+        match rows.next().await? {
+            Some(row) => Ok(row.get::<i64>(0)? != 0),
+            None => Err(Error::Query("No exists result".to_string())),
+        }
+    }
+
     /// Update a record
+    ///
+    /// Lazy columns that were not loaded (`None`) are left unchanged; clear
+    /// them explicitly with [`update_fields`](Self::update_fields).
     async fn update(&self, db: &Database) -> Result<Self> {
         let id = self.get_primary_key().ok_or_else(|| {
             Error::Validation("Cannot update record without primary key".to_string())
         })?;
 
+        let (sql, params) = self.update_statement()?;
+
+        Self::log_info(&format!("Updating record with ID: {}", mask_id(id)));
+        Self::log_debug(&format!("SQL: {sql}"));
+
+        db.execute(&sql, params).await?;
+        Self::log_info(&format!(
+            "Successfully updated record with ID: {}",
+            mask_id(id)
+        ));
+        Ok(self.clone())
+    }
+
+    /// Update only the given columns of an existing record
+    ///
+    /// The SET clause contains just `fields`, so other columns are left as
+    /// they are in the database. Large columns are not rewritten and concurrent
+    /// writes to other columns are not clobbered.
+    async fn update_fields(&self, fields: &[&str], db: &Database) -> Result<Self> {
+        let id = self.get_primary_key().ok_or_else(|| {
+            Error::Validation("Cannot update record without primary key".to_string())
+        })?;
+        if fields.is_empty() {
+            return Err(Error::Validation(
+                "update_fields requires at least one field".to_string(),
+            ));
+        }
+
         let map = self.to_map()?;
-        let set_clauses: Vec<String> = map
-            .keys()
-            .filter(|&k| k != Self::primary_key())
-            .map(|k| format!("{k} = ?"))
-            .collect();
+        let mut set_clauses = Vec::with_capacity(fields.len());
+        let mut params = Vec::with_capacity(fields.len() + 1);
+        for field in fields {
+            if *field == Self::primary_key() {
+                return Err(Error::Validation(format!(
+                    "Cannot update primary key column '{field}'"
+                )));
+            }
+            let value = map.get(*field).ok_or_else(|| {
+                Error::Validation(format!(
+                    "Unknown column '{field}' for table {}",
+                    Self::table_name()
+                ))
+            })?;
+            set_clauses.push(format!("{field} = ?"));
+            params.push(Self::value_to_libsql_value(value));
+        }
+        for meta in Self::column_meta() {
+            let hashed = meta.hash_of.iter().any(|source| fields.contains(source));
+            if hashed && !fields.contains(&meta.name) {
+                if let Some(value) = map.get(meta.name) {
+                    set_clauses.push(format!("{} = ?", meta.name));
+                    params.push(Self::value_to_libsql_value(value));
+                }
+            }
+        }
+        for meta in Self::column_meta() {
+            let Some(mirror) = meta.mirror_to else {
+                continue;
+            };
+            if let (true, Some(value)) = (fields.contains(&meta.name), map.get(mirror)) {
+                set_clauses.push(format!("{mirror} = ?"));
+                params.push(Self::value_to_libsql_value(value));
+            }
+        }
+        params.push(libsql::Value::Integer(id));
 
         let sql = format!(
             "UPDATE {} SET {} WHERE {} = ?",
@@ -377,45 +1710,198 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
             Self::primary_key()
         );
 
-        Self::log_info(&format!("Updating record with ID: {}", mask_id(id)));
+        Self::log_info(&format!(
+            "Updating fields {:?} of record with ID: {}",
+            fields,
+            mask_id(id)
+        ));
         Self::log_debug(&format!("SQL: {sql}"));
 
-        let mut params: Vec<libsql::Value> = map
-            .iter()
-            .filter(|(k, _)| k != &Self::primary_key())
-            .map(|(_, v)| Self::value_to_libsql_value(v))
-            .collect();
-        params.push(libsql::Value::Integer(id));
+        db.execute(&sql, params).await?;
+        Ok(self.clone())
+    }
+
+    /// Update every record matching a filter in a single statement
+    ///
+    /// Generates `UPDATE table SET ... WHERE ...` from `sets` without loading
+    /// any rows. Columns with a `mirror_to` copy are written to their mirror
+    /// too. Columns a content hash is computed from cannot be set this way,
+    /// as the hash is not computed in SQL. Returns the number of affected rows.
+    async fn update_where(
+        filter: FilterOperator,
+        sets: HashMap<String, crate::Value>,
+        db: &Database,
+    ) -> Result<u64> {
+        if sets.is_empty() {
+            return Err(Error::Validation(
+                "update_where requires at least one column to set".to_string(),
+            ));
+        }
+
+        let columns = Self::columns();
+        let mut set_clauses = Vec::with_capacity(sets.len());
+        let mut params = Vec::with_capacity(sets.len());
+        for (column, value) in &sets {
+            if column == Self::primary_key() || !columns.contains(&column.as_str()) {
+                return Err(Error::Validation(format!(
+                    "Cannot set column '{column}' on table {}",
+                    Self::table_name()
+                )));
+            }
+            check_not_hashed::<Self>(column)?;
+            set_clauses.push(format!("{column} = ?"));
+            params.push(Self::value_to_libsql_value(value));
+            if let Some(mirror) = mirror_column::<Self>(column) {
+                set_clauses.push(format!("{mirror} = ?"));
+                params.push(Self::value_to_libsql_value(value));
+            }
+        }
+
+        let (where_sql, where_params) = QueryBuilder::new(Self::table_name())
+            .r#where(filter)
+            .build_where()?;
+        params.extend(where_params);
+
+        let sql = format!(
+            "UPDATE {} SET {}{}",
+            Self::table_name(),
+            set_clauses.join(", "),
+            where_sql
+        );
 
-        db.inner.execute(&sql, params).await?;
         Self::log_info(&format!(
-            "Successfully updated record with ID: {}",
-            mask_id(id)
+            "Updating records in table: {}",
+            Self::table_name()
         ));
-        Ok(self.clone())
+        Self::log_debug(&format!("SQL: {sql}"));
+
+        let affected = db.execute(&sql, params).await?;
+        Self::log_info(&format!("Updated {affected} record(s)"));
+        Ok(affected)
+    }
+
+    /// Atomically add `amount` to a numeric column of the matching records
+    ///
+    /// Compiles to `UPDATE table SET column = column + ? WHERE ...`, so
+    /// concurrent increments are not lost the way a read-modify-write would
+    /// lose them. A `mirror_to` copy of the column is set to the new value;
+    /// a column a content hash is computed from is rejected. Returns the
+    /// number of affected rows.
+    async fn increment(
+        column: &str,
+        amount: i64,
+        filter: FilterOperator,
+        db: &Database,
+    ) -> Result<u64> {
+        if column == Self::primary_key() || !Self::columns().contains(&column) {
+            return Err(Error::Validation(format!(
+                "Cannot increment column '{column}' on table {}",
+                Self::table_name()
+            )));
+        }
+        check_not_hashed::<Self>(column)?;
+
+        let (where_sql, where_params) = QueryBuilder::new(Self::table_name())
+            .r#where(filter)
+            .build_where()?;
+        let mut set_clause = format!("{column} = {column} + ?");
+        let mut params = vec![libsql::Value::Integer(amount)];
+        if let Some(mirror) = mirror_column::<Self>(column) {
+            // SET expressions read the old row, so this is the new value
+            set_clause.push_str(&format!(", {mirror} = {column} + ?"));
+            params.push(libsql::Value::Integer(amount));
+        }
+        let sql = format!("UPDATE {} SET {set_clause}{where_sql}", Self::table_name());
+        params.extend(where_params);
+
+        Self::log_debug(&format!("SQL: {sql}"));
+        Ok(db.execute(&sql, params).await?)
+    }
+
+    /// Atomically subtract `amount` from a numeric column of the matching records
+    async fn decrement(
+        column: &str,
+        amount: i64,
+        filter: FilterOperator,
+        db: &Database,
+    ) -> Result<u64> {
+        Self::increment(column, -amount, filter, db).await
     }
 
     /// Update multiple records
+    ///
+    /// Sends one `UPDATE ... SET column = CASE pk WHEN ? THEN ? ... END
+    /// WHERE pk IN (...)` statement per chunk of up to 500 records, together
+    /// in one [`batch`](Database::batch). If a record appears more than once,
+    /// its last occurrence wins.
     async fn bulk_update(models: &[Self], db: &Database) -> Result<Vec<Self>> {
         if models.is_empty() {
             return Ok(Vec::new());
         }
 
-        let mut results = Vec::new();
-        // Note: Manual transaction handling for WASM
-        db.inner
-            .execute("BEGIN", vec![libsql::Value::Null; 0])
-            .await?;
-
+        let mut rows = Vec::with_capacity(models.len());
+        let mut columns: Vec<String> = Vec::new();
         for model in models {
-            let result = model.update(db).await?;
-            results.push(result);
+            let id = model.get_primary_key().ok_or_else(|| {
+                Error::Validation("Cannot update record without primary key".to_string())
+            })?;
+            let mut map = model.to_map()?;
+            remove_unloaded_lazy(&mut map, Self::column_meta());
+            map.remove(Self::primary_key());
+            for column in map.keys() {
+                if !columns.contains(column) {
+                    columns.push(column.clone());
+                }
+            }
+            rows.push((id, map));
+        }
+        if columns.is_empty() {
+            return Ok(models.to_vec());
+        }
+        // Each row scans the CASE arms, so very long ones get slow
+        let rows_per_statement = (MAX_PARAMS / (2 * columns.len() + 1)).clamp(1, 500);
+        let pk = Self::primary_key();
+
+        let mut statements = Vec::new();
+        for chunk in rows.chunks(rows_per_statement) {
+            let mut set_clauses = Vec::with_capacity(columns.len());
+            let mut params = Vec::new();
+            for column in &columns {
+                let mut cases = String::new();
+                // The first matching WHEN is used, so walk backwards to
+                // let a record's last occurrence win
+                for (id, map) in chunk.iter().rev() {
+                    // Lazy columns that were not loaded are left alone
+                    if let Some(value) = map.get(column) {
+                        cases.push_str(" WHEN ? THEN ?");
+                        params.push(libsql::Value::Integer(*id));
+                        params.push(Self::value_to_libsql_value(value));
+                    }
+                }
+                if !cases.is_empty() {
+                    set_clauses.push(format!("{column} = CASE {pk}{cases} ELSE {column} END"));
+                }
+            }
+            let sql = format!(
+                "UPDATE {} SET {} WHERE {pk} IN ({})",
+                Self::table_name(),
+                set_clauses.join(", "),
+                vec!["?"; chunk.len()].join(", ")
+            );
+            params.extend(chunk.iter().map(|(id, _)| libsql::Value::Integer(*id)));
+
+            Self::log_debug(&format!("SQL: {sql}"));
+            statements.push((sql, params));
         }
 
-        db.inner
-            .execute("COMMIT", vec![libsql::Value::Null; 0])
-            .await?;
-        Ok(results)
+        // A single statement is atomic by itself and needs no batch
+        if statements.len() == 1 {
+            let (sql, params) = statements.remove(0);
+            db.execute(&sql, params).await?;
+        } else {
+            db.batch(statements).await?;
+        }
+        Ok(models.to_vec())
     }
 
     /// Delete a record
@@ -424,18 +1910,12 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
             Error::Validation("Cannot delete record without primary key".to_string())
         })?;
 
-        let sql = format!(
-            "DELETE FROM {} WHERE {} = ?",
-            Self::table_name(),
-            Self::primary_key()
-        );
+        let (sql, params) = self.delete_statement()?;
 
         Self::log_info(&format!("Deleting record with ID: {}", mask_id(id)));
         Self::log_debug(&format!("SQL: {sql}"));
 
-        db.inner
-            .execute(&sql, vec![libsql::Value::Integer(id)])
-            .await?;
+        db.execute(&sql, params).await?;
         Self::log_info(&format!(
             "Successfully deleted record with ID: {}",
             mask_id(id)
@@ -458,21 +1938,163 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         );
 
         let params: Vec<libsql::Value> = ids.iter().map(|&id| libsql::Value::Integer(id)).collect();
-        db.inner.execute(&sql, params).await?;
+        db.execute(&sql, params).await?;
         Ok(ids.len() as u64)
     }
 
-    /// Delete records with a filter
+    /// Delete records with a filter in a single statement
+    ///
+    /// Returns the number of deleted rows.
     async fn delete_where(filter: FilterOperator, db: &Database) -> Result<u64> {
-        let builder = QueryBuilder::new(Self::table_name()).r#where(filter);
+        let (sql, params) = Self::delete_where_statement(filter)?;
 
-        let (sql, params) = builder.build()?;
-        let delete_sql = sql.replace("SELECT *", "DELETE");
-        db.inner.execute(&delete_sql, params).await?;
+        Self::log_info(&format!(
+            "Deleting records from table: {}",
+            Self::table_name()
+        ));
+        Self::log_debug(&format!("SQL: {sql}"));
 
-        // Note: SQLite doesn't return the number of affected rows directly
-        // This is a simplified implementation
-        Ok(1)
+        let affected = db.execute(&sql, params).await?;
+        Self::log_info(&format!("Deleted {affected} record(s)"));
+        Ok(affected)
+    }
+
+    /// Move records matching a filter into the `<table>_archive` table
+    ///
+    /// The archive table is created on first use with the model's columns,
+    /// and the copy and delete run in one transaction. Returns the number of
+    /// archived rows.
+    async fn archive_where(filter: FilterOperator, db: &Database) -> Result<u64> {
+        let (where_sql, params) = QueryBuilder::new(Self::table_name())
+            .r#where(filter)
+            .build_where()?;
+
+        Self::log_info(&format!(
+            "Archiving records from table: {}",
+            Self::table_name()
+        ));
+        let archived =
+            crate::archive::archive_rows(db, Self::table_name(), &where_sql, params).await?;
+        Self::log_info(&format!("Archived {archived} record(s)"));
+        Ok(archived)
+    }
+
+    /// Find archived records with a filter
+    ///
+    /// Returns nothing if no record has been archived yet.
+    async fn find_archived(filter: FilterOperator, db: &Database) -> Result<Vec<Self>> {
+        let archive = crate::archive::archive_table(Self::table_name());
+        let mut rows = db
+            .query(
+                "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?",
+                vec![libsql::Value::Text(archive.clone())],
+            )
+            .await?;
+        if rows.next().await?.is_none() {
+            return Ok(Vec::new());
+        }
+        QueryBuilder::new(archive)
+            .r#where(filter)
+            .execute_models::<Self>(db)
+            .await
+    }
+
+    /// Copy every `mirror_to` column's source into its mirror for existing rows
+    ///
+    /// New writes are mirrored by `to_map`; this backfills the rows written
+    /// before the mirror was declared, resumably, through
+    /// [`MigrationManager::backfill_in_batches`](crate::MigrationManager::backfill_in_batches).
+    async fn backfill_mirrors(manager: &crate::MigrationManager, batch_size: u32) -> Result<u64> {
+        let mirrors: Vec<(&str, &str)> = Self::column_meta()
+            .iter()
+            .filter_map(|meta| Some((meta.mirror_to?, meta.name)))
+            .collect();
+        let assignments: Vec<String> = mirrors
+            .iter()
+            .map(|(mirror, source)| format!("{mirror} = {source}"))
+            .collect();
+        let targets: Vec<&str> = mirrors.iter().map(|(mirror, _)| *mirror).collect();
+        if assignments.is_empty() {
+            return Err(Error::Validation(format!(
+                "{} has no mirror_to columns",
+                Self::table_name()
+            )));
+        }
+        manager
+            .backfill_in_batches(
+                &format!("mirror_{}_{}", Self::table_name(), targets.join("_")),
+                Self::table_name(),
+                &assignments.join(", "),
+                batch_size,
+            )
+            .await
+    }
+
+    /// Merge duplicate records into a survivor
+    ///
+    /// In one transaction, every column declared with
+    /// `#[orm_column(references = "<this table>")]` on a registered model is
+    /// re-pointed from the duplicates to `survivor_id`, then the duplicates
+    /// are deleted. Returns the number of deleted duplicates.
+    ///
+    /// ```rust,ignore
+    /// // Order.customer_id is declared with references = "customers"
+    /// Customer::merge(1, &[7, 12], &db).await?;
+    /// ```
+    async fn merge(survivor_id: i64, duplicate_ids: &[i64], db: &Database) -> Result<u64> {
+        let duplicates: Vec<i64> = duplicate_ids
+            .iter()
+            .copied()
+            .filter(|&id| id != survivor_id)
+            .collect();
+        if duplicates.is_empty() {
+            return Ok(0);
+        }
+
+        db.transaction(|tx| async move {
+            if Self::find_by_id(survivor_id, tx).await?.is_none() {
+                return Err(Error::NotFound(format!(
+                    "{} record {} not found",
+                    Self::table_name(),
+                    mask_id(survivor_id)
+                )));
+            }
+
+            for model in crate::registry() {
+                let referencing = model
+                    .columns
+                    .iter()
+                    .filter(|column| column.references == Some(Self::table_name()));
+                for column in referencing {
+                    let (where_sql, where_params) = QueryBuilder::new(model.table_name)
+                        .r#where(FilterOperator::Single(Filter::in_values(
+                            column.name,
+                            duplicates.clone(),
+                        )))
+                        .build_where()?;
+                    let sql = format!(
+                        "UPDATE {} SET {} = ?{}",
+                        model.table_name, column.name, where_sql
+                    );
+                    let mut params = vec![libsql::Value::Integer(survivor_id)];
+                    params.extend(where_params);
+                    Self::log_debug(&format!("SQL: {sql}"));
+                    tx.execute(&sql, params).await?;
+                }
+            }
+
+            let deleted = Self::delete_where(
+                FilterOperator::Single(Filter::in_values(Self::primary_key(), duplicates)),
+                tx,
+            )
+            .await?;
+            Self::log_info(&format!(
+                "Merged {deleted} duplicate(s) into record with ID: {}",
+                mask_id(survivor_id)
+            ));
+            Ok(deleted)
+        })
+        .await
     }
 
     /// List records with optional sorting and pagination
@@ -481,14 +2103,16 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         pagination: Option<&Pagination>,
         db: &Database,
     ) -> Result<PaginatedResult<Self>> {
-        let mut builder = QueryBuilder::new(Self::table_name());
+        let mut builder = Self::query_builder();
 
         if let Some(sorts) = sort {
-            builder = builder.order_by_multiple(sorts);
+            builder = builder.order_by_multiple(stable_sorts::<Self>(sorts));
         }
 
         let pagination = pagination.unwrap_or(&Pagination::default()).clone();
-        builder.execute_paginated::<Self>(db, &pagination).await
+        builder
+            .execute_models_paginated::<Self>(db, &pagination)
+            .await
     }
 
     /// List records with filter, sorting, and pagination
@@ -498,19 +2122,21 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         pagination: Option<&Pagination>,
         db: &Database,
     ) -> Result<PaginatedResult<Self>> {
-        let mut builder = QueryBuilder::new(Self::table_name()).r#where(filter);
+        let mut builder = Self::query_builder().r#where(filter);
 
         if let Some(sorts) = sort {
-            builder = builder.order_by_multiple(sorts);
+            builder = builder.order_by_multiple(stable_sorts::<Self>(sorts));
         }
 
         let pagination = pagination.unwrap_or(&Pagination::default()).clone();
-        builder.execute_paginated::<Self>(db, &pagination).await
+        builder
+            .execute_models_paginated::<Self>(db, &pagination)
+            .await
     }
 
     /// Execute a custom query
     async fn query(builder: QueryBuilder, db: &Database) -> Result<Vec<Self>> {
-        builder.execute::<Self>(db).await
+        builder.execute_models::<Self>(db).await
     }
 
     /// Execute a custom query with pagination
@@ -519,7 +2145,9 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         pagination: &Pagination,
         db: &Database,
     ) -> Result<PaginatedResult<Self>> {
-        builder.execute_paginated::<Self>(db, pagination).await
+        builder
+            .execute_models_paginated::<Self>(db, pagination)
+            .await
     }
 
     /// Get aggregate value
@@ -537,7 +2165,7 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         }
 
         let (sql, params) = builder.build()?;
-        let mut rows = db.inner.query(&sql, params).await?;
+        let mut rows = db.query(&sql, params).await?;
 
         if let Some(row) = rows.next().await? {
             let value = row
@@ -555,6 +2183,112 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         }
     }
 
+    /// Get the earliest timestamp of a column, `None` if there are no rows
+    ///
+    /// Stored values are compared as they are, so the column should hold
+    /// timestamps written by a model. The result is parsed with the
+    /// [datetime parse chain](crate::coercion).
+    ///
+    /// ```rust,ignore
+    /// let first_signup = User::min_datetime("created_at", None, &db).await?;
+    /// ```
+    async fn min_datetime(
+        column: &str,
+        filter: Option<FilterOperator>,
+        db: &Database,
+    ) -> Result<Option<DateTime<Utc>>> {
+        datetime_aggregate::<Self>(Aggregate::Min, column, filter, db).await
+    }
+
+    /// Get the latest timestamp of a column, `None` if there are no rows
+    ///
+    /// See [`min_datetime`](Self::min_datetime).
+    async fn max_datetime(
+        column: &str,
+        filter: Option<FilterOperator>,
+        db: &Database,
+    ) -> Result<Option<DateTime<Utc>>> {
+        datetime_aggregate::<Self>(Aggregate::Max, column, filter, db).await
+    }
+
+    /// Get an aggregate value per group
+    ///
+    /// Returns `(group_key, value)` pairs ordered by the group key, e.g. the
+    /// total quantity of each category:
+    ///
+    /// ```rust,ignore
+    /// let totals = Product::aggregate_group_by(Aggregate::Sum, "quantity", "category", None, &db).await?;
+    /// ```
+    ///
+    /// The value is `None` for groups where the aggregate is NULL.
+    async fn aggregate_group_by(
+        function: Aggregate,
+        column: &str,
+        group_by: &str,
+        filter: Option<FilterOperator>,
+        db: &Database,
+    ) -> Result<Vec<(crate::Value, Option<f64>)>> {
+        let mut builder = QueryBuilder::new(Self::table_name())
+            .aggregate(function, column, None::<String>)
+            .group_by(vec![group_by])
+            .order_by(Sort::asc(group_by));
+
+        if let Some(filter) = filter {
+            builder = builder.r#where(filter);
+        }
+
+        query_groups::<Self>(builder, db).await
+    }
+
+    /// Get one page of aggregate values per group
+    ///
+    /// Like [`aggregate_group_by`](Self::aggregate_group_by), but sorted by
+    /// `sort` and limited to a page of groups. The pagination total is the
+    /// number of groups. Sort on `value` to order by the aggregate, e.g. the
+    /// third page of customers by spend:
+    ///
+    /// ```rust,ignore
+    /// let page = Order::aggregate_group_by_paginated(
+    ///     Aggregate::Sum,
+    ///     "amount",
+    ///     "customer_id",
+    ///     None,
+    ///     Sort::desc("value"),
+    ///     &Pagination::new(3, 20),
+    ///     &db,
+    /// )
+    /// .await?;
+    /// ```
+    async fn aggregate_group_by_paginated(
+        function: Aggregate,
+        column: &str,
+        group_by: &str,
+        filter: Option<FilterOperator>,
+        sort: Sort,
+        pagination: &Pagination,
+        db: &Database,
+    ) -> Result<PaginatedResult<(crate::Value, Option<f64>)>> {
+        let mut builder = QueryBuilder::new(Self::table_name())
+            .aggregate(function, column, Some("value"))
+            .group_by(vec![group_by]);
+        if let Some(filter) = filter {
+            builder = builder.r#where(filter);
+        }
+        let total = builder.count_for_page(db, pagination).await?;
+
+        // Break ties on the group key so pages do not overlap
+        let tie_break = sort.column != group_by;
+        let mut page = builder.order_by(sort);
+        if tie_break {
+            page = page.order_by(Sort::asc(group_by));
+        }
+        let page = page
+            .limit(pagination.fetch_limit())
+            .offset(pagination.offset());
+        let groups = query_groups::<Self>(page, db).await?;
+        Ok(QueryBuilder::paginated(groups, pagination, total))
+    }
+
     /// Convert a database row to a HashMap
     fn row_to_map(row: &libsql::Row) -> Result<HashMap<String, crate::Value>> {
         let mut map = HashMap::new();
@@ -0,0 +1,149 @@
+//! Connection pragmas
+//!
+//! SQLite keeps settings such as the journal mode and the busy timeout per
+//! connection, so they have to be set every time a connection opens.
+//! [`DatabaseOptions`] collects them and
+//! [`Database::new_local`](crate::Database::new_local) or
+//! [`Database::apply_options`](crate::Database::apply_options) sets them
+//! with `PRAGMA` statements.
+//!
+//! The settings concern local database files and embedded replicas. Hosted
+//! databases reached with
+//! [`Database::new_connect`](crate::Database::new_connect) are configured by
+//! their server.
+//!
+//! ```no_run
+//! use libsql_orm::{Database, DatabaseOptions, JournalMode, Synchronous};
+//! use std::time::Duration;
+//!
+//! async fn open() -> Result<Database, Box<dyn std::error::Error>> {
+//!     let options = DatabaseOptions::new()
+//!         .journal_mode(JournalMode::Wal)
+//!         .synchronous(Synchronous::Normal)
+//!         .busy_timeout(Duration::from_secs(5))
+//!         .cache_size(-64_000);
+//!     Ok(Database::new_local("app.db", &options).await?)
+//! }
+//! ```
+
+use std::fmt;
+use std::time::Duration;
+
+/// How SQLite keeps its rollback information
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalMode {
+    /// Delete the rollback journal after each transaction; SQLite's default
+    Delete,
+    /// Truncate the rollback journal instead of deleting it
+    Truncate,
+    /// Overwrite the rollback journal's header instead of deleting it
+    Persist,
+    /// Keep the rollback journal in memory
+    Memory,
+    /// Write-ahead log, letting readers run alongside a writer
+    Wal,
+    /// No rollback journal; transactions cannot be rolled back safely
+    Off,
+}
+
+impl fmt::Display for JournalMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            JournalMode::Delete => "DELETE",
+            JournalMode::Truncate => "TRUNCATE",
+            JournalMode::Persist => "PERSIST",
+            JournalMode::Memory => "MEMORY",
+            JournalMode::Wal => "WAL",
+            JournalMode::Off => "OFF",
+        })
+    }
+}
+
+/// How often SQLite waits for writes to reach the disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Synchronous {
+    /// Never wait; a power loss can corrupt the database
+    Off,
+    /// Wait at the most critical moments; safe in WAL mode
+    Normal,
+    /// Wait after every transaction; SQLite's default
+    Full,
+    /// Like `Full`, and also for the directory of a deleted journal
+    Extra,
+}
+
+impl fmt::Display for Synchronous {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Synchronous::Off => "OFF",
+            Synchronous::Normal => "NORMAL",
+            Synchronous::Full => "FULL",
+            Synchronous::Extra => "EXTRA",
+        })
+    }
+}
+
+/// Pragmas set on a connection when it opens
+///
+/// Settings left at `None` keep SQLite's defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DatabaseOptions {
+    /// `PRAGMA journal_mode`
+    pub journal_mode: Option<JournalMode>,
+    /// `PRAGMA synchronous`
+    pub synchronous: Option<Synchronous>,
+    /// `PRAGMA busy_timeout`: how long to wait for a lock held by another
+    /// connection before failing with `SQLITE_BUSY`
+    pub busy_timeout: Option<Duration>,
+    /// `PRAGMA cache_size`: pages when positive, KiB when negative
+    pub cache_size: Option<i64>,
+}
+
+impl DatabaseOptions {
+    /// Options keeping every SQLite default
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the journal mode
+    pub fn journal_mode(mut self, mode: JournalMode) -> Self {
+        self.journal_mode = Some(mode);
+        self
+    }
+
+    /// Set how often writes are synced to disk
+    pub fn synchronous(mut self, synchronous: Synchronous) -> Self {
+        self.synchronous = Some(synchronous);
+        self
+    }
+
+    /// Wait up to `timeout` for locks held by other connections
+    pub fn busy_timeout(mut self, timeout: Duration) -> Self {
+        self.busy_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the page cache size, in pages when positive or KiB when negative
+    pub fn cache_size(mut self, size: i64) -> Self {
+        self.cache_size = Some(size);
+        self
+    }
+
+    /// The `PRAGMA` statements setting these options
+    pub fn pragmas(&self) -> Vec<String> {
+        let mut pragmas = Vec::new();
+        if let Some(mode) = self.journal_mode {
+            pragmas.push(format!("PRAGMA journal_mode = {mode}"));
+        }
+        if let Some(synchronous) = self.synchronous {
+            pragmas.push(format!("PRAGMA synchronous = {synchronous}"));
+        }
+        if let Some(timeout) = self.busy_timeout {
+            pragmas.push(format!("PRAGMA busy_timeout = {}", timeout.as_millis()));
+        }
+        if let Some(size) = self.cache_size {
+            pragmas.push(format!("PRAGMA cache_size = {size}"));
+        }
+        pragmas
+    }
+}
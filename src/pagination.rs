@@ -27,23 +27,29 @@
 //!
 //! # Cursor-based Pagination
 //!
-//! ```rust
-//! use libsql_orm::{CursorPagination, CursorPaginatedResult};
+//! Keyset pagination reads each page with `WHERE (column, id) > (?, ?)`
+//! instead of an OFFSET, so deep pages stay fast and concurrent writes do not
+//! shift rows between pages:
 //!
-//! async fn cursor_paginate(db: &Database) -> Result<(), Box<dyn std::error::Error>> {
-//!     let mut cursor_pagination = CursorPagination::new(10);
-//!     
-//!     loop {
-//!         // Implement cursor-based pagination logic here
-//!         // This would typically use a timestamp or ID as the cursor
+//! ```rust,ignore
+//! use libsql_orm::{CursorPagination, Model, Sort};
+//!
+//! let mut pagination = CursorPagination::new(50).order_by(Sort::desc("created_at"));
+//! loop {
+//!     let page = User::find_cursor(&pagination, &db).await?;
+//!     for user in &page.data {
+//!         println!("User: {}", user.name);
+//!     }
+//!     if !page.pagination.has_next {
 //!         break;
 //!     }
-//!     
-//!     Ok(())
+//!     pagination.set_cursor(page.pagination.next_cursor);
 //! }
 //! ```
 
+use crate::Sort;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 /// Pagination parameters for queries
 ///
@@ -74,6 +80,15 @@ pub struct Pagination {
     pub total: Option<u64>,
     /// Total number of pages (calculated)
     pub total_pages: Option<u32>,
+    /// Whether another page follows, for pages read without a total
+    #[serde(default)]
+    pub has_more: Option<bool>,
+    /// Time budget for the total count query
+    #[serde(skip)]
+    count_timeout: Option<Duration>,
+    /// Skip the total count query
+    #[serde(skip)]
+    without_total: bool,
 }
 
 impl Pagination {
@@ -84,9 +99,52 @@ impl Pagination {
             per_page,
             total: None,
             total_pages: None,
+            has_more: None,
+            count_timeout: None,
+            without_total: false,
         }
     }
 
+    /// Limit how long the total count query may take
+    ///
+    /// If the COUNT query exceeds the budget, the page data is still returned
+    /// but `total` and `total_pages` are left as `None`. Useful for list
+    /// endpoints on very large remote tables.
+    pub fn with_count_timeout(mut self, timeout: Duration) -> Self {
+        self.count_timeout = Some(timeout);
+        self
+    }
+
+    /// Skip the total count query
+    ///
+    /// The page is read with one extra row instead, which only tells whether
+    /// another page follows: `total` and `total_pages` stay `None` and
+    /// [`has_next`](Self::has_next) reports `has_more`. Saves a round trip
+    /// for infinite-scroll endpoints.
+    pub fn without_total(mut self) -> Self {
+        self.without_total = true;
+        self
+    }
+
+    /// Whether the total count query runs
+    pub fn counts_total(&self) -> bool {
+        !self.without_total
+    }
+
+    /// Rows to read for the page, one extra when there is no total
+    pub(crate) fn fetch_limit(&self) -> u32 {
+        if self.without_total {
+            self.per_page + 1
+        } else {
+            self.per_page
+        }
+    }
+
+    /// Get the time budget for the total count query
+    pub fn count_timeout(&self) -> Option<Duration> {
+        self.count_timeout
+    }
+
     /// Get the offset for SQL LIMIT/OFFSET
     pub fn offset(&self) -> u32 {
         (self.page - 1) * self.per_page
@@ -108,7 +166,7 @@ impl Pagination {
         if let (Some(total_pages), Some(current_page)) = (self.total_pages, Some(self.page)) {
             current_page < total_pages
         } else {
-            false
+            self.has_more.unwrap_or(false)
         }
     }
 
@@ -262,6 +320,12 @@ pub struct CursorPagination {
     pub prev_cursor: Option<String>,
     /// Total number of items
     pub total: Option<u64>,
+    /// Column the pages are ordered by, the primary key when `None`
+    ///
+    /// Ties are broken by the primary key. The column should be `NOT NULL`,
+    /// since rows with a NULL sort value are never returned.
+    #[serde(default)]
+    pub order_by: Option<Sort>,
 }
 
 impl CursorPagination {
@@ -276,6 +340,7 @@ impl CursorPagination {
             next_cursor: None,
             prev_cursor: None,
             total: None,
+            order_by: None,
         }
     }
 
@@ -291,6 +356,7 @@ impl CursorPagination {
             next_cursor: None,
             prev_cursor: None,
             total: None,
+            order_by: None,
         }
     }
 
@@ -305,9 +371,16 @@ impl CursorPagination {
             next_cursor: None,
             prev_cursor: None,
             total: None,
+            order_by: None,
         }
     }
 
+    /// Order the pages by a column instead of the primary key
+    pub fn order_by(mut self, sort: Sort) -> Self {
+        self.order_by = Some(sort);
+        self
+    }
+
     /// Set the cursor
     pub fn set_cursor(&mut self, cursor: Option<String>) {
         self.cursor = cursor;
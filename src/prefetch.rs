@@ -0,0 +1,216 @@
+//! Prefetching records of several models in one round trip
+//!
+//! [`Database::prefetch`] collects the ids a handler is going to need, for any
+//! number of models, and loads all of them with a single query:
+//!
+//! ```no_run
+//! use libsql_orm::{Database, Model};
+//!
+//! async fn handler<U: Model + 'static, P: Model + 'static>(
+//!     db: &Database,
+//!     user_ids: &[i64],
+//!     post_ids: &[i64],
+//! ) -> libsql_orm::Result<()> {
+//!     let loaded = db.prefetch().ids::<U>(user_ids).ids::<P>(post_ids).load().await?;
+//!     let users = loaded.get::<U>();
+//!     let posts = loaded.get::<P>();
+//!     Ok(())
+//! }
+//! ```
+//!
+//! The per-table SELECTs are combined with `UNION ALL`, each row encoded with
+//! `json_object`, so tables with different columns can share one statement.
+//! BLOB and compressed columns are transferred hex-encoded and decoded again on
+//! read.
+
+use crate::{ColumnKind, Database, Error, Model, Result, Value};
+use std::any::{Any, TypeId};
+use std::collections::{BTreeSet, HashMap};
+
+type Decoder = fn(Vec<HashMap<String, Value>>) -> Result<Box<dyn Any + Send + Sync>>;
+
+/// Ids requested for one model
+struct PrefetchEntry {
+    table: &'static str,
+    primary_key: &'static str,
+    /// Selected columns and whether each one holds BLOB data
+    columns: Vec<(&'static str, bool)>,
+    ids: BTreeSet<i64>,
+    decode: Decoder,
+}
+
+/// Builder collecting the records to prefetch
+pub struct Prefetch<'a> {
+    db: &'a Database,
+    entries: Vec<(TypeId, PrefetchEntry)>,
+}
+
+/// Records loaded by a [`Prefetch`], grouped by model and keyed by primary key
+#[derive(Default)]
+pub struct Prefetched {
+    maps: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+fn decode<M: Model + 'static>(
+    rows: Vec<HashMap<String, Value>>,
+) -> Result<Box<dyn Any + Send + Sync>> {
+    let mut records = HashMap::with_capacity(rows.len());
+    for row in rows {
+        let record = M::from_map(row)?;
+        if let Some(id) = record.get_primary_key() {
+            records.insert(id, record);
+        }
+    }
+    Ok(Box::new(records))
+}
+
+impl<'a> Prefetch<'a> {
+    pub(crate) fn new(db: &'a Database) -> Self {
+        Self {
+            db,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Request records of model `M` by primary key
+    ///
+    /// Requesting the same model more than once merges the ids.
+    pub fn ids<M: Model + 'static>(mut self, ids: &[i64]) -> Self {
+        let type_id = TypeId::of::<M>();
+        if let Some((_, entry)) = self.entries.iter_mut().find(|(t, _)| *t == type_id) {
+            entry.ids.extend(ids.iter().copied());
+            return self;
+        }
+
+        let meta = M::column_meta();
+        let columns = if meta.is_empty() {
+            M::columns().into_iter().map(|name| (name, false)).collect()
+        } else {
+            meta.iter()
+                .filter(|column| !column.lazy)
+                .map(|column| {
+                    (
+                        column.name,
+                        column.kind == ColumnKind::Blob || column.compressed,
+                    )
+                })
+                .collect()
+        };
+        self.entries.push((
+            type_id,
+            PrefetchEntry {
+                table: M::table_name(),
+                primary_key: M::primary_key(),
+                columns,
+                ids: ids.iter().copied().collect(),
+                decode: decode::<M>,
+            },
+        ));
+        self
+    }
+
+    /// Build the combined query and its parameters
+    fn build(&self) -> (String, Vec<libsql::Value>) {
+        let mut selects = Vec::new();
+        let mut params = Vec::new();
+        for (index, (_, entry)) in self.entries.iter().enumerate() {
+            if entry.ids.is_empty() {
+                continue;
+            }
+            let fields: Vec<String> = entry
+                .columns
+                .iter()
+                .map(|(name, blob)| {
+                    if *blob {
+                        format!("'{name}', hex({name})")
+                    } else {
+                        format!("'{name}', {name}")
+                    }
+                })
+                .collect();
+            let placeholders: Vec<&str> = entry.ids.iter().map(|_| "?").collect();
+            selects.push(format!(
+                "SELECT {index} AS prefetch_entry, json_object({}) AS prefetch_row FROM {} WHERE {} IN ({})",
+                fields.join(", "),
+                entry.table,
+                entry.primary_key,
+                placeholders.join(", ")
+            ));
+            params.extend(entry.ids.iter().map(|id| libsql::Value::Integer(*id)));
+        }
+        (selects.join(" UNION ALL "), params)
+    }
+
+    /// Load every requested record with a single query
+    pub async fn load(self) -> Result<Prefetched> {
+        let mut rows_by_entry: Vec<Vec<HashMap<String, Value>>> =
+            self.entries.iter().map(|_| Vec::new()).collect();
+
+        let (sql, params) = self.build();
+        if !sql.is_empty() {
+            let mut rows = self.db.query(&sql, params).await?;
+            while let Some(row) = rows.next().await? {
+                let index = row.get::<i64>(0)? as usize;
+                let json: String = row.get(1)?;
+                let object: serde_json::Map<String, serde_json::Value> =
+                    serde_json::from_str(&json)?;
+                let entry = &self.entries[index].1;
+
+                let mut map = HashMap::with_capacity(object.len());
+                for (column, value) in object {
+                    let is_blob = entry
+                        .columns
+                        .iter()
+                        .any(|(name, blob)| *blob && *name == column);
+                    let value = match (is_blob, value) {
+                        (true, serde_json::Value::String(hex)) => Value::Blob(decode_hex(&hex)?),
+                        (_, value) => Value::from(value),
+                    };
+                    map.insert(column, value);
+                }
+                rows_by_entry[index].push(map);
+            }
+        }
+
+        let mut prefetched = Prefetched::default();
+        for ((type_id, entry), rows) in self.entries.into_iter().zip(rows_by_entry) {
+            prefetched.maps.insert(type_id, (entry.decode)(rows)?);
+        }
+        Ok(prefetched)
+    }
+}
+
+impl Prefetched {
+    /// Get the loaded records of model `M`, keyed by primary key
+    ///
+    /// Returns `None` if `M` was not requested.
+    pub fn get<M: Model + 'static>(&self) -> Option<&HashMap<i64, M>> {
+        self.maps
+            .get(&TypeId::of::<M>())
+            .and_then(|records| records.downcast_ref())
+    }
+
+    /// Take ownership of the loaded records of model `M`
+    ///
+    /// Returns an empty map if `M` was not requested.
+    pub fn take<M: Model + 'static>(&mut self) -> HashMap<i64, M> {
+        self.maps
+            .remove(&TypeId::of::<M>())
+            .and_then(|records| records.downcast().ok())
+            .map(|records| *records)
+            .unwrap_or_default()
+    }
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return Err(Error::Serialization(format!("Invalid hex BLOB: {hex}")));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map_err(|e| Error::Serialization(format!("Invalid hex BLOB: {e}")))
+        })
+        .collect()
+}
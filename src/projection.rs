@@ -0,0 +1,81 @@
+//! Read-only projections of models
+//!
+//! A projection is a smaller struct holding a subset of a model's columns,
+//! typically used for list endpoints where the full record is not needed.
+//! Projections are declared on the model and generated by the `Model` derive:
+//!
+//! ```rust
+//! use libsql_orm::{Model, Projection};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+//! #[orm(projection(name = "Summary", fields(id, title)))]
+//! struct Post {
+//!     pub id: Option<i64>,
+//!     pub title: String,
+//!     pub body: String,
+//! }
+//!
+//! async fn list(db: &libsql_orm::Database) -> libsql_orm::Result<Vec<PostSummary>> {
+//!     Post::find_all_as::<PostSummary>(db).await
+//! }
+//! ```
+//!
+//! Only the projected columns are selected, and rows go through the same
+//! conversions as the model itself.
+//!
+//! Standalone read models, including rows of a join, derive `Projection`
+//! directly:
+//!
+//! ```rust
+//! use libsql_orm::Projection;
+//! use serde::Deserialize;
+//!
+//! #[derive(Projection, Deserialize)]
+//! #[table_name("posts")]
+//! #[projection(inner_join("users", "users.id = posts.author_id"))]
+//! struct PostWithAuthor {
+//!     pub id: i64,
+//!     pub title: String,
+//!     #[projection(column = "users.name")]
+//!     pub author: String,
+//! }
+//!
+//! async fn list(db: &libsql_orm::Database) -> libsql_orm::Result<Vec<PostWithAuthor>> {
+//!     PostWithAuthor::find_all(db).await
+//! }
+//! ```
+
+use crate::{Database, FilterOperator, QueryBuilder, Result, Value};
+use std::collections::HashMap;
+
+/// Trait for read-only structs mapped to a subset of a table's columns
+#[allow(async_fn_in_trait)]
+pub trait Projection: Sized + Send + Sync {
+    /// Table the projection reads from
+    fn table_name() -> &'static str;
+
+    /// Columns selected for the projection
+    fn columns() -> Vec<&'static str>;
+
+    /// Build the projection from a row's column map
+    fn from_map(map: HashMap<String, Value>) -> Result<Self>;
+
+    /// Create a query builder selecting the projection's columns
+    fn query_builder() -> QueryBuilder {
+        QueryBuilder::new(Self::table_name()).select(Self::columns())
+    }
+
+    /// Find all rows of the table as projections
+    async fn find_all(db: &Database) -> Result<Vec<Self>> {
+        Self::query_builder().execute_projections::<Self>(db).await
+    }
+
+    /// Find rows matching a filter as projections
+    async fn find_where(filter: FilterOperator, db: &Database) -> Result<Vec<Self>> {
+        Self::query_builder()
+            .r#where(filter)
+            .execute_projections::<Self>(db)
+            .await
+    }
+}
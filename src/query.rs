@@ -35,11 +35,16 @@
 //! let results = complex_query.execute::<OrderWithUser>(&db).await?;
 //! ```
 
+use crate::explain::{PlanStep, QueryPlan};
 use crate::filters::FilterValue;
 use crate::{
-    Aggregate, Database, FilterOperator, Operator, PaginatedResult, Pagination, Result, Sort, Value,
+    Aggregate, CursorPaginatedResult, CursorPagination, Database, Error, Filter, FilterOperator,
+    JsonColumn, Model, Operator, PaginatedResult, Pagination, Projection, QueryHint, Result, Sort,
+    SortOrder, Value, WindowFunction,
 };
+use futures::stream::{self, Stream, TryStreamExt};
 use std::collections::HashMap;
+use std::time::Duration;
 
 /// Query result wrapper
 ///
@@ -71,6 +76,177 @@ impl<T> QueryResult<T> {
     }
 }
 
+/// A SQL statement with its bound parameters, as it would be executed
+///
+/// Returned by [`QueryBuilder::to_sql`]; any `(sql, params)` pair, such as
+/// [`Model::insert_statement`], converts into
+/// one with `Statement::from`. `Display` renders the SQL with the parameters
+/// inlined as literals, which is how [`Database::batch`] sends statements and
+/// useful in logs:
+///
+/// ```rust
+/// use libsql_orm::{Filter, FilterOperator, QueryBuilder};
+///
+/// let statement = QueryBuilder::new("users")
+///     .r#where(FilterOperator::Single(Filter::eq("name", "O'Brien")))
+///     .to_sql()
+///     .unwrap();
+/// assert_eq!(statement.sql, "SELECT * FROM users WHERE name = ?");
+/// assert_eq!(statement.to_string(), "SELECT * FROM users WHERE name = 'O''Brien'");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Statement {
+    pub sql: String,
+    pub params: Vec<libsql::Value>,
+}
+
+impl From<(String, Vec<libsql::Value>)> for Statement {
+    fn from((sql, params): (String, Vec<libsql::Value>)) -> Self {
+        Self { sql, params }
+    }
+}
+
+impl Statement {
+    /// Run `EXPLAIN QUERY PLAN` for the statement without executing it
+    pub async fn explain(&self, db: &Database) -> Result<QueryPlan> {
+        let mut rows = db
+            .query(
+                &format!("EXPLAIN QUERY PLAN {}", self.sql),
+                self.params.clone(),
+            )
+            .await?;
+        let mut steps = Vec::new();
+        while let Some(row) = rows.next().await? {
+            steps.push(PlanStep {
+                id: row.get(0)?,
+                parent: row.get(1)?,
+                detail: row.get(3)?,
+            });
+        }
+        Ok(QueryPlan { steps })
+    }
+}
+
+impl std::fmt::Display for Statement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&inline_params(&self.sql, &self.params))
+    }
+}
+
+/// Replace the parameters of `sql` with `params` rendered as SQL literals
+///
+/// Follows SQLite's tokenizer: placeholders inside string literals, quoted
+/// identifiers (`"..."`, `` `...` ``, `[...]`) and comments are left alone.
+/// `?NNN` takes parameter `NNN`; a bare `?` and each new `:name`, `@name`
+/// or `$name` take the one after the highest used so far, and a repeated
+/// name takes the same one again. Placeholders without a parameter are kept.
+fn inline_params(sql: &str, params: &[libsql::Value]) -> String {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut out = String::with_capacity(sql.len());
+    let mut names: Vec<(String, usize)> = Vec::new();
+    let mut highest = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        // Copies the text up to and including the next `end` (and the rest
+        // when there is none), returning the index after it
+        let skip_to = |out: &mut String, from: usize, end: &[char]| {
+            let mut j = from;
+            while j < chars.len() && !chars[j..].starts_with(end) {
+                j += 1;
+            }
+            let stop = (j + end.len()).min(chars.len());
+            out.extend(&chars[i..stop]);
+            stop
+        };
+        let next = chars.get(i + 1).copied();
+        i = match c {
+            '\'' | '"' | '`' => skip_to(&mut out, i + 1, &[c]),
+            '[' => skip_to(&mut out, i + 1, &[']']),
+            '-' if next == Some('-') => skip_to(&mut out, i + 2, &['\n']),
+            '/' if next == Some('*') => skip_to(&mut out, i + 2, &['*', '/']),
+            '?' | ':' | '@' | '$' => {
+                let follows_word = i > 0 && is_word_char(chars[i - 1]);
+                let mut end = i + 1;
+                while end < chars.len() && is_word_char(chars[end]) {
+                    end += 1;
+                }
+                let token: String = chars[i + 1..end].iter().collect();
+                let index = if c == '?' {
+                    match token.parse::<usize>() {
+                        Ok(number) if !token.is_empty() => Some(number),
+                        _ if token.is_empty() => Some(highest + 1),
+                        _ => None,
+                    }
+                } else if follows_word || token.is_empty() {
+                    None
+                } else if let Some((_, index)) = names.iter().find(|(name, _)| *name == token) {
+                    Some(*index)
+                } else {
+                    names.push((token, highest + 1));
+                    Some(highest + 1)
+                };
+                match index {
+                    Some(index) => {
+                        highest = highest.max(index);
+                        match index.checked_sub(1).and_then(|at| params.get(at)) {
+                            Some(value) => push_literal(&mut out, value),
+                            None => out.extend(&chars[i..end]),
+                        }
+                        end
+                    }
+                    None => {
+                        out.push(c);
+                        i + 1
+                    }
+                }
+            }
+            _ => {
+                out.push(c);
+                i + 1
+            }
+        };
+    }
+    out
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Write a value as a SQL literal of the same type
+fn push_literal(out: &mut String, value: &libsql::Value) {
+    let hex = |out: &mut String, bytes: &[u8]| {
+        out.push_str("X'");
+        for byte in bytes {
+            out.push_str(&format!("{byte:02X}"));
+        }
+        out.push('\'');
+    };
+    match value {
+        libsql::Value::Null => out.push_str("NULL"),
+        libsql::Value::Integer(i) => out.push_str(&i.to_string()),
+        // Debug keeps the decimal point, so the value stays a REAL
+        libsql::Value::Real(r) if r.is_finite() => out.push_str(&format!("{r:?}")),
+        // SQLite stores NaN as NULL and reads 9e999 as infinity
+        libsql::Value::Real(r) if r.is_nan() => out.push_str("NULL"),
+        libsql::Value::Real(r) if *r > 0.0 => out.push_str("9e999"),
+        libsql::Value::Real(_) => out.push_str("-9e999"),
+        // A NUL would end a string literal early
+        libsql::Value::Text(t) if t.contains('\0') => {
+            out.push_str("CAST(");
+            hex(out, t.as_bytes());
+            out.push_str(" AS TEXT)");
+        }
+        libsql::Value::Text(t) => {
+            out.push('\'');
+            out.push_str(&t.replace('\'', "''"));
+            out.push('\'');
+        }
+        libsql::Value::Blob(b) => hex(out, b),
+    }
+}
+
 /// SQL query builder for complex queries
 ///
 /// Provides a fluent interface for building SQL queries with support for:
@@ -106,6 +282,7 @@ impl<T> QueryResult<T> {
 ///     .group_by(vec!["user_id"])
 ///     .having(FilterOperator::Gt("total_amount".to_string(), Value::Real(1000.0)));
 /// ```
+#[derive(Debug)]
 pub struct QueryBuilder {
     table: String,
     select_columns: Vec<String>,
@@ -118,9 +295,18 @@ pub struct QueryBuilder {
     offset: Option<u32>,
     distinct: bool,
     aggregate: Option<AggregateClause>,
+    tag: Option<String>,
+    hint: Option<QueryHint>,
+    timeout: Option<Duration>,
+    ctes: Vec<CteClause>,
+    compounds: Vec<CompoundClause>,
+    windows: Vec<WindowFunction>,
+    json_columns: Vec<JsonColumn>,
+    subquery_columns: Vec<(QueryBuilder, String)>,
 }
 
 /// Join clause for complex queries
+#[derive(Debug)]
 struct JoinClause {
     join_type: crate::JoinType,
     table: String,
@@ -128,7 +314,24 @@ struct JoinClause {
     condition: String,
 }
 
+/// Named common table expression
+#[derive(Debug, Clone)]
+struct CteClause {
+    name: String,
+    query: QueryBuilder,
+    /// Recursive member, combined with `query` by UNION
+    recursive: Option<QueryBuilder>,
+}
+
+/// Query combined with the main query by a set operator
+#[derive(Debug, Clone)]
+struct CompoundClause {
+    operator: &'static str,
+    query: QueryBuilder,
+}
+
 /// Aggregate clause for aggregation queries
+#[derive(Debug)]
 struct AggregateClause {
     function: Aggregate,
     column: String,
@@ -150,6 +353,14 @@ impl QueryBuilder {
             offset: None,
             distinct: false,
             aggregate: None,
+            tag: None,
+            hint: None,
+            timeout: None,
+            ctes: Vec::new(),
+            compounds: Vec::new(),
+            windows: Vec::new(),
+            json_columns: Vec::new(),
+            subquery_columns: Vec::new(),
         }
     }
 
@@ -255,6 +466,205 @@ impl QueryBuilder {
         self
     }
 
+    /// Select a window function as an extra column
+    ///
+    /// See [`WindowFunction`] for ranks, row numbers and running totals.
+    pub fn window(mut self, window: WindowFunction) -> Self {
+        self.windows.push(window);
+        self
+    }
+
+    /// Select the single value of a subquery as an extra column
+    ///
+    /// Correlate the subquery with [`Filter::eq_column`], e.g. a comment
+    /// count per post:
+    ///
+    /// ```rust
+    /// use libsql_orm::{Aggregate, Filter, FilterOperator, QueryBuilder};
+    ///
+    /// let comments = QueryBuilder::new("comments")
+    ///     .aggregate(Aggregate::Count, "*", None::<String>)
+    ///     .r#where(FilterOperator::Single(Filter::eq_column("comments.post_id", "posts.id")));
+    /// let (sql, _) = QueryBuilder::new("posts")
+    ///     .select(vec!["posts.id", "posts.title"])
+    ///     .select_subquery(comments, "comment_count")
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     sql,
+    ///     "SELECT posts.id, posts.title, (SELECT COUNT(*) FROM comments \
+    ///      WHERE comments.post_id = posts.id) AS comment_count FROM posts"
+    /// );
+    /// ```
+    pub fn select_subquery(mut self, query: QueryBuilder, alias: impl Into<String>) -> Self {
+        self.subquery_columns.push((query, alias.into()));
+        self
+    }
+
+    /// Select related rows as an extra JSON column
+    ///
+    /// See [`JsonColumn`]; [`execute`](Self::execute) deserializes the column
+    /// into nested structures.
+    pub fn json_column(mut self, column: JsonColumn) -> Self {
+        self.json_columns.push(column);
+        self
+    }
+
+    /// Combine with another query by UNION, removing duplicate rows
+    ///
+    /// Both queries must select the same columns, e.g. posts authored by a
+    /// user together with posts mentioning them:
+    ///
+    /// ```rust
+    /// use libsql_orm::{Filter, FilterOperator, QueryBuilder, Sort};
+    ///
+    /// let mentioned = QueryBuilder::new("posts")
+    ///     .r#where(FilterOperator::Single(Filter::like("body", "%@ada%")));
+    /// let (sql, _) = QueryBuilder::new("posts")
+    ///     .r#where(FilterOperator::Single(Filter::eq("author_id", 1i64)))
+    ///     .union(mentioned)
+    ///     .order_by(Sort::desc("id"))
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     sql,
+    ///     "SELECT * FROM posts WHERE author_id = ? UNION SELECT * FROM posts WHERE body LIKE ? ORDER BY id DESC"
+    /// );
+    /// ```
+    ///
+    /// `order_by`, `limit` and `offset` on the combining query apply to the
+    /// combined result and may only name result columns. A combined query
+    /// with its own ordering or limit is wrapped in a subquery.
+    pub fn union(self, query: QueryBuilder) -> Self {
+        self.compound("UNION", query)
+    }
+
+    /// Combine with another query by UNION ALL, keeping duplicate rows
+    pub fn union_all(self, query: QueryBuilder) -> Self {
+        self.compound("UNION ALL", query)
+    }
+
+    /// Keep only the rows also returned by another query
+    pub fn intersect(self, query: QueryBuilder) -> Self {
+        self.compound("INTERSECT", query)
+    }
+
+    /// Remove the rows returned by another query
+    pub fn except(self, query: QueryBuilder) -> Self {
+        self.compound("EXCEPT", query)
+    }
+
+    fn compound(mut self, operator: &'static str, query: QueryBuilder) -> Self {
+        self.compounds.push(CompoundClause { operator, query });
+        self
+    }
+
+    /// Attach a named common table expression
+    ///
+    /// The query is prefixed with `WITH name AS (...)`, so the CTE can be used
+    /// as the main table, in joins or in subqueries:
+    ///
+    /// ```rust
+    /// use libsql_orm::{Filter, FilterOperator, QueryBuilder};
+    ///
+    /// let recent = QueryBuilder::new("orders")
+    ///     .r#where(FilterOperator::Single(Filter::gt("created_at", "2024-01-01")));
+    /// let (sql, _) = QueryBuilder::new("recent")
+    ///     .with_cte("recent", recent)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     sql,
+    ///     "WITH recent AS (SELECT * FROM orders WHERE created_at > ?) SELECT * FROM recent"
+    /// );
+    /// ```
+    pub fn with_cte(mut self, name: impl Into<String>, query: QueryBuilder) -> Self {
+        self.ctes.push(CteClause {
+            name: name.into(),
+            query,
+            recursive: None,
+        });
+        self
+    }
+
+    /// Attach a recursive common table expression
+    ///
+    /// The CTE starts from the rows of `base` and repeatedly adds the rows of
+    /// `step`, which refers to the CTE by `name`, until no new rows appear.
+    /// `name` may list the CTE's columns, e.g. `tree(node)`. Rows are combined
+    /// with UNION, so cycles in the data terminate.
+    ///
+    /// ```rust
+    /// use libsql_orm::{Filter, FilterOperator, JoinType, QueryBuilder};
+    ///
+    /// let base = QueryBuilder::new("categories")
+    ///     .select(vec!["id"])
+    ///     .r#where(FilterOperator::Single(Filter::eq("parent_id", 1i64)));
+    /// let step = QueryBuilder::new("categories")
+    ///     .select(vec!["categories.id"])
+    ///     .join(JoinType::Inner, "tree", "categories.parent_id = tree.node");
+    /// let (sql, _) = QueryBuilder::new("tree")
+    ///     .with_recursive_cte("tree(node)", base, step)
+    ///     .build()
+    ///     .unwrap();
+    /// assert!(sql.starts_with("WITH RECURSIVE tree(node) AS (SELECT id FROM categories"));
+    /// ```
+    pub fn with_recursive_cte(
+        mut self,
+        name: impl Into<String>,
+        base: QueryBuilder,
+        step: QueryBuilder,
+    ) -> Self {
+        self.ctes.push(CteClause {
+            name: name.into(),
+            query: base,
+            recursive: Some(step),
+        });
+        self
+    }
+
+    /// Tag the query with a trailing SQL comment
+    ///
+    /// Tags show up in server-side query analytics, attributing load to the
+    /// call site, e.g. `.tag("endpoint:/posts GET")`.
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+
+    /// Run the query with a priority hint
+    ///
+    /// Overrides the [hint](Database::hint) of the handle the query runs on,
+    /// e.g. `.hint(QueryHint::Background)` for a bulk export on the request
+    /// path's handle.
+    pub fn hint(mut self, hint: QueryHint) -> Self {
+        self.hint = Some(hint);
+        self
+    }
+
+    /// Fail the query with [`Error::Timeout`] when a
+    /// statement takes longer than `timeout`
+    ///
+    /// Overrides the [timeout](Database::with_timeout) of the handle the
+    /// query runs on.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// A handle carrying the query's hint and timeout, when they differ from
+    /// `db`'s
+    fn hinted(&self, db: &Database) -> Option<Database> {
+        let hinted = self
+            .hint
+            .filter(|hint| *hint != db.hint())
+            .map(|hint| db.with_hint(hint));
+        match self.timeout {
+            Some(timeout) => Some(hinted.as_ref().unwrap_or(db).with_timeout(timeout)),
+            None => hinted,
+        }
+    }
+
     /// Select all columns
     pub fn select_all(mut self) -> Self {
         self.select_columns = vec!["*".to_string()];
@@ -348,15 +758,18 @@ impl QueryBuilder {
 
     /// Add where in clause
     pub fn where_in(mut self, field: &str, subquery: QueryBuilder) -> Self {
-        let (subquery_sql, _) = subquery.build().unwrap_or_default();
-        let condition = format!("{field} IN ({subquery_sql})");
-        self.where_clauses.push(FilterOperator::Custom(condition));
+        self.where_clauses
+            .push(FilterOperator::Single(crate::Filter::in_subquery(
+                field, subquery,
+            )));
         self
     }
 
     /// Execute count query
     pub async fn execute_count(&self, db: &Database) -> Result<u64> {
         let (sql, params) = self.build_count()?;
+        let hinted = self.hinted(db);
+        let db = hinted.as_ref().unwrap_or(db);
         let mut rows = db.query(&sql, params).await?;
 
         if let Some(row) = rows.next().await? {
@@ -375,6 +788,8 @@ impl QueryBuilder {
     /// Execute aggregate query
     pub async fn execute_aggregate(&self, db: &Database) -> Result<Vec<libsql::Row>> {
         let (sql, params) = self.build()?;
+        let hinted = self.hinted(db);
+        let db = hinted.as_ref().unwrap_or(db);
         let mut rows = db.query(&sql, params).await?;
         let mut results = Vec::new();
         while let Some(row) = rows.next().await? {
@@ -383,10 +798,19 @@ impl QueryBuilder {
         Ok(results)
     }
 
+    /// Build the query without running it, for inspection or logging
+    pub fn to_sql(&self) -> Result<Statement> {
+        self.build().map(Statement::from)
+    }
+
+    /// Run `EXPLAIN QUERY PLAN` for the query without executing it
+    pub async fn explain(&self, db: &Database) -> Result<QueryPlan> {
+        self.to_sql()?.explain(db).await
+    }
+
     /// Build the SQL query
     pub fn build(&self) -> Result<(String, Vec<libsql::Value>)> {
-        let mut sql = String::new();
-        let mut params = Vec::new();
+        let (mut sql, mut params) = self.build_ctes()?;
 
         // SELECT clause
         sql.push_str("SELECT ");
@@ -395,6 +819,10 @@ impl QueryBuilder {
         }
 
         if let Some(agg) = &self.aggregate {
+            // Grouped aggregates also select the group keys
+            for column in &self.group_by {
+                sql.push_str(&format!("{column}, "));
+            }
             sql.push_str(&format!("{}({})", agg.function, agg.column));
             if let Some(alias) = &agg.alias {
                 sql.push_str(&format!(" AS {alias}"));
@@ -402,6 +830,17 @@ impl QueryBuilder {
         } else {
             sql.push_str(&self.select_columns.join(", "));
         }
+        for window in &self.windows {
+            sql.push_str(&format!(", {}", window.to_sql()));
+        }
+        for column in &self.json_columns {
+            sql.push_str(&format!(", {}", column.to_sql()));
+        }
+        for (query, alias) in &self.subquery_columns {
+            let (subquery_sql, subquery_params) = query.build()?;
+            sql.push_str(&format!(", ({subquery_sql}) AS {alias}"));
+            params.extend(subquery_params);
+        }
 
         // FROM clause
         sql.push_str(&format!(" FROM {}", self.table));
@@ -436,14 +875,18 @@ impl QueryBuilder {
             params.extend(having_params);
         }
 
+        // Compound queries
+        for compound in &self.compounds {
+            let (compound_sql, compound_params) = compound.query.build_compound_member()?;
+            sql.push_str(&format!(" {} {compound_sql}", compound.operator));
+            params.extend(compound_params);
+        }
+
         // ORDER BY clause
         if !self.order_by.is_empty() {
             sql.push_str(" ORDER BY ");
-            let order_clauses: Vec<String> = self
-                .order_by
-                .iter()
-                .map(|sort| format!("{} {}", sort.column, sort.order))
-                .collect();
+            let order_clauses: Vec<String> =
+                self.order_by.iter().map(|sort| sort.to_string()).collect();
             sql.push_str(&order_clauses.join(", "));
         }
 
@@ -455,13 +898,78 @@ impl QueryBuilder {
             sql.push_str(&format!(" OFFSET {offset}"));
         }
 
+        if let Some(tag) = &self.tag {
+            crate::database::append_comment(&mut sql, tag);
+        }
+
         Ok((sql, params))
     }
 
+    /// Build the `WITH` prefix for the attached CTEs, or nothing if there are none
+    fn build_ctes(&self) -> Result<(String, Vec<libsql::Value>)> {
+        if self.ctes.is_empty() {
+            return Ok((String::new(), Vec::new()));
+        }
+        let mut clauses = Vec::new();
+        let mut params = Vec::new();
+        for cte in &self.ctes {
+            let (mut cte_sql, cte_params) = cte.query.build()?;
+            params.extend(cte_params);
+            if let Some(step) = &cte.recursive {
+                let (step_sql, step_params) = step.build()?;
+                cte_sql = format!("{cte_sql} UNION {step_sql}");
+                params.extend(step_params);
+            }
+            clauses.push(format!("{} AS ({cte_sql})", cte.name));
+        }
+        let keyword = if self.ctes.iter().any(|cte| cte.recursive.is_some()) {
+            "WITH RECURSIVE"
+        } else {
+            "WITH"
+        };
+        Ok((format!("{keyword} {} ", clauses.join(", ")), params))
+    }
+
+    /// Build the SQL of a query combined into another by a set operator
+    ///
+    /// Parts that SQLite does not allow in compound members are kept by
+    /// wrapping the query in a subquery.
+    fn build_compound_member(&self) -> Result<(String, Vec<libsql::Value>)> {
+        let (sql, params) = self.build()?;
+        let standalone = !self.ctes.is_empty()
+            || !self.compounds.is_empty()
+            || !self.order_by.is_empty()
+            || self.limit.is_some()
+            || self.offset.is_some();
+        if standalone {
+            Ok((format!("SELECT * FROM ({sql})"), params))
+        } else {
+            Ok((sql, params))
+        }
+    }
+
     /// Build a count query
+    ///
+    /// Grouped, distinct and compound queries count their result rows, so a
+    /// grouped query counts its groups.
     pub fn build_count(&self) -> Result<(String, Vec<libsql::Value>)> {
-        let mut sql = String::new();
-        let mut params = Vec::new();
+        let (mut sql, mut params) = self.build_ctes()?;
+
+        if !self.group_by.is_empty() || self.distinct || !self.compounds.is_empty() {
+            let mut counted = self.clone();
+            counted.ctes.clear();
+            counted.order_by.clear();
+            counted.limit = None;
+            counted.offset = None;
+            counted.tag = None;
+            let (counted_sql, counted_params) = counted.build()?;
+            sql.push_str(&format!("SELECT COUNT(*) FROM ({counted_sql})"));
+            params.extend(counted_params);
+            if let Some(tag) = &self.tag {
+                crate::database::append_comment(&mut sql, tag);
+            }
+            return Ok((sql, params));
+        }
 
         sql.push_str("SELECT COUNT(*)");
 
@@ -498,9 +1006,25 @@ impl QueryBuilder {
             params.extend(having_params);
         }
 
+        if let Some(tag) = &self.tag {
+            crate::database::append_comment(&mut sql, tag);
+        }
+
         Ok((sql, params))
     }
 
+    /// Build just the WHERE clause, including the leading ` WHERE `
+    ///
+    /// Returns an empty string when the builder has no conditions. Used to
+    /// target `UPDATE` and `DELETE` statements with the same filters as a query.
+    pub fn build_where(&self) -> Result<(String, Vec<libsql::Value>)> {
+        if self.where_clauses.is_empty() {
+            return Ok((String::new(), Vec::new()));
+        }
+        let (where_sql, params) = self.build_where_clause(&self.where_clauses)?;
+        Ok((format!(" WHERE {where_sql}"), params))
+    }
+
     /// Build where clause from filter operators
     fn build_where_clause(
         &self,
@@ -528,6 +1052,8 @@ impl QueryBuilder {
     ) -> Result<(String, Vec<libsql::Value>)> {
         match filter {
             FilterOperator::Single(filter) => self.build_filter(filter),
+            // An empty AND is always true and an empty OR never matches
+            FilterOperator::And(filters) if filters.is_empty() => Ok(("1 = 1".to_string(), vec![])),
             FilterOperator::And(filters) => {
                 let mut sql = String::new();
                 let mut params = Vec::new();
@@ -543,6 +1069,7 @@ impl QueryBuilder {
                 sql.push(')');
                 Ok((sql, params))
             }
+            FilterOperator::Or(filters) if filters.is_empty() => Ok(("1 = 0".to_string(), vec![])),
             FilterOperator::Or(filters) => {
                 let mut sql = String::new();
                 let mut params = Vec::new();
@@ -571,6 +1098,42 @@ impl QueryBuilder {
         let mut sql = String::new();
         let mut params = Vec::new();
 
+        if let FilterValue::Raw(values) = &filter.value {
+            let placeholders = count_placeholders(&filter.column);
+            if placeholders != values.len() {
+                return Err(crate::Error::Query(format!(
+                    "Raw filter '{}' has {placeholders} placeholders but {} parameters",
+                    filter.column,
+                    values.len()
+                )));
+            }
+            let params = values
+                .iter()
+                .map(|v| self.value_to_libsql_value(v))
+                .collect();
+            return Ok((format!("({})", filter.column), params));
+        }
+
+        // Empty lists are not valid SQL in every engine
+        if let FilterValue::Multiple(values) = &filter.value {
+            if values.is_empty() {
+                match filter.operator {
+                    Operator::In => return Ok(("1 = 0".to_string(), params)),
+                    Operator::NotIn => return Ok(("1 = 1".to_string(), params)),
+                    _ => {}
+                }
+            }
+        }
+
+        // `= NULL` never matches, so comparisons with NULL test for it instead
+        if let FilterValue::Single(Value::Null) = &filter.value {
+            match filter.operator {
+                Operator::Eq => return Ok((format!("{} IS NULL", filter.column), params)),
+                Operator::Ne => return Ok((format!("{} IS NOT NULL", filter.column), params)),
+                _ => {}
+            }
+        }
+
         match &filter.operator {
             Operator::IsNull => {
                 sql.push_str(&format!("{} IS NULL", filter.column));
@@ -584,6 +1147,12 @@ impl QueryBuilder {
                     FilterValue::Single(value) => {
                         sql.push('?');
                         params.push(self.value_to_libsql_value(value));
+                        if matches!(
+                            filter.operator,
+                            Operator::LikeEscaped | Operator::NotLikeEscaped
+                        ) {
+                            sql.push_str(" ESCAPE '\\'");
+                        }
                     }
                     FilterValue::Multiple(values) => {
                         sql.push('(');
@@ -601,6 +1170,13 @@ impl QueryBuilder {
                         params.push(self.value_to_libsql_value(min));
                         params.push(self.value_to_libsql_value(max));
                     }
+                    FilterValue::Subquery(query) => {
+                        let (subquery_sql, subquery_params) = query.build()?;
+                        sql.push_str(&format!("({subquery_sql})"));
+                        params.extend(subquery_params);
+                    }
+                    FilterValue::Column(other) => sql.push_str(other),
+                    FilterValue::Raw(_) => unreachable!("raw filters are built above"),
                 }
             }
         }
@@ -626,6 +1202,8 @@ impl QueryBuilder {
         T: serde::de::DeserializeOwned,
     {
         let (sql, params) = self.build()?;
+        let hinted = self.hinted(db);
+        let db = hinted.as_ref().unwrap_or(db);
         let mut rows = db.query(&sql, params).await?;
 
         let mut results = Vec::new();
@@ -634,10 +1212,15 @@ impl QueryBuilder {
             for i in 0..row.column_count() {
                 if let Some(column_name) = row.column_name(i) {
                     let value = row.get_value(i).unwrap_or(libsql::Value::Null);
-                    map.insert(
-                        column_name.to_string(),
-                        self.libsql_value_to_json_value(&value),
-                    );
+                    let value = match value {
+                        libsql::Value::Text(text)
+                            if self.json_columns.iter().any(|c| c.name() == column_name) =>
+                        {
+                            serde_json::from_str(&text)?
+                        }
+                        value => self.libsql_value_to_json_value(&value),
+                    };
+                    map.insert(column_name.to_string(), value);
                 }
             }
             let json_value = serde_json::to_value(map)?;
@@ -657,10 +1240,181 @@ impl QueryBuilder {
     where
         T: serde::de::DeserializeOwned,
     {
-        // Get total count
-        let count_builder = QueryBuilder::new(&self.table).select(vec!["COUNT(*) as count"]);
+        let total = self.count_for_page(db, pagination).await?;
+
+        // Get paginated data
+        let data_builder = self
+            .clone()
+            .limit(pagination.fetch_limit())
+            .offset(pagination.offset());
+
+        let data = data_builder.execute::<T>(db).await?;
+
+        Ok(Self::paginated(data, pagination, total))
+    }
+
+    /// Execute the query and convert each row into a model
+    ///
+    /// Unlike [`execute`](Self::execute), rows go through the model's
+    /// `from_map`, so boolean and datetime coercion are applied.
+    ///
+    /// Model columns the query does not select are filled with their
+    /// [default value](crate::ColumnMeta::default_value), so a column subset
+    /// can be read into the full model. Columns without a default value fail
+    /// with [`Error::Query`] and must be selected.
+    ///
+    /// The defaults are not what is stored: saving such a model with
+    /// [`update`](crate::Model::update) writes them over the real values.
+    /// Save it with [`update_fields`](crate::Model::update_fields) naming the
+    /// selected columns instead.
+    pub async fn execute_models<T: Model>(&self, db: &Database) -> Result<Vec<T>> {
+        let (sql, params) = self.build()?;
+        let hinted = self.hinted(db);
+        let db = hinted.as_ref().unwrap_or(db);
+        db.query_map(&sql, params, row_to_model).await
+    }
+
+    /// Execute the query and yield each row as a model as it is read
+    ///
+    /// Unlike [`execute_models`](Self::execute_models), rows are converted one
+    /// at a time while the stream is polled, so the models are never all held
+    /// in memory together. Local databases also read the rows lazily; remote
+    /// connections may still receive the statement's result in one response.
+    pub fn stream_models<T: Model>(self, db: &Database) -> impl Stream<Item = Result<T>> + '_ {
+        let rows = async move {
+            let (sql, params) = self.build()?;
+            let hinted = self.hinted(db);
+            let rows = hinted.as_ref().unwrap_or(db).query(&sql, params).await?;
+            Ok::<_, Error>(stream::try_unfold(rows, |mut rows| async move {
+                match rows.next().await? {
+                    Some(row) => Ok::<_, Error>(Some((row_to_model(&row)?, rows))),
+                    None => Ok(None),
+                }
+            }))
+        };
+        stream::once(rows).try_flatten()
+    }
+
+    /// Execute the query and convert each row into a projection
+    pub async fn execute_projections<P: Projection>(&self, db: &Database) -> Result<Vec<P>> {
+        let (sql, params) = self.build()?;
+        let hinted = self.hinted(db);
+        let db = hinted.as_ref().unwrap_or(db);
+        let mut rows = db.query(&sql, params).await?;
+
+        let mut results = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let mut map = HashMap::new();
+            for i in 0..row.column_count() {
+                if let Some(column_name) = row.column_name(i) {
+                    let value = row.get_value(i).unwrap_or(libsql::Value::Null);
+                    map.insert(column_name.to_string(), Value::from(value));
+                }
+            }
+            results.push(P::from_map(map)?);
+        }
+        Ok(results)
+    }
+
+    /// Execute the query with pagination, converting each row into a model
+    pub async fn execute_models_paginated<T: Model>(
+        &self,
+        db: &Database,
+        pagination: &Pagination,
+    ) -> Result<PaginatedResult<T>> {
+        let total = self.count_for_page(db, pagination).await?;
+
+        let data = self
+            .clone()
+            .limit(pagination.fetch_limit())
+            .offset(pagination.offset())
+            .execute_models::<T>(db)
+            .await?;
+
+        Ok(Self::paginated(data, pagination, total))
+    }
+
+    /// Execute the query as one page of keyset pagination, converting each
+    /// row into a model
+    ///
+    /// The page is ordered by the pagination's `order_by` column and then the
+    /// primary key, replacing any ordering of the query, and starts after the
+    /// row the cursor points at. `next_cursor` points at the page's last row
+    /// when more rows follow.
+    pub async fn execute_models_cursor<T: Model>(
+        &self,
+        db: &Database,
+        pagination: &CursorPagination,
+    ) -> Result<CursorPaginatedResult<T>> {
+        let pk = T::primary_key();
+        let sort = pagination.order_by.clone().unwrap_or_else(|| Sort::asc(pk));
+        let descending = matches!(sort.order, SortOrder::Desc);
+
+        let mut builder = self.clone();
+        builder.order_by = vec![sort.clone()];
+        if sort.column != pk {
+            builder.order_by.push(Sort {
+                column: pk.to_string(),
+                order: sort.order,
+                nulls: None,
+            });
+        }
+        if let Some(cursor) = &pagination.cursor {
+            let (value, id): (serde_json::Value, i64) = serde_json::from_str(cursor)
+                .map_err(|_| crate::Error::Validation(format!("Invalid cursor: {cursor}")))?;
+            let value = Value::from(value);
+            let op = match (descending, pagination.include_cursor) {
+                (false, false) => ">",
+                (false, true) => ">=",
+                (true, false) => "<",
+                (true, true) => "<=",
+            };
+            let filter = if sort.column == pk {
+                Filter::raw(format!("{pk} {op} ?"), vec![Value::Integer(id)])
+            } else {
+                Filter::raw(
+                    format!("({}, {pk}) {op} (?, ?)", sort.column),
+                    vec![value, Value::Integer(id)],
+                )
+            };
+            builder = builder.r#where(FilterOperator::Single(filter));
+        }
+
+        // One extra row tells whether another page follows
+        let mut data = builder
+            .limit(pagination.limit() + 1)
+            .execute_models::<T>(db)
+            .await?;
+        let has_next = data.len() > pagination.limit() as usize;
+        data.truncate(pagination.limit() as usize);
+
+        let mut page = pagination.clone();
+        page.has_next = has_next;
+        page.has_prev = pagination.cursor.is_some();
+        page.next_cursor = match data.last() {
+            Some(last) if has_next => {
+                let value = last.to_map()?.remove(&sort.column).unwrap_or(Value::Null);
+                let id = last.get_primary_key().unwrap_or_default();
+                Some(serde_json::to_string(&(
+                    serde_json::Value::from(value),
+                    id,
+                ))?)
+            }
+            _ => None,
+        };
+        Ok(CursorPaginatedResult::new(data, page))
+    }
+
+    /// Count the rows the query matches, ignoring LIMIT and OFFSET
+    async fn count_total(&self, db: &Database) -> Result<u64> {
+        let mut count_builder = self.clone();
+        count_builder.limit = None;
+        count_builder.offset = None;
+        count_builder.order_by.clear();
 
         let (count_sql, count_params) = count_builder.build_count()?;
+        let hinted = self.hinted(db);
+        let db = hinted.as_ref().unwrap_or(db);
         let mut count_rows = db.query(&count_sql, count_params).await?;
         let total: u64 = if let Some(row) = count_rows.next().await? {
             row.get_value(0)
@@ -673,16 +1427,45 @@ impl QueryBuilder {
         } else {
             0
         };
+        Ok(total)
+    }
 
-        // Get paginated data
-        let data_builder = self
-            .clone()
-            .limit(pagination.limit())
-            .offset(pagination.offset());
-
-        let data = data_builder.execute::<T>(db).await?;
+    /// Count the rows for a page, honoring the pagination's count time budget
+    pub(crate) async fn count_for_page(
+        &self,
+        db: &Database,
+        pagination: &Pagination,
+    ) -> Result<Option<u64>> {
+        if !pagination.counts_total() {
+            return Ok(None);
+        }
+        match pagination.count_timeout() {
+            Some(budget) => crate::runtime::timeout(budget, self.count_total(db))
+                .await
+                .transpose(),
+            None => self.count_total(db).await.map(Some),
+        }
+    }
 
-        Ok(PaginatedResult::with_total(data, pagination.clone(), total))
+    /// Assemble a paginated result, leaving the total unset when it is unknown
+    ///
+    /// Without a total, the extra row read by `fetch_limit` is dropped and
+    /// only sets `has_more`.
+    pub(crate) fn paginated<T>(
+        data: Vec<T>,
+        pagination: &Pagination,
+        total: Option<u64>,
+    ) -> PaginatedResult<T> {
+        let mut data = data;
+        let mut pagination = pagination.clone();
+        if !pagination.counts_total() {
+            pagination.has_more = Some(data.len() > pagination.limit() as usize);
+            data.truncate(pagination.limit() as usize);
+        }
+        match total {
+            Some(total) => PaginatedResult::with_total(data, pagination, total),
+            None => PaginatedResult::new(data, pagination),
+        }
     }
 
     /// Convert libsql::Value to serde_json::Value
@@ -721,6 +1504,14 @@ impl Clone for QueryBuilder {
             offset: self.offset,
             distinct: self.distinct,
             aggregate: self.aggregate.clone(),
+            tag: self.tag.clone(),
+            hint: self.hint,
+            timeout: self.timeout,
+            ctes: self.ctes.clone(),
+            compounds: self.compounds.clone(),
+            windows: self.windows.clone(),
+            json_columns: self.json_columns.clone(),
+            subquery_columns: self.subquery_columns.clone(),
         }
     }
 }
@@ -745,3 +1536,37 @@ impl Clone for AggregateClause {
         }
     }
 }
+
+/// Convert a row into a model, filling unselected columns with defaults
+fn row_to_model<T: Model>(row: &libsql::Row) -> Result<T> {
+    let mut map = T::row_to_map(row)?;
+    for column in T::column_meta() {
+        if !map.contains_key(column.name) {
+            let value = column.default_value().ok_or_else(|| {
+                Error::Query(format!(
+                    "Column '{}' of {} must be selected: it has no default value",
+                    column.name,
+                    T::table_name()
+                ))
+            })?;
+            map.insert(column.name.to_string(), value);
+        }
+    }
+    T::from_map(map)
+}
+
+/// Count the `?` placeholders of a SQL fragment, skipping quoted text
+fn count_placeholders(sql: &str) -> usize {
+    let mut quote = None;
+    let mut count = 0;
+    for c in sql.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c == '?' => count += 1,
+            None => {}
+        }
+    }
+    count
+}
@@ -0,0 +1,160 @@
+//! Global model registry for runtime reflection
+//!
+//! Every struct deriving `Model` registers a [`ModelMeta`] describing its table
+//! name, columns and schema. The registry can be queried at runtime through
+//! [`registry()`], which makes it possible to migrate, inspect or export every
+//! model in an application without listing them manually.
+//!
+//! Generic models are not registered, since their metadata depends on type
+//! parameters that are only known at the use site.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use libsql_orm::registry;
+//!
+//! for model in registry() {
+//!     println!("{} ({} columns)", model.table_name, model.columns.len());
+//!     for column in model.columns {
+//!         println!("  {} {}", column.name, column.sql_type);
+//!     }
+//! }
+//! ```
+
+use crate::{Migration, MigrationManager, Value};
+
+/// Rust-side classification of a column's field type
+///
+/// Used to drive value conversion when reading rows back into models.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    /// `bool` fields, stored as 0/1 integers
+    Boolean,
+    /// Signed and unsigned integer fields
+    Integer,
+    /// `f32` / `f64` fields
+    Real,
+    /// `String` fields
+    Text,
+    /// chrono date and datetime fields
+    DateTime,
+    /// `Vec<u8>` fields
+    Blob,
+    /// Any other type, stored through its serde representation
+    Other,
+}
+
+/// Metadata describing a single model column
+#[derive(Debug, Clone, Copy)]
+pub struct ColumnMeta {
+    /// Column name
+    pub name: &'static str,
+    /// Declared SQL type (e.g. `INTEGER`, `TEXT`)
+    pub sql_type: &'static str,
+    /// Full column definition as used in the generated `CREATE TABLE`
+    pub definition: &'static str,
+    /// Kind of the Rust field type
+    pub kind: ColumnKind,
+    /// Whether the Rust field is an `Option<T>`
+    pub nullable: bool,
+    /// Whether the column is the primary key
+    pub primary_key: bool,
+    /// Whether the column has a UNIQUE constraint
+    pub unique: bool,
+    /// Whether the column has a NOT NULL constraint
+    pub not_null: bool,
+    /// Whether the column is excluded from default SELECTs
+    pub lazy: bool,
+    /// Whether concurrent updates are merged last-write-wins
+    pub lww: bool,
+    /// Whether the column references the parent record of a tree
+    pub parent: bool,
+    /// Whether values are compressed when written
+    pub compressed: bool,
+    /// Table this column references as a foreign key
+    pub references: Option<&'static str>,
+    /// Column that receives a copy of every write, during a migration window
+    pub mirror_to: Option<&'static str>,
+    /// Columns hashed into this column, empty unless it is a content hash
+    pub hash_of: &'static [&'static str],
+    /// Whether inserts leave the column to its SQL `DEFAULT`
+    pub db_default: bool,
+}
+
+impl ColumnMeta {
+    /// Value used for the column when a query does not select it
+    ///
+    /// `Option` fields read as `None`; other fields get their type's zero
+    /// value, with the Unix epoch for dates. Fields of
+    /// [`ColumnKind::Other`] have no zero value known to the ORM and return
+    /// `None`, so they must be selected.
+    pub fn default_value(&self) -> Option<Value> {
+        if self.nullable {
+            return Some(Value::Null);
+        }
+        Some(match self.kind {
+            ColumnKind::Boolean => Value::Boolean(false),
+            ColumnKind::Integer => Value::Integer(0),
+            ColumnKind::Real => Value::Real(0.0),
+            ColumnKind::Text => Value::Text(String::new()),
+            ColumnKind::DateTime => Value::Text("1970-01-01T00:00:00Z".to_string()),
+            ColumnKind::Blob => Value::Blob(Vec::new()),
+            ColumnKind::Other => return None,
+        })
+    }
+}
+
+/// Retention policy declared with `#[orm(retention(...))]`
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionPolicy {
+    /// Rows older than this many days expire
+    pub days: u32,
+    /// Timestamp column the age is measured by
+    pub column: &'static str,
+    /// Move expired rows to `<table>_archive` instead of deleting them
+    pub archive: bool,
+}
+
+/// Metadata describing a registered model
+#[derive(Debug)]
+pub struct ModelMeta {
+    /// Table name of the model
+    pub table_name: &'static str,
+    /// Columns of the model, in field declaration order
+    pub columns: &'static [ColumnMeta],
+    /// Generates the `CREATE TABLE` statement for the model
+    pub migration_sql: fn() -> String,
+    /// Retention policy enforced by [`retention::enforce_all`](crate::retention::enforce_all)
+    pub retention: Option<RetentionPolicy>,
+}
+
+impl ModelMeta {
+    /// Look up a column by name
+    pub fn column(&self, name: &str) -> Option<&'static ColumnMeta> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    /// Create a migration for the model's table
+    pub fn migration(&self) -> Migration {
+        MigrationManager::create_migration(
+            &format!("create_table_{}", self.table_name),
+            &(self.migration_sql)(),
+        )
+    }
+}
+
+inventory::collect!(ModelMeta);
+
+/// Get all registered models, sorted by table name
+pub fn registry() -> Vec<&'static ModelMeta> {
+    let mut models: Vec<&'static ModelMeta> = inventory::iter::<ModelMeta>.into_iter().collect();
+    models.sort_by_key(|model| model.table_name);
+    models
+}
+
+/// Find a registered model by table name
+pub fn find_model(table_name: &str) -> Option<&'static ModelMeta> {
+    inventory::iter::<ModelMeta>
+        .into_iter()
+        .find(|model| model.table_name == table_name)
+}
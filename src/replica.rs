@@ -0,0 +1,77 @@
+//! Embedded replicas
+//!
+//! On native targets a [`Database`](crate::Database) can be an embedded
+//! replica: a local SQLite file that mirrors a remote libsql primary. Reads
+//! are served from the file without a network round trip, while writes are
+//! forwarded to the primary and pulled back on the next sync.
+//!
+//! ```no_run
+//! use libsql_orm::{Database, SyncConfig};
+//! use std::time::Duration;
+//!
+//! async fn open() -> Result<Database, Box<dyn std::error::Error>> {
+//!     let db = Database::new_replica(
+//!         "local.db",
+//!         "libsql://your-db.turso.io",
+//!         "your-auth-token",
+//!         SyncConfig::new().every(Duration::from_secs(30)),
+//!     )
+//!     .await?;
+//!     // Pull the latest changes right away, e.g. after a known remote write
+//!     db.sync().await?;
+//!     Ok(db)
+//! }
+//! ```
+
+use std::time::Duration;
+
+/// How an embedded replica keeps up with its primary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncConfig {
+    /// Pull changes in the background at this interval; `None` syncs only
+    /// on [`Database::sync`](crate::Database::sync)
+    pub interval: Option<Duration>,
+    /// Make the replica's own writes visible to its reads before the next
+    /// sync
+    pub read_your_writes: bool,
+    /// Sync once while opening, so the first reads are not stale
+    pub sync_on_open: bool,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            interval: None,
+            read_your_writes: true,
+            sync_on_open: true,
+        }
+    }
+}
+
+impl SyncConfig {
+    /// Sync on open and on demand, with read-your-writes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also sync in the background every `interval`
+    ///
+    /// The background task runs on the Tokio runtime the replica was opened
+    /// on.
+    pub fn every(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Set whether the replica's own writes are visible before the next sync
+    pub fn read_your_writes(mut self, enabled: bool) -> Self {
+        self.read_your_writes = enabled;
+        self
+    }
+
+    /// Set whether to sync once while opening
+    pub fn sync_on_open(mut self, enabled: bool) -> Self {
+        self.sync_on_open = enabled;
+        self
+    }
+}
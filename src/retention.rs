@@ -0,0 +1,88 @@
+//! Retention policies
+//!
+//! Models declare how long their rows are kept with
+//! `#[orm(retention(days = 90, column = "created_at"))]`, typically log and
+//! event tables that would otherwise grow without bound. [`enforce_all`]
+//! deletes the expired rows of every registered model with a policy, or
+//! moves them to `<table>_archive` for policies marked `archive`. Call it
+//! from a scheduled job, e.g. with
+//! [`maintenance::Task::custom`](crate::maintenance::Task::custom).
+//!
+//! ```no_run
+//! use libsql_orm::{chrono, Model};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Model, Clone, Serialize, Deserialize)]
+//! #[table_name("audit_events")]
+//! #[orm(retention(days = 90, column = "created_at", archive))]
+//! struct AuditEvent {
+//!     id: Option<i64>,
+//!     action: String,
+//!     created_at: chrono::DateTime<chrono::Utc>,
+//! }
+//!
+//! async fn nightly(db: &libsql_orm::Database) -> libsql_orm::Result<()> {
+//!     for report in libsql_orm::retention::enforce_all(db).await? {
+//!         println!("{}: {} rows expired", report.table, report.rows);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! The age column is compared as a point in time, so rows written in any
+//! format SQLite's date functions read expire correctly. Values those
+//! functions cannot read never expire.
+
+use crate::archive::archive_rows;
+use crate::maintenance::now_before;
+use crate::{registry, Database, ModelMeta, Result};
+
+/// Rows expired from one table by [`enforce_all`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionReport {
+    /// Table the policy was enforced on
+    pub table: &'static str,
+    /// Rows deleted or archived
+    pub rows: u64,
+    /// Whether the rows were moved to the archive table
+    pub archived: bool,
+}
+
+/// Enforce the retention policy of one model, if it has one
+pub async fn enforce(meta: &ModelMeta, db: &Database) -> Result<Option<RetentionReport>> {
+    let Some(policy) = meta.retention else {
+        return Ok(None);
+    };
+    // Compared as points in time, so every format SQLite's date functions
+    // read works, with or without `T`, `Z` or fractional seconds
+    let where_sql = format!(" WHERE julianday({}) < julianday(?)", policy.column);
+    let params = vec![now_before(policy.days)?];
+    let rows = if policy.archive {
+        archive_rows(db, meta.table_name, &where_sql, params).await?
+    } else {
+        db.execute(
+            &format!("DELETE FROM {}{}", meta.table_name, where_sql),
+            params,
+        )
+        .await?
+    };
+    Ok(Some(RetentionReport {
+        table: meta.table_name,
+        rows,
+        archived: policy.archive,
+    }))
+}
+
+/// Enforce the retention policies of all registered models
+///
+/// Stops at the first failing table; tables already handled keep their
+/// changes.
+pub async fn enforce_all(db: &Database) -> Result<Vec<RetentionReport>> {
+    let mut reports = Vec::new();
+    for meta in registry() {
+        if let Some(report) = enforce(meta, db).await? {
+            reports.push(report);
+        }
+    }
+    Ok(reports)
+}
@@ -0,0 +1,175 @@
+//! Retrying statements after transient errors
+//!
+//! Hosted databases occasionally fail a request that would succeed a moment
+//! later: an HTTP 5xx, a closed Hrana stream, a busy database. A
+//! [`RetryPolicy`] set with
+//! [`Database::with_retry_policy`](crate::Database::with_retry_policy) runs
+//! such statements again after a growing, jittered delay, so the ORM methods
+//! built on them recover without the caller noticing.
+//!
+//! Only single statements outside transactions are retried. A failure inside
+//! a transaction or [`batch`](crate::Database::batch) leaves the transaction
+//! in an unknown state, so it is returned to the caller, who can run the
+//! whole transaction again.
+//!
+//! A write whose response was lost may already have been applied; retrying
+//! it applies it twice. Restrict the policy with
+//! [`retry_on`](RetryPolicy::retry_on) where that matters.
+//!
+//! ```no_run
+//! use libsql_orm::{Database, RetryPolicy};
+//! use std::time::Duration;
+//!
+//! fn configure(db: Database) -> Database {
+//!     db.with_retry_policy(
+//!         RetryPolicy::new(3)
+//!             .backoff(Duration::from_millis(100), Duration::from_secs(2))
+//!             .background_attempts(6),
+//!     )
+//! }
+//! ```
+
+use crate::QueryHint;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// When and how often failed statements are run again
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    background_attempts: Option<u32>,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+    retry_on: Arc<dyn Fn(&libsql::Error) -> bool + Send + Sync>,
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("background_attempts", &self.background_attempts)
+            .field("base_delay", &self.base_delay)
+            .field("max_delay", &self.max_delay)
+            .field("jitter", &self.jitter)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl RetryPolicy {
+    /// Run a statement up to `max_attempts` times in total
+    ///
+    /// Transient errors (see [`is_transient`]) are retried after 50ms,
+    /// doubling up to 2s, with jitter.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            background_attempts: None,
+            base_delay: Duration::from_millis(50),
+            max_delay: Duration::from_secs(2),
+            jitter: true,
+            retry_on: Arc::new(is_transient),
+        }
+    }
+
+    /// Never retry; the default
+    pub fn none() -> Self {
+        Self::new(1)
+    }
+
+    /// Wait `base` before the first retry, doubling for each further one up
+    /// to `max`
+    pub fn backoff(mut self, base: Duration, max: Duration) -> Self {
+        self.base_delay = base;
+        self.max_delay = max.max(base);
+        self
+    }
+
+    /// Set whether delays are randomized, so clients that failed together do
+    /// not retry together
+    ///
+    /// A jittered delay lies between half and all of the unjittered one.
+    pub fn jitter(mut self, enabled: bool) -> Self {
+        self.jitter = enabled;
+        self
+    }
+
+    /// Retry the errors `classify` accepts instead of the transient ones
+    pub fn retry_on<F>(mut self, classify: F) -> Self
+    where
+        F: Fn(&libsql::Error) -> bool + Send + Sync + 'static,
+    {
+        self.retry_on = Arc::new(classify);
+        self
+    }
+
+    /// Run statements of [`QueryHint::Background`] handles up to
+    /// `max_attempts` times instead
+    ///
+    /// Background work can afford to wait out a longer outage than a request
+    /// that has a user waiting.
+    pub fn background_attempts(mut self, max_attempts: u32) -> Self {
+        self.background_attempts = Some(max_attempts.max(1));
+        self
+    }
+
+    /// Most times a statement with the given hint is run
+    pub fn attempts(&self, hint: QueryHint) -> u32 {
+        match hint {
+            QueryHint::Background => self.background_attempts.unwrap_or(self.max_attempts),
+            QueryHint::Interactive => self.max_attempts,
+        }
+    }
+
+    /// Delay before the given retry, counting from 1
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        let delay = self.base_delay.saturating_mul(factor).min(self.max_delay);
+        if !self.jitter {
+            return delay;
+        }
+        let random = uuid::Uuid::new_v4().as_u128() as u64;
+        let half = delay / 2;
+        let spread = half.as_nanos() as u64;
+        half + Duration::from_nanos(random.checked_rem(spread + 1).unwrap_or(0))
+    }
+
+    /// Whether `error` should be retried after `attempts` runs with `hint`
+    pub(crate) fn retries(&self, error: &libsql::Error, attempts: u32, hint: QueryHint) -> bool {
+        attempts < self.attempts(hint) && (self.retry_on)(error)
+    }
+}
+
+/// Whether an error is likely to go away when the statement runs again
+///
+/// Covers failed connections, closed streams and HTTP errors from a hosted
+/// database, server errors and rate limits, and busy databases. Errors in
+/// the statement itself, such as constraint violations, are not transient.
+pub fn is_transient(error: &libsql::Error) -> bool {
+    const SQLITE_BUSY: i32 = 5;
+    match error {
+        libsql::Error::ConnectionFailed(_) => true,
+        libsql::Error::SqliteFailure(code, _) => code & 0xff == SQLITE_BUSY,
+        libsql::Error::RemoteSqliteFailure(code, _, _) => code & 0xff == SQLITE_BUSY,
+        libsql::Error::Hrana(error) => {
+            let message = error.to_string().to_ascii_lowercase();
+            [
+                "stream closed",
+                "stream expired",
+                "http error",
+                "status=5",
+                "status=429",
+            ]
+            .iter()
+            .any(|pattern| message.contains(pattern))
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,87 @@
+//! Runtime-agnostic timing helpers
+//!
+//! Native builds use `futures-timer`, which works with any executor. Cloudflare
+//! Workers builds use the Workers timer through `worker::Delay`.
+//!
+//! `std::time::Instant` is unavailable on `wasm32-unknown-unknown`, so elapsed
+//! time is measured with [`Stopwatch`], which reads the JavaScript clock in
+//! Workers.
+
+use futures::future::{self, Either};
+use std::future::Future;
+use std::time::Duration;
+
+/// Wait for the given duration
+pub(crate) async fn sleep(duration: Duration) {
+    #[cfg(not(target_arch = "wasm32"))]
+    futures_timer::Delay::new(duration).await;
+
+    #[cfg(all(target_arch = "wasm32", feature = "cloudflare"))]
+    worker::Delay::from(duration).await;
+
+    // No timer is available, so the sleep never completes
+    #[cfg(all(target_arch = "wasm32", not(feature = "cloudflare")))]
+    {
+        let _ = duration;
+        future::pending::<()>().await;
+    }
+}
+
+/// Run a future with a time budget, returning `None` if it is exceeded
+pub(crate) async fn timeout<F: Future>(duration: Duration, fut: F) -> Option<F::Output> {
+    let fut = std::pin::pin!(fut);
+    let delay = std::pin::pin!(sleep(duration));
+    match future::select(fut, delay).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right(_) => None,
+    }
+}
+
+/// Yield to the executor once, letting other ready futures run first
+pub(crate) async fn yield_now() {
+    let mut yielded = false;
+    future::poll_fn(|cx| {
+        if yielded {
+            std::task::Poll::Ready(())
+        } else {
+            yielded = true;
+            cx.waker().wake_by_ref();
+            std::task::Poll::Pending
+        }
+    })
+    .await
+}
+
+/// Measures elapsed wall-clock time on every target
+pub(crate) struct Stopwatch {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: std::time::Instant,
+    #[cfg(all(target_arch = "wasm32", feature = "cloudflare"))]
+    start: u64,
+}
+
+impl Stopwatch {
+    /// Start measuring
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            start: std::time::Instant::now(),
+            #[cfg(all(target_arch = "wasm32", feature = "cloudflare"))]
+            start: worker::Date::now().as_millis(),
+        }
+    }
+
+    /// Time since the stopwatch was started
+    ///
+    /// Always zero on wasm targets without a clock.
+    pub(crate) fn elapsed(&self) -> Duration {
+        #[cfg(not(target_arch = "wasm32"))]
+        let elapsed = self.start.elapsed();
+        #[cfg(all(target_arch = "wasm32", feature = "cloudflare"))]
+        let elapsed =
+            Duration::from_millis(worker::Date::now().as_millis().saturating_sub(self.start));
+        #[cfg(all(target_arch = "wasm32", not(feature = "cloudflare")))]
+        let elapsed = Duration::ZERO;
+        elapsed
+    }
+}
@@ -0,0 +1,273 @@
+//! Schema export for registered models
+//!
+//! Generates a full schema document from the models in the global
+//! [registry](crate::registry()), so ER diagrams and schema docs can be kept
+//! in sync with the Rust definitions instead of being maintained by hand.
+//!
+//! # Supported Formats
+//!
+//! - [`Format::Sql`] - `CREATE TABLE` statements
+//! - [`Format::Dbml`] - [DBML](https://dbml.dbdiagram.io/docs/) table definitions
+//! - [`Format::MermaidErd`] - A Mermaid `erDiagram`
+//!
+//! # Examples
+//!
+//! ```rust
+//! use libsql_orm::schema::{self, Format};
+//!
+//! let sql = schema::export(Format::Sql);
+//! let diagram = schema::export(Format::MermaidErd);
+//! ```
+//!
+//! # Consistency Checks
+//!
+//! [`Database::check_consistency`] compares the registered models against the
+//! live database schema and reports drift, which makes a cheap startup health
+//! gate:
+//!
+//! ```no_run
+//! use libsql_orm::Database;
+//!
+//! async fn health_gate(db: &Database) -> libsql_orm::Result<()> {
+//!     let drift = db.check_consistency().await?;
+//!     for model in &drift {
+//!         eprintln!("{}: {:?}", model.table_name, model.findings);
+//!     }
+//!     assert!(drift.is_empty());
+//!     Ok(())
+//! }
+//! ```
+
+use crate::{Database, ModelMeta, Result};
+use std::collections::HashSet;
+
+/// Output format for schema export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// SQL `CREATE TABLE` statements
+    Sql,
+    /// DBML table definitions
+    Dbml,
+    /// Mermaid entity-relationship diagram
+    MermaidErd,
+}
+
+/// Export the schema of every registered model
+pub fn export(format: Format) -> String {
+    export_models(&crate::registry(), format)
+}
+
+/// Export the schema of the given models
+pub fn export_models(models: &[&ModelMeta], format: Format) -> String {
+    match format {
+        Format::Sql => export_sql(models),
+        Format::Dbml => export_dbml(models),
+        Format::MermaidErd => export_mermaid(models),
+    }
+}
+
+fn export_sql(models: &[&ModelMeta]) -> String {
+    models
+        .iter()
+        .map(|model| format!("{};\n", (model.migration_sql)()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn export_dbml(models: &[&ModelMeta]) -> String {
+    let mut out = String::new();
+    for (i, model) in models.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        out.push_str(&format!("Table {} {{\n", model.table_name));
+        for column in model.columns {
+            let mut settings = Vec::new();
+            if column.primary_key {
+                settings.push("pk");
+            }
+            if column.not_null {
+                settings.push("not null");
+            }
+            if column.unique {
+                settings.push("unique");
+            }
+            out.push_str(&format!(
+                "  {} {}",
+                column.name,
+                column.sql_type.to_lowercase()
+            ));
+            if !settings.is_empty() {
+                out.push_str(&format!(" [{}]", settings.join(", ")));
+            }
+            out.push('\n');
+        }
+        out.push_str("}\n");
+    }
+    out
+}
+
+fn export_mermaid(models: &[&ModelMeta]) -> String {
+    let mut out = String::from("erDiagram\n");
+    for model in models {
+        out.push_str(&format!("    {} {{\n", model.table_name));
+        for column in model.columns {
+            out.push_str(&format!(
+                "        {} {}",
+                mermaid_type(column.sql_type),
+                column.name
+            ));
+            if column.primary_key {
+                out.push_str(" PK");
+            } else if column.unique {
+                out.push_str(" UK");
+            }
+            out.push('\n');
+        }
+        out.push_str("    }\n");
+    }
+    out
+}
+
+/// A column type Mermaid accepts: the base type without its arguments, with
+/// the words of a multi-word type joined by `_`
+fn mermaid_type(sql_type: &str) -> String {
+    let base = sql_type.split('(').next().unwrap_or_default();
+    let words: Vec<&str> = base.split_whitespace().collect();
+    if words.is_empty() {
+        "TEXT".to_string()
+    } else {
+        words.join("_")
+    }
+}
+
+/// A single difference between a model and the live database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DriftFinding {
+    /// The model's table does not exist
+    MissingTable,
+    /// A model column does not exist in the table
+    MissingColumn { column: String },
+    /// The column exists but its type affinity differs from the model
+    TypeMismatch {
+        column: String,
+        expected: String,
+        actual: String,
+    },
+    /// A unique column has no unique index backing it
+    MissingIndex { column: String },
+}
+
+/// Drift findings for one model
+#[derive(Debug, Clone)]
+pub struct ModelDrift {
+    /// Table name of the model
+    pub table_name: String,
+    /// Differences found between the model and the database
+    pub findings: Vec<DriftFinding>,
+}
+
+/// Compare the given models against the live database schema
+///
+/// Only models with at least one finding are returned.
+pub async fn check_models(db: &Database, models: &[&ModelMeta]) -> Result<Vec<ModelDrift>> {
+    let mut drift = Vec::new();
+    for model in models {
+        let findings = check_model(db, model).await?;
+        if !findings.is_empty() {
+            drift.push(ModelDrift {
+                table_name: model.table_name.to_string(),
+                findings,
+            });
+        }
+    }
+    Ok(drift)
+}
+
+async fn check_model(db: &Database, model: &ModelMeta) -> Result<Vec<DriftFinding>> {
+    let table = libsql::Value::Text(model.table_name.to_string());
+
+    let mut live_columns = Vec::new();
+    let mut rows = db
+        .query(
+            "SELECT name, type FROM pragma_table_info(?)",
+            vec![table.clone()],
+        )
+        .await?;
+    while let Some(row) = rows.next().await? {
+        let name: String = row.get(0)?;
+        let declared: String = row.get(1)?;
+        live_columns.push((name, declared));
+    }
+
+    if live_columns.is_empty() {
+        return Ok(vec![DriftFinding::MissingTable]);
+    }
+
+    let mut unique_columns = HashSet::new();
+    let mut rows = db
+        .query(
+            "SELECT il.name FROM pragma_index_list(?) AS il WHERE il.\"unique\" = 1",
+            vec![table],
+        )
+        .await?;
+    let mut unique_indexes = Vec::new();
+    while let Some(row) = rows.next().await? {
+        unique_indexes.push(row.get::<String>(0)?);
+    }
+    for index in unique_indexes {
+        let mut rows = db
+            .query(
+                "SELECT name FROM pragma_index_info(?)",
+                vec![libsql::Value::Text(index)],
+            )
+            .await?;
+        let mut columns = Vec::new();
+        while let Some(row) = rows.next().await? {
+            columns.push(row.get::<String>(0)?);
+        }
+        if let [column] = columns.as_slice() {
+            unique_columns.insert(column.clone());
+        }
+    }
+
+    let mut findings = Vec::new();
+    for column in model.columns {
+        match live_columns.iter().find(|(name, _)| name == column.name) {
+            None => findings.push(DriftFinding::MissingColumn {
+                column: column.name.to_string(),
+            }),
+            Some((_, declared)) => {
+                if affinity(declared) != affinity(column.sql_type) {
+                    findings.push(DriftFinding::TypeMismatch {
+                        column: column.name.to_string(),
+                        expected: column.sql_type.to_string(),
+                        actual: declared.clone(),
+                    });
+                }
+                if column.unique && !column.primary_key && !unique_columns.contains(column.name) {
+                    findings.push(DriftFinding::MissingIndex {
+                        column: column.name.to_string(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(findings)
+}
+
+/// Determine the SQLite type affinity of a declared column type
+fn affinity(declared: &str) -> &'static str {
+    let declared = declared.to_uppercase();
+    if declared.contains("INT") {
+        "INTEGER"
+    } else if declared.contains("CHAR") || declared.contains("CLOB") || declared.contains("TEXT") {
+        "TEXT"
+    } else if declared.is_empty() || declared.contains("BLOB") {
+        "BLOB"
+    } else if declared.contains("REAL") || declared.contains("FLOA") || declared.contains("DOUB") {
+        "REAL"
+    } else {
+        "NUMERIC"
+    }
+}
@@ -0,0 +1,105 @@
+//! Request-scoped identity map
+//!
+//! [`Database::scoped`] returns a [`ScopedDatabase`] that caches records loaded
+//! by primary key. Within the scope, repeated [`ScopedDatabase::find_by_id`]
+//! calls for the same model and id return the already-loaded record instead
+//! of querying again, which removes duplicate lookups in nested handler logic.
+//!
+//! The scope dereferences to [`Database`], so all other model methods work on
+//! it unchanged. Writes are not tracked: call [`ScopedDatabase::remember`] or
+//! [`ScopedDatabase::forget`] after changing a cached record.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use libsql_orm::{Database, Model};
+//!
+//! async fn handler<M: Model + 'static>(db: &Database) -> libsql_orm::Result<()> {
+//!     let scope = db.scoped();
+//!     let first = scope.find_by_id::<M>(1).await?;
+//!     let again = scope.find_by_id::<M>(1).await?; // served from the scope
+//!     let all = M::find_all(&scope).await?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::{Database, Model, Result};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::Mutex;
+
+type CacheKey = (TypeId, i64);
+
+/// A database handle with a per-scope identity map
+pub struct ScopedDatabase<'a> {
+    db: &'a Database,
+    cache: Mutex<HashMap<CacheKey, Option<Box<dyn Any + Send + Sync>>>>,
+}
+
+impl<'a> ScopedDatabase<'a> {
+    pub(crate) fn new(db: &'a Database) -> Self {
+        Self {
+            db,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Find a record by primary key, reusing a record already loaded in this scope
+    ///
+    /// Missing records are cached as well, so a repeated lookup of an unknown
+    /// id does not query again.
+    pub async fn find_by_id<M: Model + 'static>(&self, id: i64) -> Result<Option<M>> {
+        let key = (TypeId::of::<M>(), id);
+        if let Some(cached) = self.cache.lock().unwrap().get(&key) {
+            return Ok(cached
+                .as_ref()
+                .and_then(|record| record.downcast_ref::<M>())
+                .cloned());
+        }
+
+        let record = M::find_by_id(id, self.db).await?;
+        self.cache.lock().unwrap().insert(
+            key,
+            record
+                .clone()
+                .map(|record| Box::new(record) as Box<dyn Any + Send + Sync>),
+        );
+        Ok(record)
+    }
+
+    /// Store a record in the scope, replacing any cached copy
+    ///
+    /// Records without a primary key are ignored.
+    pub fn remember<M: Model + 'static>(&self, record: &M) {
+        if let Some(id) = record.get_primary_key() {
+            self.cache
+                .lock()
+                .unwrap()
+                .insert((TypeId::of::<M>(), id), Some(Box::new(record.clone())));
+        }
+    }
+
+    /// Drop a cached record so the next lookup queries the database
+    pub fn forget<M: Model + 'static>(&self, id: i64) {
+        self.cache.lock().unwrap().remove(&(TypeId::of::<M>(), id));
+    }
+
+    /// Drop every cached record
+    pub fn clear(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    /// Number of cached lookups, including cached misses
+    pub fn cached(&self) -> usize {
+        self.cache.lock().unwrap().len()
+    }
+}
+
+impl Deref for ScopedDatabase<'_> {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        self.db
+    }
+}
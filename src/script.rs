@@ -0,0 +1,104 @@
+//! Splitting SQL scripts into statements
+
+/// Split a script into its statements, with the byte offset of each
+///
+/// Semicolons inside string literals, quoted identifiers and comments do not
+/// end a statement, and neither do the ones between a trigger's `BEGIN` and
+/// `END`. Statements are returned without their terminating semicolon;
+/// fragments holding only whitespace and comments are dropped.
+pub(crate) fn split_statements(script: &str) -> Vec<(usize, &str)> {
+    let bytes = script.as_bytes();
+    let mut statements = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut leading: Vec<String> = Vec::new();
+    let mut in_body = false;
+    let mut case_depth = 0usize;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = script[i + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |end| i + 2 + end + 2);
+            }
+            quote @ (b'\'' | b'"' | b'`' | b'[') => {
+                start.get_or_insert(i);
+                let close = if quote == b'[' { b']' } else { quote };
+                i += 1;
+                while i < bytes.len() {
+                    if bytes[i] == close {
+                        // A doubled quote is an escaped quote
+                        if close != b']' && bytes.get(i + 1) == Some(&close) {
+                            i += 2;
+                            continue;
+                        }
+                        break;
+                    }
+                    i += 1;
+                }
+                i += 1;
+            }
+            b';' => {
+                if !in_body {
+                    if let Some(begin) = start.take() {
+                        statements.push((begin, script[begin..i].trim_end()));
+                    }
+                    leading.clear();
+                    case_depth = 0;
+                }
+                i += 1;
+            }
+            c if c.is_ascii_alphanumeric() || c == b'_' => {
+                let begin = i;
+                start.get_or_insert(begin);
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                let word = script[begin..i].to_ascii_uppercase();
+                if leading.len() < 3 {
+                    leading.push(word.clone());
+                }
+                if is_trigger(&leading) {
+                    match word.as_str() {
+                        "BEGIN" if !in_body => in_body = true,
+                        "CASE" if in_body => case_depth += 1,
+                        "END" if in_body && case_depth > 0 => case_depth -= 1,
+                        "END" if in_body => in_body = false,
+                        _ => {}
+                    }
+                }
+            }
+            c if c.is_ascii_whitespace() => i += 1,
+            _ => {
+                start.get_or_insert(i);
+                i += 1;
+            }
+        }
+    }
+    if let Some(begin) = start {
+        statements.push((begin, script[begin..].trim_end()));
+    }
+    statements
+}
+
+/// Whether a statement's first keywords start a `CREATE TRIGGER`
+fn is_trigger(leading: &[String]) -> bool {
+    match leading {
+        [create, trigger, ..] if create == "CREATE" && trigger == "TRIGGER" => true,
+        [create, temp, trigger] => {
+            create == "CREATE" && (temp == "TEMP" || temp == "TEMPORARY") && trigger == "TRIGGER"
+        }
+        _ => false,
+    }
+}
+
+/// The 1-based line of a byte offset
+pub(crate) fn line_at(script: &str, offset: usize) -> usize {
+    script[..offset].matches('\n').count() + 1
+}
@@ -0,0 +1,76 @@
+//! String similarity for spelling-tolerant search
+//!
+//! libSQL does not let clients register SQL functions, so
+//! [`Model::fuzzy_search`](crate::Model::fuzzy_search) narrows the candidates
+//! with `LIKE` and ranks them here.
+
+/// Edit distance between two strings, counted in characters
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Similarity of two strings from 0 (nothing in common) to 1 (equal)
+fn ratio(a: &[char], b: &[char]) -> f64 {
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(a, b) as f64 / longest as f64
+}
+
+/// How closely `text` contains `term`, ignoring case
+///
+/// The term is compared with the whole text and with every run of as many
+/// consecutive words as it has, and the best match counts.
+pub(crate) fn similarity(term: &str, text: &str) -> f64 {
+    let term = term.to_lowercase();
+    let text = text.to_lowercase();
+    let wanted: Vec<char> = term.chars().collect();
+    let mut best = ratio(&wanted, &text.chars().collect::<Vec<_>>());
+
+    let size = term.split_whitespace().count().max(1);
+    let words: Vec<&str> = text.split_whitespace().collect();
+    for window in words.windows(size) {
+        let candidate: Vec<char> = window.join(" ").chars().collect();
+        best = best.max(ratio(&wanted, &candidate));
+    }
+    best
+}
+
+/// Pairs of characters of which every text reaching `threshold` contains at
+/// least one
+///
+/// A match needs no more than `(1 - threshold) / threshold * n` edits of an
+/// `n`-character term, and every edit destroys at most two of its `n - 1`
+/// pairs. Returns `None` when no pair is guaranteed to survive, or when the
+/// term is not a single ASCII word (`LIKE` only folds ASCII case and the
+/// words of a text may be spaced differently).
+pub(crate) fn required_bigrams(term: &str, threshold: f64) -> Option<Vec<String>> {
+    let term = term.to_ascii_lowercase();
+    if threshold <= 0.0 || !term.is_ascii() || term.chars().any(char::is_whitespace) {
+        return None;
+    }
+    let n = term.len();
+    let max_edits = ((1.0 - threshold) / threshold * n as f64).floor() as usize;
+    if n < 2 || (n - 1) <= 2 * max_edits {
+        return None;
+    }
+    let mut pairs: Vec<String> = term
+        .as_bytes()
+        .windows(2)
+        .map(|pair| String::from_utf8_lossy(pair).into_owned())
+        .collect();
+    pairs.sort();
+    pairs.dedup();
+    Some(pairs)
+}
@@ -0,0 +1,146 @@
+//! Prepared statement cache
+//!
+//! Local connections compile every statement before running it. A
+//! [`Database`](crate::Database) keeps the most recently used prepared
+//! statements, keyed by their SQL text, and reuses them when the same SQL runs
+//! again, so the queries behind `find_by_id`, `create` and friends are only
+//! compiled once per connection.
+//!
+//! Statements go through the cache when they are run with
+//! [`Database::execute`](crate::Database::execute) or by the model and query
+//! builder methods, which read every row before returning.
+//! [`Database::query`](crate::Database::query) hands its rows to the caller and
+//! is not cached. Tags and request contexts become part of the SQL text, so
+//! handles with different ones do not share entries.
+//!
+//! The libsql client for Cloudflare Workers cannot prepare statements, and
+//! its remote server keeps no statements between requests, so there is no
+//! cache there: [`with_statement_cache`](crate::Database::with_statement_cache)
+//! has no effect and the stats always read zero.
+//!
+//! ```no_run
+//! use libsql_orm::Database;
+//!
+//! fn report(db: &Database) {
+//!     let stats = db.statement_cache_stats();
+//!     println!(
+//!         "{} of {} statements cached, {} hits, {} misses",
+//!         stats.cached, stats.capacity, stats.hits, stats.misses
+//!     );
+//! }
+//! ```
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Mutex;
+
+/// Number of statements a connection caches unless configured otherwise
+pub const DEFAULT_CAPACITY: usize = 64;
+
+/// Usage of a connection's statement cache
+///
+/// Every field is 0 on Cloudflare Workers, where statements are not cached.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatementCacheStats {
+    /// Statements that reused a cached prepared statement
+    pub hits: u64,
+    /// Statements that had to be prepared
+    pub misses: u64,
+    /// Prepared statements currently cached
+    pub cached: usize,
+    /// Most statements kept, 0 when caching is disabled
+    pub capacity: usize,
+}
+
+/// Least recently used prepared statements of one connection
+pub(crate) struct StatementCache {
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    capacity: usize,
+    /// Oldest first; a statement in use is taken out, so it is never shared
+    #[cfg(not(target_arch = "wasm32"))]
+    statements: Mutex<VecDeque<(String, libsql::Statement)>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl StatementCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            #[cfg(not(target_arch = "wasm32"))]
+            statements: Mutex::new(VecDeque::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn stats(&self) -> StatementCacheStats {
+        #[cfg(not(target_arch = "wasm32"))]
+        let (cached, capacity) = (self.lock().len(), self.capacity);
+        // Nothing can be cached, so report the cache as disabled
+        #[cfg(target_arch = "wasm32")]
+        let (cached, capacity) = (0, 0);
+        StatementCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            cached,
+            capacity,
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<(String, libsql::Statement)>> {
+        // The queue stays consistent even if a holder panicked
+        self.statements
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Take the prepared statement for `sql` out of the cache, preparing it
+    /// on a miss
+    ///
+    /// Returns `None` when caching is disabled. Hand the statement back with
+    /// [`checkin`](Self::checkin) once its rows have been read.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) async fn checkout(
+        &self,
+        conn: &libsql::Connection,
+        sql: &str,
+    ) -> libsql::Result<Option<libsql::Statement>> {
+        if self.capacity == 0 {
+            return Ok(None);
+        }
+        let cached = {
+            let mut statements = self.lock();
+            statements
+                .iter()
+                .rposition(|(cached, _)| cached == sql)
+                .and_then(|index| statements.remove(index))
+        };
+        match cached {
+            Some((_, statement)) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Ok(Some(statement))
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                conn.prepare(sql).await.map(Some)
+            }
+        }
+    }
+
+    /// Return a statement taken with [`checkout`](Self::checkout), evicting
+    /// the least recently used one when the cache is full
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn checkin(&self, sql: String, statement: libsql::Statement) {
+        // An unfinished statement would keep its tables locked
+        statement.reset();
+        let mut statements = self.lock();
+        statements.push_back((sql, statement));
+        while statements.len() > self.capacity {
+            statements.pop_front();
+        }
+    }
+}
@@ -0,0 +1,510 @@
+//! Incremental sync for offline-first clients
+//!
+//! A [`SyncServer`] serves a pull/push protocol over registered models, so a
+//! Worker can keep mobile or desktop SQLite replicas up to date:
+//!
+//! - Every synced table has an INTEGER version column (`sync_version` unless
+//!   configured). Triggers installed by [`SyncServer::setup`] stamp each
+//!   insert and update with the table's next version, and record deletes as
+//!   tombstones, so writes made through the ORM or raw SQL are tracked alike.
+//! - A client keeps a [`VersionVector`], the last version it has seen of each
+//!   table. A pull returns every row and tombstone newer than that.
+//! - A push carries the client's changes together with the versions they were
+//!   based on. A change to a row the server modified or deleted after that
+//!   version is a conflict, settled by a [`ConflictStrategy`].
+//!
+//! Rows written by a push get new versions too, so the client sees its own
+//! changes again on the next pull; applying them is idempotent.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use libsql_orm::sync::{ChangeSet, ClientWins, SyncServer};
+//! use libsql_orm::{Database, Model};
+//!
+//! async fn serve<M: Model>(db: &Database, push: ChangeSet) -> libsql_orm::Result<ChangeSet> {
+//!     let server = SyncServer::new().table::<M>().conflict_strategy(ClientWins);
+//!     server.setup(db).await?;
+//!
+//!     let base = push.versions.clone();
+//!     let result = server.push(db, push).await?;
+//!     println!("applied {} changes, {} conflicts", result.applied, result.conflicts.len());
+//!     server.pull(db, &base).await
+//! }
+//! ```
+
+use crate::{Database, Error, Model, Result, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Last seen version of each table, keyed by table name
+pub type VersionVector = HashMap<String, i64>;
+
+/// A row as a map of column names to values
+pub type Row = HashMap<String, Value>;
+
+/// Table holding the current version of each synced table
+const VERSIONS_TABLE: &str = "_sync_versions";
+
+/// Table holding a tombstone for each deleted row
+const TOMBSTONES_TABLE: &str = "_sync_tombstones";
+
+/// Changes to a single table
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TableChanges {
+    /// Rows that were inserted or updated
+    pub upserts: Vec<Row>,
+    /// Primary keys of rows that were deleted
+    pub deletes: Vec<i64>,
+}
+
+impl TableChanges {
+    /// Whether there are no changes
+    pub fn is_empty(&self) -> bool {
+        self.upserts.is_empty() && self.deletes.is_empty()
+    }
+}
+
+/// Changes to several tables, along with the versions they relate to
+///
+/// Returned by a pull, where `versions` are the server's current versions,
+/// and sent with a push, where `versions` are the client's base versions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChangeSet {
+    /// Version of each table the changes relate to
+    pub versions: VersionVector,
+    /// Changes keyed by table name
+    pub tables: HashMap<String, TableChanges>,
+}
+
+/// How a conflicting change is settled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Resolution {
+    /// Discard the client's change
+    KeepServer,
+    /// Apply the client's change
+    TakeClient,
+    /// Write the given row instead of either side
+    Merge(Row),
+}
+
+/// Settles conflicts between client and server changes
+///
+/// `server` is `None` when the server deleted the row, and `client` is `None`
+/// when the client deleted it. Closures with the same signature implement
+/// this trait.
+pub trait ConflictStrategy: Send + Sync {
+    /// Decide how to settle a conflict on a row of `table`
+    fn resolve(&self, table: &str, server: Option<&Row>, client: Option<&Row>) -> Resolution;
+}
+
+impl<F> ConflictStrategy for F
+where
+    F: Fn(&str, Option<&Row>, Option<&Row>) -> Resolution + Send + Sync,
+{
+    fn resolve(&self, table: &str, server: Option<&Row>, client: Option<&Row>) -> Resolution {
+        self(table, server, client)
+    }
+}
+
+/// Strategy that always keeps the server's version
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ServerWins;
+
+impl ConflictStrategy for ServerWins {
+    fn resolve(&self, _table: &str, _server: Option<&Row>, _client: Option<&Row>) -> Resolution {
+        Resolution::KeepServer
+    }
+}
+
+/// Strategy that always applies the client's change
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClientWins;
+
+impl ConflictStrategy for ClientWins {
+    fn resolve(&self, _table: &str, _server: Option<&Row>, _client: Option<&Row>) -> Resolution {
+        Resolution::TakeClient
+    }
+}
+
+/// A conflict found during a push
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Conflict {
+    /// Table of the conflicting row
+    pub table: String,
+    /// Primary key of the conflicting row
+    pub id: i64,
+    /// The server's row, or `None` if the server deleted it
+    pub server: Option<Row>,
+    /// The client's row, or `None` if the client deleted it
+    pub client: Option<Row>,
+    /// How the conflict was settled
+    pub resolution: Resolution,
+}
+
+/// Outcome of a push
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PushResult {
+    /// Number of changes written
+    pub applied: u64,
+    /// Conflicts found, including those settled in the client's favor
+    pub conflicts: Vec<Conflict>,
+    /// Current version of each table after the push
+    pub versions: VersionVector,
+}
+
+/// A table taking part in sync
+#[derive(Debug, Clone)]
+struct SyncTable {
+    name: &'static str,
+    primary_key: &'static str,
+    columns: Vec<&'static str>,
+}
+
+/// Serves pull and push requests for a set of models
+pub struct SyncServer {
+    tables: Vec<SyncTable>,
+    version_column: String,
+    strategy: Box<dyn ConflictStrategy>,
+}
+
+impl Default for SyncServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SyncServer {
+    /// Create a server with no tables, resolving conflicts with [`ServerWins`]
+    pub fn new() -> Self {
+        Self {
+            tables: Vec::new(),
+            version_column: "sync_version".to_string(),
+            strategy: Box::new(ServerWins),
+        }
+    }
+
+    /// Add a model's table to sync
+    pub fn table<M: Model>(mut self) -> Self {
+        self.tables.push(SyncTable {
+            name: M::table_name(),
+            primary_key: M::primary_key(),
+            columns: M::columns(),
+        });
+        self
+    }
+
+    /// Set the name of the version column shared by all synced tables
+    pub fn version_column(mut self, column: impl Into<String>) -> Self {
+        self.version_column = column.into();
+        self
+    }
+
+    /// Set the strategy used to settle conflicts
+    pub fn conflict_strategy(mut self, strategy: impl ConflictStrategy + 'static) -> Self {
+        self.strategy = Box::new(strategy);
+        self
+    }
+
+    /// SQL creating the bookkeeping tables and change-tracking triggers
+    pub fn setup_sql(&self) -> String {
+        let mut sql = format!(
+            "CREATE TABLE IF NOT EXISTS {VERSIONS_TABLE} (table_name TEXT PRIMARY KEY, version INTEGER NOT NULL);\n\
+             CREATE TABLE IF NOT EXISTS {TOMBSTONES_TABLE} (table_name TEXT NOT NULL, row_id INTEGER NOT NULL, \
+             version INTEGER NOT NULL, PRIMARY KEY (table_name, row_id));\n"
+        );
+        let v = &self.version_column;
+        for table in &self.tables {
+            let (t, pk) = (table.name, table.primary_key);
+            let bump = format!(
+                "INSERT INTO {VERSIONS_TABLE} (table_name, version) VALUES ('{t}', 1) \
+                 ON CONFLICT(table_name) DO UPDATE SET version = version + 1;"
+            );
+            let current =
+                format!("(SELECT version FROM {VERSIONS_TABLE} WHERE table_name = '{t}')");
+            sql.push_str(&format!(
+                "CREATE TRIGGER IF NOT EXISTS {t}_sync_insert AFTER INSERT ON {t} BEGIN \
+                 {bump} \
+                 UPDATE {t} SET {v} = {current} WHERE {pk} = NEW.{pk}; \
+                 DELETE FROM {TOMBSTONES_TABLE} WHERE table_name = '{t}' AND row_id = NEW.{pk}; \
+                 END;\n\
+                 CREATE TRIGGER IF NOT EXISTS {t}_sync_update AFTER UPDATE ON {t} WHEN NEW.{v} IS OLD.{v} BEGIN \
+                 {bump} \
+                 UPDATE {t} SET {v} = {current} WHERE {pk} = NEW.{pk}; \
+                 END;\n\
+                 CREATE TRIGGER IF NOT EXISTS {t}_sync_delete AFTER DELETE ON {t} BEGIN \
+                 {bump} \
+                 INSERT OR REPLACE INTO {TOMBSTONES_TABLE} (table_name, row_id, version) VALUES ('{t}', OLD.{pk}, {current}); \
+                 END;\n"
+            ));
+        }
+        sql
+    }
+
+    /// Create the bookkeeping tables and change-tracking triggers
+    ///
+    /// Synced tables must already exist and have the version column.
+    pub async fn setup(&self, db: &Database) -> Result<()> {
+        db.execute_batch(&self.setup_sql()).await?;
+        Ok(())
+    }
+
+    /// Current version of each synced table
+    pub async fn versions(&self, db: &Database) -> Result<VersionVector> {
+        let mut versions = VersionVector::new();
+        for table in &self.tables {
+            versions.insert(
+                table.name.to_string(),
+                current_version(db, table.name).await?,
+            );
+        }
+        Ok(versions)
+    }
+
+    /// Collect the changes a client has not seen yet
+    ///
+    /// Tables missing from `since` are returned in full.
+    pub async fn pull(&self, db: &Database, since: &VersionVector) -> Result<ChangeSet> {
+        db.transaction(|tx| async move {
+            let mut changes = ChangeSet::default();
+            for table in &self.tables {
+                let base = since.get(table.name).copied().unwrap_or(0);
+                let mut table_changes = TableChanges::default();
+
+                let sql = format!(
+                    "SELECT * FROM {} WHERE {} > ? ORDER BY {}",
+                    table.name, self.version_column, self.version_column
+                );
+                let mut rows = tx.query(&sql, vec![base.into()]).await?;
+                while let Some(row) = rows.next().await? {
+                    table_changes.upserts.push(read_row(&row)?);
+                }
+
+                let sql = format!(
+                    "SELECT row_id FROM {TOMBSTONES_TABLE} WHERE table_name = ? AND version > ? ORDER BY version"
+                );
+                let mut rows = tx
+                    .query(&sql, vec![table.name.into(), base.into()])
+                    .await?;
+                while let Some(row) = rows.next().await? {
+                    table_changes.deletes.push(row.get::<i64>(0)?);
+                }
+
+                changes.versions.insert(
+                    table.name.to_string(),
+                    current_version(tx, table.name).await?,
+                );
+                if !table_changes.is_empty() {
+                    changes.tables.insert(table.name.to_string(), table_changes);
+                }
+            }
+            Ok(changes)
+        })
+        .await
+    }
+
+    /// Apply a client's changes in a single transaction
+    ///
+    /// `changes.versions` holds the versions the client last pulled. Changes
+    /// to unknown tables or columns are rejected, and values of the version
+    /// column sent by the client are ignored.
+    pub async fn push(&self, db: &Database, changes: ChangeSet) -> Result<PushResult> {
+        for name in changes.tables.keys() {
+            self.find_table(name)?;
+        }
+
+        db.transaction(|tx| async move {
+            let mut result = PushResult::default();
+            for (name, table_changes) in changes.tables {
+                let table = self.find_table(&name)?;
+                let base = changes.versions.get(&name).copied().unwrap_or(0);
+
+                for mut row in table_changes.upserts {
+                    row.remove(&self.version_column);
+                    let id = match row.get(table.primary_key) {
+                        Some(Value::Integer(id)) => *id,
+                        _ => {
+                            return Err(Error::Validation(format!(
+                                "Row for table {name} is missing an integer {}",
+                                table.primary_key
+                            )))
+                        }
+                    };
+                    let row = match self.check(tx, table, id, base, Some(row)).await? {
+                        Checked::Apply(row) => row,
+                        Checked::Skip(conflict) => {
+                            result.conflicts.push(conflict);
+                            continue;
+                        }
+                        Checked::Resolved(row, conflict) => {
+                            result.conflicts.push(conflict);
+                            row
+                        }
+                    };
+                    result.applied += self.write(tx, table, id, row).await?;
+                }
+
+                for id in table_changes.deletes {
+                    let row = match self.check(tx, table, id, base, None).await? {
+                        Checked::Apply(row) => row,
+                        Checked::Skip(conflict) => {
+                            result.conflicts.push(conflict);
+                            continue;
+                        }
+                        Checked::Resolved(row, conflict) => {
+                            result.conflicts.push(conflict);
+                            row
+                        }
+                    };
+                    result.applied += self.write(tx, table, id, row).await?;
+                }
+            }
+            result.versions = self.versions(tx).await?;
+            Ok(result)
+        })
+        .await
+    }
+
+    fn find_table(&self, name: &str) -> Result<&SyncTable> {
+        self.tables
+            .iter()
+            .find(|table| table.name == name)
+            .ok_or_else(|| Error::Validation(format!("Table {name} is not synced")))
+    }
+
+    /// Decide what to write for a client change, settling any conflict
+    ///
+    /// `client` is `None` for a delete, and so is the row to write.
+    async fn check(
+        &self,
+        db: &Database,
+        table: &SyncTable,
+        id: i64,
+        base: i64,
+        client: Option<Row>,
+    ) -> Result<Checked> {
+        let sql = format!(
+            "SELECT * FROM {} WHERE {} = ?",
+            table.name, table.primary_key
+        );
+        let mut rows = db.query(&sql, vec![id.into()]).await?;
+        let server = match rows.next().await? {
+            Some(row) => Some(read_row(&row)?),
+            None => None,
+        };
+
+        let conflicted = match &server {
+            Some(server) => match server.get(&self.version_column) {
+                Some(Value::Integer(version)) => *version > base,
+                _ => false,
+            },
+            None => {
+                let sql = format!(
+                    "SELECT version FROM {TOMBSTONES_TABLE} WHERE table_name = ? AND row_id = ?"
+                );
+                let mut rows = db.query(&sql, vec![table.name.into(), id.into()]).await?;
+                match rows.next().await? {
+                    Some(row) => row.get::<i64>(0)? > base,
+                    None => false,
+                }
+            }
+        };
+        if !conflicted || (server.is_none() && client.is_none()) {
+            return Ok(Checked::Apply(client));
+        }
+
+        let resolution = self
+            .strategy
+            .resolve(table.name, server.as_ref(), client.as_ref());
+        let write = match &resolution {
+            Resolution::KeepServer => None,
+            Resolution::TakeClient => Some(client.clone()),
+            Resolution::Merge(row) => {
+                let mut row = row.clone();
+                row.remove(&self.version_column);
+                row.insert(table.primary_key.to_string(), Value::Integer(id));
+                Some(Some(row))
+            }
+        };
+        let conflict = Conflict {
+            table: table.name.to_string(),
+            id,
+            server,
+            client,
+            resolution,
+        };
+        Ok(match write {
+            Some(row) => Checked::Resolved(row, conflict),
+            None => Checked::Skip(conflict),
+        })
+    }
+
+    /// Upsert a row, or delete the row with `id` when `row` is `None`
+    async fn write(
+        &self,
+        db: &Database,
+        table: &SyncTable,
+        id: i64,
+        row: Option<Row>,
+    ) -> Result<u64> {
+        let Some(row) = row else {
+            let sql = format!("DELETE FROM {} WHERE {} = ?", table.name, table.primary_key);
+            return Ok(db.execute(&sql, vec![libsql::Value::Integer(id)]).await?);
+        };
+        if let Some(column) = row.keys().find(|c| !table.columns.contains(&c.as_str())) {
+            return Err(Error::Validation(format!(
+                "Unknown column '{column}' for table {}",
+                table.name
+            )));
+        }
+
+        let columns: Vec<&str> = row.keys().map(String::as_str).collect();
+        let placeholders = vec!["?"; columns.len()].join(", ");
+        let mut updates: Vec<String> = columns
+            .iter()
+            .filter(|c| **c != table.primary_key)
+            .map(|c| format!("{c} = excluded.{c}"))
+            .collect();
+        if updates.is_empty() {
+            updates.push(format!("{0} = excluded.{0}", table.primary_key));
+        }
+        let sql = format!(
+            "INSERT INTO {} ({}) VALUES ({placeholders}) ON CONFLICT({}) DO UPDATE SET {}",
+            table.name,
+            columns.join(", "),
+            table.primary_key,
+            updates.join(", ")
+        );
+        let params: Vec<libsql::Value> = row.into_values().map(libsql::Value::from).collect();
+        Ok(db.execute(&sql, params).await?)
+    }
+}
+
+/// What to do with a client change after checking for conflicts
+enum Checked {
+    /// No conflict; write the change
+    Apply(Option<Row>),
+    /// Conflict settled in favor of the client or a merge; write the row
+    Resolved(Option<Row>, Conflict),
+    /// Conflict settled in favor of the server; write nothing
+    Skip(Conflict),
+}
+
+async fn current_version(db: &Database, table: &str) -> Result<i64> {
+    let sql = format!("SELECT version FROM {VERSIONS_TABLE} WHERE table_name = ?");
+    let mut rows = db.query(&sql, vec![table.into()]).await?;
+    match rows.next().await? {
+        Some(row) => Ok(row.get::<i64>(0)?),
+        None => Ok(0),
+    }
+}
+
+fn read_row(row: &libsql::Row) -> Result<Row> {
+    let mut map = Row::new();
+    for i in 0..row.column_count() {
+        if let Some(name) = row.column_name(i) {
+            map.insert(name.to_string(), Value::from(row.get_value(i)?));
+        }
+    }
+    Ok(map)
+}
@@ -0,0 +1,90 @@
+//! Schema checks for models
+//!
+//! [`model_schema_tests!`](crate::model_schema_tests) turns these checks into
+//! unit tests, so a model whose attributes produce broken SQL or whose fields
+//! do not survive the trip to the database fails `cargo test` instead of a
+//! deployment. The functions can also be called directly, e.g. with a sample
+//! instance that is not the model's `Default`.
+//!
+//! The migration is run against an in-memory SQLite database, so the checks
+//! are not available on Cloudflare Workers.
+//!
+//! ```
+//! use libsql_orm::{Deserialize, Model, Serialize};
+//!
+//! #[derive(Model, Debug, Clone, Default, Serialize, Deserialize)]
+//! #[table_name("schema_users")]
+//! struct User {
+//!     id: Option<i64>,
+//!     name: String,
+//!     is_active: bool,
+//! }
+//!
+//! libsql_orm::model_schema_tests!(User);
+//! # fn main() {}
+//! ```
+
+use crate::Model;
+use std::collections::HashSet;
+
+/// Runs every check on `M`, using its `Default` value as the sample
+pub fn check_model_schema<M: Model + Default>() {
+    check_migration_sql::<M>();
+    check_unique_columns::<M>();
+    check_round_trip(&M::default());
+}
+
+/// Panics unless `M::migration_sql()` runs on an empty SQLite database
+///
+/// Tables named in foreign keys do not need to exist.
+pub fn check_migration_sql<M: Model>() {
+    let sql = M::migration_sql();
+    let result = futures::executor::block_on(async {
+        let db = crate::Database::new_memory().await?;
+        db.execute_batch(&sql).await
+    });
+    if let Err(error) = result {
+        panic!(
+            "migration SQL of `{}` does not run: {error}\n{sql}",
+            M::table_name()
+        );
+    }
+}
+
+/// Panics if two columns of `M` share a name
+///
+/// SQLite compares column names without regard to ASCII case, and so does
+/// this check.
+pub fn check_unique_columns<M: Model>() {
+    let mut seen = HashSet::new();
+    for column in M::columns() {
+        assert!(
+            seen.insert(column.to_ascii_lowercase()),
+            "column `{column}` appears more than once in `{}`",
+            M::table_name()
+        );
+    }
+}
+
+/// Panics unless `sample` comes back unchanged from
+/// [`to_map`](Model::to_map) and [`from_map`](Model::from_map)
+///
+/// The two values are compared in their JSON form, so `M` needs no
+/// `PartialEq`.
+pub fn check_round_trip<M: Model>(sample: &M) {
+    let table = M::table_name();
+    let map = sample
+        .to_map()
+        .unwrap_or_else(|error| panic!("`{table}` sample does not convert to a row: {error}"));
+    let back = M::from_map(map).unwrap_or_else(|error| {
+        panic!("`{table}` sample does not convert back from a row: {error}")
+    });
+    let expected = serde_json::to_value(sample)
+        .unwrap_or_else(|error| panic!("`{table}` sample does not serialize: {error}"));
+    let actual = serde_json::to_value(&back)
+        .unwrap_or_else(|error| panic!("`{table}` round trip does not serialize: {error}"));
+    assert_eq!(
+        actual, expected,
+        "`{table}` sample changed in the round trip"
+    );
+}
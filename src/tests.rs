@@ -3,6 +3,14 @@
 //! These tests verify core functionality including boolean type conversion
 //! and value operations.
 
+/// Open an in-memory database and run the given schema script on it
+#[cfg(test)]
+async fn memory_db(schema: &str) -> crate::Database {
+    let db = crate::Database::new_memory().await.unwrap();
+    db.inner.execute_batch(schema).await.unwrap();
+    db
+}
+
 #[cfg(test)]
 mod value_tests {
     use crate::Value;
@@ -102,3 +110,5479 @@ mod value_tests {
         assert_eq!(value, Value::Null);
     }
 }
+
+#[cfg(test)]
+mod registry_tests {
+    use crate::{ColumnKind, Model};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("registry_widgets")]
+    struct Widget {
+        pub id: Option<i64>,
+        #[orm_column(not_null, unique)]
+        pub name: String,
+        pub price: f64,
+        pub in_stock: bool,
+    }
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("registry_prices")]
+    struct Price {
+        pub id: Option<i64>,
+        #[orm_column(type = "decimal(10, 2) NOT NULL")]
+        pub amount: f64,
+        #[orm_column(type = "unsigned big int")]
+        pub units: i64,
+    }
+
+    #[test]
+    fn test_declared_types_keep_their_arguments() {
+        let meta = crate::find_model("registry_prices").unwrap();
+        assert_eq!(meta.column("amount").unwrap().sql_type, "DECIMAL(10, 2)");
+        assert!(meta.column("amount").unwrap().not_null);
+        assert_eq!(meta.column("units").unwrap().sql_type, "UNSIGNED BIG INT");
+        assert_eq!(Price::columns().len(), 3);
+    }
+
+    #[test]
+    fn test_derived_models_are_registered() {
+        let meta = crate::find_model("registry_widgets").expect("model should be registered");
+        assert_eq!(meta.columns.len(), 4);
+        assert!(crate::registry()
+            .iter()
+            .any(|m| m.table_name == "registry_widgets"));
+
+        let name = meta.column("name").unwrap();
+        assert_eq!(name.sql_type, "TEXT");
+        assert!(name.unique && name.not_null && !name.nullable);
+
+        let id = meta.column("id").unwrap();
+        assert!(id.primary_key && id.nullable);
+        assert_eq!(meta.column("price").unwrap().kind, ColumnKind::Real);
+        assert_eq!(meta.column("in_stock").unwrap().kind, ColumnKind::Boolean);
+        assert_eq!(Widget::column_meta().len(), 4);
+        assert!((meta.migration_sql)().contains("name TEXT NOT NULL UNIQUE"));
+    }
+
+    #[test]
+    fn test_schema_export_formats() {
+        use crate::schema::{export_models, Format};
+
+        let meta = crate::find_model("registry_widgets").unwrap();
+
+        let dbml = export_models(&[meta], Format::Dbml);
+        assert!(dbml.starts_with("Table registry_widgets {"));
+        assert!(dbml.contains("  name text [not null, unique]"));
+
+        let mermaid = export_models(&[meta], Format::MermaidErd);
+        assert!(mermaid.starts_with("erDiagram\n    registry_widgets {"));
+        assert!(mermaid.contains("        TEXT name UK"));
+        let prices = crate::find_model("registry_prices").unwrap();
+        let mermaid = export_models(&[prices], Format::MermaidErd);
+        assert!(mermaid.contains("        DECIMAL amount\n"), "{mermaid}");
+        assert!(
+            mermaid.contains("        UNSIGNED_BIG_INT units\n"),
+            "{mermaid}"
+        );
+
+        let sql = export_models(&[meta], Format::Sql);
+        assert!(sql.starts_with("CREATE TABLE IF NOT EXISTS registry_widgets"));
+    }
+
+    #[tokio::test]
+    async fn test_pluck_single_column() {
+        let meta = crate::find_model("registry_widgets").unwrap();
+        let db = super::memory_db(&(meta.migration_sql)()).await;
+        db.inner
+            .execute_batch(
+                "INSERT INTO registry_widgets (name, price, in_stock) VALUES ('bolt', 1, 1);
+                 INSERT INTO registry_widgets (name, price, in_stock) VALUES ('nut', 0.5, 0);",
+            )
+            .await
+            .unwrap();
+
+        let names: Vec<String> = Widget::pluck("name", None, &db).await.unwrap();
+        assert_eq!(names, vec!["bolt", "nut"]);
+        let prices: Vec<f64> = Widget::pluck("price", None, &db).await.unwrap();
+        assert_eq!(prices, vec![1.0, 0.5]);
+
+        let in_stock = crate::FilterOperator::Single(crate::Filter::eq("in_stock", true));
+        let flags: Vec<bool> = Widget::pluck("in_stock", Some(in_stock), &db)
+            .await
+            .unwrap();
+        assert_eq!(flags, vec![true]);
+        assert!(Widget::pluck::<String>("missing", None, &db).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_distinct_column_values() {
+        let meta = crate::find_model("registry_widgets").unwrap();
+        let db = super::memory_db(&(meta.migration_sql)()).await;
+        db.inner
+            .execute_batch(
+                "INSERT INTO registry_widgets (name, price, in_stock) VALUES ('washer', 2, 1);
+                 INSERT INTO registry_widgets (name, price, in_stock) VALUES ('bolt', 1, 1);
+                 INSERT INTO registry_widgets (name, price, in_stock) VALUES ('nut', 2, 0);",
+            )
+            .await
+            .unwrap();
+
+        let prices: Vec<f64> = Widget::distinct("price", &db).await.unwrap();
+        assert_eq!(prices, vec![1.0, 2.0]);
+        let flags: Vec<bool> = Widget::distinct("in_stock", &db).await.unwrap();
+        assert_eq!(flags, vec![false, true]);
+        assert!(Widget::distinct::<String>("missing", &db).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_group_by() {
+        use crate::{Aggregate, Filter, FilterOperator, Value};
+
+        let meta = crate::find_model("registry_widgets").unwrap();
+        let db = super::memory_db(&(meta.migration_sql)()).await;
+        db.inner
+            .execute_batch(
+                "INSERT INTO registry_widgets (name, price, in_stock) VALUES ('washer', 2, 1);
+                 INSERT INTO registry_widgets (name, price, in_stock) VALUES ('bolt', 1.5, 1);
+                 INSERT INTO registry_widgets (name, price, in_stock) VALUES ('nut', 4, 0);",
+            )
+            .await
+            .unwrap();
+
+        let totals = Widget::aggregate_group_by(Aggregate::Sum, "price", "in_stock", None, &db)
+            .await
+            .unwrap();
+        assert_eq!(
+            totals,
+            vec![
+                (Value::Integer(0), Some(4.0)),
+                (Value::Integer(1), Some(3.5))
+            ]
+        );
+
+        let cheap = FilterOperator::Single(Filter::lt("price", 3));
+        let counts =
+            Widget::aggregate_group_by(Aggregate::Count, "*", "in_stock", Some(cheap), &db)
+                .await
+                .unwrap();
+        assert_eq!(counts, vec![(Value::Integer(1), Some(2.0))]);
+    }
+
+    #[tokio::test]
+    async fn test_grouped_pagination() {
+        use crate::{Aggregate, Pagination, QueryBuilder, Sort, Value};
+
+        let meta = crate::find_model("registry_widgets").unwrap();
+        let db = super::memory_db(&(meta.migration_sql)()).await;
+        db.inner
+            .execute_batch(
+                "INSERT INTO registry_widgets (name, price, in_stock) VALUES
+                    ('a', 1, 1), ('b', 5, 1), ('c', 3, 0), ('d', 4, 1), ('e', 2, 0), ('f', 4, 0);",
+            )
+            .await
+            .unwrap();
+
+        let page = Widget::aggregate_group_by_paginated(
+            Aggregate::Sum,
+            "price",
+            "name",
+            None,
+            Sort::desc("value"),
+            &Pagination::new(2, 2),
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(page.pagination.total, Some(6));
+        assert_eq!(page.pagination.total_pages, Some(3));
+        assert_eq!(
+            page.data,
+            vec![
+                (Value::Text("f".to_string()), Some(4.0)),
+                (Value::Text("c".to_string()), Some(3.0))
+            ]
+        );
+
+        #[derive(serde::Deserialize)]
+        struct StockTotal {
+            in_stock: i64,
+            total: f64,
+        }
+        let grouped = QueryBuilder::new("registry_widgets")
+            .aggregate(Aggregate::Sum, "price", Some("total"))
+            .group_by(vec!["in_stock"])
+            .order_by(Sort::desc("total"));
+        assert_eq!(grouped.execute_count(&db).await.unwrap(), 2);
+        let page = grouped
+            .execute_paginated::<StockTotal>(&db, &Pagination::new(1, 1))
+            .await
+            .unwrap();
+        assert_eq!(page.pagination.total, Some(2));
+        assert_eq!((page.data[0].in_stock, page.data[0].total), (1, 10.0));
+    }
+
+    #[tokio::test]
+    async fn test_unique_columns_get_finders() {
+        let meta = crate::find_model("registry_widgets").unwrap();
+        let db = super::memory_db(&(meta.migration_sql)()).await;
+        db.inner
+            .execute(
+                "INSERT INTO registry_widgets (name, price, in_stock) VALUES ('bolt', 0.25, 1)",
+                (),
+            )
+            .await
+            .unwrap();
+
+        let bolt = Widget::find_by_name("bolt", &db).await.unwrap().unwrap();
+        assert!(bolt.in_stock);
+        assert!(Widget::find_by_name("nut", &db).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_consistency_check_reports_drift() {
+        use crate::schema::{check_models, DriftFinding};
+
+        let meta = crate::find_model("registry_widgets").unwrap();
+        let db = super::memory_db(&(meta.migration_sql)()).await;
+        assert!(check_models(&db, &[meta]).await.unwrap().is_empty());
+
+        let db = super::memory_db(
+            "CREATE TABLE registry_widgets (id TEXT, name TEXT NOT NULL, price TEXT)",
+        )
+        .await;
+        let drift = check_models(&db, &[meta]).await.unwrap();
+        let findings = &drift[0].findings;
+        assert!(findings.contains(&DriftFinding::MissingIndex {
+            column: "name".to_string()
+        }));
+        assert!(findings.contains(&DriftFinding::MissingColumn {
+            column: "in_stock".to_string()
+        }));
+        assert!(findings
+            .iter()
+            .any(|f| matches!(f, DriftFinding::TypeMismatch { column, .. } if column == "price")));
+
+        let db = super::memory_db("").await;
+        let drift = check_models(&db, &[meta]).await.unwrap();
+        assert_eq!(drift[0].findings, vec![DriftFinding::MissingTable]);
+    }
+}
+
+#[cfg(test)]
+mod transaction_tests {
+    use crate::{Database, Error, RequestContext};
+
+    async fn memory_db() -> Database {
+        super::memory_db("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)").await
+    }
+
+    async fn count(db: &Database) -> i64 {
+        let mut rows = db
+            .query("SELECT COUNT(*) FROM items", vec![])
+            .await
+            .unwrap();
+        rows.next().await.unwrap().unwrap().get(0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_transaction_commits_and_rolls_back() {
+        let db = memory_db().await;
+
+        db.transaction(|tx| async move {
+            tx.inner
+                .execute("INSERT INTO items (name) VALUES ('a')", ())
+                .await?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+        assert_eq!(count(&db).await, 1);
+
+        let result: crate::Result<()> = db
+            .transaction(|tx| async move {
+                tx.inner
+                    .execute("INSERT INTO items (name) VALUES ('b')", ())
+                    .await?;
+                Err(Error::Validation("abort".to_string()))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(count(&db).await, 1);
+        assert!(!db.in_transaction());
+    }
+
+    #[tokio::test]
+    async fn test_nested_transaction_rolls_back_to_savepoint() {
+        let db = memory_db().await;
+
+        db.transaction(|tx| async move {
+            tx.inner
+                .execute("INSERT INTO items (name) VALUES ('outer')", ())
+                .await?;
+            let inner: crate::Result<()> = tx
+                .transaction(|tx| async move {
+                    tx.inner
+                        .execute("INSERT INTO items (name) VALUES ('inner')", ())
+                        .await?;
+                    Err(Error::Validation("abort inner".to_string()))
+                })
+                .await;
+            assert!(inner.is_err());
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(count(&db).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_transactions_take_turns() {
+        let db = memory_db().await;
+        let first = db.with_context(RequestContext::new().request("first"));
+        let second = db.with_context(RequestContext::new().request("second"));
+
+        let failed = first.transaction(|tx| async {
+            tx.execute("INSERT INTO items (name) VALUES ('first')", vec![])
+                .await?;
+            // Let the second request try to start its transaction
+            tokio::task::yield_now().await;
+            assert!(!second.in_transaction());
+            Err::<(), _>(Error::Validation("abort".to_string()))
+        });
+        let committed = second.transaction(|tx| async {
+            tx.execute("INSERT INTO items (name) VALUES ('second')", vec![])
+                .await?;
+            Ok(())
+        });
+        let (failed, committed) = futures::join!(failed, committed);
+        assert!(failed.is_err());
+        committed.unwrap();
+
+        let names: Vec<serde_json::Value> =
+            db.query_as("SELECT name FROM items", vec![]).await.unwrap();
+        assert_eq!(names, [serde_json::json!({"name": "second"})]);
+        assert!(!db.in_transaction());
+    }
+}
+
+#[cfg(test)]
+mod upsert_tests {
+    use crate::{Database, Model};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("accounts")]
+    struct Account {
+        pub id: Option<i64>,
+        pub email: String,
+        pub plan: String,
+    }
+
+    async fn memory_db() -> Database {
+        super::memory_db(
+            "CREATE TABLE accounts (id INTEGER PRIMARY KEY, email TEXT UNIQUE, plan TEXT)",
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_upsert_inserts_then_updates_on_conflict() {
+        let db = memory_db().await;
+        let account = Account {
+            id: None,
+            email: "a@example.com".to_string(),
+            plan: "free".to_string(),
+        };
+
+        let inserted = account.upsert(&["email"], &db).await.unwrap();
+        assert!(inserted.id.is_some());
+
+        let changed = Account {
+            plan: "pro".to_string(),
+            ..account
+        };
+        let updated = changed.upsert(&["email"], &db).await.unwrap();
+        assert_eq!(updated.id, inserted.id);
+        assert_eq!(updated.plan, "pro");
+        assert_eq!(Account::count(&db).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_save_inserts_then_updates() {
+        let db = memory_db().await;
+        let account = Account {
+            id: None,
+            email: "c@example.com".to_string(),
+            plan: "free".to_string(),
+        };
+        account.save(&db).await.unwrap();
+
+        let mut stored = Account::find_all(&db).await.unwrap().remove(0);
+        stored.plan = "pro".to_string();
+        stored.save(&db).await.unwrap();
+
+        let reloaded = Account::find_by_id(stored.id.unwrap(), &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(reloaded.plan, "pro");
+        assert_eq!(Account::count(&db).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_update_fields_only_writes_listed_columns() {
+        let db = memory_db().await;
+        db.inner
+            .execute(
+                "INSERT INTO accounts (id, email, plan) VALUES (1, 'd@example.com', 'free')",
+                (),
+            )
+            .await
+            .unwrap();
+
+        let stale = Account {
+            id: Some(1),
+            email: "stale@example.com".to_string(),
+            plan: "pro".to_string(),
+        };
+        stale.update_fields(&["plan"], &db).await.unwrap();
+
+        let stored = Account::find_by_id(1, &db).await.unwrap().unwrap();
+        assert_eq!(stored.plan, "pro");
+        assert_eq!(stored.email, "d@example.com");
+        assert!(stale.update_fields(&["missing"], &db).await.is_err());
+        assert!(stale.update_fields(&["id"], &db).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_update_where_returns_affected_rows() {
+        let db = memory_db().await;
+        db.inner
+            .execute_batch(
+                "INSERT INTO accounts (email, plan) VALUES ('e1@example.com', 'trial');
+                 INSERT INTO accounts (email, plan) VALUES ('e2@example.com', 'trial');
+                 INSERT INTO accounts (email, plan) VALUES ('e3@example.com', 'pro');",
+            )
+            .await
+            .unwrap();
+
+        let mut sets = std::collections::HashMap::new();
+        sets.insert("plan".to_string(), crate::Value::Text("free".to_string()));
+        let filter = crate::FilterOperator::Single(crate::Filter::eq("plan", "trial"));
+        let affected = Account::update_where(filter, sets.clone(), &db)
+            .await
+            .unwrap();
+        assert_eq!(affected, 2);
+
+        let free = crate::FilterOperator::Single(crate::Filter::eq("plan", "free"));
+        assert_eq!(Account::count_where(free, &db).await.unwrap(), 2);
+
+        sets.insert("unknown".to_string(), crate::Value::Null);
+        let all = crate::FilterOperator::Single(crate::Filter::is_not_null("id"));
+        assert!(Account::update_where(all, sets, &db).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_delete_where_returns_affected_rows() {
+        let db = memory_db().await;
+        db.inner
+            .execute_batch(
+                "INSERT INTO accounts (email, plan) VALUES ('f1@example.com', 'expired');
+                 INSERT INTO accounts (email, plan) VALUES ('f2@example.com', 'expired');
+                 INSERT INTO accounts (email, plan) VALUES ('f3@example.com', 'pro');",
+            )
+            .await
+            .unwrap();
+
+        let expired = crate::FilterOperator::Single(crate::Filter::eq("plan", "expired"));
+        assert_eq!(
+            Account::delete_where(expired.clone(), &db).await.unwrap(),
+            2
+        );
+        assert_eq!(Account::delete_where(expired, &db).await.unwrap(), 0);
+        assert_eq!(Account::count(&db).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_exists() {
+        let db = memory_db().await;
+        let pro = crate::FilterOperator::Single(crate::Filter::eq("plan", "pro"));
+        assert!(!Account::exists(pro.clone(), &db).await.unwrap());
+
+        db.inner
+            .execute(
+                "INSERT INTO accounts (email, plan) VALUES ('g@example.com', 'pro')",
+                (),
+            )
+            .await
+            .unwrap();
+        assert!(Account::exists(pro, &db).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_find_first_applies_order() {
+        let db = memory_db().await;
+        db.inner
+            .execute_batch(
+                "INSERT INTO accounts (email, plan) VALUES ('h1@example.com', 'pro');
+                 INSERT INTO accounts (email, plan) VALUES ('h2@example.com', 'pro');
+                 INSERT INTO accounts (email, plan) VALUES ('h3@example.com', 'free');",
+            )
+            .await
+            .unwrap();
+
+        let pro = crate::FilterOperator::Single(crate::Filter::eq("plan", "pro"));
+        let newest = Account::find_first(pro.clone(), vec![crate::Sort::desc("id")], &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(newest.email, "h2@example.com");
+
+        let oldest = Account::find_first(pro, vec![crate::Sort::asc("id")], &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(oldest.email, "h1@example.com");
+    }
+
+    #[tokio::test]
+    async fn test_update_or_create() {
+        let db = memory_db().await;
+        let filter = crate::FilterOperator::Single(crate::Filter::eq("email", "b@example.com"));
+        let mut changes = std::collections::HashMap::new();
+        changes.insert("plan".to_string(), crate::Value::Text("free".to_string()));
+
+        let created = Account::update_or_create(filter.clone(), changes.clone(), &db)
+            .await
+            .unwrap();
+        assert_eq!(created.email, "b@example.com");
+        assert_eq!(created.plan, "free");
+
+        changes.insert("plan".to_string(), crate::Value::Text("team".to_string()));
+        let updated = Account::update_or_create(filter, changes, &db)
+            .await
+            .unwrap();
+        assert_eq!(updated.plan, "team");
+        assert_eq!(Account::count(&db).await.unwrap(), 1);
+    }
+}
+
+#[cfg(test)]
+mod coercion_tests {
+    use crate::coercion::{DateTimeFormat, DateTimeParsing};
+    use crate::{Model, Value};
+    use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("events")]
+    struct Event {
+        pub id: Option<i64>,
+        pub happened_at: DateTime<Utc>,
+        pub local_time: NaiveDateTime,
+        pub day: Option<NaiveDate>,
+    }
+
+    #[tokio::test]
+    async fn test_mixed_datetime_formats_load() {
+        let db = super::memory_db(
+            "CREATE TABLE events (id INTEGER PRIMARY KEY, happened_at TEXT, local_time TEXT, day TEXT);
+             INSERT INTO events VALUES (1, '2024-01-31T12:00:00+02:00', '2024-01-31 10:00:00', '2024-01-31');
+             INSERT INTO events VALUES (2, '2024-01-31 10:00:00', '2024-01-31T10:00:00.5', NULL);
+             INSERT INTO events VALUES (3, 1706695200, '1706695200', '2024-01-31 23:59:59');",
+        )
+        .await;
+
+        let events = Event::find_all(&db).await.unwrap();
+        assert_eq!(events.len(), 3);
+        let expected: DateTime<Utc> = "2024-01-31T10:00:00Z".parse().unwrap();
+        assert!(events.iter().all(|e| e.happened_at == expected));
+        assert_eq!(events[0].local_time, expected.naive_utc());
+        assert_eq!(events[1].day, None);
+        assert_eq!(events[2].day, NaiveDate::from_ymd_opt(2024, 1, 31));
+    }
+
+    #[test]
+    fn test_strict_parse_chain() {
+        let parsing = DateTimeParsing::new()
+            .formats(vec![
+                DateTimeFormat::Custom("%d/%m/%Y %H:%M".to_string()),
+                DateTimeFormat::EpochMillis,
+            ])
+            .strict(true);
+        assert!(parsing.is_strict());
+
+        let custom = parsing
+            .parse(&Value::Text("31/01/2024 10:00".to_string()))
+            .unwrap();
+        let millis = parsing.parse(&Value::Integer(1_706_695_200_000)).unwrap();
+        assert_eq!(custom, millis);
+        assert!(parsing
+            .parse(&Value::Text("2024-01-31T10:00:00Z".to_string()))
+            .is_none());
+    }
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("measurements")]
+    struct Measurement {
+        pub id: Option<i64>,
+        pub count: i32,
+        pub ratio: f64,
+    }
+
+    #[tokio::test]
+    async fn test_lossless_numeric_coercion() {
+        let db = super::memory_db(
+            "CREATE TABLE measurements (id INTEGER PRIMARY KEY, count REAL, ratio INTEGER);
+             INSERT INTO measurements VALUES (1, 3.0, 2);",
+        )
+        .await;
+        let stored = Measurement::find_by_id(1, &db).await.unwrap().unwrap();
+        assert_eq!(stored.count, 3);
+        assert_eq!(stored.ratio, 2.0);
+
+        db.inner
+            .execute("UPDATE measurements SET count = 3.5", ())
+            .await
+            .unwrap();
+        assert!(Measurement::find_by_id(1, &db).await.is_err());
+    }
+
+    #[test]
+    fn test_strict_numeric_coercion() {
+        use crate::coercion::NumericCoercion;
+        use crate::ColumnKind;
+
+        let lossless = NumericCoercion::Lossless;
+        assert_eq!(
+            lossless
+                .coerce("n", Value::Real(4.0), ColumnKind::Integer)
+                .unwrap(),
+            Value::Integer(4)
+        );
+        assert!(lossless
+            .coerce("n", Value::Integer(1 << 60), ColumnKind::Real)
+            .is_err());
+
+        let strict = NumericCoercion::Strict;
+        assert!(strict
+            .coerce("n", Value::Real(4.0), ColumnKind::Integer)
+            .is_err());
+        assert_eq!(
+            strict
+                .coerce("n", Value::Integer(4), ColumnKind::Integer)
+                .unwrap(),
+            Value::Integer(4)
+        );
+    }
+}
+
+#[cfg(test)]
+mod lazy_tests {
+    use crate::Model;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("posts")]
+    struct Post {
+        pub id: Option<i64>,
+        pub title: String,
+        #[orm_column(lazy)]
+        pub content: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn test_lazy_column_is_loaded_on_demand() {
+        let db = super::memory_db(
+            "CREATE TABLE posts (id INTEGER PRIMARY KEY, title TEXT, content TEXT);
+             INSERT INTO posts VALUES (1, 'Hello', 'A very long body');",
+        )
+        .await;
+
+        assert!(Post::column_meta()[2].lazy);
+        let (sql, _) = Post::query_builder().build().unwrap();
+        assert_eq!(sql, "SELECT id, title FROM posts");
+
+        let mut post = Post::find_by_id(1, &db).await.unwrap().unwrap();
+        assert_eq!(post.content, None);
+
+        post.title = "Renamed".to_string();
+        post.update(&db).await.unwrap();
+
+        let body = post.load_content(&db).await.unwrap();
+        assert_eq!(body.map(String::as_str), Some("A very long body"));
+        assert_eq!(post.title, "Renamed");
+    }
+}
+
+#[cfg(test)]
+mod projection_tests {
+    use crate::{Filter, FilterOperator, Model, Projection};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("articles")]
+    #[orm(projection(name = "Summary", fields(id, title, published)))]
+    struct Article {
+        pub id: Option<i64>,
+        pub title: String,
+        pub body: String,
+        pub published: bool,
+    }
+
+    #[tokio::test]
+    async fn test_find_as_projection() {
+        let db = super::memory_db(
+            "CREATE TABLE articles (id INTEGER PRIMARY KEY, title TEXT, body TEXT, published BOOLEAN);
+             INSERT INTO articles VALUES (1, 'First', 'Long body', 1);
+             INSERT INTO articles VALUES (2, 'Draft', 'Other body', 0);",
+        )
+        .await;
+
+        assert_eq!(ArticleSummary::columns(), vec!["id", "title", "published"]);
+
+        let all = Article::find_all_as::<ArticleSummary>(&db).await.unwrap();
+        assert_eq!(all.len(), 2);
+
+        let published = FilterOperator::Single(Filter::eq("published", true));
+        let found = Article::find_where_as::<ArticleSummary>(published.clone(), &db)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].title, "First");
+        assert!(found[0].published);
+
+        let direct = ArticleSummary::find_where(published, &db).await.unwrap();
+        assert_eq!(direct[0].id, Some(1));
+    }
+}
+
+#[cfg(test)]
+mod increment_tests {
+    use crate::Model;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("products")]
+    struct Product {
+        pub id: Option<i64>,
+        pub stock: i64,
+    }
+
+    #[tokio::test]
+    async fn test_increment_and_decrement() {
+        let db = super::memory_db(
+            "CREATE TABLE products (id INTEGER PRIMARY KEY, stock INTEGER);
+             INSERT INTO products VALUES (1, 10);
+             INSERT INTO products VALUES (2, 10);",
+        )
+        .await;
+        let first = crate::FilterOperator::Single(crate::Filter::eq("id", 1i64));
+
+        let affected = Product::increment("stock", 5, first.clone(), &db)
+            .await
+            .unwrap();
+        assert_eq!(affected, 1);
+        Product::decrement("stock", 1, first, &db).await.unwrap();
+
+        let stored = Product::find_by_id(1, &db).await.unwrap().unwrap();
+        assert_eq!(stored.stock, 14);
+        let other = Product::find_by_id(2, &db).await.unwrap().unwrap();
+        assert_eq!(other.stock, 10);
+
+        let all = crate::FilterOperator::Single(crate::Filter::is_not_null("id"));
+        assert!(Product::increment("missing", 1, all, &db).await.is_err());
+    }
+}
+
+#[cfg(test)]
+mod pagination_tests {
+    use crate::{Filter, FilterOperator, Model, Pagination};
+    use serde::{Deserialize, Serialize};
+    use std::time::Duration;
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("tasks")]
+    struct Task {
+        pub id: Option<i64>,
+        pub done: bool,
+    }
+
+    #[tokio::test]
+    async fn test_paginated_count_respects_filter() {
+        let db = super::memory_db(
+            "CREATE TABLE tasks (id INTEGER PRIMARY KEY, done BOOLEAN);
+             INSERT INTO tasks (done) VALUES (1), (1), (1), (0), (0);",
+        )
+        .await;
+
+        let done = FilterOperator::Single(Filter::eq("done", true));
+        let pagination = Pagination::new(1, 2).with_count_timeout(Duration::from_secs(5));
+        let page = Task::find_where_paginated(done, &pagination, &db)
+            .await
+            .unwrap();
+        assert_eq!(page.data.len(), 2);
+        assert_eq!(page.pagination.total, Some(3));
+        assert_eq!(page.pagination.total_pages, Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_gives_up_on_slow_futures() {
+        let slow =
+            crate::runtime::timeout(Duration::from_millis(10), futures::future::pending::<u64>());
+        assert_eq!(slow.await, None);
+
+        let fast = crate::runtime::timeout(Duration::from_secs(5), async { 7 });
+        assert_eq!(fast.await, Some(7));
+    }
+}
+
+#[cfg(test)]
+mod unit_of_work_tests {
+    use crate::{Model, UnitOfWork};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("notes")]
+    struct Note {
+        pub id: Option<i64>,
+        pub text: String,
+    }
+
+    fn note(id: i64, text: &str) -> Note {
+        Note {
+            id: Some(id),
+            text: text.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_flush_dedups_by_identity() {
+        let db = super::memory_db(
+            "CREATE TABLE notes (id INTEGER PRIMARY KEY, text TEXT);
+             INSERT INTO notes VALUES (1, 'one'), (2, 'two');",
+        )
+        .await;
+
+        let mut uow = UnitOfWork::new();
+        uow.register_dirty(&note(1, "first edit")).unwrap();
+        uow.register_dirty(&note(1, "second edit")).unwrap();
+        uow.register_new(&note(3, "draft")).unwrap();
+        uow.register_dirty(&note(3, "final draft")).unwrap();
+        uow.register_new(&note(4, "scratch")).unwrap();
+        uow.register_deleted(&note(4, "scratch")).unwrap();
+        uow.register_deleted(&note(2, "two")).unwrap();
+        assert_eq!(uow.len(), 3);
+
+        assert_eq!(uow.flush(&db).await.unwrap(), 3);
+        assert!(uow.is_empty());
+
+        let notes = Note::find_all(&db).await.unwrap();
+        let texts: Vec<_> = notes.iter().map(|n| n.text.as_str()).collect();
+        assert_eq!(texts, vec!["second edit", "final draft"]);
+    }
+}
+
+#[cfg(test)]
+mod scope_tests {
+    use crate::Model;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("tags")]
+    struct Tag {
+        pub id: Option<i64>,
+        pub label: String,
+    }
+
+    #[tokio::test]
+    async fn test_scope_reuses_loaded_records() {
+        let db = super::memory_db(
+            "CREATE TABLE tags (id INTEGER PRIMARY KEY, label TEXT);
+             INSERT INTO tags VALUES (1, 'rust');",
+        )
+        .await;
+        let scope = db.scoped();
+
+        let first = scope.find_by_id::<Tag>(1).await.unwrap().unwrap();
+        assert!(scope.find_by_id::<Tag>(2).await.unwrap().is_none());
+        assert_eq!(scope.cached(), 2);
+
+        db.inner
+            .execute("UPDATE tags SET label = 'changed' WHERE id = 1", ())
+            .await
+            .unwrap();
+        let cached = scope.find_by_id::<Tag>(1).await.unwrap().unwrap();
+        assert_eq!(cached.label, first.label);
+
+        scope.forget::<Tag>(1);
+        let fresh = scope.find_by_id::<Tag>(1).await.unwrap().unwrap();
+        assert_eq!(fresh.label, "changed");
+        assert_eq!(Tag::count(&scope).await.unwrap(), 1);
+    }
+}
+
+#[cfg(test)]
+mod loader_tests {
+    use crate::{BatchLoader, Model};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("authors")]
+    struct Author {
+        pub id: Option<i64>,
+        pub name: String,
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_loads_are_batched() {
+        let db = super::memory_db(
+            "CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT);
+             INSERT INTO authors VALUES (1, 'Ann'), (2, 'Bob'), (3, 'Cid');",
+        )
+        .await;
+        let loader = BatchLoader::<Author>::new(&db);
+
+        let loaded = loader.load_many(&[3, 1, 9, 1]).await.unwrap();
+        let names: Vec<_> = loaded
+            .iter()
+            .map(|a| a.as_ref().map(|a| a.name.as_str()))
+            .collect();
+        assert_eq!(names, vec![Some("Cid"), Some("Ann"), None, Some("Ann")]);
+
+        // Served from the loader even after the row changes
+        db.inner
+            .execute("UPDATE authors SET name = 'Changed' WHERE id = 1", ())
+            .await
+            .unwrap();
+        assert_eq!(loader.load(1).await.unwrap().unwrap().name, "Ann");
+    }
+}
+
+#[cfg(test)]
+mod prefetch_tests {
+    use crate::Model;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("prefetch_users")]
+    struct User {
+        pub id: Option<i64>,
+        pub name: String,
+        pub active: bool,
+    }
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("prefetch_files")]
+    struct File {
+        pub id: Option<i64>,
+        pub size: f64,
+        pub data: Vec<u8>,
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_loads_several_models() {
+        let db = super::memory_db(
+            "CREATE TABLE prefetch_users (id INTEGER PRIMARY KEY, name TEXT, active BOOLEAN);
+             CREATE TABLE prefetch_files (id INTEGER PRIMARY KEY, size REAL, data BLOB);
+             INSERT INTO prefetch_users VALUES (1, 'Ann', 1), (2, 'Bob', 0), (3, 'Cid', 1);
+             INSERT INTO prefetch_files VALUES (10, 2.0, x'00ff10');",
+        )
+        .await;
+
+        let mut loaded = db
+            .prefetch()
+            .ids::<User>(&[1, 2])
+            .ids::<File>(&[10, 11])
+            .ids::<User>(&[2])
+            .load()
+            .await
+            .unwrap();
+
+        let users = loaded.get::<User>().unwrap();
+        assert_eq!(users.len(), 2);
+        assert!(users[&1].active && !users[&2].active);
+
+        let files = loaded.take::<File>();
+        assert_eq!(files[&10].data, vec![0x00, 0xff, 0x10]);
+        assert_eq!(files[&10].size, 2.0);
+    }
+}
+
+#[cfg(test)]
+mod watcher_tests {
+    use crate::{Model, Watcher};
+    use serde::{Deserialize, Serialize};
+    use std::time::Duration;
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("documents")]
+    struct Document {
+        pub id: Option<i64>,
+        pub title: String,
+        pub version: i64,
+    }
+
+    #[tokio::test]
+    async fn test_poll_advances_checkpoint() {
+        let db = super::memory_db(
+            "CREATE TABLE documents (id INTEGER PRIMARY KEY, title TEXT, version INTEGER);
+             INSERT INTO documents VALUES (1, 'a', 1), (2, 'b', 1), (3, 'c', 2);",
+        )
+        .await;
+        let mut watcher = Watcher::<Document>::new("version").batch_size(2);
+
+        let first = watcher.poll(&db).await.unwrap();
+        assert_eq!(
+            first.iter().map(|d| d.id).collect::<Vec<_>>(),
+            [Some(1), Some(2)]
+        );
+        let second = watcher.poll(&db).await.unwrap();
+        assert_eq!(second[0].id, Some(3));
+        assert!(watcher.poll(&db).await.unwrap().is_empty());
+
+        db.inner
+            .execute("UPDATE documents SET version = 3 WHERE id = 1", ())
+            .await
+            .unwrap();
+        let mut seen = Vec::new();
+        watcher
+            .interval(Duration::from_millis(1))
+            .run(&db, |changed| {
+                seen.extend(changed);
+                async { Ok(false) }
+            })
+            .await
+            .unwrap();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].version, 3);
+    }
+}
+
+#[cfg(test)]
+mod sync_tests {
+    use crate::sync::{ChangeSet, ClientWins, Resolution, SyncServer, TableChanges};
+    use crate::{Model, Value};
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("todos")]
+    struct Todo {
+        pub id: Option<i64>,
+        pub title: String,
+        pub sync_version: Option<i64>,
+    }
+
+    fn todo_row(id: i64, title: &str) -> HashMap<String, Value> {
+        HashMap::from([
+            ("id".to_string(), Value::Integer(id)),
+            ("title".to_string(), Value::Text(title.to_string())),
+        ])
+    }
+
+    fn push_of(base: i64, changes: TableChanges) -> ChangeSet {
+        ChangeSet {
+            versions: HashMap::from([("todos".to_string(), base)]),
+            tables: HashMap::from([("todos".to_string(), changes)]),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pull_and_push_changes() {
+        let db = super::memory_db(
+            "CREATE TABLE todos (id INTEGER PRIMARY KEY, title TEXT, sync_version INTEGER)",
+        )
+        .await;
+        let server = SyncServer::new().table::<Todo>();
+        server.setup(&db).await.unwrap();
+
+        db.inner
+            .execute_batch(
+                "INSERT INTO todos (id, title) VALUES (1, 'milk'), (2, 'eggs');
+                 DELETE FROM todos WHERE id = 2;",
+            )
+            .await
+            .unwrap();
+        let pulled = server.pull(&db, &HashMap::new()).await.unwrap();
+        assert_eq!(pulled.versions["todos"], 3);
+        let todos = &pulled.tables["todos"];
+        assert_eq!(todos.upserts.len(), 1);
+        assert_eq!(todos.upserts[0]["title"], Value::Text("milk".to_string()));
+        assert_eq!(todos.deletes, vec![2]);
+
+        // A push based on the latest version applies cleanly
+        let changes = TableChanges {
+            upserts: vec![todo_row(1, "oat milk"), todo_row(3, "bread")],
+            deletes: vec![],
+        };
+        let result = server.push(&db, push_of(3, changes)).await.unwrap();
+        assert_eq!(result.applied, 2);
+        assert!(result.conflicts.is_empty());
+        assert_eq!(result.versions["todos"], 5);
+
+        let pulled = server.pull(&db, &result.versions).await.unwrap();
+        assert!(pulled.tables.is_empty());
+
+        // A stale push conflicts, and the server wins by default
+        let stale = TableChanges {
+            upserts: vec![todo_row(1, "soy milk")],
+            deletes: vec![3],
+        };
+        let result = server.push(&db, push_of(3, stale.clone())).await.unwrap();
+        assert_eq!(result.applied, 0);
+        assert_eq!(result.conflicts.len(), 2);
+        assert_eq!(result.conflicts[0].resolution, Resolution::KeepServer);
+        let titles: Vec<String> = Todo::pluck("title", None, &db).await.unwrap();
+        assert_eq!(titles, vec!["oat milk", "bread"]);
+
+        let server = SyncServer::new()
+            .table::<Todo>()
+            .conflict_strategy(ClientWins);
+        let result = server.push(&db, push_of(3, stale)).await.unwrap();
+        assert_eq!(result.applied, 2);
+        let titles: Vec<String> = Todo::pluck("title", None, &db).await.unwrap();
+        assert_eq!(titles, vec!["soy milk"]);
+
+        let unknown = TableChanges {
+            upserts: vec![HashMap::from([
+                ("id".to_string(), Value::Integer(9)),
+                ("secret".to_string(), Value::Integer(1)),
+            ])],
+            deletes: vec![],
+        };
+        assert!(server.push(&db, push_of(99, unknown)).await.is_err());
+    }
+}
+
+#[cfg(test)]
+mod lww_tests {
+    use crate::lww::{self, LwwRow};
+    use crate::{Model, Value};
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("profiles")]
+    struct Profile {
+        pub id: Option<i64>,
+        #[orm_column(lww)]
+        pub name: String,
+        #[orm_column(lww)]
+        pub bio: String,
+    }
+
+    fn lww_row(values: &[(&str, &str)], clocks: &[(&str, i64)]) -> LwwRow {
+        LwwRow {
+            values: values
+                .iter()
+                .map(|(k, v)| (k.to_string(), Value::Text(v.to_string())))
+                .collect(),
+            clocks: clocks.iter().map(|(k, c)| (k.to_string(), *c)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_merge_is_order_independent() {
+        let a = lww_row(
+            &[("name", "Ann"), ("bio", "old")],
+            &[("name", 5), ("bio", 1)],
+        );
+        let b = lww_row(
+            &[("name", "Anna"), ("bio", "new")],
+            &[("name", 5), ("bio", 2)],
+        );
+        let merged = lww::merge(&a, &b);
+        assert_eq!(merged, lww::merge(&b, &a));
+        assert_eq!(merged.values["name"], Value::Text("Anna".to_string()));
+        assert_eq!(merged.values["bio"], Value::Text("new".to_string()));
+        assert_eq!(
+            merged.clocks,
+            HashMap::from([("name".to_string(), 5), ("bio".to_string(), 2)])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_into_database() {
+        let db =
+            super::memory_db("CREATE TABLE profiles (id INTEGER PRIMARY KEY, name TEXT, bio TEXT)")
+                .await;
+        lww::setup(&db).await.unwrap();
+        assert_eq!(lww::lww_columns::<Profile>(), vec!["name", "bio"]);
+
+        let remote = lww_row(&[("name", "Bo"), ("bio", "hi")], &[("name", 1), ("bio", 1)]);
+        let created = lww::merge_into::<Profile>(7, &remote, &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(created.name, "Bo");
+
+        // A local edit of the bio wins over an older remote one
+        db.inner
+            .execute("UPDATE profiles SET bio = 'local' WHERE id = 7", ())
+            .await
+            .unwrap();
+        lww::stamp(&created, &["bio"], 10, &db).await.unwrap();
+        let remote = lww_row(
+            &[("name", "Bob"), ("bio", "remote")],
+            &[("name", 3), ("bio", 4)],
+        );
+        let merged = lww::merge_into::<Profile>(7, &remote, &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (merged.name.as_str(), merged.bio.as_str()),
+            ("Bob", "local")
+        );
+        assert_eq!(lww::clocks::<Profile>(7, &db).await.unwrap()["name"], 3);
+
+        let bad = lww_row(&[("id", "1")], &[("id", 1)]);
+        assert!(lww::merge_into::<Profile>(7, &bad, &db).await.is_err());
+    }
+}
+
+#[cfg(test)]
+mod metrics_tests {
+    use crate::{metrics, Model};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("metric_gauges")]
+    struct Gauge {
+        pub id: Option<i64>,
+        pub reading: f64,
+    }
+
+    #[tokio::test]
+    async fn test_queries_are_counted_by_table_and_operation() {
+        let db =
+            super::memory_db("CREATE TABLE metric_gauges (id INTEGER PRIMARY KEY, reading REAL)")
+                .await;
+        Gauge::find_all(&db).await.unwrap();
+        Gauge::find_all(&db).await.unwrap();
+        db.execute(
+            "INSERT INTO metric_gauges (reading) VALUES (?)",
+            vec![libsql::Value::Real(1.5)],
+        )
+        .await
+        .unwrap();
+        assert!(db
+            .execute("UPDATE metric_gauges SET missing = 1", vec![])
+            .await
+            .is_err());
+
+        let snapshot = metrics::snapshot();
+        let selects = snapshot.get("metric_gauges", "select").unwrap();
+        assert_eq!((selects.queries, selects.errors), (2, 0));
+        assert_eq!(selects.latency.count, 2);
+        assert_eq!(snapshot.get("metric_gauges", "insert").unwrap().queries, 1);
+        assert_eq!(snapshot.get("metric_gauges", "update").unwrap().errors, 1);
+
+        let text = snapshot.to_prometheus();
+        assert!(text.contains("# TYPE libsql_orm_queries_total counter"));
+        assert!(text
+            .contains("libsql_orm_queries_total{table=\"metric_gauges\",operation=\"select\"} 2"));
+        assert!(text.contains(
+            "libsql_orm_query_duration_seconds_bucket{table=\"metric_gauges\",operation=\"select\",le=\"+Inf\"} 2"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_internal_statements_are_counted() {
+        let db = super::memory_db(
+            "CREATE TABLE metric_loads (id INTEGER PRIMARY KEY, reading REAL);
+             INSERT INTO metric_loads (id, reading) VALUES (1, 2.5);",
+        )
+        .await;
+        #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+        #[table_name("metric_loads")]
+        struct Load {
+            pub id: Option<i64>,
+            pub reading: f64,
+        }
+        let load = Load {
+            id: Some(1),
+            reading: 0.0,
+        };
+        assert_eq!(load.load_column("reading", &db).await.unwrap().reading, 2.5);
+
+        let snapshot = metrics::snapshot();
+        assert_eq!(snapshot.get("metric_loads", "select").unwrap().queries, 1);
+    }
+}
+
+#[cfg(test)]
+mod tagging_tests {
+    use crate::{Model, QueryBuilder};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("tagged_posts")]
+    struct TaggedPost {
+        pub id: Option<i64>,
+        pub title: String,
+    }
+
+    #[test]
+    fn test_query_builder_tag() {
+        let (sql, _) = QueryBuilder::new("posts")
+            .limit(5)
+            .tag("endpoint:/posts GET")
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT * FROM posts LIMIT 5 /* endpoint:/posts GET */");
+
+        let (sql, _) = QueryBuilder::new("posts")
+            .tag("a*/b")
+            .build_count()
+            .unwrap();
+        assert_eq!(sql, "SELECT COUNT(*) FROM posts /* a* /b */");
+    }
+
+    #[tokio::test]
+    async fn test_tagged_database_runs_statements() {
+        let db = super::memory_db(
+            "CREATE TABLE tagged_posts (id INTEGER PRIMARY KEY, title TEXT);
+             INSERT INTO tagged_posts (title) VALUES ('hello');",
+        )
+        .await;
+        let tagged = db.tagged("job:nightly */ DROP TABLE tagged_posts; --");
+        assert_eq!(
+            tagged.tag(),
+            Some("job:nightly */ DROP TABLE tagged_posts; --")
+        );
+        assert_eq!(db.tag(), None);
+
+        let posts = TaggedPost::find_all(&tagged).await.unwrap();
+        assert_eq!(posts.len(), 1);
+        let paged = TaggedPost::find_paginated(&crate::Pagination::new(1, 10), &tagged)
+            .await
+            .unwrap();
+        assert_eq!(paged.data.len(), 1);
+        assert_eq!(TaggedPost::find_all(&db).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_request_context_is_attached() {
+        use crate::RequestContext;
+
+        let db = super::memory_db(
+            "CREATE TABLE tagged_posts (id INTEGER PRIMARY KEY, title TEXT);
+             INSERT INTO tagged_posts (title) VALUES ('hello');",
+        )
+        .await;
+        let context = RequestContext::new().tenant("acme").request("r-1");
+        assert_eq!(context.to_string(), "tenant=acme request=r-1");
+        assert!(RequestContext::new().is_empty());
+
+        let scoped = db.tagged("endpoint:/posts").with_context(context.clone());
+        assert_eq!(scoped.context(), Some(&context));
+        assert_eq!(scoped.tag(), Some("endpoint:/posts"));
+        assert!(db.context().is_none());
+        assert_eq!(TaggedPost::find_all(&scoped).await.unwrap().len(), 1);
+    }
+}
+
+#[cfg(test)]
+mod subquery_tests {
+    use crate::{Filter, FilterOperator, Model, QueryBuilder};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("blog_posts")]
+    struct BlogPost {
+        pub id: Option<i64>,
+        pub user_id: i64,
+        pub title: String,
+    }
+
+    fn active_authors() -> QueryBuilder {
+        QueryBuilder::new("blog_users")
+            .select(vec!["id"])
+            .r#where(FilterOperator::Single(Filter::eq("active", true)))
+    }
+
+    #[tokio::test]
+    async fn test_in_subquery_filter() {
+        let db = super::memory_db(
+            "CREATE TABLE blog_users (id INTEGER PRIMARY KEY, active INTEGER);
+             CREATE TABLE blog_posts (id INTEGER PRIMARY KEY, user_id INTEGER, title TEXT);
+             INSERT INTO blog_users VALUES (1, 1), (2, 0);
+             INSERT INTO blog_posts VALUES (1, 1, 'kept'), (2, 2, 'hidden'), (3, 1, 'draft');",
+        )
+        .await;
+
+        let filter = FilterOperator::And(vec![
+            FilterOperator::Single(Filter::in_subquery("user_id", active_authors())),
+            FilterOperator::Single(Filter::ne("title", "draft")),
+        ]);
+        let (sql, params) = QueryBuilder::new("blog_posts")
+            .r#where(filter.clone())
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM blog_posts WHERE (user_id IN (SELECT id FROM blog_users WHERE active = ?) AND title != ?)"
+        );
+        assert_eq!(params.len(), 2);
+
+        let posts = BlogPost::find_where(filter, &db).await.unwrap();
+        assert_eq!(
+            posts.iter().map(|p| p.title.as_str()).collect::<Vec<_>>(),
+            ["kept"]
+        );
+
+        let excluded = FilterOperator::Single(Filter::not_in_subquery("user_id", active_authors()));
+        let posts = BlogPost::find_where(excluded, &db).await.unwrap();
+        assert_eq!(posts[0].title, "hidden");
+
+        let posts: Vec<BlogPost> = QueryBuilder::new("blog_posts")
+            .where_in("user_id", active_authors())
+            .execute_models(&db)
+            .await
+            .unwrap();
+        assert_eq!(posts.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_common_table_expressions() {
+        let db = super::memory_db(
+            "CREATE TABLE blog_users (id INTEGER PRIMARY KEY, active INTEGER);
+             CREATE TABLE blog_posts (id INTEGER PRIMARY KEY, user_id INTEGER, title TEXT);
+             INSERT INTO blog_users VALUES (1, 1), (2, 0);
+             INSERT INTO blog_posts VALUES (1, 1, 'a'), (2, 2, 'b'), (3, 1, 'c');",
+        )
+        .await;
+
+        let query = QueryBuilder::new("blog_posts")
+            .with_cte("authors", active_authors())
+            .with_cte(
+                "later",
+                QueryBuilder::new("blog_posts")
+                    .select(vec!["id"])
+                    .r#where(FilterOperator::Single(Filter::gt("id", 1))),
+            )
+            .join(
+                crate::JoinType::Inner,
+                "authors",
+                "authors.id = blog_posts.user_id",
+            )
+            .select(vec!["blog_posts.*"])
+            .r#where(FilterOperator::Custom(
+                "blog_posts.id IN (SELECT id FROM later)".to_string(),
+            ));
+        let (sql, params) = query.build().unwrap();
+        assert!(sql.starts_with(
+            "WITH authors AS (SELECT id FROM blog_users WHERE active = ?), later AS (SELECT id FROM blog_posts WHERE id > ?) SELECT"
+        ));
+        assert_eq!(params.len(), 2);
+
+        let posts: Vec<BlogPost> = query.execute_models(&db).await.unwrap();
+        assert_eq!(
+            posts.iter().map(|p| p.title.as_str()).collect::<Vec<_>>(),
+            ["c"]
+        );
+        assert_eq!(query.execute_count(&db).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_raw_filters_bind_parameters() {
+        let db = super::memory_db(
+            "CREATE TABLE blog_posts (id INTEGER PRIMARY KEY, user_id INTEGER, title TEXT);
+             INSERT INTO blog_posts VALUES (1, 1, '{\"tag\":\"rust\"}'), (2, 2, '{\"tag\":\"go\"}');",
+        )
+        .await;
+        let tagged = Filter::raw("json_extract(title, '$.tag') = ?", vec!["rust"]);
+        let filter = FilterOperator::Or(vec![
+            FilterOperator::Single(tagged),
+            FilterOperator::Single(Filter::raw("user_id > ? AND user_id < ?", vec![5, 9])),
+        ]);
+        let (sql, params) = QueryBuilder::new("blog_posts")
+            .r#where(filter.clone())
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM blog_posts WHERE ((json_extract(title, '$.tag') = ?) OR (user_id > ? AND user_id < ?))"
+        );
+        assert_eq!(params.len(), 3);
+        let posts = BlogPost::find_where(filter, &db).await.unwrap();
+        assert_eq!(posts.iter().map(|p| p.id).collect::<Vec<_>>(), [Some(1)]);
+
+        // Quoted question marks are not placeholders
+        let literal = Filter::raw("title != '?' AND user_id = ?", vec![2]);
+        let posts = BlogPost::find_where(FilterOperator::Single(literal), &db)
+            .await
+            .unwrap();
+        assert_eq!(posts.len(), 1);
+
+        let mismatched = Filter::raw("user_id = ? OR user_id = ?", vec![1]);
+        let result = BlogPost::find_where(FilterOperator::Single(mismatched), &db).await;
+        assert!(matches!(result, Err(crate::Error::Query(_))));
+    }
+
+    #[tokio::test]
+    async fn test_in_list_filters() {
+        use crate::Value;
+
+        let db = super::memory_db(
+            "CREATE TABLE blog_posts (id INTEGER PRIMARY KEY, user_id INTEGER, title TEXT);
+             INSERT INTO blog_posts VALUES (1, 1, 'a'), (2, 2, 'b'), (3, 3, 'c');",
+        )
+        .await;
+        let ids =
+            |posts: Vec<BlogPost>| posts.into_iter().map(|p| p.id.unwrap()).collect::<Vec<_>>();
+
+        let list = vec![
+            Value::Integer(3),
+            Value::Integer(1),
+            Value::Text("x'); --".to_string()),
+        ];
+        let posts = BlogPost::find_where(
+            FilterOperator::Single(Filter::in_list("id", list.clone())),
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(ids(posts), [1, 3]);
+        let posts =
+            BlogPost::find_where(FilterOperator::Single(Filter::not_in_list("id", list)), &db)
+                .await
+                .unwrap();
+        assert_eq!(ids(posts), [2]);
+
+        let none = FilterOperator::Single(Filter::in_list("id", vec![]));
+        assert_eq!(
+            QueryBuilder::new("blog_posts")
+                .r#where(none.clone())
+                .build()
+                .unwrap()
+                .0,
+            "SELECT * FROM blog_posts WHERE 1 = 0"
+        );
+        assert!(BlogPost::find_where(none, &db).await.unwrap().is_empty());
+        let all = FilterOperator::Single(Filter::not_in_list("id", vec![]));
+        assert_eq!(BlogPost::find_where(all, &db).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_compound_queries() {
+        let db = super::memory_db(
+            "CREATE TABLE blog_posts (id INTEGER PRIMARY KEY, user_id INTEGER, title TEXT);
+             INSERT INTO blog_posts VALUES
+                (1, 1, 'mine'), (2, 2, 'hi @1'), (3, 1, 'mine, hi @1'), (4, 3, 'other');",
+        )
+        .await;
+        let authored =
+            || BlogPost::query_builder().r#where(FilterOperator::Single(Filter::eq("user_id", 1)));
+        let mentioned = || {
+            BlogPost::query_builder().r#where(FilterOperator::Single(Filter::like("title", "%@1%")))
+        };
+        let ids =
+            |posts: Vec<BlogPost>| posts.into_iter().map(|p| p.id.unwrap()).collect::<Vec<_>>();
+
+        let feed = authored()
+            .union(mentioned())
+            .order_by(crate::Sort::desc("id"));
+        let posts: Vec<BlogPost> = feed.execute_models(&db).await.unwrap();
+        assert_eq!(ids(posts), [3, 2, 1]);
+        assert_eq!(feed.execute_count(&db).await.unwrap(), 3);
+
+        let all = authored()
+            .union_all(mentioned())
+            .order_by(crate::Sort::asc("id"));
+        assert_eq!(ids(all.execute_models(&db).await.unwrap()), [1, 2, 3, 3]);
+
+        let both = authored().intersect(mentioned());
+        assert_eq!(ids(both.execute_models(&db).await.unwrap()), [3]);
+
+        let only_mine = authored().except(mentioned());
+        assert_eq!(ids(only_mine.execute_models(&db).await.unwrap()), [1]);
+
+        let newest_mention = mentioned().order_by(crate::Sort::desc("id")).limit(1);
+        let (sql, _) = authored().union(newest_mention.clone()).build().unwrap();
+        assert!(sql.contains("UNION SELECT * FROM (SELECT * FROM blog_posts WHERE title LIKE ? ORDER BY id DESC LIMIT 1)"));
+        let posts = authored()
+            .except(newest_mention)
+            .execute_models::<BlogPost>(&db)
+            .await
+            .unwrap();
+        assert_eq!(ids(posts), [1]);
+    }
+}
+
+#[cfg(test)]
+mod compression_tests {
+    use crate::compression;
+    use crate::{Model, Value};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("pages")]
+    struct Page {
+        pub id: Option<i64>,
+        #[orm_column(compressed = "zstd")]
+        pub html: String,
+    }
+
+    #[tokio::test]
+    async fn test_plain_text_is_read_unchanged() {
+        let db = super::memory_db(
+            "CREATE TABLE pages (id INTEGER PRIMARY KEY, html TEXT);
+             INSERT INTO pages VALUES (1, '<p>legacy</p>');",
+        )
+        .await;
+        let page = Page::find_by_id(1, &db).await.unwrap().unwrap();
+        assert_eq!(page.html, "<p>legacy</p>");
+        assert!(Page::column_meta()[1].compressed);
+
+        let short = compression::compress_value("html", Value::Text("hi".to_string()));
+        #[cfg(feature = "compression")]
+        assert_eq!(short.unwrap(), Value::Text("hi".to_string()));
+        #[cfg(not(feature = "compression"))]
+        assert!(short.is_err());
+    }
+
+    #[cfg(feature = "compression")]
+    #[tokio::test]
+    async fn test_compressed_round_trip() {
+        let db = super::memory_db("CREATE TABLE pages (id INTEGER PRIMARY KEY, html TEXT)").await;
+        let html = "<div>hello world</div>".repeat(200);
+        let page = Page {
+            id: Some(1),
+            html: html.clone(),
+        };
+        let (sql, params) = page.insert_statement().unwrap();
+        db.execute(&sql, params).await.unwrap();
+
+        let mut rows = db.query("SELECT html FROM pages", vec![]).await.unwrap();
+        let stored = rows.next().await.unwrap().unwrap().get_value(0).unwrap();
+        let libsql::Value::Blob(bytes) = stored else {
+            panic!("expected a compressed BLOB, got {stored:?}");
+        };
+        assert!(bytes.starts_with(compression::MAGIC));
+        assert!(bytes.len() < html.len() / 10);
+
+        assert_eq!(Page::find_by_id(1, &db).await.unwrap().unwrap().html, html);
+        let plucked: Vec<String> = Page::pluck("html", None, &db).await.unwrap();
+        assert_eq!(plucked, vec![html.clone()]);
+        let prefetched = db.prefetch().ids::<Page>(&[1]).load().await.unwrap();
+        assert_eq!(prefetched.get::<Page>().unwrap()[&1].html, html);
+    }
+}
+
+#[cfg(test)]
+mod content_hash_tests {
+    use crate::{Error, Filter, FilterOperator, Model, Value};
+    use serde::{Deserialize, Serialize};
+    use std::collections::{HashMap, HashSet};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("articles")]
+    struct Article {
+        pub id: Option<i64>,
+        pub title: String,
+        pub content: String,
+        pub views: i64,
+        #[orm_column(content_hash(of("title", "content")))]
+        pub etag: Option<String>,
+    }
+
+    fn article(id: i64, title: &str) -> Article {
+        Article {
+            id: Some(id),
+            title: title.to_string(),
+            content: "body".to_string(),
+            views: 0,
+            etag: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hash_is_maintained_on_writes() {
+        let db = super::memory_db(
+            "CREATE TABLE articles (id INTEGER PRIMARY KEY, title TEXT, content TEXT, views INTEGER, etag TEXT)",
+        )
+        .await;
+        let mut first = article(1, "Hello");
+        let (sql, params) = first.insert_statement().unwrap();
+        db.execute(&sql, params).await.unwrap();
+
+        let stored = Article::find_by_id(1, &db).await.unwrap().unwrap();
+        let hash = stored.etag.clone().unwrap();
+        assert_eq!(hash.len(), 32);
+        assert_eq!(first.content_hash().unwrap(), Some(hash.clone()));
+        assert_eq!(Article::column_meta()[4].hash_of, &["title", "content"]);
+
+        first.views = 10;
+        assert_eq!(first.content_hash().unwrap(), Some(hash.clone()));
+
+        first.title = "Hello again".to_string();
+        first.update_fields(&["title"], &db).await.unwrap();
+        let updated = Article::find_by_id(1, &db).await.unwrap().unwrap();
+        assert_ne!(updated.etag, Some(hash));
+        assert_eq!(updated.etag, first.content_hash().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_find_changed_since() {
+        let db = super::memory_db(
+            "CREATE TABLE articles (id INTEGER PRIMARY KEY, title TEXT, content TEXT, views INTEGER, etag TEXT);
+             INSERT INTO articles VALUES (3, 'legacy', 'body', 0, NULL);",
+        )
+        .await;
+        for record in [article(1, "One"), article(2, "Two")] {
+            let (sql, params) = record.insert_statement().unwrap();
+            db.execute(&sql, params).await.unwrap();
+        }
+
+        let all = Article::find_changed_since(&HashSet::new(), &db)
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 3);
+
+        let known: HashSet<String> = all.iter().filter_map(|a| a.etag.clone()).collect();
+        let changed = Article::find_changed_since(&known, &db).await.unwrap();
+        assert_eq!(
+            changed.iter().map(|a| a.id).collect::<Vec<_>>(),
+            vec![Some(3)]
+        );
+
+        let two = article(2, "Two, edited");
+        two.update_fields(&["title"], &db).await.unwrap();
+        let mut ids: Vec<_> = Article::find_changed_since(&known, &db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|a| a.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec![Some(2), Some(3)]);
+    }
+
+    #[tokio::test]
+    async fn test_raw_writes_keep_hash_current() {
+        let db = super::memory_db(
+            "CREATE TABLE articles (id INTEGER PRIMARY KEY, title TEXT, content TEXT, views INTEGER, etag TEXT)",
+        )
+        .await;
+        let (sql, params) = article(1, "One").insert_statement().unwrap();
+        db.execute(&sql, params).await.unwrap();
+        let one = || FilterOperator::Single(Filter::eq("id", 1i64));
+
+        let sets = HashMap::from([("title".to_string(), Value::Text("Two".to_string()))]);
+        let error = Article::update_where(one(), sets, &db).await.unwrap_err();
+        assert!(
+            matches!(&error, Error::Validation(msg) if msg.contains("'etag'")),
+            "{error}"
+        );
+        // Columns outside the hash are still fine
+        assert_eq!(Article::increment("views", 1, one(), &db).await.unwrap(), 1);
+
+        // LWW merges rewrite the hash of the merged row
+        #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+        #[table_name("articles")]
+        struct SyncedArticle {
+            pub id: Option<i64>,
+            #[orm_column(lww)]
+            pub title: String,
+            pub content: String,
+            pub views: i64,
+            #[orm_column(content_hash(of("title", "content")))]
+            pub etag: Option<String>,
+        }
+        crate::lww::setup(&db).await.unwrap();
+        let remote = crate::lww::LwwRow {
+            values: HashMap::from([("title".to_string(), Value::Text("Merged".to_string()))]),
+            clocks: HashMap::from([("title".to_string(), 1)]),
+        };
+        let merged = crate::lww::merge_into::<SyncedArticle>(1, &remote, &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(merged.title, "Merged");
+        assert_eq!(merged.etag, merged.content_hash().unwrap());
+    }
+}
+
+#[cfg(test)]
+mod tree_tests {
+    use crate::{Database, Model};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("categories")]
+    struct Category {
+        pub id: Option<i64>,
+        pub name: String,
+        #[orm_column(parent)]
+        pub parent: Option<i64>,
+    }
+
+    async fn memory_db() -> Database {
+        // 1 ─┬─ 2 ── 4 ── 5
+        //    └─ 3
+        // 6
+        super::memory_db(
+            "CREATE TABLE categories (id INTEGER PRIMARY KEY, name TEXT, parent INTEGER);
+             INSERT INTO categories VALUES
+                (1, 'root', NULL), (2, 'a', 1), (3, 'b', 1),
+                (4, 'a1', 2), (5, 'a1x', 4), (6, 'other', NULL);",
+        )
+        .await
+    }
+
+    fn ids(categories: &[Category]) -> Vec<i64> {
+        categories.iter().filter_map(|c| c.id).collect()
+    }
+
+    #[tokio::test]
+    async fn test_descendants() {
+        let db = memory_db().await;
+        let all = Category::descendants(1, &db).await.unwrap();
+        assert_eq!(ids(&all), vec![2, 3, 4, 5]);
+        let below = Category::descendants(2, &db).await.unwrap();
+        assert_eq!(ids(&below), vec![4, 5]);
+        assert!(Category::descendants(6, &db).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ancestors_are_nearest_first() {
+        let db = memory_db().await;
+        let above = Category::ancestors(5, &db).await.unwrap();
+        assert_eq!(ids(&above), vec![4, 2, 1]);
+        assert!(Category::ancestors(1, &db).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cycles_terminate() {
+        let db = memory_db().await;
+        db.execute("UPDATE categories SET parent = 5 WHERE id = 1", vec![])
+            .await
+            .unwrap();
+        let below = Category::descendants(1, &db).await.unwrap();
+        assert_eq!(ids(&below), vec![1, 2, 3, 4, 5]);
+        assert_eq!(Category::ancestors(4, &db).await.unwrap().len(), 4);
+    }
+}
+
+#[cfg(all(test, feature = "attachments"))]
+mod attachment_tests {
+    use crate::attachments::{Attachment, Attachments, MemoryStore};
+    use crate::{Database, Model};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("gallery_posts")]
+    struct GalleryPost {
+        pub id: Option<i64>,
+        pub title: String,
+    }
+
+    fn post(id: i64) -> GalleryPost {
+        GalleryPost {
+            id: Some(id),
+            title: format!("Post {id}"),
+        }
+    }
+
+    async fn setup() -> (Database, Attachments<MemoryStore>) {
+        let db = super::memory_db(
+            "CREATE TABLE gallery_posts (id INTEGER PRIMARY KEY, title TEXT);
+             INSERT INTO gallery_posts VALUES (1, 'one'), (2, 'two');",
+        )
+        .await;
+        let attachments = Attachments::new(MemoryStore::new()).prefix("uploads");
+        attachments.setup(&db).await.unwrap();
+        (db, attachments)
+    }
+
+    #[tokio::test]
+    async fn test_attach_list_and_read() {
+        let (db, attachments) = setup().await;
+        let file = attachments
+            .attach(&post(1), "a.txt", "text/plain", b"hello".to_vec(), &db)
+            .await
+            .unwrap();
+        assert!(file.id.is_some());
+        assert!(file.key.starts_with("uploads/gallery_posts/1/"));
+        assert_eq!(file.size, 5);
+        assert_eq!(attachments.read(&file).await.unwrap(), b"hello");
+
+        attachments
+            .attach(&post(2), "b.txt", "text/plain", b"other".to_vec(), &db)
+            .await
+            .unwrap();
+        assert_eq!(
+            attachments.list(&post(1), &db).await.unwrap(),
+            vec![file.clone()]
+        );
+
+        attachments.remove(&file, &db).await.unwrap();
+        assert!(attachments.list(&post(1), &db).await.unwrap().is_empty());
+        assert!(attachments.read(&file).await.is_err());
+        assert_eq!(attachments.store().keys().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_owner_deletion_cleans_up_files() {
+        let (db, attachments) = setup().await;
+        for owner in [post(1), post(2)] {
+            attachments
+                .attach(
+                    &owner,
+                    "f.bin",
+                    "application/octet-stream",
+                    vec![1, 2, 3],
+                    &db,
+                )
+                .await
+                .unwrap();
+        }
+
+        assert!(attachments.delete_owner(&post(1), &db).await.unwrap());
+        assert_eq!(Attachment::count(&db).await.unwrap(), 1);
+        assert_eq!(attachments.store().keys().len(), 1);
+
+        db.execute("DELETE FROM gallery_posts WHERE id = 2", vec![])
+            .await
+            .unwrap();
+        assert_eq!(attachments.cleanup_orphans(&db).await.unwrap(), 1);
+        assert_eq!(Attachment::count(&db).await.unwrap(), 0);
+        assert!(attachments.store().keys().is_empty());
+    }
+}
+
+#[cfg(test)]
+mod window_tests {
+    use crate::{Aggregate, QueryBuilder, Sort, WindowFunction};
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    struct Standing {
+        player: String,
+        position: i64,
+        row: i64,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Balance {
+        id: i64,
+        balance: f64,
+    }
+
+    #[tokio::test]
+    async fn test_rank_within_partitions() {
+        let db = super::memory_db(
+            "CREATE TABLE scores (player TEXT, league TEXT, points INTEGER);
+             INSERT INTO scores VALUES
+                ('ann', 'a', 30), ('bob', 'a', 50), ('cid', 'a', 30), ('dan', 'b', 10);",
+        )
+        .await;
+        let standings: Vec<Standing> = QueryBuilder::new("scores")
+            .select(vec!["player"])
+            .window(
+                WindowFunction::rank()
+                    .partition_by(vec!["league"])
+                    .order_by(Sort::desc("points"))
+                    .alias("position"),
+            )
+            .window(
+                WindowFunction::row_number()
+                    .partition_by(vec!["league"])
+                    .order_by(Sort::desc("points"))
+                    .order_by(Sort::asc("player"))
+                    .alias("row"),
+            )
+            .order_by(Sort::asc("player"))
+            .execute(&db)
+            .await
+            .unwrap();
+        let summary: Vec<_> = standings
+            .iter()
+            .map(|s| (s.player.as_str(), s.position, s.row))
+            .collect();
+        assert_eq!(
+            summary,
+            [("ann", 2, 2), ("bob", 1, 1), ("cid", 2, 3), ("dan", 1, 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_running_total() {
+        let db = super::memory_db(
+            "CREATE TABLE ledger (id INTEGER PRIMARY KEY, day TEXT, amount REAL);
+             INSERT INTO ledger VALUES (1, 'mon', 10), (2, 'mon', 5), (3, 'tue', -3);",
+        )
+        .await;
+        let window = WindowFunction::aggregate(Aggregate::Sum, "amount")
+            .order_by(Sort::asc("day"))
+            .order_by(Sort::asc("id"))
+            .running()
+            .alias("balance");
+        assert_eq!(
+            window.to_sql(),
+            "SUM(amount) OVER (ORDER BY day ASC, id ASC ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW) AS balance"
+        );
+        let balances: Vec<Balance> = QueryBuilder::new("ledger")
+            .select(vec!["id"])
+            .window(window)
+            .order_by(Sort::asc("id"))
+            .execute(&db)
+            .await
+            .unwrap();
+        let summary: Vec<_> = balances.iter().map(|b| (b.id, b.balance)).collect();
+        assert_eq!(summary, [(1, 10.0), (2, 15.0), (3, 12.0)]);
+    }
+}
+
+#[cfg(test)]
+mod blob_tests {
+    use crate::{Filter, FilterOperator, Model, Value};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[table_name("chunks")]
+    struct Chunk {
+        pub id: Option<i64>,
+        #[orm_column(unique)]
+        pub digest: Vec<u8>,
+        pub data: Option<Vec<u8>>,
+    }
+
+    #[tokio::test]
+    async fn test_binary_values_are_stored_and_filtered_as_blobs() {
+        let db = super::memory_db(
+            "CREATE TABLE chunks (id INTEGER PRIMARY KEY, digest BLOB UNIQUE, data BLOB);
+             INSERT INTO chunks VALUES (9, '[7,7]', NULL);",
+        )
+        .await;
+        let chunk = Chunk {
+            id: Some(1),
+            digest: vec![0xde, 0xad, 0xbe, 0xef],
+            data: Some(vec![0, 1, 2]),
+        };
+        assert!(Chunk::migration_sql().contains("digest BLOB UNIQUE"));
+        assert!(Chunk::migration_sql().contains("data BLOB"));
+        assert_eq!(
+            chunk.to_map().unwrap()["digest"],
+            Value::Blob(vec![0xde, 0xad, 0xbe, 0xef])
+        );
+        let (sql, params) = chunk.insert_statement().unwrap();
+        db.execute(&sql, params).await.unwrap();
+
+        let mut rows = db
+            .query(
+                "SELECT typeof(digest), typeof(data) FROM chunks WHERE id = 1",
+                vec![],
+            )
+            .await
+            .unwrap();
+        let row = rows.next().await.unwrap().unwrap();
+        assert_eq!(row.get::<String>(0).unwrap(), "blob");
+        assert_eq!(row.get::<String>(1).unwrap(), "blob");
+
+        let digest: [u8; 4] = [0xde, 0xad, 0xbe, 0xef];
+        let found = Chunk::find_one(
+            FilterOperator::Single(Filter::eq("digest", &digest[..])),
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(found, Some(chunk.clone()));
+        let found = Chunk::find_by_digest(&digest, &db).await.unwrap();
+        assert_eq!(found, Some(chunk));
+        assert!(Chunk::find_by_digest(&[1, 2], &db).await.unwrap().is_none());
+
+        let legacy = Chunk::find_by_id(9, &db).await.unwrap().unwrap();
+        assert_eq!((legacy.digest, legacy.data), (vec![7, 7], None));
+    }
+}
+
+#[cfg(test)]
+mod query_as_tests {
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct AuthorStats {
+        name: String,
+        posts: i64,
+        #[serde(deserialize_with = "crate::deserialize_bool")]
+        active: bool,
+        avatar: Option<Vec<u8>>,
+    }
+
+    #[tokio::test]
+    async fn test_query_as_maps_rows_to_structs() {
+        let db = super::memory_db(
+            "CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT, active INTEGER, avatar BLOB);
+             CREATE TABLE posts (id INTEGER PRIMARY KEY, author_id INTEGER);
+             INSERT INTO authors VALUES (1, 'ada', 1, x'0102'), (2, 'bob', 0, NULL);
+             INSERT INTO posts VALUES (1, 1), (2, 1), (3, 2);",
+        )
+        .await;
+
+        let stats: Vec<AuthorStats> = db
+            .query_as(
+                "SELECT a.name, COUNT(p.id) AS posts, a.active, a.avatar FROM authors a
+                 LEFT JOIN posts p ON p.author_id = a.id
+                 WHERE a.id >= ? GROUP BY a.id ORDER BY a.id",
+                vec![libsql::Value::Integer(1)],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            stats,
+            [
+                AuthorStats {
+                    name: "ada".to_string(),
+                    posts: 2,
+                    active: true,
+                    avatar: Some(vec![1, 2]),
+                },
+                AuthorStats {
+                    name: "bob".to_string(),
+                    posts: 1,
+                    active: false,
+                    avatar: None,
+                },
+            ]
+        );
+
+        let mismatch = db
+            .query_as::<AuthorStats>("SELECT name FROM authors", vec![])
+            .await;
+        assert!(matches!(mismatch, Err(crate::Error::Serialization(_))));
+    }
+}
+
+#[cfg(test)]
+mod identity_tests {
+    use crate::Model;
+    use serde::{Deserialize, Serialize};
+    use std::collections::{HashMap, HashSet};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("identity_users")]
+    #[orm(identity_eq)]
+    struct User {
+        pub id: Option<i64>,
+        pub name: String,
+    }
+
+    fn user(id: Option<i64>, name: &str) -> User {
+        User {
+            id,
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_identity_eq_compares_primary_keys() {
+        assert_eq!(user(Some(1), "ada"), user(Some(1), "ada lovelace"));
+        assert_ne!(user(Some(1), "ada"), user(Some(2), "ada"));
+        // Unsaved records are only equal to an identical record
+        assert_ne!(user(None, "a"), user(None, "b"));
+        assert_eq!(user(None, "a"), user(None, "a"));
+        assert_ne!(user(None, "a"), user(Some(1), "a"));
+        let new: HashSet<User> = [user(None, "a"), user(None, "b")].into_iter().collect();
+        assert_eq!(new.len(), 2);
+
+        let loaded = vec![user(Some(1), "a"), user(Some(2), "b"), user(Some(1), "a2")];
+        let unique: HashSet<User> = loaded.into_iter().collect();
+        assert_eq!(unique.len(), 2);
+
+        let mut edits = HashMap::new();
+        edits.insert(user(Some(2), "b"), "renamed");
+        assert_eq!(edits.get(&user(Some(2), "")), Some(&"renamed"));
+    }
+}
+
+#[cfg(test)]
+mod diff_tests {
+    use crate::{Model, ModelDiff, Value};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("diff_users")]
+    struct User {
+        pub id: Option<i64>,
+        pub name: String,
+        pub email: String,
+        pub admin: bool,
+    }
+
+    #[tokio::test]
+    async fn test_diff_and_apply() {
+        let db = super::memory_db(
+            "CREATE TABLE diff_users (id INTEGER PRIMARY KEY, name TEXT, email TEXT, admin INTEGER);
+             INSERT INTO diff_users VALUES (1, 'ada', 'ada@old.example', 0);",
+        )
+        .await;
+        let before = User::find_by_id(1, &db).await.unwrap().unwrap();
+        assert!(ModelDiff::between(&before, &before.clone())
+            .unwrap()
+            .is_empty());
+
+        let mut after = before.clone();
+        after.email = "ada@new.example".to_string();
+        after.admin = true;
+        let diff = ModelDiff::between(&before, &after).unwrap();
+        assert_eq!(diff.columns(), vec!["email", "admin"]);
+        assert_eq!(diff.get("admin").unwrap().before, Value::Boolean(false));
+        assert_eq!(
+            diff.to_json(),
+            serde_json::json!({
+                "email": {"before": "ada@old.example", "after": "ada@new.example"},
+                "admin": {"before": false, "after": true},
+            })
+        );
+
+        // Only the changed columns are written
+        db.execute("UPDATE diff_users SET name = 'renamed'", vec![])
+            .await
+            .unwrap();
+        diff.apply(&after, &db).await.unwrap();
+        let stored = User::find_by_id(1, &db).await.unwrap().unwrap();
+        assert_eq!(
+            (stored.name.as_str(), stored.email.as_str(), stored.admin),
+            ("renamed", "ada@new.example", true)
+        );
+    }
+}
+
+#[cfg(test)]
+mod dashboard_tests {
+    use crate::{Filter, FilterOperator};
+
+    #[tokio::test]
+    async fn test_dashboard_counts_in_one_query() {
+        let db = super::memory_db(
+            "CREATE TABLE dash_users (id INTEGER PRIMARY KEY);
+             CREATE TABLE dash_orders (id INTEGER PRIMARY KEY, status TEXT);
+             INSERT INTO dash_users VALUES (1), (2), (3);
+             INSERT INTO dash_orders VALUES (1, 'pending'), (2, 'paid'), (3, 'pending');",
+        )
+        .await;
+        let pending = FilterOperator::Single(Filter::eq("status", "pending"));
+        let counts = db
+            .dashboard_counts(&[
+                ("dash_users", None),
+                ("dash_orders", Some(pending)),
+                ("dash_orders", None),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(
+            counts,
+            vec![
+                ("dash_users".to_string(), 3),
+                ("dash_orders".to_string(), 2),
+                ("dash_orders".to_string(), 3),
+            ]
+        );
+
+        assert!(db.dashboard_counts(&[]).await.unwrap().is_empty());
+        assert!(db.dashboard_counts(&[("missing", None)]).await.is_err());
+    }
+}
+
+#[cfg(test)]
+mod null_filter_tests {
+    use crate::{Filter, FilterOperator, QueryBuilder, Value};
+
+    #[tokio::test]
+    async fn test_null_comparisons_use_is_null() {
+        let db = super::memory_db(
+            "CREATE TABLE null_items (id INTEGER PRIMARY KEY, deleted_at TEXT);
+             INSERT INTO null_items VALUES (1, NULL), (2, '2024-01-01'), (3, NULL);",
+        )
+        .await;
+        let cases = [
+            (Filter::is_null("deleted_at"), 2),
+            (Filter::is_not_null("deleted_at"), 1),
+            (Filter::eq("deleted_at", Value::Null), 2),
+            (Filter::ne("deleted_at", Value::Null), 1),
+        ];
+        for (filter, expected) in cases {
+            let count = QueryBuilder::new("null_items")
+                .r#where(FilterOperator::Single(filter))
+                .execute_count(&db)
+                .await
+                .unwrap();
+            assert_eq!(count, expected);
+        }
+
+        let (sql, params) = QueryBuilder::new("null_items")
+            .r#where(FilterOperator::Single(Filter::eq(
+                "deleted_at",
+                Value::Null,
+            )))
+            .build()
+            .unwrap();
+        assert!(sql.contains("deleted_at IS NULL"), "{sql}");
+        assert!(params.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod timestamp_finder_tests {
+    use crate::Model;
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("ts_orders")]
+    struct Order {
+        pub id: Option<i64>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    fn at(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_created_between_and_updated_since() {
+        let db = super::memory_db(
+            "CREATE TABLE ts_orders (id INTEGER PRIMARY KEY, created_at TEXT, updated_at TEXT);
+             INSERT INTO ts_orders VALUES
+                 (1, '2024-01-10T00:00:00Z', '2024-03-01T00:00:00Z'),
+                 (2, '2024-01-01T00:00:00Z', '2024-02-01T00:00:00Z'),
+                 (3, '2024-02-15T00:00:00Z', '2024-02-15T00:00:00Z');",
+        )
+        .await;
+        let ids = |orders: Vec<Order>| orders.iter().map(|o| o.id.unwrap()).collect::<Vec<_>>();
+
+        let january = Order::created_between(
+            &at("2024-01-01T00:00:00Z"),
+            &at("2024-01-31T23:59:59Z"),
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(ids(january), vec![2, 1]);
+
+        let recent = Order::updated_since(&at("2024-02-15T00:00:00Z"), &db)
+            .await
+            .unwrap();
+        assert_eq!(ids(recent), vec![3, 1]);
+    }
+}
+
+#[cfg(test)]
+mod pattern_filter_tests {
+    use crate::{Filter, FilterOperator, QueryBuilder};
+
+    #[tokio::test]
+    async fn test_like_and_glob_with_escaping() {
+        let db = super::memory_db(
+            "CREATE TABLE patterns (id INTEGER PRIMARY KEY, title TEXT);
+             INSERT INTO patterns VALUES
+                 (1, 'Rust in 100% of cases'), (2, 'Rust in 1000 cases'),
+                 (3, 'snake_case'), (4, 'snakeXcase'), (5, 'what?');",
+        )
+        .await;
+        let db = &db;
+        let ids = |filter: Filter| {
+            let builder = QueryBuilder::new("patterns")
+                .select(vec!["id"])
+                .r#where(FilterOperator::Single(filter));
+            async move {
+                builder
+                    .execute::<serde_json::Value>(db)
+                    .await
+                    .unwrap()
+                    .iter()
+                    .map(|row| row["id"].as_i64().unwrap())
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(ids(Filter::like("title", "%100%")).await, vec![1, 2]);
+        // Plain LIKE keeps backslashes ordinary; only the escaped form adds ESCAPE
+        let (sql, _) = QueryBuilder::new("t")
+            .r#where(FilterOperator::Single(Filter::like("path", "C:\\%")))
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT * FROM t WHERE path LIKE ?");
+        let literal = format!("%{}%", Filter::escape_like("100%"));
+        assert_eq!(ids(Filter::like_escaped("title", literal)).await, vec![1]);
+        let literal = format!("%{}%", Filter::escape_like("_"));
+        assert_eq!(
+            ids(Filter::like_escaped("title", literal.clone())).await,
+            vec![3]
+        );
+        assert_eq!(
+            ids(Filter::not_like_escaped("title", literal)).await,
+            vec![1, 2, 4, 5]
+        );
+
+        assert_eq!(ids(Filter::glob("title", "Rust*")).await, vec![1, 2]);
+        assert!(ids(Filter::glob("title", "rust*")).await.is_empty());
+        assert_eq!(ids(Filter::glob("title", "*?")).await.len(), 5);
+        let literal = format!("*{}", Filter::escape_glob("?"));
+        assert_eq!(ids(Filter::glob("title", literal.clone())).await, vec![5]);
+        assert_eq!(ids(Filter::not_glob("title", literal)).await.len(), 4);
+    }
+}
+
+#[cfg(test)]
+mod sample_tests {
+    use crate::Model;
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashSet;
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("sample_rows")]
+    struct Row {
+        pub id: Option<i64>,
+        pub label: String,
+    }
+
+    #[tokio::test]
+    async fn test_sample_strategies() {
+        let db = super::memory_db(
+            "CREATE TABLE sample_rows (id INTEGER PRIMARY KEY, label TEXT);
+             WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 100)
+             INSERT INTO sample_rows SELECT i, 'row ' || i FROM n;",
+        )
+        .await;
+        let ids = |rows: Vec<Row>| rows.iter().map(|r| r.id.unwrap()).collect::<HashSet<_>>();
+
+        assert_eq!(ids(Row::sample(5, &db).await.unwrap()).len(), 5);
+        assert!(Row::sample(0, &db).await.unwrap().is_empty());
+
+        let probed = Row::sample_by_rowid(5, &db).await.unwrap();
+        assert!(!probed.is_empty() && probed.len() <= 5);
+        assert_eq!(ids(probed.clone()).len(), probed.len());
+        assert!(ids(probed).iter().all(|id| (1..=100).contains(id)));
+
+        db.execute("DELETE FROM sample_rows", vec![]).await.unwrap();
+        assert!(Row::sample_by_rowid(5, &db).await.unwrap().is_empty());
+    }
+}
+
+#[cfg(test)]
+mod not_filter_tests {
+    use crate::{filter_op, Filter, FilterOperator, QueryBuilder};
+
+    #[tokio::test]
+    async fn test_negated_condition_trees() {
+        let db = super::memory_db(
+            "CREATE TABLE not_items (id INTEGER PRIMARY KEY, status TEXT, price INTEGER);
+             INSERT INTO not_items VALUES
+                 (1, 'active', 5), (2, 'active', 50), (3, 'archived', 5), (4, 'draft', 50);",
+        )
+        .await;
+        let count = |filter: FilterOperator| {
+            let builder = QueryBuilder::new("not_items").r#where(filter);
+            let db = &db;
+            async move { builder.execute_count(db).await.unwrap() }
+        };
+
+        let cheap_active = filter_op!(
+            and,
+            filter_op!(Filter::eq("status", "active")),
+            filter_op!(Filter::lt("price", 10i64))
+        );
+        assert_eq!(count(filter_op!(not, cheap_active.clone())).await, 3);
+        assert_eq!(count(!cheap_active.clone()).await, 3);
+        assert_eq!(count(!!cheap_active).await, 1);
+
+        let either = FilterOperator::or(vec![
+            filter_op!(Filter::eq("status", "archived")),
+            filter_op!(Filter::gt("price", 10i64)),
+        ]);
+        assert_eq!(count(FilterOperator::negate(either)).await, 1);
+
+        assert_eq!(count(FilterOperator::and(vec![])).await, 4);
+        assert_eq!(count(FilterOperator::or(vec![])).await, 0);
+        assert_eq!(count(!FilterOperator::or(vec![])).await, 4);
+    }
+}
+
+#[cfg(test)]
+mod json_filter_tests {
+    use crate::{FilterOperator, QueryBuilder};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_filters_from_request_parameters() {
+        let db = super::memory_db(
+            "CREATE TABLE json_products (id INTEGER PRIMARY KEY, status TEXT, price INTEGER, sku TEXT);
+             INSERT INTO json_products VALUES
+                 (1, 'active', 5, 'A_1'), (2, 'active', 50, 'AB1'),
+                 (3, 'archived', 20, NULL), (4, 'draft', 80, 'C_9');",
+        )
+        .await;
+        let allowed = ["status", "price", "sku"];
+        let db = &db;
+        let ids = |params: serde_json::Value| async move {
+            let filter = FilterOperator::from_json(&params, &allowed).unwrap();
+            QueryBuilder::new("json_products")
+                .select(vec!["id"])
+                .r#where(filter)
+                .execute::<serde_json::Value>(db)
+                .await
+                .unwrap()
+                .iter()
+                .map(|row| row["id"].as_i64().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        // Query-string values arrive as text
+        assert_eq!(
+            ids(json!({"status": "active", "price_gte": "10"})).await,
+            vec![2]
+        );
+        assert_eq!(
+            ids(json!({"price_lt": 30, "status_ne": "draft"})).await,
+            vec![1, 3]
+        );
+        assert_eq!(
+            ids(json!({"status_in": "active, draft"})).await,
+            vec![1, 2, 4]
+        );
+        assert_eq!(ids(json!({"price_in": [5, 80]})).await, vec![1, 4]);
+        assert_eq!(ids(json!({"sku_contains": "_"})).await, vec![1, 4]);
+        assert_eq!(ids(json!({"sku_null": "true"})).await, vec![3]);
+        assert_eq!(ids(json!({})).await, vec![1, 2, 3, 4]);
+
+        for bad in [
+            json!({"password": "x"}),
+            json!({"price_between": 1}),
+            json!({"status": ["a"]}),
+            json!({"sku_null": "maybe"}),
+            json!(["status"]),
+        ] {
+            assert!(FilterOperator::from_json(&bad, &allowed).is_err(), "{bad}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod merge_tests {
+    use crate::Model;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("merge_customers")]
+    struct Customer {
+        pub id: Option<i64>,
+        pub email: String,
+    }
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("merge_orders")]
+    struct Order {
+        pub id: Option<i64>,
+        #[orm_column(references = "merge_customers")]
+        pub customer_id: i64,
+    }
+
+    #[tokio::test]
+    async fn test_merge_repoints_references() {
+        assert!(Order::migration_sql().contains("customer_id INTEGER REFERENCES merge_customers"));
+        let db = super::memory_db(
+            "CREATE TABLE merge_customers (id INTEGER PRIMARY KEY, email TEXT);
+             CREATE TABLE merge_orders (id INTEGER PRIMARY KEY, customer_id INTEGER);
+             INSERT INTO merge_customers VALUES (1, 'a@x'), (2, 'A@x'), (3, 'a@x '), (4, 'b@x');
+             INSERT INTO merge_orders VALUES (1, 1), (2, 2), (3, 3), (4, 4), (5, 2);",
+        )
+        .await;
+
+        // A missing survivor rolls everything back
+        assert!(Customer::merge(99, &[2], &db).await.is_err());
+        assert_eq!(Customer::count(&db).await.unwrap(), 4);
+
+        assert_eq!(Customer::merge(1, &[1, 2, 3], &db).await.unwrap(), 2);
+        let ids: Vec<i64> = Customer::find_all(&db)
+            .await
+            .unwrap()
+            .iter()
+            .map(|c| c.id.unwrap())
+            .collect();
+        assert_eq!(ids, vec![1, 4]);
+        let owners: Vec<i64> = Order::find_all(&db)
+            .await
+            .unwrap()
+            .iter()
+            .map(|o| o.customer_id)
+            .collect();
+        assert_eq!(owners, vec![1, 1, 1, 4, 1]);
+
+        assert_eq!(Customer::merge(1, &[1], &db).await.unwrap(), 0);
+    }
+}
+
+#[cfg(test)]
+mod sort_tests {
+    use crate::{FilterOperator, Model, Pagination, Sort};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("sort_tasks")]
+    struct Task {
+        pub id: Option<i64>,
+        pub priority: i64,
+        pub due: Option<String>,
+    }
+
+    fn ids(tasks: Vec<Task>) -> Vec<i64> {
+        tasks.iter().map(|t| t.id.unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_multi_column_sorts_with_nulls_last() {
+        let db = super::memory_db(
+            "CREATE TABLE sort_tasks (id INTEGER PRIMARY KEY, priority INTEGER, due TEXT);
+             INSERT INTO sort_tasks VALUES
+                 (1, 1, NULL), (2, 2, '2024-03-01'), (3, 2, NULL), (4, 1, '2024-01-01'), (5, 2, '2024-02-01');",
+        )
+        .await;
+
+        let sorted = Task::find_where_sorted(
+            FilterOperator::And(vec![]),
+            vec![Sort::desc("priority"), Sort::asc("due").nulls_last()],
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(ids(sorted), vec![5, 2, 3, 4, 1]);
+
+        let sorted = Task::find_where_sorted(
+            FilterOperator::And(vec![]),
+            vec![Sort::desc("due").nulls_first(), Sort::asc("id")],
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(ids(sorted), vec![1, 3, 2, 5, 4]);
+
+        // Ties are broken by primary key, so pages never overlap
+        let page = |n| Pagination::new(n, 2);
+        let mut seen = Vec::new();
+        for n in 1..=3 {
+            let result = Task::list(Some(vec![Sort::asc("priority")]), Some(&page(n)), &db)
+                .await
+                .unwrap();
+            seen.extend(ids(result.data));
+        }
+        assert_eq!(seen, vec![1, 4, 2, 3, 5]);
+    }
+}
+
+#[cfg(test)]
+mod column_type_tests {
+    use crate::{templates, Database, MigrationManager};
+
+    async fn sql_of(db: &Database, name: &str) -> String {
+        let mut rows = db
+            .query(
+                "SELECT sql FROM sqlite_master WHERE name = ?",
+                vec![libsql::Value::Text(name.to_string())],
+            )
+            .await
+            .unwrap();
+        rows.next().await.unwrap().unwrap().get(0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_change_column_type_rebuilds_table() {
+        let db = super::memory_db(
+            "PRAGMA foreign_keys = OFF;
+             CREATE TABLE ct_users (id INTEGER PRIMARY KEY);
+             CREATE TABLE ct_log (order_id INTEGER);
+             CREATE TABLE ct_orders (
+                 id INTEGER PRIMARY KEY,
+                 user_id INTEGER REFERENCES ct_users(id),
+                 total TEXT NOT NULL DEFAULT('0'),
+                 note VARCHAR(20, 2) UNIQUE
+             );
+             CREATE INDEX idx_ct_orders_total ON ct_orders(total);
+             CREATE TRIGGER ct_orders_touch AFTER INSERT ON ct_orders BEGIN
+                 INSERT INTO ct_log VALUES (NEW.id);
+             END;
+             CREATE VIEW ct_big_orders AS SELECT id FROM ct_orders WHERE total > 10;
+             CREATE TABLE ct_items (id INTEGER PRIMARY KEY, order_id INTEGER REFERENCES ct_orders(id));
+             INSERT INTO ct_users VALUES (1);
+             INSERT INTO ct_orders VALUES (1, 1, '12.5', 'a'), (2, 1, '3', 'b');
+             INSERT INTO ct_items VALUES (1, 1), (2, 2);",
+        )
+        .await;
+
+        let migration = templates::change_column_type(
+            "ct_orders",
+            "total",
+            "INTEGER",
+            "CAST(total * 100 AS INTEGER)",
+            &db,
+        )
+        .await
+        .unwrap();
+        let manager = MigrationManager::new(db);
+        let db = manager.database();
+        manager.init().await.unwrap();
+        manager.execute_migration(&migration).await.unwrap();
+
+        let table = sql_of(db, "ct_orders").await;
+        assert!(
+            table.contains("total INTEGER NOT NULL DEFAULT('0')"),
+            "{table}"
+        );
+        assert!(table.contains("user_id INTEGER REFERENCES ct_users(id)"));
+        assert!(table.contains("note VARCHAR(20, 2) UNIQUE"));
+        assert!(sql_of(db, "idx_ct_orders_total").await.contains("total"));
+        assert!(sql_of(db, "ct_orders_touch").await.contains("BEGIN"));
+
+        let mut rows = db
+            .query(
+                "SELECT id, typeof(total), total FROM ct_big_orders JOIN ct_orders USING (id) ORDER BY id",
+                vec![],
+            )
+            .await
+            .unwrap();
+        let mut found = Vec::new();
+        while let Some(row) = rows.next().await.unwrap() {
+            found.push((
+                row.get::<i64>(0).unwrap(),
+                row.get::<String>(1).unwrap(),
+                row.get::<i64>(2).unwrap(),
+            ));
+        }
+        assert_eq!(
+            found,
+            vec![
+                (1, "integer".to_string(), 1250),
+                (2, "integer".to_string(), 300)
+            ]
+        );
+
+        assert!(
+            templates::change_column_type("ct_orders", "missing", "TEXT", "missing", db)
+                .await
+                .is_err()
+        );
+        // Referencing rows survive the rebuild
+        let mut rows = db
+            .query(
+                "SELECT COUNT(*) FROM ct_items JOIN ct_orders ON ct_orders.id = order_id",
+                vec![],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            rows.next().await.unwrap().unwrap().get::<i64>(0).unwrap(),
+            2
+        );
+
+        // ct_items references the table, so enforcement must be off
+        db.execute("PRAGMA foreign_keys = ON", vec![])
+            .await
+            .unwrap();
+        assert!(
+            templates::change_column_type("ct_orders", "total", "TEXT", "total", db)
+                .await
+                .is_err()
+        );
+    }
+}
+
+#[cfg(test)]
+mod select_columns_tests {
+    use crate::{Filter, FilterOperator, Model};
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("select_users")]
+    struct User {
+        pub id: Option<i64>,
+        pub email: String,
+        pub score: f64,
+        pub admin: bool,
+        pub avatar: Vec<u8>,
+        pub bio: Option<String>,
+        pub joined_at: DateTime<Utc>,
+    }
+
+    #[tokio::test]
+    async fn test_column_subset_reads_into_model() {
+        let db = super::memory_db(
+            "CREATE TABLE select_users (
+                 id INTEGER PRIMARY KEY, email TEXT, score REAL, admin INTEGER,
+                 avatar BLOB, bio TEXT, joined_at TEXT
+             );
+             INSERT INTO select_users VALUES
+                 (1, 'a@x', 4.5, 1, x'0102', 'hi', '2024-01-01T00:00:00Z'),
+                 (2, 'b@x', 1.0, 0, x'03', NULL, '2024-02-01T00:00:00Z');",
+        )
+        .await;
+
+        let users = User::select(&["id", "email"])
+            .r#where(FilterOperator::Single(Filter::gt("score", 2.0)))
+            .execute_models::<User>(&db)
+            .await
+            .unwrap();
+        assert_eq!(users.len(), 1);
+        let user = &users[0];
+        assert_eq!((user.id, user.email.as_str()), (Some(1), "a@x"));
+        assert_eq!(user.score, 0.0);
+        assert!(!user.admin);
+        assert!(user.avatar.is_empty());
+        assert_eq!(user.bio, None);
+        assert_eq!(user.joined_at, DateTime::<Utc>::UNIX_EPOCH);
+
+        let (sql, _) = User::select(&["id", "email"]).build().unwrap();
+        assert_eq!(sql, "SELECT id, email FROM select_users");
+
+        // Saving only the selected columns keeps the others
+        let mut renamed = user.clone();
+        renamed.email = "c@x".to_string();
+        renamed.update_fields(&["email"], &db).await.unwrap();
+        let stored = User::find_by_id(1, &db).await.unwrap().unwrap();
+        assert_eq!((stored.email.as_str(), stored.score), ("c@x", 4.5));
+    }
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("select_posts")]
+    struct Post {
+        pub id: Option<i64>,
+        pub title: String,
+        pub tags: Vec<String>,
+    }
+
+    #[tokio::test]
+    async fn test_unselected_column_without_default_fails() {
+        let db = super::memory_db(
+            "CREATE TABLE select_posts (id INTEGER PRIMARY KEY, title TEXT, tags TEXT);
+             INSERT INTO select_posts VALUES (1, 'a', NULL);",
+        )
+        .await;
+
+        let error = Post::select(&["id", "title"])
+            .execute_models::<Post>(&db)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&error, crate::Error::Query(msg) if msg.contains("'tags'")),
+            "{error}"
+        );
+    }
+}
+
+#[cfg(test)]
+mod mirror_tests {
+    use crate::{Database, Filter, FilterOperator, MigrationManager, Model};
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("mirror_users")]
+    struct User {
+        pub id: Option<i64>,
+        #[orm_column(mirror_to = "email_address")]
+        pub email: String,
+    }
+
+    async fn mirrored(db: &Database) -> Vec<Option<String>> {
+        let mut rows = db
+            .query("SELECT email_address FROM mirror_users ORDER BY id", vec![])
+            .await
+            .unwrap();
+        let mut values = Vec::new();
+        while let Some(row) = rows.next().await.unwrap() {
+            values.push(row.get(0).unwrap());
+        }
+        values
+    }
+
+    #[tokio::test]
+    async fn test_dual_writes_and_resumable_backfill() {
+        let db = super::memory_db(
+            "CREATE TABLE mirror_users (id INTEGER PRIMARY KEY, email TEXT, email_address TEXT);
+             INSERT INTO mirror_users (id, email) VALUES (1, 'a@x'), (2, 'b@x'), (3, 'c@x');",
+        )
+        .await;
+
+        let user = User {
+            id: Some(4),
+            email: "d@x".to_string(),
+        };
+        user.create(&db).await.unwrap();
+        let mut renamed = user.clone();
+        renamed.email = "e@x".to_string();
+        renamed.update_fields(&["email"], &db).await.unwrap();
+        assert_eq!(mirrored(&db).await, [None, None, None, Some("e@x".into())]);
+
+        let manager = MigrationManager::new(db);
+        let db = manager.database();
+        manager.init().await.unwrap();
+
+        // Pretend an earlier run stopped after the first row
+        db.execute(
+            "INSERT INTO migrations (id, name, sql, created_at, progress)
+             VALUES ('backfill_mirror_mirror_users_email_address', 'x', '', '2024-01-01T00:00:00Z', 1)",
+            vec![],
+        )
+        .await
+        .unwrap();
+        assert_eq!(User::backfill_mirrors(&manager, 2).await.unwrap(), 3);
+        assert_eq!(
+            mirrored(db).await,
+            [
+                None,
+                Some("b@x".into()),
+                Some("c@x".into()),
+                Some("e@x".into())
+            ]
+        );
+
+        // Finished backfills are not run again
+        assert_eq!(User::backfill_mirrors(&manager, 2).await.unwrap(), 0);
+        let pending = manager.get_pending_migrations().await.unwrap();
+        assert!(pending.is_empty());
+    }
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("mirror_counters")]
+    struct Counter {
+        pub id: Option<i64>,
+        pub name: String,
+        #[orm_column(mirror_to = "hits_total")]
+        pub hits: i64,
+    }
+
+    #[tokio::test]
+    async fn test_filtered_updates_write_mirrors() {
+        let db = super::memory_db(
+            "CREATE TABLE mirror_counters (id INTEGER PRIMARY KEY, name TEXT, hits INTEGER, hits_total INTEGER);
+             INSERT INTO mirror_counters VALUES (1, 'a', 1, 1), (2, 'b', 5, 5);",
+        )
+        .await;
+        let first = || FilterOperator::Single(Filter::eq("id", 1i64));
+
+        let sets = HashMap::from([("hits".to_string(), crate::Value::Integer(10))]);
+        assert_eq!(Counter::update_where(first(), sets, &db).await.unwrap(), 1);
+        assert_eq!(
+            Counter::increment("hits", 3, first(), &db).await.unwrap(),
+            1
+        );
+        Counter::decrement("hits", 1, first(), &db).await.unwrap();
+
+        let rows: Vec<serde_json::Value> = db
+            .query_as(
+                "SELECT hits, hits_total FROM mirror_counters ORDER BY id",
+                vec![],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            rows,
+            [
+                serde_json::json!({"hits": 12, "hits_total": 12}),
+                serde_json::json!({"hits": 5, "hits_total": 5})
+            ]
+        );
+    }
+}
+
+#[cfg(test)]
+mod derive_projection_tests {
+    use crate::{Filter, FilterOperator, Projection};
+    use serde::Deserialize;
+
+    #[derive(Projection, Debug, Deserialize, PartialEq)]
+    #[table_name("dp_posts")]
+    struct PostTitle {
+        pub id: i64,
+        pub title: String,
+        pub published: bool,
+    }
+
+    #[derive(Projection, Debug, Deserialize, PartialEq)]
+    #[table_name("dp_posts")]
+    #[projection(left_join("dp_users", "dp_users.id = dp_posts.author_id"))]
+    struct PostRow {
+        pub id: i64,
+        pub title: String,
+        #[projection(column = "dp_users.name")]
+        pub author: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn test_derived_projections() {
+        let db = super::memory_db(
+            "CREATE TABLE dp_users (id INTEGER PRIMARY KEY, name TEXT);
+             CREATE TABLE dp_posts (id INTEGER PRIMARY KEY, title TEXT, body TEXT, published INTEGER, author_id INTEGER);
+             INSERT INTO dp_users VALUES (1, 'ada');
+             INSERT INTO dp_posts VALUES (1, 'hello', 'long', 1, 1), (2, 'draft', 'long', 0, NULL);",
+        )
+        .await;
+
+        assert_eq!(PostTitle::columns(), vec!["id", "title", "published"]);
+        let published =
+            PostTitle::find_where(FilterOperator::Single(Filter::eq("published", true)), &db)
+                .await
+                .unwrap();
+        assert_eq!(
+            published,
+            vec![PostTitle {
+                id: 1,
+                title: "hello".to_string(),
+                published: true
+            }]
+        );
+
+        let rows = PostRow::find_all(&db).await.unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].author.as_deref(), Some("ada"));
+        assert_eq!(rows[1].author, None);
+        let by_ada = PostRow::find_where(
+            FilterOperator::Single(Filter::eq("dp_users.name", "ada")),
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(by_ada.len(), 1);
+        assert_eq!(by_ada[0].title, "hello");
+    }
+}
+
+#[cfg(test)]
+mod backfill_tests {
+    use crate::{Backfill, Error, Model};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("accounts")]
+    struct Account {
+        pub id: Option<i64>,
+        pub email: String,
+    }
+
+    async fn emails(db: &crate::Database) -> Vec<String> {
+        Account::find_all(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|a| a.email)
+            .collect()
+    }
+
+    async fn normalize(
+        rows: Vec<Account>,
+        tx: &crate::Database,
+        fail_on: Option<i64>,
+    ) -> crate::Result<()> {
+        for mut row in rows {
+            if row.id == fail_on {
+                return Err(Error::Validation("interrupted".to_string()));
+            }
+            row.email = row.email.to_lowercase();
+            row.update(tx).await?;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_backfill_resumes_after_failed_batch() {
+        let db = super::memory_db(
+            "CREATE TABLE accounts (id INTEGER PRIMARY KEY, email TEXT);
+             INSERT INTO accounts VALUES (1, 'A@x'), (2, 'B@x'), (3, 'C@x'), (4, 'D@x'), (5, 'E@x');",
+        )
+        .await;
+        let backfill = Backfill::<Account>::new("normalize_emails").batch(2);
+        let interrupted = backfill
+            .run(&db, |rows, tx| normalize(rows, tx, Some(4)))
+            .await;
+        assert!(interrupted.is_err());
+        // The failed batch rolled back, the first one stayed committed
+        assert_eq!(emails(&db).await, ["a@x", "b@x", "C@x", "D@x", "E@x"]);
+
+        let report = backfill
+            .run(&db, |rows, tx| normalize(rows, tx, None))
+            .await
+            .unwrap();
+        assert_eq!((report.rows, report.batches, report.finished), (3, 2, true));
+        assert_eq!(emails(&db).await, ["a@x", "b@x", "c@x", "d@x", "e@x"]);
+
+        let again = backfill
+            .run(&db, |rows, tx| normalize(rows, tx, None))
+            .await
+            .unwrap();
+        assert_eq!((again.rows, again.finished), (0, true));
+
+        backfill.reset(&db).await.unwrap();
+        let rerun = backfill
+            .run(&db, |rows, tx| normalize(rows, tx, None))
+            .await
+            .unwrap();
+        assert_eq!(rerun.rows, 5);
+    }
+}
+
+#[cfg(test)]
+mod maintenance_tests {
+    use crate::maintenance::{run_scheduled, Task};
+    use crate::{Backfill, Model};
+    use chrono::{Duration, Utc};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("sessions")]
+    struct Session {
+        pub id: Option<i64>,
+        pub expires_at: chrono::DateTime<Utc>,
+        pub deleted_at: Option<chrono::DateTime<Utc>>,
+    }
+
+    #[tokio::test]
+    async fn test_run_scheduled_continues_past_failures() {
+        let db = super::memory_db(
+            "CREATE TABLE sessions (id INTEGER PRIMARY KEY, expires_at TEXT NOT NULL, deleted_at TEXT);",
+        )
+        .await;
+        let now = Utc::now();
+        for (expires, deleted) in [
+            (now - Duration::hours(1), None),
+            (now + Duration::hours(1), None),
+            (now + Duration::hours(1), Some(now - Duration::days(40))),
+            (now + Duration::hours(1), Some(now - Duration::days(2))),
+        ] {
+            Session {
+                id: None,
+                expires_at: expires,
+                deleted_at: deleted,
+            }
+            .create(&db)
+            .await
+            .unwrap();
+        }
+
+        let tasks = vec![
+            Task::purge_expired("sessions", "expires_at"),
+            Task::purge_expired("missing_table", "expires_at"),
+            Task::sweep_soft_deleted("sessions", "deleted_at", 30),
+            Task::custom("touch", |db| {
+                Box::pin(async move {
+                    let report = Backfill::<Session>::new("touch")
+                        .run(db, |rows, tx| async move {
+                            for row in rows {
+                                row.update(tx).await?;
+                            }
+                            Ok(())
+                        })
+                        .await?;
+                    Ok(report.rows)
+                })
+            }),
+            Task::analyze(),
+        ];
+        let reports = run_scheduled(&db, &tasks).await;
+
+        let outcomes: Vec<_> = reports
+            .iter()
+            .map(|r| (r.name.as_str(), r.outcome.as_ref().ok().copied()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            [
+                ("purge_expired:sessions", Some(1)),
+                ("purge_expired:missing_table", None),
+                ("sweep_soft_deleted:sessions", Some(1)),
+                ("touch", Some(2)),
+                ("analyze", Some(0)),
+            ]
+        );
+        assert_eq!(Session::count(&db).await.unwrap(), 2);
+    }
+}
+
+#[cfg(test)]
+mod statement_tests {
+    use crate::{Filter, FilterOperator, Model, Statement};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("notes")]
+    struct Note {
+        pub id: Option<i64>,
+        pub body: String,
+    }
+
+    #[test]
+    fn test_statements_render_without_running() {
+        let note = Note {
+            id: Some(7),
+            body: "it's".to_string(),
+        };
+        let update = Statement::from(note.update_statement().unwrap());
+        assert_eq!(update.sql, "UPDATE notes SET body = ? WHERE id = ?");
+        assert_eq!(
+            update.to_string(),
+            "UPDATE notes SET body = 'it''s' WHERE id = 7"
+        );
+
+        let filter = FilterOperator::Single(Filter::gt("id", 3i64));
+        let find = Statement::from(Note::find_where_statement(filter.clone()).unwrap());
+        assert_eq!(find.to_string(), "SELECT * FROM notes WHERE id > 3");
+        let delete = Statement::from(Note::delete_where_statement(filter).unwrap());
+        assert_eq!(delete.to_string(), "DELETE FROM notes WHERE id > 3");
+
+        let raw = Statement {
+            sql: "SELECT '?', ? , ?".to_string(),
+            params: vec![libsql::Value::Null, libsql::Value::Blob(vec![0xab, 1])],
+        };
+        assert_eq!(raw.to_string(), "SELECT '?', NULL , X'AB01'");
+    }
+}
+
+#[cfg(test)]
+mod explain_tests {
+    use crate::{Filter, FilterOperator, Model, Sort};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("customers")]
+    struct Customer {
+        pub id: Option<i64>,
+        pub email: String,
+        pub city: String,
+    }
+
+    #[tokio::test]
+    async fn test_explain_detects_index_use_and_scans() {
+        let db = super::memory_db(
+            "CREATE TABLE customers (id INTEGER PRIMARY KEY, email TEXT, city TEXT);
+             CREATE INDEX idx_customers_email ON customers (email);",
+        )
+        .await;
+
+        let by_email = Customer::query_builder()
+            .r#where(FilterOperator::Single(Filter::eq("email", "a@x")))
+            .explain(&db)
+            .await
+            .unwrap();
+        assert!(by_email.uses_index("idx_customers_email"), "{by_email}");
+        assert!(by_email.full_scans().is_empty());
+
+        let by_city = Customer::query_builder()
+            .r#where(FilterOperator::Single(Filter::eq("city", "Oslo")))
+            .explain(&db)
+            .await
+            .unwrap();
+        assert!(!by_city.uses_index("idx_customers_email"));
+        assert_eq!(by_city.full_scans(), ["customers"]);
+
+        let sorted = Customer::query_builder()
+            .order_by(Sort::asc("city"))
+            .explain(&db)
+            .await
+            .unwrap();
+        assert!(sorted.uses_temp_btree(), "{sorted}");
+
+        // Statements other than SELECT are explained too, without running
+        let customer = Customer {
+            id: Some(1),
+            email: "a@x".to_string(),
+            city: "Oslo".to_string(),
+        };
+        let update = crate::Statement::from(customer.update_statement().unwrap())
+            .explain(&db)
+            .await
+            .unwrap();
+        assert!(!update.steps.is_empty());
+        assert_eq!(Customer::count(&db).await.unwrap(), 0);
+    }
+}
+
+#[cfg(test)]
+mod retention_tests {
+    use crate::retention::{enforce_all, RetentionReport};
+    use crate::Model;
+    use chrono::{DateTime, Duration, Utc};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("request_logs")]
+    #[orm(retention(days = 7))]
+    struct RequestLog {
+        pub id: Option<i64>,
+        pub path: String,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("audit_events")]
+    #[orm(retention(days = 30, column = "recorded_at", archive))]
+    struct AuditEvent {
+        pub id: Option<i64>,
+        pub action: String,
+        pub recorded_at: DateTime<Utc>,
+    }
+
+    #[tokio::test]
+    async fn test_expiry_compares_points_in_time() {
+        let db = super::memory_db(
+            "CREATE TABLE request_logs (id INTEGER PRIMARY KEY, path TEXT, created_at TEXT);
+             CREATE TABLE audit_events (id INTEGER PRIMARY KEY, action TEXT, recorded_at TEXT);",
+        )
+        .await;
+        let cutoff = Utc::now() - Duration::days(7);
+        // Just inside the window, but sorting before the cutoff as text
+        let kept = (cutoff + Duration::minutes(1)).format("%Y-%m-%d %H:%M:%S");
+        let expired = (cutoff - Duration::minutes(1)).format("%Y-%m-%dT%H:%M:%S%.3f");
+        db.execute(
+            "INSERT INTO request_logs (path, created_at) VALUES ('kept', ?), ('expired', ?)",
+            vec![kept.to_string().into(), expired.to_string().into()],
+        )
+        .await
+        .unwrap();
+
+        enforce_all(&db).await.unwrap();
+        let paths: Vec<serde_json::Value> = db
+            .query_as("SELECT path FROM request_logs", vec![])
+            .await
+            .unwrap();
+        assert_eq!(paths, [serde_json::json!({"path": "kept"})]);
+    }
+
+    #[tokio::test]
+    async fn test_enforce_all_deletes_and_archives_expired_rows() {
+        let db = super::memory_db(
+            "CREATE TABLE request_logs (id INTEGER PRIMARY KEY, path TEXT, created_at TEXT);
+             CREATE TABLE audit_events (id INTEGER PRIMARY KEY, action TEXT, recorded_at TEXT);",
+        )
+        .await;
+        let now = Utc::now();
+        for days in [1, 10, 40] {
+            RequestLog {
+                id: None,
+                path: format!("/{days}"),
+                created_at: now - Duration::days(days),
+            }
+            .create(&db)
+            .await
+            .unwrap();
+            AuditEvent {
+                id: None,
+                action: format!("login {days}"),
+                recorded_at: now - Duration::days(days),
+            }
+            .create(&db)
+            .await
+            .unwrap();
+        }
+
+        let reports = enforce_all(&db).await.unwrap();
+        assert_eq!(
+            reports,
+            [
+                RetentionReport {
+                    table: "audit_events",
+                    rows: 1,
+                    archived: true,
+                },
+                RetentionReport {
+                    table: "request_logs",
+                    rows: 2,
+                    archived: false,
+                },
+            ]
+        );
+        assert_eq!(RequestLog::count(&db).await.unwrap(), 1);
+        assert_eq!(AuditEvent::count(&db).await.unwrap(), 2);
+
+        let mut rows = db
+            .query("SELECT id, action FROM audit_events_archive", vec![])
+            .await
+            .unwrap();
+        let row = rows.next().await.unwrap().unwrap();
+        assert_eq!(row.get::<i64>(0).unwrap(), 3);
+        assert_eq!(row.get::<String>(1).unwrap(), "login 40");
+        assert!(rows.next().await.unwrap().is_none());
+    }
+}
+
+#[cfg(test)]
+mod archive_tests {
+    use crate::{Filter, FilterOperator, Model};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("tickets")]
+    struct Ticket {
+        pub id: Option<i64>,
+        pub title: String,
+        pub closed: bool,
+    }
+
+    #[tokio::test]
+    async fn test_archive_where_moves_rows() {
+        let db = super::memory_db(
+            "CREATE TABLE tickets (id INTEGER PRIMARY KEY, title TEXT NOT NULL, closed INTEGER);
+             INSERT INTO tickets VALUES (1, 'a', 1), (2, 'b', 0), (3, 'c', 1);",
+        )
+        .await;
+        let closed = FilterOperator::Single(Filter::eq("closed", true));
+        assert!(Ticket::find_archived(closed.clone(), &db)
+            .await
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(Ticket::archive_where(closed.clone(), &db).await.unwrap(), 2);
+        let hot = Ticket::find_all(&db).await.unwrap();
+        assert_eq!(hot.iter().map(|t| t.id).collect::<Vec<_>>(), [Some(2)]);
+
+        // A column added later is added to the archive on the next move
+        db.inner
+            .execute_batch(
+                "ALTER TABLE tickets ADD COLUMN priority INTEGER;
+                 UPDATE tickets SET closed = 1, priority = 5;",
+            )
+            .await
+            .unwrap();
+        assert_eq!(Ticket::archive_where(closed.clone(), &db).await.unwrap(), 1);
+        assert_eq!(Ticket::count(&db).await.unwrap(), 0);
+
+        let archived = Ticket::find_archived(closed, &db).await.unwrap();
+        assert_eq!(
+            archived
+                .iter()
+                .map(|t| t.title.as_str())
+                .collect::<Vec<_>>(),
+            ["a", "c", "b"]
+        );
+        let mut rows = db
+            .query("SELECT priority FROM tickets_archive WHERE id = 2", vec![])
+            .await
+            .unwrap();
+        let row = rows.next().await.unwrap().unwrap();
+        assert_eq!(row.get::<i64>(0).unwrap(), 5);
+    }
+}
+
+#[cfg(test)]
+mod cursor_pagination_tests {
+    use crate::{CursorPagination, Filter, FilterOperator, Model, Sort};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("scores")]
+    struct Score {
+        pub id: Option<i64>,
+        pub player: String,
+        pub points: i64,
+    }
+
+    #[tokio::test]
+    async fn test_find_cursor_pages_by_keyset() {
+        let db = super::memory_db(
+            "CREATE TABLE scores (id INTEGER PRIMARY KEY, player TEXT, points INTEGER NOT NULL);
+             INSERT INTO scores VALUES (1, 'a', 10), (2, 'b', 30), (3, 'c', 20), (4, 'd', 30), (5, 'e', 20);",
+        )
+        .await;
+
+        let mut pagination = CursorPagination::new(2).order_by(Sort::desc("points"));
+        let mut pages = Vec::new();
+        loop {
+            let page = Score::find_cursor(&pagination, &db).await.unwrap();
+            pages.push(page.data.iter().map(|s| s.id.unwrap()).collect::<Vec<_>>());
+            if pages.len() == 1 {
+                // A row inserted before the cursor does not shift later pages
+                db.inner
+                    .execute("INSERT INTO scores VALUES (6, 'f', 40)", ())
+                    .await
+                    .unwrap();
+            }
+            if !page.pagination.has_next {
+                assert!(page.pagination.next_cursor.is_none());
+                break;
+            }
+            pagination.set_cursor(page.pagination.next_cursor);
+        }
+        assert_eq!(pages, [vec![4, 2], vec![5, 3], vec![1]]);
+
+        let by_id = CursorPagination::with_cursor(2, Some("[null, 2]".to_string()));
+        let page = Score::find_where_cursor(
+            FilterOperator::Single(Filter::ge("points", 20i64)),
+            &by_id,
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            page.data.iter().map(|s| s.id).collect::<Vec<_>>(),
+            [Some(3), Some(4)]
+        );
+        assert!(page.pagination.has_prev && page.pagination.has_next);
+
+        let invalid = CursorPagination::with_cursor(2, Some("nope".to_string()));
+        assert!(Score::find_cursor(&invalid, &db).await.is_err());
+    }
+}
+
+#[cfg(test)]
+mod pagination_without_total_tests {
+    use crate::{Model, Pagination};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("posts")]
+    struct Post {
+        pub id: Option<i64>,
+        pub title: String,
+    }
+
+    #[tokio::test]
+    async fn test_find_paginated_without_total() {
+        let db = super::memory_db(
+            "CREATE TABLE posts (id INTEGER PRIMARY KEY, title TEXT);
+             INSERT INTO posts VALUES (1, 'a'), (2, 'b'), (3, 'c'), (4, 'd');",
+        )
+        .await;
+
+        let first = Post::find_paginated(&Pagination::new(1, 3).without_total(), &db)
+            .await
+            .unwrap();
+        assert_eq!(first.len(), 3);
+        assert_eq!(first.pagination.total, None);
+        assert!(first.pagination.has_next());
+
+        let last = Post::find_paginated(&Pagination::new(2, 2).without_total(), &db)
+            .await
+            .unwrap();
+        assert_eq!(last.len(), 2);
+        assert!(!last.pagination.has_next());
+        assert!(last.pagination.has_prev());
+
+        let counted = Post::find_paginated(&Pagination::new(1, 3), &db)
+            .await
+            .unwrap();
+        assert_eq!(counted.pagination.total, Some(4));
+        assert_eq!(counted.pagination.has_more, None);
+    }
+}
+
+#[cfg(test)]
+mod json_column_tests {
+    use crate::{JsonColumn, QueryBuilder, Sort};
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Comment {
+        body: String,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Post {
+        id: i64,
+        title: String,
+        comments: Vec<Comment>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Team {
+        name: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct UserWithPosts {
+        name: String,
+        posts: Vec<Post>,
+        team: Option<Team>,
+    }
+
+    #[tokio::test]
+    async fn test_json_columns_deserialize_nested() {
+        let db = super::memory_db(
+            "CREATE TABLE teams (id INTEGER PRIMARY KEY, name TEXT);
+             CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, team_id INTEGER);
+             CREATE TABLE posts (id INTEGER PRIMARY KEY, user_id INTEGER, title TEXT);
+             CREATE TABLE comments (id INTEGER PRIMARY KEY, post_id INTEGER, body TEXT);
+             INSERT INTO teams VALUES (1, 'core');
+             INSERT INTO users VALUES (1, 'ann', 1), (2, 'bob', NULL);
+             INSERT INTO posts VALUES (1, 1, 'second'), (2, 1, 'first');
+             INSERT INTO comments VALUES (1, 1, 'nice'), (2, 1, 'it''s \"quoted\"');",
+        )
+        .await;
+
+        let users: Vec<UserWithPosts> = QueryBuilder::new("users")
+            .select(vec!["users.name"])
+            .json_column(
+                JsonColumn::array("posts", "posts.user_id = users.id")
+                    .columns(vec!["id", "title"])
+                    .nest(
+                        JsonColumn::array("comments", "comments.post_id = posts.id")
+                            .columns(vec!["body"])
+                            .order_by(Sort::asc("comments.id")),
+                    )
+                    .order_by(Sort::desc("posts.id")),
+            )
+            .json_column(
+                JsonColumn::object("teams", "teams.id = users.team_id")
+                    .columns(vec!["name"])
+                    .alias("team"),
+            )
+            .order_by(Sort::asc("users.id"))
+            .execute(&db)
+            .await
+            .unwrap();
+
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[0].name, "ann");
+        assert_eq!(
+            users[0].posts,
+            [
+                Post {
+                    id: 2,
+                    title: "first".to_string(),
+                    comments: vec![],
+                },
+                Post {
+                    id: 1,
+                    title: "second".to_string(),
+                    comments: vec![
+                        Comment {
+                            body: "nice".to_string()
+                        },
+                        Comment {
+                            body: "it's \"quoted\"".to_string()
+                        },
+                    ],
+                },
+            ]
+        );
+        assert_eq!(
+            users[0].team,
+            Some(Team {
+                name: "core".to_string()
+            })
+        );
+        assert!(users[1].posts.is_empty());
+        assert_eq!(users[1].team, None);
+    }
+}
+
+#[cfg(test)]
+mod subquery_column_tests {
+    use crate::{Aggregate, Filter, FilterOperator, QueryBuilder, Sort};
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct PostListing {
+        title: String,
+        comment_count: i64,
+        latest_approved: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn test_select_subquery_binds_params_in_order() {
+        let db = super::memory_db(
+            "CREATE TABLE posts (id INTEGER PRIMARY KEY, title TEXT, published INTEGER);
+             CREATE TABLE comments (id INTEGER PRIMARY KEY, post_id INTEGER, body TEXT, approved INTEGER);
+             INSERT INTO posts VALUES (1, 'a', 1), (2, 'b', 1), (3, 'c', 0);
+             INSERT INTO comments VALUES (1, 1, 'x', 1), (2, 1, 'y', 0), (3, 1, 'z', 1);",
+        )
+        .await;
+        let correlated =
+            || FilterOperator::Single(Filter::eq_column("comments.post_id", "posts.id"));
+
+        let listings: Vec<PostListing> = QueryBuilder::new("posts")
+            .select(vec!["posts.title"])
+            .select_subquery(
+                QueryBuilder::new("comments")
+                    .aggregate(Aggregate::Count, "*", None::<String>)
+                    .r#where(correlated()),
+                "comment_count",
+            )
+            .select_subquery(
+                QueryBuilder::new("comments")
+                    .select(vec!["body"])
+                    .r#where(correlated())
+                    .r#where(FilterOperator::Single(Filter::eq("approved", true)))
+                    .order_by(Sort::desc("comments.id"))
+                    .limit(1),
+                "latest_approved",
+            )
+            .r#where(FilterOperator::Single(Filter::eq("published", true)))
+            .order_by(Sort::asc("posts.id"))
+            .execute(&db)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            listings,
+            [
+                PostListing {
+                    title: "a".to_string(),
+                    comment_count: 3,
+                    latest_approved: Some("z".to_string()),
+                },
+                PostListing {
+                    title: "b".to_string(),
+                    comment_count: 0,
+                    latest_approved: None,
+                },
+            ]
+        );
+    }
+}
+
+#[cfg(test)]
+mod stream_tests {
+    use crate::{Filter, FilterOperator, Model};
+    use futures::{StreamExt, TryStreamExt};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("events")]
+    struct Event {
+        pub id: Option<i64>,
+        pub kind: String,
+    }
+
+    #[tokio::test]
+    async fn test_stream_where_yields_rows_lazily() {
+        let db = super::memory_db(
+            "CREATE TABLE events (id INTEGER PRIMARY KEY, kind TEXT);
+             WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 1000)
+             INSERT INTO events SELECT i, CASE WHEN i % 2 THEN 'odd' ELSE 'even' END FROM n;",
+        )
+        .await;
+        let odd = || FilterOperator::Single(Filter::eq("kind", "odd"));
+
+        let first: Vec<Event> = Event::stream_where(odd(), &db)
+            .take(3)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(
+            first.iter().map(|e| e.id).collect::<Vec<_>>(),
+            [Some(1), Some(3), Some(5)]
+        );
+
+        let mut count = 0;
+        let mut stream = std::pin::pin!(Event::stream_where(odd(), &db));
+        while let Some(event) = stream.next().await {
+            assert_eq!(event.unwrap().kind, "odd");
+            count += 1;
+        }
+        assert_eq!(count, 500);
+
+        let mut broken = std::pin::pin!(Event::stream_where(
+            FilterOperator::Single(Filter::eq("missing", 1i64)),
+            &db
+        ));
+        assert!(broken.next().await.unwrap().is_err());
+        assert!(broken.next().await.is_none());
+    }
+}
+
+#[cfg(test)]
+mod conditional_upsert_tests {
+    use crate::{Filter, FilterOperator, Model, Operator};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("orders")]
+    struct Order {
+        pub id: Option<i64>,
+        pub external_id: String,
+        pub status: String,
+        pub version: i64,
+    }
+
+    #[tokio::test]
+    async fn test_upsert_where_skips_stale_updates() {
+        let db = super::memory_db(
+            "CREATE TABLE orders (id INTEGER PRIMARY KEY, external_id TEXT UNIQUE, status TEXT, version INTEGER);",
+        )
+        .await;
+        let newer = || {
+            FilterOperator::Single(Filter::compare_column(
+                "excluded.version",
+                Operator::Gt,
+                "version",
+            ))
+        };
+        let event = |status: &str, version| Order {
+            id: None,
+            external_id: "ord_1".to_string(),
+            status: status.to_string(),
+            version,
+        };
+
+        let created = event("paid", 2)
+            .upsert_where(&["external_id"], newer(), &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(created.status, "paid");
+
+        // A late, older webhook does not overwrite the newer state
+        let stale = event("pending", 1)
+            .upsert_where(&["external_id"], newer(), &db)
+            .await
+            .unwrap();
+        assert!(stale.is_none());
+
+        let shipped = event("shipped", 3)
+            .upsert_where(&["external_id"], newer(), &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((shipped.id, shipped.version), (created.id, 3));
+
+        // Conditions can also bind values
+        let only_if_shipped = FilterOperator::Single(Filter::eq("status", "shipped"));
+        let delivered = event("delivered", 4)
+            .upsert_where(&["external_id"], only_if_shipped, &db)
+            .await
+            .unwrap();
+        assert_eq!(delivered.unwrap().status, "delivered");
+        assert_eq!(Order::count(&db).await.unwrap(), 1);
+    }
+}
+
+#[cfg(test)]
+mod batch_iteration_tests {
+    use crate::{Filter, FilterOperator, Model};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("invoices")]
+    struct Invoice {
+        pub id: Option<i64>,
+        pub amount: i64,
+    }
+
+    #[tokio::test]
+    async fn test_find_in_batches_and_each() {
+        let db = super::memory_db(
+            "CREATE TABLE invoices (id INTEGER PRIMARY KEY, amount INTEGER);
+             INSERT INTO invoices VALUES (1, 10), (2, 20), (4, 40), (7, 70), (9, 90);",
+        )
+        .await;
+
+        let mut batches = Vec::new();
+        let visited = Invoice::find_in_batches(2, &db, |batch| {
+            batches.push(batch.iter().map(|i| i.id.unwrap()).collect::<Vec<_>>());
+            async { Ok(()) }
+        })
+        .await
+        .unwrap();
+        assert_eq!(visited, 5);
+        assert_eq!(batches, [vec![1, 2], vec![4, 7], vec![9]]);
+
+        let mut total = 0;
+        let visited = Invoice::find_where_in_batches(
+            FilterOperator::Single(Filter::ge("amount", 40i64)),
+            2,
+            &db,
+            |batch| {
+                total += batch.iter().map(|i| i.amount).sum::<i64>();
+                async { Ok(()) }
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!((visited, total), (3, 200));
+
+        // Records deleted mid-walk do not shift later batches
+        let db = &db;
+        let mut seen = Vec::new();
+        Invoice::find_each(2, db, |invoice| {
+            seen.push(invoice.id.unwrap());
+            async move {
+                if invoice.id == Some(1) {
+                    Invoice::delete_where(FilterOperator::Single(Filter::eq("id", 2i64)), db)
+                        .await?;
+                }
+                Ok(())
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(seen, [1, 2, 4, 7, 9]);
+
+        let failed = Invoice::find_each(2, db, |_| async {
+            Err(crate::Error::Validation("stop".to_string()))
+        })
+        .await;
+        assert!(failed.is_err());
+        assert!(Invoice::find_in_batches(0, db, |_| async { Ok(()) })
+            .await
+            .is_err());
+    }
+}
+
+#[cfg(test)]
+mod bulk_create_tests {
+    use crate::Model;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("readings")]
+    struct Reading {
+        pub id: Option<i64>,
+        pub sensor: String,
+        pub value: f64,
+    }
+
+    #[tokio::test]
+    async fn test_bulk_create_chunks_and_assigns_ids() {
+        let db = super::memory_db(
+            "CREATE TABLE readings (id INTEGER PRIMARY KEY, sensor TEXT, value REAL);
+             INSERT INTO readings VALUES (100, 'old', 0);",
+        )
+        .await;
+        // Enough rows to need more than one statement
+        let readings: Vec<Reading> = (0..12_000)
+            .map(|i| Reading {
+                id: None,
+                sensor: format!("s{i}"),
+                value: i as f64,
+            })
+            .collect();
+
+        let created = Reading::bulk_create(&readings, &db).await.unwrap();
+        assert_eq!(created.len(), 12_000);
+        assert_eq!(created[0].id, Some(101));
+        assert_eq!(created[11_999].id, Some(12_100));
+        assert_eq!(Reading::count(&db).await.unwrap(), 12_001);
+
+        let last = Reading::find_by_id(12_100, &db).await.unwrap().unwrap();
+        assert_eq!(last.sensor, "s11999");
+        assert!(Reading::bulk_create(&[], &db).await.unwrap().is_empty());
+    }
+
+    /// Assert that each record was returned with the key of its own row
+    async fn assert_stored(records: &[Reading], db: &crate::Database) {
+        for record in records {
+            let stored = Reading::find_by_id(record.id.unwrap(), db)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(stored.sensor, record.sensor);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bulk_create_matches_rows_by_key() {
+        let db = super::memory_db(
+            "CREATE TABLE readings (id INTEGER PRIMARY KEY, sensor TEXT, value REAL);
+             INSERT INTO readings VALUES (10, 'old', 0);",
+        )
+        .await;
+        let reading = |id, sensor: &str| Reading {
+            id,
+            sensor: sensor.to_string(),
+            value: 1.0,
+        };
+        let created = Reading::bulk_create(
+            &[
+                reading(None, "a"),
+                reading(Some(500), "b"),
+                reading(None, "c"),
+                reading(Some(3), "d"),
+                reading(None, "e"),
+            ],
+            &db,
+        )
+        .await
+        .unwrap();
+        let ids: Vec<_> = created.iter().map(|r| r.id.unwrap()).collect();
+        assert_eq!(ids, [11, 500, 501, 3, 502]);
+        assert_stored(&created, &db).await;
+
+        // Past the largest key SQLite picks random ones
+        let created = Reading::bulk_create(
+            &[
+                reading(Some(i64::MAX), "max"),
+                reading(None, "f"),
+                reading(None, "g"),
+                reading(None, "h"),
+            ],
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_stored(&created, &db).await;
+        let more = Reading::bulk_create(&[reading(None, "i"), reading(None, "j")], &db)
+            .await
+            .unwrap();
+        assert_stored(&more, &db).await;
+        assert_eq!(Reading::count(&db).await.unwrap(), 12);
+    }
+}
+
+#[cfg(test)]
+mod script_tests {
+    use crate::script::split_statements;
+    use crate::Error;
+
+    #[test]
+    fn test_split_statements_respects_literals_and_triggers() {
+        let script = "-- header; not a statement\n\
+            CREATE TABLE t (id INTEGER PRIMARY KEY, note TEXT, \"odd;name\" TEXT);\n\
+            INSERT INTO t (note) VALUES ('a;b'), ('it''s; fine'); /* c; d */\n\
+            CREATE TRIGGER t_ai AFTER INSERT ON t BEGIN\n\
+              UPDATE t SET note = CASE WHEN note IS NULL THEN 'x' ELSE note END WHERE id = new.id;\n\
+              SELECT 1;\n\
+            END;\n\
+            SELECT 2";
+        let statements: Vec<&str> = split_statements(script)
+            .into_iter()
+            .map(|(_, sql)| sql)
+            .collect();
+        assert_eq!(statements.len(), 4);
+        assert!(statements[0].starts_with("CREATE TABLE t"));
+        assert!(statements[1].ends_with("('it''s; fine')"));
+        assert!(statements[2].starts_with("CREATE TRIGGER") && statements[2].ends_with("END"));
+        assert_eq!(statements[3], "SELECT 2");
+        assert!(split_statements(" ; -- only a comment\n").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_execute_script_reports_failing_statement() {
+        let db = super::memory_db("").await;
+        let script = "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT);\n\
+            CREATE TRIGGER notes_ai AFTER INSERT ON notes BEGIN\n\
+              UPDATE notes SET body = body || ';' WHERE id = new.id;\n\
+            END;\n\
+            INSERT INTO notes (body) VALUES ('one');\n\
+            PRAGMA table_info(notes);";
+        assert_eq!(db.execute_script(script).await.unwrap(), 4);
+        let bodies: Vec<serde_json::Value> =
+            db.query_as("SELECT body FROM notes", vec![]).await.unwrap();
+        assert_eq!(bodies, [serde_json::json!({"body": "one;"})]);
+
+        let broken = "INSERT INTO notes (body) VALUES ('two');\nINSERT INTO missing VALUES (1);\nINSERT INTO notes (body) VALUES ('three');";
+        match db.execute_script(broken).await {
+            Err(Error::Script {
+                statement,
+                line,
+                offset,
+                message,
+            }) => {
+                assert_eq!((statement, line, offset), (2, 2, 41));
+                assert!(message.contains("missing"), "{message}");
+            }
+            other => panic!("expected a script error, got {other:?}"),
+        }
+        let count: Vec<serde_json::Value> = db
+            .query_as("SELECT COUNT(*) AS n FROM notes", vec![])
+            .await
+            .unwrap();
+        assert_eq!(count, [serde_json::json!({"n": 2})]);
+    }
+}
+
+#[cfg(test)]
+mod bulk_update_tests {
+    use crate::{Filter, FilterOperator, Model};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("stock")]
+    struct Stock {
+        pub id: Option<i64>,
+        pub sku: String,
+        pub quantity: i64,
+    }
+
+    #[tokio::test]
+    async fn test_bulk_update_updates_each_row_in_chunks() {
+        let db = super::memory_db(
+            "CREATE TABLE stock (id INTEGER PRIMARY KEY, sku TEXT, quantity INTEGER)",
+        )
+        .await;
+        // Enough rows to need more than one statement
+        let created = Stock::bulk_create(
+            &(0..1_200)
+                .map(|i| Stock {
+                    id: None,
+                    sku: format!("sku{i}"),
+                    quantity: 0,
+                })
+                .collect::<Vec<_>>(),
+            &db,
+        )
+        .await
+        .unwrap();
+
+        let mut changed: Vec<Stock> = created
+            .iter()
+            .skip(1)
+            .map(|stock| Stock {
+                quantity: stock.id.unwrap() * 2,
+                ..stock.clone()
+            })
+            .collect();
+        // The last occurrence of a record wins
+        changed.push(Stock {
+            quantity: -1,
+            ..created[5].clone()
+        });
+        let updated = Stock::bulk_update(&changed, &db).await.unwrap();
+        assert_eq!(updated.len(), changed.len());
+
+        let untouched = Stock::find_by_id(created[0].id.unwrap(), &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(untouched.quantity, 0);
+        let last = created[1_199].id.unwrap();
+        let stored = Stock::find_by_id(last, &db).await.unwrap().unwrap();
+        assert_eq!(
+            (stored.sku.as_str(), stored.quantity),
+            ("sku1199", last * 2)
+        );
+        let repeated = Stock::find_by_id(created[5].id.unwrap(), &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(repeated.quantity, -1);
+        let doubled = Stock::count_where(FilterOperator::Single(Filter::gt("quantity", 0i64)), &db)
+            .await
+            .unwrap();
+        assert_eq!(doubled, 1_198);
+
+        let missing_id = Stock {
+            id: None,
+            sku: "x".to_string(),
+            quantity: 1,
+        };
+        assert!(Stock::bulk_update(&[missing_id], &db).await.is_err());
+    }
+}
+
+#[cfg(test)]
+mod sql_migration_tests {
+    use crate::{Migration, MigrationBuilder, MigrationManager};
+
+    const CREATE_TAGS: &str = "-- Tags for posts
+-- +migrate Up
+CREATE TABLE tags (id INTEGER PRIMARY KEY, name TEXT);
+-- +migrate StatementBegin
+CREATE TRIGGER tags_lower AFTER INSERT ON tags BEGIN
+    UPDATE tags SET name = lower(name) WHERE id = new.id;
+END;
+-- +migrate StatementEnd
+
+-- +migrate Down
+DROP TABLE tags;
+";
+
+    async fn table_exists(manager: &MigrationManager, table: &str) -> bool {
+        let mut rows = manager
+            .database()
+            .query(
+                "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?",
+                vec![libsql::Value::Text(table.to_string())],
+            )
+            .await
+            .unwrap();
+        rows.next().await.unwrap().is_some()
+    }
+
+    #[test]
+    fn test_from_sql_splits_sections() {
+        let migration = Migration::from_sql("0001_tags", CREATE_TAGS).unwrap();
+        assert_eq!(migration.id, "0001_tags");
+        assert!(migration.sql.starts_with("CREATE TABLE tags"));
+        assert!(migration.sql.trim_end().ends_with("END;"));
+        assert_eq!(migration.down_sql.as_deref(), Some("DROP TABLE tags;"));
+
+        let plain = Migration::from_sql("plain", "CREATE TABLE a (id INTEGER);").unwrap();
+        assert_eq!(plain.sql, "CREATE TABLE a (id INTEGER);");
+        assert!(plain.down_sql.is_none());
+
+        assert!(Migration::from_sql("x", "DROP TABLE a;\n-- +migrate Up\nSELECT 1;").is_err());
+        assert!(Migration::from_sql("x", "-- +migrate Sideways\nSELECT 1;").is_err());
+        assert!(Migration::from_sql("x", "-- +migrate Down\nDROP TABLE a;").is_err());
+
+        let built = MigrationBuilder::new("b")
+            .up("SELECT 1")
+            .down("SELECT 2")
+            .build();
+        assert_eq!(built.down_sql.as_deref(), Some("SELECT 2"));
+    }
+
+    #[tokio::test]
+    async fn test_sql_dir_migrations_run_once_and_roll_back() {
+        let dir = std::env::temp_dir().join(format!("orm_sql_migrations_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("0002_tag_index.sql"), "-- +migrate Up\nCREATE INDEX idx_tags_name ON tags (name);\n-- +migrate Down\nDROP INDEX idx_tags_name;\n").unwrap();
+        std::fs::write(dir.join("0001_tags.sql"), CREATE_TAGS).unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a migration").unwrap();
+        let migrations = MigrationManager::load_sql_dir(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let names: Vec<&str> = migrations.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["0001_tags", "0002_tag_index"]);
+
+        let manager = MigrationManager::new(super::memory_db("").await);
+        manager.init().await.unwrap();
+        manager.run_migrations(migrations.clone()).await.unwrap();
+        // Already executed migrations are skipped on the next run
+        manager.run_migrations(migrations).await.unwrap();
+        assert_eq!(manager.get_executed_migrations().await.unwrap().len(), 2);
+
+        let db = manager.database();
+        db.execute("INSERT INTO tags (name) VALUES ('Rust')", vec![])
+            .await
+            .unwrap();
+        let names: Vec<serde_json::Value> =
+            db.query_as("SELECT name FROM tags", vec![]).await.unwrap();
+        assert_eq!(names, [serde_json::json!({"name": "rust"})]);
+
+        manager.rollback_migration("0002_tag_index").await.unwrap();
+        manager.rollback_migration("0001_tags").await.unwrap();
+        assert!(!table_exists(&manager, "tags").await);
+        assert!(manager.get_executed_migrations().await.unwrap().is_empty());
+    }
+}
+
+#[cfg(test)]
+mod bulk_upsert_tests {
+    use crate::Model;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("feed_items")]
+    struct FeedItem {
+        pub id: Option<i64>,
+        pub external_id: String,
+        pub title: String,
+    }
+
+    fn item(external_id: &str, title: &str) -> FeedItem {
+        FeedItem {
+            id: None,
+            external_id: external_id.to_string(),
+            title: title.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bulk_upsert_inserts_and_updates() {
+        let db = super::memory_db(
+            "CREATE TABLE feed_items (id INTEGER PRIMARY KEY, external_id TEXT UNIQUE, title TEXT);
+             INSERT INTO feed_items VALUES (7, 'b', 'old b');",
+        )
+        .await;
+
+        let stored = FeedItem::bulk_upsert(
+            &[item("a", "A"), item("b", "new b"), item("c", "C")],
+            &["external_id"],
+            &db,
+        )
+        .await
+        .unwrap();
+        let summary: Vec<(Option<i64>, &str)> = stored
+            .iter()
+            .map(|item| (item.id, item.title.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [(Some(8), "A"), (Some(7), "new b"), (Some(9), "C")]
+        );
+        assert_eq!(FeedItem::count(&db).await.unwrap(), 3);
+
+        // Enough rows to need more than one statement
+        let many: Vec<FeedItem> = (0..12_000)
+            .map(|i| item(&format!("e{i}"), "bulk"))
+            .collect();
+        let stored = FeedItem::bulk_upsert(&many, &["external_id"], &db)
+            .await
+            .unwrap();
+        assert_eq!(stored.len(), 12_000);
+        assert!(stored
+            .iter()
+            .zip(&many)
+            .all(|(stored, sent)| stored.external_id == sent.external_id));
+        assert_eq!(FeedItem::count(&db).await.unwrap(), 12_003);
+
+        assert!(FeedItem::bulk_upsert(&many[..1], &[], &db).await.is_err());
+        assert!(FeedItem::bulk_upsert(&many[..1], &["missing"], &db)
+            .await
+            .is_err());
+        assert!(FeedItem::bulk_upsert(&[], &["external_id"], &db)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
+
+#[cfg(test)]
+mod conditional_migration_tests {
+    use crate::{MigrationBuilder, MigrationManager};
+
+    async fn columns(manager: &MigrationManager) -> Vec<String> {
+        let rows: Vec<serde_json::Value> = manager
+            .database()
+            .query_as("SELECT name FROM pragma_table_info('plans')", vec![])
+            .await
+            .unwrap();
+        rows.iter()
+            .map(|row| row["name"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_run_if_skips_unmet_migrations() {
+        let manager = MigrationManager::new(
+            super::memory_db(
+                "CREATE TABLE plans (id INTEGER PRIMARY KEY, name TEXT, price INTEGER)",
+            )
+            .await,
+        );
+        manager.init().await.unwrap();
+
+        let add_price = MigrationBuilder::new("add_price")
+            .up("ALTER TABLE plans ADD COLUMN price INTEGER")
+            .run_if_column_missing("plans", "price")
+            .build();
+        let add_tier = MigrationBuilder::new("add_tier")
+            .up("ALTER TABLE plans ADD COLUMN tier TEXT")
+            .run_if_column_missing("plans", "tier")
+            .build();
+        let seed = MigrationBuilder::new("seed")
+            .up("INSERT INTO plans (name) VALUES ('free')")
+            .run_if_empty("plans")
+            .build();
+        let failing = MigrationBuilder::new("failing")
+            .up("DROP TABLE plans")
+            .run_if(|_| Box::pin(async { Err(crate::Error::Validation("no".to_string())) }))
+            .build();
+
+        manager
+            .run_migrations(vec![add_price, add_tier, seed.clone()])
+            .await
+            .unwrap();
+        assert_eq!(columns(&manager).await, ["id", "name", "price", "tier"]);
+        // Skipped migrations are recorded too
+        assert_eq!(manager.get_executed_migrations().await.unwrap().len(), 3);
+
+        let mut reseed = seed;
+        reseed.id = "reseed".to_string();
+        manager.execute_migration(&reseed).await.unwrap();
+        let count: Vec<serde_json::Value> = manager
+            .database()
+            .query_as("SELECT COUNT(*) AS n FROM plans", vec![])
+            .await
+            .unwrap();
+        assert_eq!(count, [serde_json::json!({"n": 1})]);
+
+        // A failing predicate fails the migration
+        assert!(manager.execute_migration(&failing).await.is_err());
+        assert_eq!(manager.get_executed_migrations().await.unwrap().len(), 4);
+    }
+}
+
+#[cfg(test)]
+mod migration_verification_tests {
+    use crate::{Error, MigrationBuilder, MigrationManager};
+
+    async fn nulls(manager: &MigrationManager) -> serde_json::Value {
+        let rows: Vec<serde_json::Value> = manager
+            .database()
+            .query_as(
+                "SELECT COUNT(*) AS n FROM users WHERE email IS NULL",
+                vec![],
+            )
+            .await
+            .unwrap();
+        rows[0]["n"].clone()
+    }
+
+    #[tokio::test]
+    async fn test_failed_verification_rolls_back_unless_kept() {
+        let manager = MigrationManager::new(
+            super::memory_db(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, login TEXT, email TEXT);
+                 INSERT INTO users (login, email) VALUES ('a', NULL), (NULL, NULL);",
+            )
+            .await,
+        );
+        manager.init().await.unwrap();
+
+        // The second user has no login, so the backfill leaves a NULL
+        let backfill = MigrationBuilder::new("backfill")
+            .up("UPDATE users SET email = login || '@example.com'")
+            .verify("SELECT COUNT(*) FROM users WHERE email IS NULL", 0);
+        let error = manager
+            .run_migrations(vec![backfill.build()])
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&error, Error::Validation(msg) if msg.contains("returned 1, expected 0")),
+            "{error}"
+        );
+        assert_eq!(nulls(&manager).await, 2);
+        assert!(manager.get_executed_migrations().await.unwrap().is_empty());
+
+        let kept = MigrationBuilder::new("backfill_kept")
+            .up("UPDATE users SET email = login || '@example.com'")
+            .verify("SELECT COUNT(*) FROM users WHERE email IS NULL", 0)
+            .keep_on_failed_verification()
+            .build();
+        assert!(manager.execute_migration(&kept).await.is_err());
+        assert_eq!(nulls(&manager).await, 1);
+        assert_eq!(manager.get_executed_migrations().await.unwrap().len(), 1);
+
+        let fixed = MigrationBuilder::new("fix")
+            .up("UPDATE users SET email = 'unknown@example.com' WHERE email IS NULL")
+            .verify("SELECT COUNT(*) FROM users WHERE email IS NULL", 0)
+            .verify("SELECT COUNT(*) FROM users", 2)
+            .build();
+        manager.execute_migration(&fixed).await.unwrap();
+        assert_eq!(nulls(&manager).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_multi_statement_migration_runs_in_one_transaction() {
+        let manager = MigrationManager::new(
+            super::memory_db("CREATE TABLE users (id INTEGER PRIMARY KEY, login TEXT, email TEXT)")
+                .await,
+        );
+        manager.init().await.unwrap();
+
+        let broken = MigrationBuilder::new("broken")
+            .up("INSERT INTO users (login) VALUES ('a'); INSERT INTO missing VALUES (1);")
+            .build();
+        assert!(manager.execute_migration(&broken).await.is_err());
+        assert_eq!(nulls(&manager).await, 0);
+        assert!(manager.get_executed_migrations().await.unwrap().is_empty());
+
+        let seed = MigrationBuilder::new("seed")
+            .up("INSERT INTO users (login) VALUES ('a'); INSERT INTO users (login) VALUES ('b');")
+            .build();
+        manager.execute_migration(&seed).await.unwrap();
+        assert_eq!(nulls(&manager).await, 2);
+    }
+}
+
+#[cfg(test)]
+mod create_returning_tests {
+    use crate::Model;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("tickets")]
+    struct Ticket {
+        pub id: Option<i64>,
+        pub subject: String,
+        pub status: Option<String>,
+        pub open: bool,
+    }
+
+    #[tokio::test]
+    async fn test_create_returns_assigned_ids() {
+        let db = super::memory_db(
+            "CREATE TABLE tickets (
+                id INTEGER PRIMARY KEY,
+                subject TEXT,
+                status TEXT DEFAULT 'new',
+                open INTEGER
+             );
+             INSERT INTO tickets (id, subject, open) VALUES (41, 'old', 0);",
+        )
+        .await;
+        let ticket = Ticket {
+            id: None,
+            subject: "printer".to_string(),
+            status: None,
+            open: true,
+        };
+
+        let first = ticket.create(&db).await.unwrap();
+        let second = ticket.create(&db).await.unwrap();
+        assert_eq!((first.id, second.id), (Some(42), Some(43)));
+        assert!(first.open);
+        // An explicit NULL is stored, not the column default
+        assert_eq!(first.status, None);
+
+        let stored = Ticket::find_by_id(43, &db).await.unwrap().unwrap();
+        assert_eq!(stored.subject, "printer");
+        assert_eq!(Ticket::count(&db).await.unwrap(), 3);
+    }
+}
+
+#[cfg(test)]
+mod statement_cache_tests {
+    use crate::{Model, StatementCacheStats};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("notes")]
+    struct Note {
+        pub id: Option<i64>,
+        pub body: String,
+    }
+
+    const SCHEMA: &str = "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)";
+
+    #[tokio::test]
+    async fn test_repeated_statements_reuse_prepared_statements() {
+        let db = super::memory_db(SCHEMA).await;
+        let note = Note {
+            id: None,
+            body: "a".to_string(),
+        };
+        let created = note.create(&db).await.unwrap();
+        note.create(&db).await.unwrap();
+        for _ in 0..3 {
+            Note::find_by_id(created.id.unwrap(), &db).await.unwrap();
+        }
+        let stats = db.statement_cache_stats();
+        assert_eq!((stats.hits, stats.misses, stats.cached), (3, 2, 2));
+
+        // Rows were reset, so the table can be dropped
+        db.execute("DROP TABLE notes", vec![]).await.unwrap();
+        assert!(Note::find_by_id(1, &db).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_statement_cache_capacity() {
+        let db = super::memory_db(SCHEMA).await.with_statement_cache(2);
+        for id in 0..3 {
+            db.execute(
+                &format!("UPDATE notes SET body = 'x' WHERE id = {id}"),
+                vec![],
+            )
+            .await
+            .unwrap();
+        }
+        // The least recently used statement was evicted
+        db.execute("UPDATE notes SET body = 'x' WHERE id = 0", vec![])
+            .await
+            .unwrap();
+        db.execute("UPDATE notes SET body = 'x' WHERE id = 2", vec![])
+            .await
+            .unwrap();
+        let stats = db.statement_cache_stats();
+        assert_eq!((stats.hits, stats.misses, stats.cached), (1, 4, 2));
+
+        let db = db.with_statement_cache(0);
+        Note::find_all(&db).await.unwrap();
+        assert_eq!(
+            db.statement_cache_stats(),
+            StatementCacheStats {
+                hits: 0,
+                misses: 0,
+                cached: 0,
+                capacity: 0
+            }
+        );
+    }
+}
+
+#[cfg(test)]
+mod datetime_aggregate_tests {
+    use crate::{Filter, FilterOperator, Model};
+    use chrono::{DateTime, TimeZone, Utc};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("logins")]
+    struct Login {
+        pub id: Option<i64>,
+        pub user_id: i64,
+        pub at: DateTime<Utc>,
+    }
+
+    #[tokio::test]
+    async fn test_min_and_max_datetime() {
+        let db = super::memory_db(
+            "CREATE TABLE logins (id INTEGER PRIMARY KEY, user_id INTEGER, at TEXT)",
+        )
+        .await;
+        assert_eq!(Login::max_datetime("at", None, &db).await.unwrap(), None);
+
+        let times = [
+            Utc.with_ymd_and_hms(2024, 3, 1, 9, 30, 0).unwrap(),
+            Utc.with_ymd_and_hms(2023, 12, 31, 23, 59, 59).unwrap(),
+            Utc.with_ymd_and_hms(2024, 11, 5, 0, 0, 1).unwrap(),
+        ];
+        for (user_id, at) in [(1, times[0]), (1, times[1]), (2, times[2])] {
+            Login {
+                id: None,
+                user_id,
+                at,
+            }
+            .create(&db)
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(
+            Login::min_datetime("at", None, &db).await.unwrap(),
+            Some(times[1])
+        );
+        assert_eq!(
+            Login::max_datetime("at", None, &db).await.unwrap(),
+            Some(times[2])
+        );
+        let first_user = Some(FilterOperator::Single(Filter::eq("user_id", 1i64)));
+        assert_eq!(
+            Login::max_datetime("at", first_user, &db).await.unwrap(),
+            Some(times[0])
+        );
+
+        db.execute("UPDATE logins SET at = 'soon' WHERE user_id = 2", vec![])
+            .await
+            .unwrap();
+        assert!(Login::max_datetime("at", None, &db).await.is_err());
+    }
+}
+
+#[cfg(test)]
+mod batch_tests {
+    use libsql::Value;
+
+    #[tokio::test]
+    async fn test_batch_runs_statements_atomically() {
+        let db = super::memory_db(
+            "CREATE TABLE entries (id INTEGER PRIMARY KEY, label TEXT, amount REAL, data BLOB);
+             INSERT INTO entries (id, label) VALUES (1, 'old');",
+        )
+        .await;
+        let insert = "INSERT INTO entries (label, amount, data) VALUES (?, ?, ?)";
+        let changed = db
+            .batch(vec![
+                (
+                    insert.to_string(),
+                    vec![
+                        Value::Text("it's ? here".to_string()),
+                        Value::Real(2.0),
+                        Value::Blob(vec![0, 255]),
+                    ],
+                ),
+                (
+                    insert.to_string(),
+                    vec![Value::Null, Value::Real(f64::INFINITY), Value::Null],
+                ),
+                (
+                    "UPDATE entries SET label = ? WHERE id = ?".to_string(),
+                    vec![Value::Text("new".to_string()), Value::Integer(1)],
+                ),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(changed, 3);
+
+        let rows: Vec<serde_json::Value> = db
+            .query_as(
+                "SELECT label, typeof(amount) AS kind, hex(data) AS data FROM entries ORDER BY id",
+                vec![],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            rows,
+            [
+                serde_json::json!({"label": "new", "kind": "null", "data": ""}),
+                serde_json::json!({"label": "it's ? here", "kind": "real", "data": "00FF"}),
+                serde_json::json!({"label": null, "kind": "real", "data": ""}),
+            ]
+        );
+
+        // A failing statement rolls back the ones before it
+        let failed = db
+            .batch(vec![
+                ("DELETE FROM entries".to_string(), vec![]),
+                ("INSERT INTO missing VALUES (1)".to_string(), vec![]),
+            ])
+            .await;
+        assert!(failed.is_err());
+        let count: Vec<serde_json::Value> = db
+            .query_as("SELECT COUNT(*) AS n FROM entries", vec![])
+            .await
+            .unwrap();
+        assert_eq!(count, [serde_json::json!({"n": 3})]);
+        assert_eq!(db.batch(Vec::<crate::Statement>::new()).await.unwrap(), 0);
+
+        // Inside a transaction the batch joins it and still counts its changes
+        let changed = db
+            .transaction(|tx| async move {
+                tx.batch(vec![
+                    ("UPDATE entries SET amount = 1".to_string(), vec![]),
+                    ("DELETE FROM entries WHERE id = 1".to_string(), vec![]),
+                ])
+                .await
+            })
+            .await
+            .unwrap();
+        assert_eq!(changed, 4);
+    }
+
+    #[test]
+    fn test_parameters_are_inlined_like_sqlite_binds_them() {
+        let statement = |sql: &str, params: Vec<Value>| {
+            crate::Statement::from((sql.to_string(), params)).to_string()
+        };
+        assert_eq!(
+            statement(
+                "SELECT ? -- is it ?\n, '?', \"?\", [?], `?` /* ? */, ?",
+                vec![Value::Integer(1), Value::Integer(2)]
+            ),
+            "SELECT 1 -- is it ?\n, '?', \"?\", [?], `?` /* ? */, 2"
+        );
+        assert_eq!(
+            statement(
+                "SELECT ?2, ?1, :name, @other, :name, ?, a$b",
+                vec![
+                    Value::Integer(1),
+                    Value::Integer(2),
+                    Value::Integer(3),
+                    Value::Integer(4),
+                    Value::Integer(5),
+                ]
+            ),
+            "SELECT 2, 1, 3, 4, 3, 5, a$b"
+        );
+        assert_eq!(
+            statement("SELECT ?", vec![Value::Text("a\0b".to_string())]),
+            "SELECT CAST(X'610062' AS TEXT)"
+        );
+        assert_eq!(
+            statement("SELECT ?, ?", vec![Value::Null]),
+            "SELECT NULL, ?"
+        );
+    }
+}
+
+#[cfg(test)]
+mod having_count_tests {
+    use crate::Model;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("hc_posts")]
+    struct Post {
+        pub id: Option<i64>,
+        pub title: String,
+    }
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("hc_comments")]
+    struct Comment {
+        pub id: Option<i64>,
+        #[orm_column(references = "hc_posts")]
+        pub post_id: Option<i64>,
+    }
+
+    fn ids(posts: Vec<Post>) -> Vec<i64> {
+        posts.iter().map(|p| p.id.unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_having_counts_over_relation() {
+        let db = super::memory_db(
+            "CREATE TABLE hc_posts (id INTEGER PRIMARY KEY, title TEXT);
+             CREATE TABLE hc_comments (id INTEGER PRIMARY KEY, post_id INTEGER);
+             INSERT INTO hc_posts VALUES (1, 'a'), (2, 'b'), (3, 'c');
+             INSERT INTO hc_comments (post_id) VALUES (1), (1), (1), (2), (NULL);",
+        )
+        .await;
+
+        assert_eq!(
+            ids(Post::having_at_least::<Comment>(3, &db).await.unwrap()),
+            vec![1]
+        );
+        assert_eq!(
+            ids(Post::having_at_least::<Comment>(1, &db).await.unwrap()),
+            vec![1, 2]
+        );
+        // Posts without comments count as zero, NULL references are ignored
+        assert_eq!(
+            ids(Post::having_at_most::<Comment>(1, &db).await.unwrap()),
+            vec![2, 3]
+        );
+        assert_eq!(
+            ids(Post::having_at_most::<Comment>(0, &db).await.unwrap()),
+            vec![3]
+        );
+
+        // Comments do not reference comments
+        assert!(Comment::having_at_least::<Comment>(1, &db).await.is_err());
+    }
+}
+
+#[cfg(test)]
+mod search_columns_tests {
+    use crate::Model;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("sc_posts")]
+    struct Post {
+        pub id: Option<i64>,
+        pub title: String,
+        pub content: Option<String>,
+        pub author: String,
+    }
+
+    #[tokio::test]
+    async fn test_search_columns_ranks_by_column_weight() {
+        let db = super::memory_db(
+            "CREATE TABLE sc_posts (id INTEGER PRIMARY KEY, title TEXT, content TEXT, author TEXT);
+             INSERT INTO sc_posts VALUES
+                 (1, 'Cooking', 'no match', 'Rusty'),
+                 (2, 'Intro', 'learning rust', 'ann'),
+                 (3, 'RUST tips', NULL, 'bob'),
+                 (4, 'Rust and more', 'rust everywhere', 'rustacean'),
+                 (5, 'Go', 'gophers', 'carl'),
+                 (6, '100% rust', 'odd', 'dan');",
+        )
+        .await;
+
+        let ids = |posts: Vec<Post>| posts.iter().map(|p| p.id.unwrap()).collect::<Vec<_>>();
+        let found = Post::search_columns(&["title", "content", "author"], "rust", &db)
+            .await
+            .unwrap();
+        assert_eq!(ids(found), vec![4, 3, 6, 2, 1]);
+
+        // LIKE wildcards in the term match literally
+        let found = Post::search_columns(&["title"], "100%", &db).await.unwrap();
+        assert_eq!(ids(found), vec![6]);
+
+        assert!(Post::search_columns(&["title", "body"], "rust", &db)
+            .await
+            .is_err());
+        assert!(Post::search_columns(&[], "rust", &db).await.is_err());
+    }
+}
+
+#[cfg(test)]
+mod fuzzy_search_tests {
+    use crate::Model;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("fz_posts")]
+    struct Post {
+        pub id: Option<i64>,
+        pub title: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn test_fuzzy_search_tolerates_typos() {
+        let db = super::memory_db(
+            "CREATE TABLE fz_posts (id INTEGER PRIMARY KEY, title TEXT);
+             INSERT INTO fz_posts VALUES
+                 (1, 'Trust issues'),
+                 (2, 'Learning Rust'),
+                 (3, 'Python tips'),
+                 (4, NULL),
+                 (5, 'RUST'),
+                 (6, 'Rusty nails');",
+        )
+        .await;
+        let ids = |posts: Vec<Post>| posts.iter().map(|p| p.id.unwrap()).collect::<Vec<_>>();
+
+        let found = Post::fuzzy_search("title", "rust", 0.75, &db)
+            .await
+            .unwrap();
+        assert_eq!(ids(found), vec![2, 5, 1, 6]);
+        let found = Post::fuzzy_search("title", "rsut", 0.5, &db).await.unwrap();
+        assert_eq!(ids(found), vec![2, 5]);
+        let found = Post::fuzzy_search("title", "lerning rust", 0.9, &db)
+            .await
+            .unwrap();
+        assert_eq!(ids(found), vec![2]);
+        assert_eq!(
+            Post::fuzzy_search("title", "", 0.0, &db)
+                .await
+                .unwrap()
+                .len(),
+            5
+        );
+
+        assert!(Post::fuzzy_search("title", "rust", 1.5, &db).await.is_err());
+        assert!(Post::fuzzy_search("body", "rust", 0.5, &db).await.is_err());
+    }
+}
+
+#[cfg(test)]
+mod replica_tests {
+    use crate::SyncConfig;
+    use std::time::Duration;
+
+    #[test]
+    fn test_sync_config_builder() {
+        let config = SyncConfig::new();
+        assert_eq!(config.interval, None);
+        assert!(config.read_your_writes && config.sync_on_open);
+
+        let config = SyncConfig::new()
+            .every(Duration::from_secs(30))
+            .read_your_writes(false)
+            .sync_on_open(false);
+        assert_eq!(config.interval, Some(Duration::from_secs(30)));
+        assert!(!config.read_your_writes && !config.sync_on_open);
+    }
+
+    #[tokio::test]
+    async fn test_sync_requires_replica() {
+        let db = super::memory_db("").await;
+        assert!(!db.is_replica());
+        assert!(db.tagged("t").sync().await.is_err());
+    }
+}
+
+#[cfg(test)]
+mod query_hint_tests {
+    use crate::{Model, QueryBuilder, QueryHint};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("hint_items")]
+    struct Item {
+        pub id: Option<i64>,
+    }
+
+    #[tokio::test]
+    async fn test_hints_follow_handles_and_queries() {
+        let db = super::memory_db(
+            "CREATE TABLE hint_items (id INTEGER PRIMARY KEY);
+             INSERT INTO hint_items VALUES (1), (2);",
+        )
+        .await;
+        assert_eq!(db.hint(), QueryHint::Interactive);
+
+        let background = db.with_hint(QueryHint::Background);
+        assert_eq!(background.hint(), QueryHint::Background);
+        assert_eq!(background.tagged("export").hint(), QueryHint::Background);
+        assert_eq!(db.hint(), QueryHint::Interactive);
+
+        let items = QueryBuilder::new("hint_items")
+            .hint(QueryHint::Background)
+            .execute_models::<Item>(&db)
+            .await
+            .unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(Item::count(&background).await.unwrap(), 2);
+    }
+}
+
+#[cfg(test)]
+mod join_all_tests {
+    use crate::{Error, Model};
+    use serde::{Deserialize, Serialize};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("join_items")]
+    struct Item {
+        pub id: Option<i64>,
+    }
+
+    #[tokio::test]
+    async fn test_join_all_keeps_order_and_caps_concurrency() {
+        let db = super::memory_db(
+            "CREATE TABLE join_items (id INTEGER PRIMARY KEY);
+             INSERT INTO join_items VALUES (1), (2), (3);",
+        )
+        .await;
+        let (running, peak) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        let lookups = [3, 1, 5, 2].map(|id| {
+            let (db, running, peak) = (&db, &running, &peak);
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::task::yield_now().await;
+                let item = Item::find_by_id(id, db).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                item
+            }
+        });
+        let found = db.join_all(lookups, 2).await.unwrap();
+        let ids: Vec<Option<i64>> = found
+            .iter()
+            .map(|i| i.as_ref().and_then(|i| i.id))
+            .collect();
+        assert_eq!(ids, vec![Some(3), Some(1), None, Some(2)]);
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+
+        let failing = vec![
+            Box::pin(async { Ok(1) }) as futures::future::LocalBoxFuture<'_, _>,
+            Box::pin(async { Err(Error::Query("boom".to_string())) }),
+        ];
+        assert!(db.join_all(failing, 0).await.is_err());
+    }
+}
+
+#[cfg(test)]
+mod read_replica_tests {
+    use crate::{Database, Model};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("rr_items")]
+    struct Item {
+        pub id: Option<i64>,
+        pub origin: String,
+    }
+
+    async fn node(origin: &str) -> Database {
+        super::memory_db(&format!(
+            "CREATE TABLE rr_items (id INTEGER PRIMARY KEY, origin TEXT);
+             INSERT INTO rr_items VALUES (1, '{origin}');"
+        ))
+        .await
+    }
+
+    async fn origin(db: &Database) -> String {
+        Item::find_by_id(1, db).await.unwrap().unwrap().origin
+    }
+
+    #[tokio::test]
+    async fn test_reads_go_to_replicas_and_writes_to_primary() {
+        let db = node("primary")
+            .await
+            .with_read_replica(node("replica a").await)
+            .with_read_replica(node("replica b").await);
+
+        assert_eq!(origin(&db).await, "replica a");
+        assert_eq!(origin(&db.tagged("t")).await, "replica b");
+        assert_eq!(origin(&db).await, "replica a");
+        assert_eq!(origin(&db.primary()).await, "primary");
+
+        let created = Item {
+            id: None,
+            origin: "new".to_string(),
+        }
+        .create(&db)
+        .await
+        .unwrap();
+        assert_eq!(created.id, Some(2));
+        assert_eq!(Item::count(&db.primary()).await.unwrap(), 2);
+        assert_eq!(Item::count(&db).await.unwrap(), 1);
+
+        let in_tx = db
+            .transaction(|tx| async move { Ok(origin(tx).await) })
+            .await
+            .unwrap();
+        assert_eq!(in_tx, "primary");
+    }
+}
+
+#[cfg(test)]
+mod retry_tests {
+    use crate::retry::is_transient;
+    use crate::{QueryHint, RetryPolicy};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let policy = RetryPolicy::new(5)
+            .backoff(Duration::from_millis(100), Duration::from_millis(300))
+            .jitter(false);
+        let delays: Vec<u128> = (1..=4)
+            .map(|retry| policy.delay(retry).as_millis())
+            .collect();
+        assert_eq!(delays, vec![100, 200, 300, 300]);
+
+        let jittered =
+            RetryPolicy::new(5).backoff(Duration::from_millis(100), Duration::from_secs(1));
+        for _ in 0..20 {
+            let delay = jittered.delay(2);
+            assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(200));
+        }
+        assert_eq!(
+            RetryPolicy::new(2)
+                .background_attempts(5)
+                .attempts(QueryHint::Background),
+            5
+        );
+        assert_eq!(RetryPolicy::default().attempts(QueryHint::Interactive), 1);
+    }
+
+    #[test]
+    fn test_transient_errors() {
+        assert!(is_transient(&libsql::Error::ConnectionFailed(
+            "reset".to_string()
+        )));
+        assert!(is_transient(&libsql::Error::SqliteFailure(
+            5,
+            "busy".to_string()
+        )));
+        assert!(is_transient(&libsql::Error::Hrana(
+            "api error: `status=503 Service Unavailable, body=`".into()
+        )));
+        assert!(!is_transient(&libsql::Error::SqliteFailure(
+            19,
+            "constraint".to_string()
+        )));
+        assert!(!is_transient(&libsql::Error::Hrana(
+            "api error: `status=401 Unauthorized, body=`".into()
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_statements_retry_outside_transactions() {
+        let retried = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&retried);
+        let db = super::memory_db("CREATE TABLE retry_items (id INTEGER PRIMARY KEY)")
+            .await
+            .with_retry_policy(
+                RetryPolicy::new(3)
+                    .background_attempts(5)
+                    .backoff(Duration::ZERO, Duration::ZERO)
+                    .retry_on(move |_| {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        true
+                    }),
+            );
+
+        assert!(db.query("SELECT * FROM missing", vec![]).await.is_err());
+        assert_eq!(retried.swap(0, Ordering::SeqCst), 2);
+        assert!(db.execute("DELETE FROM missing", vec![]).await.is_err());
+        assert_eq!(retried.swap(0, Ordering::SeqCst), 2);
+
+        let background = db.with_hint(QueryHint::Background);
+        assert!(background
+            .query_as::<serde_json::Value>("SELECT * FROM missing", vec![])
+            .await
+            .is_err());
+        assert_eq!(retried.swap(0, Ordering::SeqCst), 4);
+
+        let in_tx = db
+            .transaction(|tx| async move {
+                tx.execute("DELETE FROM missing", vec![]).await?;
+                Ok(())
+            })
+            .await;
+        assert!(in_tx.is_err());
+        assert_eq!(retried.load(Ordering::SeqCst), 0);
+    }
+}
+
+#[cfg(test)]
+mod db_default_tests {
+    use crate::Model;
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("dd_events")]
+    struct Event {
+        pub id: Option<i64>,
+        pub name: String,
+        #[orm_column(db_default = "'pending'")]
+        pub status: String,
+        #[orm_column(db_default)]
+        pub created_at: DateTime<Utc>,
+    }
+
+    fn event(name: &str) -> Event {
+        Event {
+            id: None,
+            name: name.to_string(),
+            status: "ignored".to_string(),
+            created_at: DateTime::UNIX_EPOCH,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_db_default_columns_come_from_the_database() {
+        assert!(Event::migration_sql().contains("status TEXT DEFAULT ('pending')"));
+        let db = super::memory_db(
+            "CREATE TABLE dd_events (
+                 id INTEGER PRIMARY KEY,
+                 name TEXT,
+                 status TEXT DEFAULT 'pending',
+                 created_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+             );",
+        )
+        .await;
+
+        let (sql, _) = event("a").insert_statement().unwrap();
+        assert_eq!(sql, "INSERT INTO dd_events (id, name) VALUES (?, ?)");
+
+        let created = event("a").create(&db).await.unwrap();
+        assert_eq!(created.status, "pending");
+        assert!(created.created_at > DateTime::UNIX_EPOCH);
+
+        let bulk = Event::bulk_create(&[event("b"), event("c")], &db)
+            .await
+            .unwrap();
+        assert!(bulk
+            .iter()
+            .all(|e| e.status == "pending" && e.created_at > DateTime::UNIX_EPOCH));
+
+        // Updates send the field like any other
+        let mut done = created.clone();
+        done.status = "done".to_string();
+        let updated = done.update(&db).await.unwrap();
+        assert_eq!(updated.status, "done");
+        assert_eq!(updated.created_at, created.created_at);
+    }
+}
+
+#[cfg(test)]
+mod ping_tests {
+    use crate::database::{is_connection_error, is_stale_stream};
+    use crate::RetryPolicy;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_ping_and_reconnect_local() {
+        let db = super::memory_db("CREATE TABLE ping_items (id INTEGER PRIMARY KEY)").await;
+        assert!(db.ping().await.is_ok());
+
+        // Resetting a local connection keeps the database usable
+        db.execute("INSERT INTO ping_items (id) VALUES (1)", vec![])
+            .await
+            .unwrap();
+        db.reconnect().await;
+        assert!(db.ping().await.is_ok());
+        let rows: Vec<serde_json::Value> = db
+            .query_as("SELECT id FROM ping_items", vec![])
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+    }
+
+    #[test]
+    fn test_connection_errors() {
+        assert!(is_connection_error(&libsql::Error::ConnectionFailed(
+            "reset".to_string()
+        )));
+        assert!(is_connection_error(&libsql::Error::Hrana(
+            "api error: `status=400 Bad Request, body={\"error\":\"Received an invalid baton\"}`"
+                .into()
+        )));
+        assert!(!is_connection_error(&libsql::Error::SqliteFailure(
+            1,
+            "no such table: missing".to_string()
+        )));
+        assert!(is_stale_stream(&libsql::Error::Hrana(
+            "api error: `status=400 Bad Request, body={\"error\":\"Received an invalid baton\"}`"
+                .into()
+        )));
+        assert!(!is_stale_stream(&libsql::Error::ConnectionFailed(
+            "reset".to_string()
+        )));
+    }
+
+    async fn rerun(db: &crate::Database, error: &libsql::Error, read: bool) -> Option<Duration> {
+        let mut reconnected = false;
+        let delay = db.rerun_delay(error, read, 1, &mut reconnected).await;
+        assert!(reconnected);
+        delay
+    }
+
+    #[tokio::test]
+    async fn test_only_safe_statements_rerun_after_reconnecting() {
+        let db = super::memory_db("CREATE TABLE ping_reruns (id INTEGER PRIMARY KEY)").await;
+        let failed = libsql::Error::ConnectionFailed("reset".to_string());
+        let expired = libsql::Error::Hrana("stream expired".into());
+
+        // Without a retry policy nothing runs again
+        assert_eq!(rerun(&db, &failed, true).await, None);
+
+        let db = db.with_retry_policy(RetryPolicy::new(2).retry_on(|_| false));
+        assert_eq!(rerun(&db, &failed, true).await, Some(Duration::ZERO));
+        assert_eq!(rerun(&db, &expired, false).await, Some(Duration::ZERO));
+        // The write may have been applied before the connection broke
+        assert_eq!(rerun(&db, &failed, false).await, None);
+    }
+}
+
+#[cfg(test)]
+mod schema_check_tests {
+    use crate::Model;
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Default, Serialize, Deserialize)]
+    #[table_name("sc_users")]
+    struct User {
+        pub id: Option<i64>,
+        pub name: String,
+        pub is_active: bool,
+        pub joined_at: DateTime<Utc>,
+    }
+
+    #[derive(Model, Debug, Clone, Default, Serialize, Deserialize)]
+    #[table_name("sc_posts")]
+    struct Post {
+        pub id: Option<i64>,
+        #[orm_column(references = "sc_users")]
+        pub user_id: i64,
+        pub title: Option<String>,
+    }
+
+    #[derive(Model, Debug, Clone, Default, Serialize, Deserialize)]
+    #[table_name("sc_broken")]
+    struct Broken {
+        pub id: Option<i64>,
+        #[orm_column(db_default = "nonsense(")]
+        pub status: String,
+    }
+
+    crate::model_schema_tests!(User, Post);
+
+    #[test]
+    #[should_panic(expected = "migration SQL of `sc_broken` does not run")]
+    fn test_broken_migration_fails_the_check() {
+        crate::testing::check_migration_sql::<Broken>();
+    }
+}
+
+#[cfg(test)]
+mod filter_expression_tests {
+    use crate::{filter, QueryBuilder};
+
+    fn where_sql(condition: crate::FilterOperator) -> (String, usize) {
+        let (sql, params) = QueryBuilder::new("items")
+            .r#where(condition)
+            .build()
+            .unwrap();
+        (sql.replace("SELECT * FROM items WHERE ", ""), params.len())
+    }
+
+    #[test]
+    fn test_filter_expressions_follow_precedence() {
+        let min_price = 100.0;
+        let (sql, params) = where_sql(filter!(
+            status == "active" && price > min_price || category == "sale"
+        ));
+        assert_eq!(sql, "((status = ? AND price > ?) OR category = ?)");
+        assert_eq!(params, 3);
+
+        let (sql, _) = where_sql(filter!(
+            !(role == "admin" || role != "owner") && age >= 18i64 && age <= 65i64
+        ));
+        assert_eq!(
+            sql,
+            "(NOT ((role = ? OR role != ?)) AND age >= ? AND age <= ?)"
+        );
+
+        let (sql, params) = where_sql(filter!(score < 10 - 3));
+        assert_eq!((sql.as_str(), params), ("score < ?", 1));
+
+        // The comma forms still build single filters
+        assert_eq!(filter!("status", is_null).column, "status");
+    }
+}
+
+#[cfg(test)]
+mod timeout_tests {
+    use crate::{Error, QueryHint};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_timeouts_follow_hint_and_overrides() {
+        let db = super::memory_db("CREATE TABLE to_items (id INTEGER PRIMARY KEY)").await;
+        assert_eq!(db.timeout(), None);
+
+        let db = db
+            .with_timeout(Duration::from_secs(2))
+            .with_background_timeout(Duration::from_secs(30));
+        assert_eq!(db.timeout(), Some(Duration::from_secs(2)));
+        let background = db.with_hint(QueryHint::Background);
+        assert_eq!(background.timeout(), Some(Duration::from_secs(30)));
+        // A per-call timeout wins over the background one
+        assert_eq!(
+            background.with_timeout(Duration::from_secs(5)).timeout(),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(background.without_timeout().timeout(), None);
+
+        // Statements finishing in time are unaffected
+        let rows: Vec<serde_json::Value> = db
+            .query_as("SELECT id FROM to_items", vec![])
+            .await
+            .unwrap();
+        assert!(rows.is_empty());
+        let count = crate::QueryBuilder::new("to_items")
+            .timeout(Duration::from_millis(500))
+            .execute_count(&db)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_hanging_statement_times_out() {
+        let db = super::memory_db("CREATE TABLE to_hang (id INTEGER PRIMARY KEY)")
+            .await
+            .with_timeout(Duration::from_millis(20));
+        let hang = futures::future::pending::<Result<(), libsql::Error>>();
+        let error: Error = db.within_timeout(hang).await.unwrap_err().into();
+        match error {
+            Error::Timeout(message) => assert!(message.contains("20ms")),
+            other => panic!("expected a timeout, got {other}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod options_tests {
+    use crate::{Database, DatabaseOptions, JournalMode, Synchronous};
+    use std::time::Duration;
+
+    async fn pragma(db: &Database, name: &str) -> serde_json::Value {
+        let rows: Vec<serde_json::Value> = db
+            .query_as(&format!("PRAGMA {name}"), vec![])
+            .await
+            .unwrap();
+        // Read the single column whatever its name
+        rows[0]
+            .as_object()
+            .unwrap()
+            .values()
+            .next()
+            .unwrap()
+            .clone()
+    }
+
+    #[tokio::test]
+    async fn test_options_are_applied_on_open() {
+        let options = DatabaseOptions::new()
+            .journal_mode(JournalMode::Wal)
+            .synchronous(Synchronous::Normal)
+            .busy_timeout(Duration::from_millis(2500))
+            .cache_size(-4000);
+        assert_eq!(
+            options.pragmas(),
+            vec![
+                "PRAGMA journal_mode = WAL",
+                "PRAGMA synchronous = NORMAL",
+                "PRAGMA busy_timeout = 2500",
+                "PRAGMA cache_size = -4000",
+            ]
+        );
+
+        let path =
+            std::env::temp_dir().join(format!("libsql_orm_options_{}.db", uuid::Uuid::new_v4()));
+        let db = Database::new_local(&path, &options).await.unwrap();
+        assert_eq!(pragma(&db, "journal_mode").await, "wal");
+        assert_eq!(pragma(&db, "synchronous").await, 1);
+        assert_eq!(pragma(&db, "busy_timeout").await, 2500);
+        assert_eq!(pragma(&db, "cache_size").await, -4000);
+        drop(db);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+
+        // In-memory databases cannot use WAL; the rest still applies
+        let memory = Database::new_memory().await.unwrap();
+        memory.apply_options(&options).await.unwrap();
+        assert_eq!(pragma(&memory, "journal_mode").await, "memory");
+        assert_eq!(pragma(&memory, "busy_timeout").await, 2500);
+    }
+}
@@ -103,6 +103,24 @@ impl From<Vec<u8>> for Value {
     }
 }
 
+impl From<&[u8]> for Value {
+    fn from(v: &[u8]) -> Self {
+        Value::Blob(v.to_vec())
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for Value {
+    fn from(v: [u8; N]) -> Self {
+        Value::Blob(v.to_vec())
+    }
+}
+
+impl<const N: usize> From<&[u8; N]> for Value {
+    fn from(v: &[u8; N]) -> Self {
+        Value::Blob(v.to_vec())
+    }
+}
+
 impl From<Option<String>> for Value {
     fn from(v: Option<String>) -> Self {
         match v {
@@ -170,6 +188,48 @@ impl From<serde_json::Value> for Value {
     }
 }
 
+impl From<Value> for serde_json::Value {
+    fn from(v: Value) -> Self {
+        match v {
+            Value::Null => serde_json::Value::Null,
+            Value::Integer(i) => serde_json::Value::Number(i.into()),
+            Value::Real(f) => serde_json::Number::from_f64(f)
+                .map(serde_json::Value::Number)
+                .unwrap_or(serde_json::Value::Null),
+            Value::Text(s) => serde_json::Value::String(s),
+            Value::Blob(b) => {
+                serde_json::Value::Array(b.into_iter().map(serde_json::Value::from).collect())
+            }
+            Value::Boolean(b) => serde_json::Value::Bool(b),
+        }
+    }
+}
+
+impl From<libsql::Value> for Value {
+    fn from(v: libsql::Value) -> Self {
+        match v {
+            libsql::Value::Null => Value::Null,
+            libsql::Value::Integer(i) => Value::Integer(i),
+            libsql::Value::Real(f) => Value::Real(f),
+            libsql::Value::Text(s) => Value::Text(s),
+            libsql::Value::Blob(b) => Value::Blob(b),
+        }
+    }
+}
+
+impl From<Value> for libsql::Value {
+    fn from(v: Value) -> Self {
+        match v {
+            Value::Null => libsql::Value::Null,
+            Value::Integer(i) => libsql::Value::Integer(i),
+            Value::Real(f) => libsql::Value::Real(f),
+            Value::Text(s) => libsql::Value::Text(s),
+            Value::Blob(b) => libsql::Value::Blob(b),
+            Value::Boolean(b) => libsql::Value::Integer(b as i64),
+        }
+    }
+}
+
 /// Sort order for queries
 ///
 /// Specifies whether query results should be sorted in ascending or descending order.
@@ -198,6 +258,25 @@ impl std::fmt::Display for SortOrder {
     }
 }
 
+/// Placement of NULL values in a sort
+///
+/// SQLite sorts NULLs first in ascending order and last in descending order
+/// unless told otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NullsOrder {
+    First,
+    Last,
+}
+
+impl std::fmt::Display for NullsOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NullsOrder::First => write!(f, "NULLS FIRST"),
+            NullsOrder::Last => write!(f, "NULLS LAST"),
+        }
+    }
+}
+
 /// Aggregate functions
 ///
 /// SQL aggregate functions for performing calculations on sets of values.
@@ -298,6 +377,12 @@ pub enum Operator {
     Ge,
     Like,
     NotLike,
+    /// `LIKE` with `\` escaping `%`, `_` and itself in the pattern
+    LikeEscaped,
+    /// `NOT LIKE` with `\` escaping `%`, `_` and itself in the pattern
+    NotLikeEscaped,
+    Glob,
+    NotGlob,
     In,
     NotIn,
     IsNull,
@@ -317,6 +402,10 @@ impl std::fmt::Display for Operator {
             Operator::Ge => write!(f, ">="),
             Operator::Like => write!(f, "LIKE"),
             Operator::NotLike => write!(f, "NOT LIKE"),
+            Operator::LikeEscaped => write!(f, "LIKE"),
+            Operator::NotLikeEscaped => write!(f, "NOT LIKE"),
+            Operator::Glob => write!(f, "GLOB"),
+            Operator::NotGlob => write!(f, "NOT GLOB"),
             Operator::In => write!(f, "IN"),
             Operator::NotIn => write!(f, "NOT IN"),
             Operator::IsNull => write!(f, "IS NULL"),
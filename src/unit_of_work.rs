@@ -0,0 +1,170 @@
+//! Unit of work for batching writes
+//!
+//! A [`UnitOfWork`] records model mutations during a request and flushes them
+//! in a single transaction at the end, which saves round trips for handlers
+//! that touch many rows.
+//!
+//! Pending writes are keyed by table and primary key, so registering the same
+//! record more than once only keeps its latest state:
+//!
+//! - Updating a record twice writes it once, with the final values
+//! - Updating a record that is pending insertion inserts the final values
+//! - Deleting a record that is pending insertion writes nothing
+//! - Deleting a record that is pending update only deletes it
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use libsql_orm::{Database, Model, UnitOfWork};
+//!
+//! async fn handler<M: Model>(records: Vec<M>, db: &Database) -> libsql_orm::Result<()> {
+//!     let mut uow = UnitOfWork::new();
+//!     for record in &records {
+//!         uow.register_dirty(record)?;
+//!     }
+//!     uow.flush(db).await?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::{Database, Model, Result};
+use std::collections::HashMap;
+
+/// Kind of pending write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WriteKind {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// A recorded write waiting to be flushed
+#[derive(Debug, Clone)]
+struct PendingWrite {
+    kind: WriteKind,
+    sql: String,
+    params: Vec<libsql::Value>,
+}
+
+/// Records model writes and flushes them as one transaction
+#[derive(Debug, Default)]
+pub struct UnitOfWork {
+    writes: Vec<Option<PendingWrite>>,
+    identity: HashMap<(&'static str, i64), usize>,
+}
+
+impl UnitOfWork {
+    /// Create an empty unit of work
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a new model to insert
+    pub fn register_new<M: Model>(&mut self, model: &M) -> Result<()> {
+        let (sql, params) = model.insert_statement()?;
+        self.record(WriteKind::Insert, Self::key(model), sql, params);
+        Ok(())
+    }
+
+    /// Record a changed model to update
+    ///
+    /// Fails if the model has no primary key.
+    pub fn register_dirty<M: Model>(&mut self, model: &M) -> Result<()> {
+        let key = Self::key(model);
+        if let Some(index) = key.and_then(|key| self.identity.get(&key).copied()) {
+            if self.writes[index].as_ref().map(|w| w.kind) == Some(WriteKind::Insert) {
+                // Still unwritten, so insert the latest state instead
+                let (sql, params) = model.insert_statement()?;
+                self.writes[index] = Some(PendingWrite {
+                    kind: WriteKind::Insert,
+                    sql,
+                    params,
+                });
+                return Ok(());
+            }
+        }
+        let (sql, params) = model.update_statement()?;
+        self.record(WriteKind::Update, key, sql, params);
+        Ok(())
+    }
+
+    /// Record a model to delete
+    ///
+    /// Fails if the model has no primary key.
+    pub fn register_deleted<M: Model>(&mut self, model: &M) -> Result<()> {
+        let (sql, params) = model.delete_statement()?;
+        let key = Self::key(model);
+        if let Some(index) = key.and_then(|key| self.identity.get(&key).copied()) {
+            if self.writes[index].as_ref().map(|w| w.kind) == Some(WriteKind::Insert) {
+                // Never written, so there is nothing to delete
+                self.writes[index] = None;
+                self.identity.remove(&key.unwrap());
+                return Ok(());
+            }
+        }
+        self.record(WriteKind::Delete, key, sql, params);
+        Ok(())
+    }
+
+    /// Number of pending writes
+    pub fn len(&self) -> usize {
+        self.writes.iter().flatten().count()
+    }
+
+    /// Whether there are no pending writes
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Discard all pending writes
+    pub fn clear(&mut self) {
+        self.writes.clear();
+        self.identity.clear();
+    }
+
+    /// Execute all pending writes in a single transaction
+    ///
+    /// The writes are sent as one [`batch`](Database::batch). Returns the total
+    /// number of affected rows. Pending writes are only cleared when the
+    /// transaction commits, so a failed flush can be retried.
+    pub async fn flush(&mut self, db: &Database) -> Result<u64> {
+        if self.is_empty() {
+            return Ok(0);
+        }
+
+        let writes: Vec<(String, Vec<libsql::Value>)> = self
+            .writes
+            .iter()
+            .flatten()
+            .map(|write| (write.sql.clone(), write.params.clone()))
+            .collect();
+        let affected = db.batch(writes).await?;
+
+        self.clear();
+        Ok(affected)
+    }
+
+    fn key<M: Model>(model: &M) -> Option<(&'static str, i64)> {
+        model.get_primary_key().map(|id| (M::table_name(), id))
+    }
+
+    /// Record a write, replacing any pending write for the same record
+    fn record(
+        &mut self,
+        kind: WriteKind,
+        key: Option<(&'static str, i64)>,
+        sql: String,
+        params: Vec<libsql::Value>,
+    ) {
+        let write = PendingWrite { kind, sql, params };
+        match key.and_then(|key| self.identity.get(&key).copied()) {
+            Some(index) => self.writes[index] = Some(write),
+            None => {
+                if let Some(key) = key {
+                    self.identity.insert(key, self.writes.len());
+                }
+                self.writes.push(Some(write));
+            }
+        }
+    }
+}
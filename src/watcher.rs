@@ -0,0 +1,160 @@
+//! Polling for changed rows
+//!
+//! libsql has no LISTEN/NOTIFY, so a [`Watcher`] polls a table for rows whose
+//! change column (an `updated_at` timestamp or an increasing change counter)
+//! moved past the last row it has seen, and hands the changed models to a
+//! callback.
+//!
+//! Rows are visited in `(column, primary key)` order and the checkpoint records
+//! both values, so rows sharing the same timestamp are never skipped.
+//!
+//! # Examples
+//!
+//! Polling on an interval:
+//!
+//! ```no_run
+//! use libsql_orm::{Database, Model, Watcher};
+//! use std::time::Duration;
+//!
+//! async fn sync<M: Model>(db: &Database) -> libsql_orm::Result<()> {
+//!     let mut watcher = Watcher::<M>::new("updated_at").interval(Duration::from_secs(30));
+//!     watcher
+//!         .run(db, |changed| async move {
+//!             println!("{} rows changed", changed.len());
+//!             Ok(true) // keep watching
+//!         })
+//!         .await
+//! }
+//! ```
+//!
+//! In a Cloudflare Workers cron trigger, persist the checkpoint between
+//! invocations and call [`Watcher::poll`] once per event:
+//!
+//! ```no_run
+//! use libsql_orm::{Database, Model, Value, Watcher};
+//!
+//! async fn on_cron<M: Model>(db: &Database, saved: Option<(Value, i64)>) -> libsql_orm::Result<()> {
+//!     let mut watcher = Watcher::<M>::new("updated_at");
+//!     if let Some((value, id)) = saved {
+//!         watcher = watcher.resume_from(value, id);
+//!     }
+//!     let changed = watcher.poll(db).await?;
+//!     // handle `changed`, then store `watcher.checkpoint()`
+//!     Ok(())
+//! }
+//! ```
+
+use crate::{Database, Filter, FilterOperator, Model, Result, Sort, Value};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+/// Polls a table for rows changed since the last check
+pub struct Watcher<M: Model> {
+    column: String,
+    filter: Option<FilterOperator>,
+    checkpoint: Option<(Value, i64)>,
+    batch_size: u32,
+    interval: Duration,
+    _model: PhantomData<M>,
+}
+
+impl<M: Model> Watcher<M> {
+    /// Create a watcher tracking the given change column
+    pub fn new(column: impl Into<String>) -> Self {
+        Self {
+            column: column.into(),
+            filter: None,
+            checkpoint: None,
+            batch_size: 100,
+            interval: Duration::from_secs(60),
+            _model: PhantomData,
+        }
+    }
+
+    /// Only watch rows matching a filter
+    pub fn filter(mut self, filter: FilterOperator) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Resume after the row with the given change value and primary key
+    pub fn resume_from(mut self, value: impl Into<Value>, id: i64) -> Self {
+        self.checkpoint = Some((value.into(), id));
+        self
+    }
+
+    /// Set the maximum number of rows returned by one poll
+    pub fn batch_size(mut self, batch_size: u32) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Set the delay between polls in [`run`](Self::run)
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// The change value and primary key of the last row seen
+    pub fn checkpoint(&self) -> Option<&(Value, i64)> {
+        self.checkpoint.as_ref()
+    }
+
+    /// Fetch the next batch of changed rows and advance the checkpoint
+    pub async fn poll(&mut self, db: &Database) -> Result<Vec<M>> {
+        let mut builder = M::query_builder()
+            .order_by(Sort::asc(self.column.as_str()))
+            .order_by(Sort::asc(M::primary_key()))
+            .limit(self.batch_size);
+        if let Some((value, id)) = &self.checkpoint {
+            builder = builder.r#where(FilterOperator::Or(vec![
+                FilterOperator::Single(Filter::gt(self.column.as_str(), value.clone())),
+                FilterOperator::And(vec![
+                    FilterOperator::Single(Filter::eq(self.column.as_str(), value.clone())),
+                    FilterOperator::Single(Filter::gt(M::primary_key(), *id)),
+                ]),
+            ]));
+        }
+        if let Some(filter) = &self.filter {
+            builder = builder.r#where(filter.clone());
+        }
+
+        let (sql, params) = builder.build()?;
+        let mut rows = db.query(&sql, params).await?;
+        let mut changed = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let map = M::row_to_map(&row)?;
+            // Keep the raw stored value, so comparisons match the column's format
+            let value = map.get(&self.column).cloned().unwrap_or(Value::Null);
+            let model = M::from_map(map)?;
+            if let Some(id) = model.get_primary_key() {
+                self.checkpoint = Some((value, id));
+            }
+            changed.push(model);
+        }
+        Ok(changed)
+    }
+
+    /// Poll forever, passing each non-empty batch of changes to `callback`
+    ///
+    /// A full batch is followed immediately by another poll; otherwise the
+    /// watcher sleeps for the configured interval. Stops when the callback
+    /// returns `Ok(false)` or any error occurs.
+    pub async fn run<F, Fut>(&mut self, db: &Database, mut callback: F) -> Result<()>
+    where
+        F: FnMut(Vec<M>) -> Fut,
+        Fut: Future<Output = Result<bool>>,
+    {
+        loop {
+            let changed = self.poll(db).await?;
+            let full = changed.len() as u32 >= self.batch_size;
+            if !changed.is_empty() && !callback(changed).await? {
+                return Ok(());
+            }
+            if !full {
+                crate::runtime::sleep(self.interval).await;
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Window functions
+//!
+//! [`WindowFunction`] adds a computed column such as a rank or a running
+//! total to a query, without collapsing rows the way `GROUP BY` does:
+//!
+//! ```rust
+//! use libsql_orm::{QueryBuilder, Sort, WindowFunction};
+//!
+//! let (sql, _) = QueryBuilder::new("scores")
+//!     .select(vec!["player", "league", "points"])
+//!     .window(
+//!         WindowFunction::rank()
+//!             .partition_by(vec!["league"])
+//!             .order_by(Sort::desc("points"))
+//!             .alias("position"),
+//!     )
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(
+//!     sql,
+//!     "SELECT player, league, points, RANK() OVER (PARTITION BY league ORDER BY points DESC) AS position FROM scores"
+//! );
+//! ```
+//!
+//! Window columns are selected under their alias, so results map onto any
+//! struct with a matching field through
+//! [`QueryBuilder::execute`](crate::QueryBuilder::execute). To filter on a
+//! window column, e.g. the top three per league, select it in a CTE and query
+//! the CTE.
+
+use crate::{Aggregate, Sort};
+
+/// A window function call selected as an extra column
+#[derive(Debug, Clone)]
+pub struct WindowFunction {
+    function: String,
+    partition_by: Vec<String>,
+    order_by: Vec<Sort>,
+    frame: Option<&'static str>,
+    alias: Option<String>,
+}
+
+impl WindowFunction {
+    fn new(function: impl Into<String>) -> Self {
+        Self {
+            function: function.into(),
+            partition_by: Vec::new(),
+            order_by: Vec::new(),
+            frame: None,
+            alias: None,
+        }
+    }
+
+    /// `ROW_NUMBER()`: position of the row within its partition, from 1
+    pub fn row_number() -> Self {
+        Self::new("ROW_NUMBER()")
+    }
+
+    /// `RANK()`: rank with gaps after ties
+    pub fn rank() -> Self {
+        Self::new("RANK()")
+    }
+
+    /// `DENSE_RANK()`: rank without gaps after ties
+    pub fn dense_rank() -> Self {
+        Self::new("DENSE_RANK()")
+    }
+
+    /// `LAG(column)`: value of `column` in the previous row
+    pub fn lag(column: &str) -> Self {
+        Self::new(format!("LAG({column})"))
+    }
+
+    /// `LEAD(column)`: value of `column` in the next row
+    pub fn lead(column: &str) -> Self {
+        Self::new(format!("LEAD({column})"))
+    }
+
+    /// An aggregate over the window, e.g. `SUM(amount)`
+    ///
+    /// Combined with [`order_by`](Self::order_by) and
+    /// [`running`](Self::running), this computes running totals.
+    pub fn aggregate(function: Aggregate, column: &str) -> Self {
+        Self::new(format!("{function}({column})"))
+    }
+
+    /// Compute the function separately for each group of these columns
+    pub fn partition_by(mut self, columns: Vec<impl Into<String>>) -> Self {
+        self.partition_by = columns.into_iter().map(|c| c.into()).collect();
+        self
+    }
+
+    /// Order the rows of each partition
+    pub fn order_by(mut self, sort: Sort) -> Self {
+        self.order_by.push(sort);
+        self
+    }
+
+    /// Limit the frame to the rows up to and including the current one
+    ///
+    /// Unlike the default frame, rows that tie on the ordering are added one
+    /// at a time, so each row gets its own running total.
+    pub fn running(mut self) -> Self {
+        self.frame = Some("ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW");
+        self
+    }
+
+    /// Name the resulting column
+    pub fn alias(mut self, alias: impl Into<String>) -> Self {
+        self.alias = Some(alias.into());
+        self
+    }
+
+    /// Build the SQL expression, including the alias
+    pub fn to_sql(&self) -> String {
+        let mut over = Vec::new();
+        if !self.partition_by.is_empty() {
+            over.push(format!("PARTITION BY {}", self.partition_by.join(", ")));
+        }
+        if !self.order_by.is_empty() {
+            let sorts: Vec<String> = self.order_by.iter().map(|sort| sort.to_string()).collect();
+            over.push(format!("ORDER BY {}", sorts.join(", ")));
+        }
+        if let Some(frame) = self.frame {
+            over.push(frame.to_string());
+        }
+        let mut sql = format!("{} OVER ({})", self.function, over.join(" "));
+        if let Some(alias) = &self.alias {
+            sql.push_str(&format!(" AS {alias}"));
+        }
+        sql
+    }
+}